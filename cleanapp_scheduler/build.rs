@@ -0,0 +1,13 @@
+fn main() {
+    // The gRPC API, compiled with the vendored protoc so that the build host needs none.
+    #[cfg(feature = "grpc")]
+    {
+        match protoc_bin_vendored::protoc_bin_path() {
+            Ok(protoc) => std::env::set_var("PROTOC", protoc),
+            Err(err) => panic!("No vendored protoc for the build host: {}", err),
+        }
+        if let Err(err) = tonic_build::compile_protos("proto/solver.proto") {
+            panic!("Cannot compile proto/solver.proto: {}", err);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+#!/usr/bin/env bash
+# Builds and tests the solver with every combination of the optional features, so that
+# slimmed down deployment binaries keep compiling. The ledger feature needs the USB
+# libraries of the build host and is left out.
+set -euo pipefail
+cd "$(dirname "$0")"
+
+FEATURES=(hooks tls grpc)
+
+for ((mask = 0; mask < 1 << ${#FEATURES[@]}; mask++)); do
+    selected=()
+    for i in "${!FEATURES[@]}"; do
+        if ((mask & 1 << i)); then
+            selected+=("${FEATURES[$i]}")
+        fi
+    done
+    features=$(IFS=,; echo "${selected[*]}")
+    echo "=== features: ${features:-none}"
+    cargo test --no-default-features --features "$features" "$@"
+done
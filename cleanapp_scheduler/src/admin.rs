@@ -0,0 +1,140 @@
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::Json,
+};
+use ethers::{
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+    types::Address,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, str::FromStr, sync::Arc};
+use utoipa::ToSchema;
+
+use crate::{contracts_abi::Laminator, laminator_listener::ProxyListeners};
+
+// Guards the admin endpoints, which are not served if no token is configured.
+#[derive(Clone)]
+pub struct AdminState {
+    // Expected as a bearer token in the Authorization header.
+    pub token: String,
+}
+
+impl AdminState {
+    pub fn authorize(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        if self.accepts(token) {
+            Ok(())
+        } else {
+            Err((
+                StatusCode::UNAUTHORIZED,
+                "Missing or wrong admin token".to_string(),
+            ))
+        }
+    }
+
+    // Whether the value of an Authorization header carries the token.
+    pub fn accepts(&self, authorization: Option<&str>) -> bool {
+        match authorization.and_then(|value| value.strip_prefix("Bearer ")) {
+            Some(token) => constant_time_eq(token.as_bytes(), self.token.as_bytes()),
+            None => false,
+        }
+    }
+}
+
+// Compares the tokens in a time that doesn't depend on the matching prefix.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+pub struct RotationRequest {
+    // File with the private key of the new wallet. It's read on the solver host, the key
+    // isn't sent to the API.
+    pub key_file: String,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct RotationResult {
+    #[schema(value_type = String)]
+    pub address: Address,
+    // The laminated proxy of the new wallet, watched from now on.
+    #[schema(value_type = String)]
+    pub proxy: Address,
+    // Running schedules started again with the new wallet.
+    pub schedules: usize,
+}
+
+// Rotates the solver wallet, the new keys sign on the connection of the solver.
+pub struct WalletRotation<M: Clone> {
+    pub admin: AdminState,
+    pub laminator_address: Address,
+    pub listeners: Arc<ProxyListeners<M>>,
+    pub signer: Arc<dyn Fn(LocalWallet) -> M + Send + Sync>,
+}
+
+impl<M: Clone> Clone for WalletRotation<M> {
+    fn clone(&self) -> Self {
+        WalletRotation {
+            admin: self.admin.clone(),
+            laminator_address: self.laminator_address,
+            listeners: self.listeners.clone(),
+            signer: self.signer.clone(),
+        }
+    }
+}
+
+// Switches the solver to a new key without a restart. The laminated proxy is derived
+// from the new wallet and watched, the old one stays watched for the calls pushed to it.
+// The running schedules are started again with the new key once their disbursements in
+// flight are over. The disbursal data keep their signing key.
+#[utoipa::path(
+    post,
+    path = "/admin/wallet/rotate",
+    request_body = RotationRequest,
+    responses(
+        (status = 200, description = "The new wallet and its proxy", body = RotationResult),
+        (status = 400, description = "The key can't be read", body = String),
+        (status = 401, description = "Missing or wrong admin token", body = String),
+        (status = 409, description = "The state is handed over", body = String),
+        (status = 503, description = "The chain can't be reached", body = String),
+    )
+)]
+pub async fn rotate_wallet<M: Middleware + Clone + 'static>(
+    rotation: WalletRotation<M>,
+    headers: HeaderMap,
+    Json(request): Json<RotationRequest>,
+) -> Result<Json<RotationResult>, (StatusCode, String)> {
+    rotation.admin.authorize(&headers)?;
+    let wallet = fs::read_to_string(&request.key_file)
+        .map_err(|err| err.to_string())
+        .and_then(|key| LocalWallet::from_str(key.trim()).map_err(|err| err.to_string()))
+        .map_err(|err| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Error reading the key in {}: {}", request.key_file, err),
+            )
+        })?;
+    let address = wallet.address();
+    let middleware = Arc::new((rotation.signer)(wallet));
+    let proxy = Laminator::new(rotation.laminator_address, middleware.clone())
+        .compute_proxy_address(address)
+        .await
+        .map_err(|err| (StatusCode::SERVICE_UNAVAILABLE, err.to_string()))?;
+    let schedules = rotation
+        .listeners
+        .rotate(middleware, proxy)
+        .await
+        .map_err(|err| (StatusCode::CONFLICT, err))?;
+    println!(
+        "Rotated the wallet to {:?} with the proxy {:?}, {} schedules started again",
+        address, proxy, schedules
+    );
+    Ok(Json(RotationResult {
+        address,
+        proxy,
+        schedules,
+    }))
+}
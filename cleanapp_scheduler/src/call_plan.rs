@@ -0,0 +1,100 @@
+use ethers::{
+    abi::{self, AbiEncode, Token},
+    providers::{Middleware, MiddlewareError},
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, TransactionRequest},
+};
+
+use crate::{
+    contracts_abi::{CallObject, ReturnObject},
+    solver::{transient, SolverError},
+};
+
+// How the expected return of a planned call is found out.
+enum PlannedReturn {
+    // Observed by simulating the call from the call breaker.
+    Simulated,
+    // The returns of the calls pushed to the proxy, simulated from the proxy.
+    Pull(Vec<CallObject>),
+}
+
+// The calls of a final transaction with their expected returns, derived by simulating
+// the calls in the planned order rather than written by hand. Each call is simulated on
+// its own against the latest state, it doesn't see the effects of the calls before it.
+pub struct CallPlan {
+    call_breaker: Address,
+    calls: Vec<(CallObject, PlannedReturn)>,
+}
+
+impl CallPlan {
+    pub fn new(call_breaker: Address) -> CallPlan {
+        CallPlan {
+            call_breaker,
+            calls: Vec::new(),
+        }
+    }
+
+    pub fn call(mut self, call: CallObject) -> CallPlan {
+        self.calls.push((call, PlannedReturn::Simulated));
+        self
+    }
+
+    // The pull of the calls pushed to the proxy the call is made to.
+    pub fn pull(mut self, call: CallObject, pushed: Vec<CallObject>) -> CallPlan {
+        self.calls.push((call, PlannedReturn::Pull(pushed)));
+        self
+    }
+
+    pub fn call_objects(&self) -> Vec<CallObject> {
+        self.calls.iter().map(|(call, _)| call.clone()).collect()
+    }
+
+    pub async fn return_objects<M: Middleware>(
+        &self,
+        middleware: &M,
+    ) -> Result<Vec<ReturnObject>, SolverError> {
+        let mut return_objects = Vec::new();
+        for (i, (call, planned)) in self.calls.iter().enumerate() {
+            let returnvalue = match planned {
+                PlannedReturn::Simulated => {
+                    simulate(middleware, self.call_breaker, call, i).await?
+                }
+                PlannedReturn::Pull(pushed) => {
+                    let mut pushed_returns = Vec::new();
+                    for pushed_call in pushed {
+                        pushed_returns.push(ReturnObject {
+                            returnvalue: simulate(middleware, call.addr, pushed_call, i).await?,
+                        });
+                    }
+                    abi::encode(&[Token::Bytes(pushed_returns.encode())]).into()
+                }
+            };
+            return_objects.push(ReturnObject { returnvalue });
+        }
+        Ok(return_objects)
+    }
+}
+
+// The output of the call made from the given account, with eth_call. Errors answered by
+// the node are final, the simulation may be repeated if the node didn't answer.
+async fn simulate<M: Middleware>(
+    middleware: &M,
+    from: Address,
+    call: &CallObject,
+    index: usize,
+) -> Result<Bytes, SolverError> {
+    let tx: TypedTransaction = TransactionRequest::new()
+        .from(from)
+        .to(call.addr)
+        .value(call.amount)
+        .gas(call.gas)
+        .data(call.callvalue.clone())
+        .into();
+    middleware.call(&tx, None).await.map_err(|err| {
+        let message = format!("Simulation of the call {} error: {}", index, err);
+        if transient(err.as_error_response()) {
+            SolverError::Rpc(message)
+        } else {
+            SolverError::Exec(message)
+        }
+    })
+}
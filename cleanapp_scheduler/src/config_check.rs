@@ -0,0 +1,142 @@
+use axum::{extract::State, http::StatusCode, response::Json};
+use ethers::{providers::Middleware, types::Address};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Debug, sync::Arc};
+use utoipa::ToSchema;
+
+use crate::solvers::cleanapp_scheduler::KITNDisburmentScheduler;
+
+// A configuration value which doesn't match the parameters of the deployed contracts.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ConfigMismatch {
+    pub field: String,
+    pub configured: String,
+    pub deployed: String,
+}
+
+// The contracts the solver is configured with.
+pub struct DeployedConfig {
+    pub laminator_address: Address,
+    pub call_breaker_address: Address,
+    pub kitn_disbursement_scheduler_address: Address,
+    // Checked only if configured.
+    pub kitn_owner: Option<Address>,
+}
+
+struct Diff {
+    mismatches: Vec<ConfigMismatch>,
+}
+
+impl Diff {
+    fn expect<T: Debug + PartialEq, E: ToString>(
+        &mut self,
+        field: String,
+        configured: T,
+        deployed: Result<T, E>,
+    ) {
+        match deployed {
+            Ok(deployed) if deployed == configured => {}
+            Ok(deployed) => self.mismatches.push(ConfigMismatch {
+                field,
+                configured: format!("{:?}", configured),
+                deployed: format!("{:?}", deployed),
+            }),
+            Err(err) => self.mismatches.push(ConfigMismatch {
+                field,
+                configured: format!("{:?}", configured),
+                deployed: format!("error: {}", err.to_string()),
+            }),
+        }
+    }
+
+    async fn expect_code<M: Middleware>(&mut self, middleware: &M, field: &str, address: Address) {
+        let deployed = match middleware.get_code(address, None).await {
+            Ok(code) => Ok(if code.is_empty() {
+                "no contract"
+            } else {
+                "contract"
+            }),
+            Err(err) => Err(err.to_string()),
+        };
+        self.expect(format!("{} {:?}", field, address), "contract", deployed);
+    }
+}
+
+// Reads the parameters of the deployed contracts and compares them with the
+// configuration, field by field.
+pub async fn validate<M: Middleware>(
+    middleware: Arc<M>,
+    config: &DeployedConfig,
+) -> Vec<ConfigMismatch> {
+    let mut diff = Diff {
+        mismatches: Vec::new(),
+    };
+    diff.expect_code(middleware.as_ref(), "laminator", config.laminator_address)
+        .await;
+    diff.expect_code(
+        middleware.as_ref(),
+        "call_breaker",
+        config.call_breaker_address,
+    )
+    .await;
+    diff.expect_code(
+        middleware.as_ref(),
+        "kitn_disbursement_scheduler",
+        config.kitn_disbursement_scheduler_address,
+    )
+    .await;
+
+    let kitn_scheduler =
+        KITNDisburmentScheduler::new(config.kitn_disbursement_scheduler_address, middleware);
+    diff.expect(
+        "kitn_disbursement_scheduler.callbreaker".to_string(),
+        config.call_breaker_address,
+        kitn_scheduler.callbreaker().call().await,
+    );
+    if let Some(kitn_owner) = config.kitn_owner {
+        diff.expect(
+            "kitn_disbursement_scheduler.owner".to_string(),
+            kitn_owner,
+            kitn_scheduler.owner().call().await,
+        );
+    }
+    diff.mismatches
+}
+
+// The transactions are signed for the configured chain with EIP-155, a node of another
+// chain would reject them, or replay them on a chain they weren't meant for.
+pub async fn verify_chain_id<M: Middleware>(middleware: &M, chain_id: u64) -> Result<(), String> {
+    let actual = middleware
+        .get_chainid()
+        .await
+        .map_err(|err| format!("Error reading the chain ID of the node: {}", err))?;
+    if actual != chain_id.into() {
+        return Err(format!(
+            "The node is on chain {}, the solver is configured for chain {}",
+            actual, chain_id
+        ));
+    }
+    Ok(())
+}
+
+// Fails while the configuration doesn't match the deployed contracts.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    responses(
+        (status = 200, body = Vec<ConfigMismatch>),
+        (status = 503, description = "The configuration doesn't match the deployed contracts", body = Vec<ConfigMismatch>),
+    )
+)]
+pub async fn get_readiness(
+    mismatches: State<Arc<Vec<ConfigMismatch>>>,
+) -> (StatusCode, Json<Vec<ConfigMismatch>>) {
+    if mismatches.is_empty() {
+        (StatusCode::OK, Json(Vec::new()))
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(mismatches.as_ref().clone()),
+        )
+    }
+}
@@ -0,0 +1,226 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json},
+};
+use ethers::providers::Middleware;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    sync::Mutex,
+    time::{sleep, timeout},
+};
+use utoipa::ToSchema;
+
+use crate::{
+    openapi::duration,
+    supervisor::{self, TaskCountsState},
+};
+
+// How often the chain is probed, and how long the probe waits for the latest block.
+const PROBE_INTERVAL: Duration = Duration::from_secs(60);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Connectivity of the chain provider. Degraded while the provider is slow or the event
+// stream fails, Reconnecting while the events can't be subscribed to, Down when the
+// subscription is given up.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, ToSchema)]
+pub enum ConnectionState {
+    Connected,
+    Degraded,
+    Reconnecting,
+    Down,
+}
+
+impl ConnectionState {
+    const ALL: [ConnectionState; 4] = [
+        ConnectionState::Connected,
+        ConnectionState::Degraded,
+        ConnectionState::Reconnecting,
+        ConnectionState::Down,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            ConnectionState::Connected => "connected",
+            ConnectionState::Degraded => "degraded",
+            ConnectionState::Reconnecting => "reconnecting",
+            ConnectionState::Down => "down",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct Connectivity {
+    pub state: ConnectionState,
+    // Time of the last transition since Unix epoch.
+    #[schema(schema_with = duration)]
+    pub since: Duration,
+    // Number of times each state was entered.
+    #[schema(value_type = HashMap<String, u64>)]
+    pub transitions: HashMap<ConnectionState, u64>,
+    pub last_error: Option<String>,
+}
+
+impl Connectivity {
+    // The provider is connected at startup.
+    pub fn new() -> Connectivity {
+        Connectivity {
+            state: ConnectionState::Connected,
+            since: now(),
+            transitions: HashMap::new(),
+            last_error: None,
+        }
+    }
+
+    pub fn transition(&mut self, state: ConnectionState, error: Option<String>) {
+        if error.is_some() {
+            self.last_error = error;
+        }
+        if state == self.state {
+            return;
+        }
+        println!(
+            "Chain connection {:?} -> {:?}{}",
+            self.state,
+            state,
+            match &self.last_error {
+                Some(err) if state != ConnectionState::Connected => format!(": {}", err),
+                _ => String::new(),
+            }
+        );
+        self.state = state;
+        self.since = now();
+        *self.transitions.entry(state).or_default() += 1;
+    }
+
+    // Degrades a healthy connection, keeps the worse states.
+    pub fn degrade(&mut self, error: String) {
+        if self.state == ConnectionState::Connected {
+            self.transition(ConnectionState::Degraded, Some(error));
+        } else {
+            self.last_error = Some(error);
+        }
+    }
+
+    // Back to connected if the connection is still in the given state.
+    pub fn recover(&mut self, from: ConnectionState) {
+        if self.state == from {
+            self.transition(ConnectionState::Connected, None);
+        }
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+// Periodically reads the latest block, degrading the connection while the node doesn't
+// respond.
+pub async fn run_connectivity_probe<M: Middleware>(
+    middleware: Arc<M>,
+    connectivity: Arc<Mutex<Connectivity>>,
+) {
+    loop {
+        sleep(PROBE_INTERVAL).await;
+        let res = match timeout(PROBE_TIMEOUT, middleware.get_block_number()).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(err)) => Err(err.to_string()),
+            Err(_) => Err(format!("No block number within {:?}", PROBE_TIMEOUT)),
+        };
+        let mut connectivity = connectivity.lock().await;
+        match res {
+            Ok(()) => connectivity.recover(ConnectionState::Degraded),
+            Err(err) => connectivity.degrade(err),
+        }
+    }
+}
+
+// Healthy while connected or degraded.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "Connected or degraded", body = Connectivity),
+        (status = 503, description = "Reconnecting or down", body = Connectivity),
+    )
+)]
+pub async fn get_healthz(
+    connectivity: State<Arc<Mutex<Connectivity>>>,
+) -> (StatusCode, Json<Connectivity>) {
+    let connectivity = connectivity.lock().await.clone();
+    match connectivity.state {
+        ConnectionState::Connected | ConnectionState::Degraded => {
+            (StatusCode::OK, Json(connectivity))
+        }
+        _ => (StatusCode::SERVICE_UNAVAILABLE, Json(connectivity)),
+    }
+}
+
+#[derive(Clone)]
+pub struct MetricsState {
+    pub connectivity: Arc<Mutex<Connectivity>>,
+    pub tasks: TaskCountsState,
+}
+
+// The connectivity and the supervised tasks in the Prometheus text format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses((status = 200, body = String, content_type = "text/plain"))
+)]
+pub async fn get_metrics(metrics: State<MetricsState>) -> impl IntoResponse {
+    let connectivity = metrics.connectivity.lock().await.clone();
+    let mut body = String::new();
+    let _ = writeln!(
+        body,
+        "# HELP solver_chain_connection_state Current state of the chain connection."
+    );
+    let _ = writeln!(body, "# TYPE solver_chain_connection_state gauge");
+    for state in ConnectionState::ALL {
+        let _ = writeln!(
+            body,
+            "solver_chain_connection_state{{state=\"{}\"}} {}",
+            state.label(),
+            (state == connectivity.state) as u8
+        );
+    }
+    let _ = writeln!(
+        body,
+        "# HELP solver_chain_connection_state_since_seconds Time of the last state transition."
+    );
+    let _ = writeln!(
+        body,
+        "# TYPE solver_chain_connection_state_since_seconds gauge"
+    );
+    let _ = writeln!(
+        body,
+        "solver_chain_connection_state_since_seconds {}",
+        connectivity.since.as_secs()
+    );
+    let _ = writeln!(
+        body,
+        "# HELP solver_chain_connection_transitions_total Transitions into each state."
+    );
+    let _ = writeln!(
+        body,
+        "# TYPE solver_chain_connection_transitions_total counter"
+    );
+    for state in ConnectionState::ALL {
+        let _ = writeln!(
+            body,
+            "solver_chain_connection_transitions_total{{state=\"{}\"}} {}",
+            state.label(),
+            connectivity.transitions.get(&state).copied().unwrap_or(0)
+        );
+    }
+    supervisor::write_metrics(&metrics.tasks.lock().await.clone(), &mut body);
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
@@ -0,0 +1,41 @@
+use std::{collections::HashMap, hash::Hash, time::Duration};
+use tokio::time::Instant;
+
+// Remembers recently received objectives, so that events re-delivered by the stream
+// (after a reorg or a resubscription) don't spawn duplicate executors.
+pub struct DedupCache<K> {
+    ttl: Duration,
+    seen: HashMap<K, Instant>,
+}
+
+impl<K: Eq + Hash> DedupCache<K> {
+    pub fn new(ttl: Duration) -> DedupCache<K> {
+        DedupCache {
+            ttl,
+            seen: HashMap::new(),
+        }
+    }
+
+    // Returns true if the key was already seen within the TTL, otherwise records it.
+    pub fn is_duplicate(&mut self, key: K) -> bool {
+        let ttl = self.ttl;
+        self.seen.retain(|_, seen| seen.elapsed() < ttl);
+        if self.seen.contains_key(&key) {
+            return true;
+        }
+        self.seen.insert(key, Instant::now());
+        false
+    }
+
+    // The keys still within the TTL.
+    pub fn keys(&self) -> Vec<K>
+    where
+        K: Clone,
+    {
+        self.seen
+            .iter()
+            .filter(|(_, seen)| seen.elapsed() < self.ttl)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
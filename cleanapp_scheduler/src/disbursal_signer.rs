@@ -0,0 +1,126 @@
+use ethers::{
+    abi::{self, ParamType, Token},
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, Signature, H256, U256},
+    utils::{hash_message, keccak256},
+};
+
+use crate::encoded_data::get_disbursed_data;
+
+// The disbursal data and the signature the KITN scheduler verifies it with.
+pub struct SignedDisbursal {
+    pub data: Bytes,
+    // 65 bytes, r, s and v.
+    pub signature: Bytes,
+}
+
+// Signs the DisbursalData of the disbursements. The KITN scheduler checks the signature
+// over getEthSignedMessageHash(data), keccak256 of the encoded data signed as an
+// Ethereum message.
+pub struct DisbursalSigner {
+    wallet: LocalWallet,
+}
+
+impl DisbursalSigner {
+    pub fn new(wallet: LocalWallet) -> DisbursalSigner {
+        DisbursalSigner { wallet }
+    }
+
+    pub fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    // Encodes and signs the disbursement, then checks both locally so that a malformed
+    // disbursement isn't sent only to revert.
+    pub fn sign(&self, receivers: &[Address], amounts: &[U256]) -> Result<SignedDisbursal, String> {
+        if receivers.is_empty() || receivers.len() != amounts.len() {
+            return Err(format!(
+                "Invalid disbursement of {} receivers and {} amounts",
+                receivers.len(),
+                amounts.len()
+            ));
+        }
+        let data = get_disbursed_data(receivers.to_vec(), amounts.to_vec());
+        let signature = self
+            .wallet
+            .sign_hash(signing_hash(&data))
+            .map_err(|err| format!("Error signing the disbursal data: {}", err))?;
+        let signed = SignedDisbursal {
+            data,
+            signature: signature.to_vec().into(),
+        };
+        verify(&signed, receivers, amounts, self.address())?;
+        Ok(signed)
+    }
+}
+
+fn signing_hash(data: &Bytes) -> H256 {
+    hash_message(keccak256(data))
+}
+
+// Checks that the data decodes back to the disbursement and that the signature recovers
+// to the signer.
+fn verify(
+    signed: &SignedDisbursal,
+    receivers: &[Address],
+    amounts: &[U256],
+    signer: Address,
+) -> Result<(), String> {
+    let decoded = abi::decode(
+        &[ParamType::Tuple(vec![
+            ParamType::Array(Box::new(ParamType::Address)),
+            ParamType::Array(Box::new(ParamType::Uint(256))),
+        ])],
+        &signed.data,
+    )
+    .map_err(|err| format!("Error decoding the disbursal data: {}", err))?;
+    let expected = Token::Tuple(vec![
+        Token::Array(receivers.iter().copied().map(Token::Address).collect()),
+        Token::Array(amounts.iter().copied().map(Token::Uint).collect()),
+    ]);
+    if decoded != [expected] {
+        return Err("The disbursal data doesn't match the disbursement".to_string());
+    }
+    Signature::try_from(signed.signature.as_ref())
+        .map_err(|err| err.to_string())
+        .and_then(|signature| {
+            signature
+                .verify(signing_hash(&signed.data), signer)
+                .map_err(|err| err.to_string())
+        })
+        .map_err(|err| format!("Invalid disbursal signature: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_disbursals_recover_to_the_signer() {
+        let wallet = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse::<LocalWallet>()
+            .ok()
+            .unwrap();
+        let signer = DisbursalSigner::new(wallet);
+        let receivers = [Address::repeat_byte(1), Address::repeat_byte(2)];
+        let amounts = [U256::from(10), U256::from(20)];
+        let signed = signer.sign(&receivers, &amounts).ok().unwrap();
+        assert_eq!(signed.signature.len(), 65);
+        let signature = Signature::try_from(signed.signature.as_ref()).ok().unwrap();
+        assert_eq!(
+            signature.recover(signing_hash(&signed.data)).ok().unwrap(),
+            signer.address()
+        );
+        assert!(verify(&signed, &receivers, &amounts, signer.address()).is_ok());
+        assert!(verify(
+            &signed,
+            &receivers,
+            &[amounts[1], amounts[0]],
+            signer.address()
+        )
+        .is_err());
+        assert!(verify(&signed, &receivers, &amounts, Address::zero()).is_err());
+        assert!(signer.sign(&receivers, &amounts[..1]).is_err());
+        assert!(signer.sign(&[], &[]).is_err());
+    }
+}
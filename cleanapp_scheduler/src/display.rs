@@ -0,0 +1,73 @@
+use ethers::{
+    abi::AbiDecode,
+    types::{Address, Bytes},
+};
+use std::fmt;
+
+use crate::{
+    contracts_abi::{
+        CallBreakerCalls, CallPushedFilter, IERC20Calls, LaminatedProxyCalls, LaminatorCalls,
+    },
+    solvers::cleanapp_scheduler::KITNDisburmentSchedulerCalls,
+};
+
+// A call pushed to a proxy as logged, its mev-time data by name and its calls decoded.
+pub struct PushedCall<'a> {
+    event: &'a CallPushedFilter,
+    proxy: Address,
+}
+
+pub fn pushed_call(event: &CallPushedFilter, proxy: Address) -> PushedCall<'_> {
+    PushedCall { event, proxy }
+}
+
+impl fmt::Display for PushedCall<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Call {} of the proxy {:?}",
+            self.event.sequence_number, self.proxy
+        )?;
+        writeln!(f, "  Data values:")?;
+        for data in &self.event.data {
+            writeln!(
+                f,
+                "    {} = {} (type {})",
+                data.name, data.value, data.datatype
+            )?;
+        }
+        write!(f, "  Call objects:")?;
+        for (i, call) in self.event.call_objs.iter().enumerate() {
+            write!(
+                f,
+                "\n    {}: to {:?}, amount {}, gas {}\n       {}",
+                i,
+                call.addr,
+                call.amount,
+                call.gas,
+                describe_call(&call.callvalue)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// The call decoded with the first of the bundled ABIs that knows its function.
+pub fn describe_call(callvalue: &Bytes) -> String {
+    if let Ok(call) = KITNDisburmentSchedulerCalls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    if let Ok(call) = IERC20Calls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    if let Ok(call) = LaminatedProxyCalls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    if let Ok(call) = LaminatorCalls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    if let Ok(call) = CallBreakerCalls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    format!("undecoded {}", callvalue)
+}
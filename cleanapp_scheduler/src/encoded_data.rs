@@ -1,93 +1,90 @@
-use std::str::FromStr as _;
-
 use ethers::{
-    abi::AbiEncode,
-    types::{Address, Bytes, U256},
+    abi::{self, AbiEncode, Token},
+    types::{Address, Bytes, H256, U256},
+    utils::keccak256,
 };
 
-const DISBURSED_DATA: [&str; 10] = [
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000800000000000000000000000000000000000000000000000000000000000000001000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000a00000000000000000000000000000000000000000000000000000000000000002000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000002ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000c00000000000000000000000000000000000000000000000000000000000000003000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000003ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000004000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000004ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000005000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000005ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001200000000000000000000000000000000000000000000000000000000000000006000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000006ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001400000000000000000000000000000000000000000000000000000000000000007000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000007ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001600000000000000000000000000000000000000000000000000000000000000008000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000008ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001800000000000000000000000000000000000000000000000000000000000000009000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000009ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000a000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000000000000000000000000000000000000000000affffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
-];
+use crate::{contracts_abi::CallObject, disbursal_signer::SignedDisbursal};
 
-const DISBURSED_DATA_RECEIVER_OFFSET: usize = 140;
-const DISBURSED_DATA_RECEIVER_LENGTH: usize = 32;
-const DISBURSED_DATA_RECEIVER_SHORT_LENGTH: usize = 20;
-const DISBURSED_DATA_AMOUNT_OFFSET: usize = 192;
-const DISBURSED_DATA_AMOUNT_LENGTH: usize = 32;
+// Tip receiver of the disbursement transactions.
+const TIP_ADDRESS: &str = "0xf821ada310c3c7da23abea279ba5bf22b359a7e1";
 
-const ASSOCIATED_DATA: [&str; 10] = [
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000800000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000018000000000000000000000000000000000000000000000000000000000000002c040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014f821ada310c3c7da23abea279ba5bf22b359a7e1000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff2bd597e6b50326bd285716c41e0fe5dd5e96ff26cf0b00218c78ae8aee4b44a5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000800000000000000000000000000000000000000000000000000000000000000001000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff357785a920be2d29aba411d3ea566fe2f90a5469044cacbffbe037cd4b756b2f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000037273760000000000000000000000000000000000000000000000000000000000",
-    "0x00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000180000000000000000000000000000000000000000000000000000000000000030040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014f821ada310c3c7da23abea279ba5bf22b359a7e1000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff2bd597e6b50326bd285716c41e0fe5dd5e96ff26cf0b00218c78ae8aee4b44a5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001200000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000a00000000000000000000000000000000000000000000000000000000000000002000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000002ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff357785a920be2d29aba411d3ea566fe2f90a5469044cacbffbe037cd4b756b2f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000037273760000000000000000000000000000000000000000000000000000000000",
-    "0x00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000180000000000000000000000000000000000000000000000000000000000000034040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014f821ada310c3c7da23abea279ba5bf22b359a7e1000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff2bd597e6b50326bd285716c41e0fe5dd5e96ff26cf0b00218c78ae8aee4b44a5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001600000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000c00000000000000000000000000000000000000000000000000000000000000003000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000003ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff357785a920be2d29aba411d3ea566fe2f90a5469044cacbffbe037cd4b756b2f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000037273760000000000000000000000000000000000000000000000000000000000",
-    "0x00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000180000000000000000000000000000000000000000000000000000000000000038040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014f821ada310c3c7da23abea279ba5bf22b359a7e1000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff2bd597e6b50326bd285716c41e0fe5dd5e96ff26cf0b00218c78ae8aee4b44a5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001a00000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000004000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000004ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff357785a920be2d29aba411d3ea566fe2f90a5469044cacbffbe037cd4b756b2f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000037273760000000000000000000000000000000000000000000000000000000000",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000800000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000018000000000000000000000000000000000000000000000000000000000000003c040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014f821ada310c3c7da23abea279ba5bf22b359a7e1000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff2bd597e6b50326bd285716c41e0fe5dd5e96ff26cf0b00218c78ae8aee4b44a5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001e00000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000005000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000005ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff357785a920be2d29aba411d3ea566fe2f90a5469044cacbffbe037cd4b756b2f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000037273760000000000000000000000000000000000000000000000000000000000",
-    "0x00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000180000000000000000000000000000000000000000000000000000000000000040040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014f821ada310c3c7da23abea279ba5bf22b359a7e1000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff2bd597e6b50326bd285716c41e0fe5dd5e96ff26cf0b00218c78ae8aee4b44a5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000002200000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001200000000000000000000000000000000000000000000000000000000000000006000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000006ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff357785a920be2d29aba411d3ea566fe2f90a5469044cacbffbe037cd4b756b2f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000037273760000000000000000000000000000000000000000000000000000000000",
-    "0x00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000180000000000000000000000000000000000000000000000000000000000000044040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014f821ada310c3c7da23abea279ba5bf22b359a7e1000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff2bd597e6b50326bd285716c41e0fe5dd5e96ff26cf0b00218c78ae8aee4b44a5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000002600000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001400000000000000000000000000000000000000000000000000000000000000007000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000007ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff357785a920be2d29aba411d3ea566fe2f90a5469044cacbffbe037cd4b756b2f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000037273760000000000000000000000000000000000000000000000000000000000",
-    "0x00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000180000000000000000000000000000000000000000000000000000000000000048040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014f821ada310c3c7da23abea279ba5bf22b359a7e1000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff2bd597e6b50326bd285716c41e0fe5dd5e96ff26cf0b00218c78ae8aee4b44a5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000002a00000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001600000000000000000000000000000000000000000000000000000000000000008000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000008ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff357785a920be2d29aba411d3ea566fe2f90a5469044cacbffbe037cd4b756b2f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000037273760000000000000000000000000000000000000000000000000000000000",
-    "0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000800000000000000000000000000000000000000000000000000000000000000100000000000000000000000000000000000000000000000000000000000000018000000000000000000000000000000000000000000000000000000000000004c040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014f821ada310c3c7da23abea279ba5bf22b359a7e1000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff2bd597e6b50326bd285716c41e0fe5dd5e96ff26cf0b00218c78ae8aee4b44a5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000002e00000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001800000000000000000000000000000000000000000000000000000000000000009000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff0000000000000000000000000000000000000000000000000000000000000009ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff357785a920be2d29aba411d3ea566fe2f90a5469044cacbffbe037cd4b756b2f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000037273760000000000000000000000000000000000000000000000000000000000",
-    "0x00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000001000000000000000000000000000000000000000000000000000000000000000180000000000000000000000000000000000000000000000000000000000000050040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014f821ada310c3c7da23abea279ba5bf22b359a7e1000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff2bd597e6b50326bd285716c41e0fe5dd5e96ff26cf0b00218c78ae8aee4b44a5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000003200000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000001a0000000000000000000000000000000000000000000000000000000000000000a000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000ffffffffffffffffffffffffffffffffffffffff000000000000000000000000000000000000000000000000000000000000000affffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff357785a920be2d29aba411d3ea566fe2f90a5469044cacbffbe037cd4b756b2f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000037273760000000000000000000000000000000000000000000000000000000000",
-];
+// Entries of the CallBreaker associated data store, encoded as AdditionalData[].
+#[derive(Clone, Debug, Default)]
+pub struct AssociatedData {
+    entries: Vec<(H256, Bytes)>,
+}
 
-const ASSOCIATED_DATA_SEQ_NUMBER_OFFSET: usize = 416;
-const ASSOCIATED_DATA_SEQ_NUMBER_LENGTH: usize = 32;
-const ASSOCIATED_DATA_RECEIVER_OFFSET: usize = 684;
-const ASSOCIATED_DATA_RECEIVER_LENGTH: usize = 32;
-const ASSOCIATED_DATA_RECEIVER_SHORT_LENGTH: usize = 20;
-const ASSOCIATED_DATA_AMOUNT_OFFSET: usize = 736;
-const ASSOCIATED_DATA_AMOUNT_LENGTH: usize = 32;
+impl AssociatedData {
+    pub fn new() -> AssociatedData {
+        AssociatedData::default()
+    }
 
-pub fn get_disbursed_data(receivers: Vec<Address>, amounts: Vec<U256>) -> Bytes {
-    let cnt = receivers.len();
-    let mut encoded = Bytes::from_str(DISBURSED_DATA[cnt - 1]).unwrap().to_vec();
+    // Adds the value under keccak256 of the name, the key the contracts look it up by.
+    pub fn with(mut self, name: &str, value: Bytes) -> AssociatedData {
+        self.entries.push((H256(keccak256(name)), value));
+        self
+    }
 
-    for i in 0..cnt {
-        let receiver_offset = DISBURSED_DATA_RECEIVER_OFFSET + i * DISBURSED_DATA_RECEIVER_LENGTH;
-        encoded.splice(
-            receiver_offset..receiver_offset + DISBURSED_DATA_RECEIVER_SHORT_LENGTH,
-            receivers[i].as_bytes().to_vec(),
-        );
-        let amount_offset = DISBURSED_DATA_AMOUNT_OFFSET
-            + (cnt - 1) * DISBURSED_DATA_RECEIVER_LENGTH
-            + i * DISBURSED_DATA_AMOUNT_LENGTH;
-        encoded.splice(
-            amount_offset..amount_offset + DISBURSED_DATA_AMOUNT_LENGTH,
-            amounts[i].encode(),
-        );
+    pub fn encode(self) -> Bytes {
+        encode_additional_data(self.entries)
     }
-    encoded.into()
 }
 
-pub fn get_associated_data(sequence_number: U256, receivers: Vec<Address>, amounts: Vec<U256>) -> Bytes {
-    let cnt = receivers.len();
-    let mut encoded = Bytes::from_str(ASSOCIATED_DATA[cnt - 1]).unwrap().to_vec();
+// Positions of the calls in the call list, keyed by the call id the CallBreaker computes
+// for each call, keccak256(abi.encode(callObj)).
+pub fn hint_indices(calls: &[CallObject]) -> Bytes {
+    encode_additional_data(
+        calls
+            .iter()
+            .enumerate()
+            .map(|(i, call)| {
+                (
+                    H256(keccak256(call.clone().encode())),
+                    U256::from(i).encode().into(),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn encode_additional_data(entries: Vec<(H256, Bytes)>) -> Bytes {
+    abi::encode(&[Token::Array(
+        entries
+            .into_iter()
+            .map(|(key, value)| {
+                Token::Tuple(vec![
+                    Token::FixedBytes(key.as_bytes().to_vec()),
+                    Token::Bytes(value.to_vec()),
+                ])
+            })
+            .collect(),
+    )])
+    .into()
+}
 
-    encoded.splice(
-        ASSOCIATED_DATA_SEQ_NUMBER_OFFSET
-            ..ASSOCIATED_DATA_SEQ_NUMBER_OFFSET + ASSOCIATED_DATA_SEQ_NUMBER_LENGTH,
-        sequence_number.encode(),
-    );
-    for i in 0..cnt {
-        let receiver_offset = ASSOCIATED_DATA_RECEIVER_OFFSET + i * ASSOCIATED_DATA_RECEIVER_LENGTH;
-        encoded.splice(
-            receiver_offset..receiver_offset + ASSOCIATED_DATA_RECEIVER_SHORT_LENGTH,
-            receivers[i].as_bytes().to_vec(),
-        );
-        let amount_offset = ASSOCIATED_DATA_AMOUNT_OFFSET
-            + (cnt - 1) * ASSOCIATED_DATA_RECEIVER_LENGTH
-            + i * ASSOCIATED_DATA_AMOUNT_LENGTH;
-        encoded.splice(
-            amount_offset..amount_offset + ASSOCIATED_DATA_AMOUNT_LENGTH,
-            amounts[i].encode(),
-        );
-    }
-    encoded.into()
+// The disbursement, encoded as the DisbursalData struct of the KITN scheduler.
+pub fn get_disbursed_data(receivers: Vec<Address>, amounts: Vec<U256>) -> Bytes {
+    abi::encode(&[Token::Tuple(vec![
+        Token::Array(receivers.into_iter().map(Token::Address).collect()),
+        Token::Array(amounts.into_iter().map(Token::Uint).collect()),
+    ])])
+    .into()
+}
+
+pub fn get_associated_data(sequence_number: U256, disbursal: &SignedDisbursal) -> Bytes {
+    AssociatedData::new()
+        .with(
+            "tipYourBartender",
+            TIP_ADDRESS
+                .parse::<Address>()
+                .unwrap()
+                .as_bytes()
+                .to_vec()
+                .into(),
+        )
+        .with("pullIndex", sequence_number.encode().into())
+        .with("KITNDisbursalData", disbursal.data.clone())
+        .with("CleanAppSignature", disbursal.signature.clone())
+        .encode()
 }
@@ -0,0 +1,141 @@
+use ethers::types::{H256, U256};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{
+    broadcast::{self, error::RecvError, Receiver, Sender},
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+};
+use uuid::Uuid;
+
+use crate::stats::TimerExecutorStats;
+
+// Internal notifications, each consumer subscribes to the bus on its own.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "event")]
+pub enum Event {
+    ExecutorStarted {
+        id: Uuid,
+        sequence_number: U256,
+    },
+    TriggerFired {
+        id: Uuid,
+        sequence_number: U256,
+        message: String,
+    },
+    TxSubmitted {
+        sequence_number: U256,
+        tx_hash: H256,
+    },
+    TxConfirmed {
+        sequence_number: U256,
+        tx_hash: H256,
+        succeeded: bool,
+        block: Option<u64>,
+    },
+    PoolWarning {
+        message: String,
+    },
+    // The current state of an executor.
+    Stats(TimerExecutorStats),
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    tx: Sender<Event>,
+    // Consumers getting every event, e.g. the final stats of the executors.
+    lossless: Arc<Mutex<Vec<UnboundedSender<Event>>>>,
+}
+
+impl EventBus {
+    // Consumers lagging behind by more than capacity events miss the oldest ones, unless
+    // they subscribed to every event.
+    pub fn new(capacity: usize) -> EventBus {
+        let (tx, _) = broadcast::channel(capacity);
+        EventBus {
+            tx,
+            lossless: Arc::default(),
+        }
+    }
+
+    pub fn publish(&self, event: Event) {
+        // The consumers which are gone are dropped.
+        self.lossless
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(event.clone()).is_ok());
+        // Fails only if nobody is subscribed.
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> Receiver<Event> {
+        self.tx.subscribe()
+    }
+
+    // Every event is kept until received, for the consumers which must not miss any and
+    // keep up with the executors.
+    pub fn subscribe_lossless(&self) -> UnboundedReceiver<Event> {
+        let (tx, rx) = unbounded_channel();
+        self.lossless.lock().unwrap().push(tx);
+        rx
+    }
+}
+
+// Waits for the next event, skipping over the missed ones.
+pub async fn next_event(rx: &mut Receiver<Event>, consumer: &str) -> Option<Event> {
+    loop {
+        match rx.recv().await {
+            Ok(event) => return Some(event),
+            Err(RecvError::Lagged(missed)) => {
+                println!("The {} consumer missed {} events", consumer, missed);
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+// Logs the notifications for audit, the executor stats are served by the stats API.
+pub async fn run_event_log(mut rx: Receiver<Event>) {
+    while let Some(event) = next_event(&mut rx, "log").await {
+        if matches!(event, Event::Stats(_)) {
+            continue;
+        }
+        match serde_json::to_string(&event) {
+            Ok(line) => println!("Event: {}", line),
+            Err(err) => println!("Error logging event: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lossless_consumers_get_every_event() {
+        let events = EventBus::new(2);
+        let mut lagging = events.subscribe();
+        let mut lossless = events.subscribe_lossless();
+        for sequence_number in 0..5u64 {
+            events.publish(Event::TxSubmitted {
+                sequence_number: sequence_number.into(),
+                tx_hash: H256::zero(),
+            });
+        }
+        assert!(matches!(lagging.recv().await, Err(RecvError::Lagged(3))));
+        for sequence_number in 0..5u64 {
+            match lossless.recv().await {
+                Some(Event::TxSubmitted {
+                    sequence_number: received,
+                    ..
+                }) => assert_eq!(received, sequence_number.into()),
+                _ => panic!("the events should be received in order"),
+            }
+        }
+        // A consumer which is gone isn't sent to anymore.
+        drop(lossless);
+        events.publish(Event::PoolWarning {
+            message: "low".to_string(),
+        });
+        assert!(events.lossless.lock().unwrap().is_empty());
+    }
+}
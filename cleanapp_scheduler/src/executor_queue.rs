@@ -0,0 +1,117 @@
+use ethers::types::U256;
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::oneshot;
+
+// Limits the number of concurrently running executors. Objectives above the limit wait
+// for a free slot, the highest priority first and FIFO within the same priority.
+pub struct ExecutorQueue {
+    // None means no limit.
+    max_running: Option<usize>,
+    state: Mutex<QueueState>,
+}
+
+struct QueueState {
+    running: usize,
+    next_seq: u64,
+    waiting: BinaryHeap<Waiting>,
+}
+
+struct Waiting {
+    priority: U256,
+    seq: u64,
+    wake: oneshot::Sender<()>,
+}
+
+impl PartialEq for Waiting {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiting {}
+
+impl PartialOrd for Waiting {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiting {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then(other.seq.cmp(&self.seq))
+    }
+}
+
+// A slot of a running executor, released on drop.
+pub struct QueuePermit {
+    queue: Arc<ExecutorQueue>,
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        self.queue.release();
+    }
+}
+
+impl ExecutorQueue {
+    pub fn new(max_running: Option<usize>) -> ExecutorQueue {
+        ExecutorQueue {
+            max_running,
+            state: Mutex::new(QueueState {
+                running: 0,
+                next_seq: 0,
+                waiting: BinaryHeap::new(),
+            }),
+        }
+    }
+
+    // Waits for a free slot.
+    pub async fn acquire(self: &Arc<Self>, priority: U256) -> QueuePermit {
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            if self.max_running.iter().all(|max| state.running < *max) {
+                state.running += 1;
+                None
+            } else {
+                let (wake, wait) = oneshot::channel();
+                let seq = state.next_seq;
+                state.next_seq += 1;
+                state.waiting.push(Waiting {
+                    priority,
+                    seq,
+                    wake,
+                });
+                println!(
+                    "Objective queued, running: {}, waiting: {}",
+                    state.running,
+                    state.waiting.len()
+                );
+                Some(wait)
+            }
+        };
+        if let Some(wait) = wait {
+            // The slot is handed over by the released permit.
+            let _ = wait.await;
+        }
+        QueuePermit {
+            queue: self.clone(),
+        }
+    }
+
+    // Hands the slot over to the next waiting objective, if any.
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(next) = state.waiting.pop() {
+            if next.wake.send(()).is_ok() {
+                return;
+            }
+        }
+        state.running -= 1;
+    }
+}
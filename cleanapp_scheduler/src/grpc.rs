@@ -0,0 +1,297 @@
+use axum::http::StatusCode;
+use ethers::types::{Address, Bytes, U256};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::{mpsc, Mutex};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tonic::{service::Interceptor, transport::Server, Request, Response, Status};
+use uuid::Uuid;
+
+use crate::{
+    admin::AdminState,
+    event_bus::{next_event, Event, EventBus},
+    handover::Handover,
+    rate_limit::RateLimiter,
+    reports_aggr::{add_report, Attester, Report, ReportsPool},
+    stats::{Status as ExecutorStatus, TimerExecutorStats},
+};
+
+mod proto {
+    tonic::include_proto!("solver.v1");
+}
+
+use proto::{
+    solver_api_server::{SolverApi, SolverApiServer},
+    CancelRequest, ControlResponse, ExecutorStats, PauseRequest, ResumeRequest, StreamStatsRequest,
+    SubmitReportResponse,
+};
+
+// The gRPC API of proto/solver.proto, over the same state as the HTTP API.
+pub struct GrpcApi {
+    pub stats_map: Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>,
+    pub events: EventBus,
+    pub reports_pool: Arc<Mutex<ReportsPool>>,
+    pub attester: Option<Attester>,
+    pub handover: Arc<Handover>,
+    pub paused: Arc<AtomicBool>,
+    // Limits the reports per client, together with POST /report.
+    pub report_limiter: Option<Arc<RateLimiter>>,
+}
+
+// Marks the requests carrying the admin token, the controls are refused without it.
+#[derive(Clone, Copy)]
+struct Authorized;
+
+#[derive(Clone)]
+struct AdminInterceptor {
+    admin: Option<AdminState>,
+}
+
+impl Interceptor for AdminInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+        if self
+            .admin
+            .as_ref()
+            .is_some_and(|admin| admin.accepts(authorization))
+        {
+            request.extensions_mut().insert(Authorized);
+        }
+        Ok(request)
+    }
+}
+
+// The status of the control request if it lacks the admin token.
+fn unauthorized<T>(request: &Request<T>) -> Option<Status> {
+    match request.extensions().get::<Authorized>() {
+        Some(_) => None,
+        None => Some(Status::unauthenticated("Missing or wrong admin token")),
+    }
+}
+
+impl GrpcApi {
+    fn control(&self, message: String) -> Response<ControlResponse> {
+        println!("{}", message);
+        Response::new(ControlResponse {
+            paused: self.paused.load(Ordering::Relaxed),
+            message,
+        })
+    }
+}
+
+type StatsStream = Pin<Box<dyn Stream<Item = Result<ExecutorStats, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl SolverApi for GrpcApi {
+    type StreamStatsStream = StatsStream;
+
+    async fn stream_stats(
+        &self,
+        request: Request<StreamStatsRequest>,
+    ) -> Result<Response<StatsStream>, Status> {
+        let request = request.into_inner();
+        // Subscribed before reading the current stats, a change in between is sent twice
+        // rather than missed.
+        let mut events_rx = self.events.subscribe();
+        let mut current = match request.changes_only {
+            true => Vec::new(),
+            false => self
+                .stats_map
+                .lock()
+                .await
+                .values()
+                .cloned()
+                .collect::<Vec<_>>(),
+        };
+        current.sort_by_key(|stats| stats.creation_time);
+        let (tx, rx) = mpsc::channel(100);
+        let app = request.app;
+        // Stops at the first change after the client is gone.
+        tokio::spawn(async move {
+            let selected = |stats: &TimerExecutorStats| match &app {
+                Some(app) => stats.app == *app,
+                None => true,
+            };
+            for stats in current.iter().filter(|stats| selected(stats)) {
+                if tx.send(Ok(executor_stats(stats))).await.is_err() {
+                    return;
+                }
+            }
+            while let Some(event) = next_event(&mut events_rx, "gRPC stats").await {
+                if let Event::Stats(stats) = event {
+                    if selected(&stats) && tx.send(Ok(executor_stats(&stats))).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn submit_report(
+        &self,
+        request: Request<proto::Report>,
+    ) -> Result<Response<SubmitReportResponse>, Status> {
+        if let Some(report_limiter) = &self.report_limiter {
+            let forwarded_for = request
+                .metadata()
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok());
+            let address = request.remote_addr().map(|address| address.ip());
+            if let Err(retry_after) = report_limiter.check(forwarded_for, address) {
+                return Err(Status::resource_exhausted(format!(
+                    "Too many requests, retry after {}s",
+                    retry_after.as_secs_f64().ceil()
+                )));
+            }
+        }
+        let report = report(request.into_inner()).map_err(Status::invalid_argument)?;
+        add_report(report, &self.reports_pool, self.attester.as_ref())
+            .await
+            .map_err(|(status, err)| match status {
+                StatusCode::BAD_REQUEST => Status::invalid_argument(err),
+                StatusCode::UNAUTHORIZED => Status::unauthenticated(err),
+                StatusCode::CONFLICT => Status::already_exists(err),
+                _ => Status::internal(err),
+            })?;
+        Ok(Response::new(SubmitReportResponse {}))
+    }
+
+    async fn pause(
+        &self,
+        request: Request<PauseRequest>,
+    ) -> Result<Response<ControlResponse>, Status> {
+        if let Some(status) = unauthorized(&request) {
+            return Err(status);
+        }
+        self.paused.store(true, Ordering::Relaxed);
+        Ok(self.control("The disbursements are paused".to_string()))
+    }
+
+    async fn resume(
+        &self,
+        request: Request<ResumeRequest>,
+    ) -> Result<Response<ControlResponse>, Status> {
+        if let Some(status) = unauthorized(&request) {
+            return Err(status);
+        }
+        self.paused.store(false, Ordering::Relaxed);
+        Ok(self.control("The disbursements are resumed".to_string()))
+    }
+
+    async fn cancel(
+        &self,
+        request: Request<CancelRequest>,
+    ) -> Result<Response<ControlResponse>, Status> {
+        if let Some(status) = unauthorized(&request) {
+            return Err(status);
+        }
+        let request = request.into_inner();
+        let proxy = Address::from_str(&request.proxy).map_err(|err| {
+            Status::invalid_argument(format!("invalid proxy {}: {}", request.proxy, err))
+        })?;
+        let call = match self
+            .handover
+            .cancel(proxy, U256::from(request.sequence_number))
+            .await
+        {
+            Ok(Some(call)) => call,
+            Ok(None) => {
+                return Err(Status::not_found(format!(
+                    "No schedule of the call {} of the proxy {:?} is running",
+                    request.sequence_number, proxy
+                )))
+            }
+            Err(err) => return Err(Status::failed_precondition(err)),
+        };
+        // The executor was stopped before sending its last stats.
+        let stats = self
+            .stats_map
+            .lock()
+            .await
+            .values()
+            .find(|stats| {
+                stats.proxy == Some(proxy)
+                    && U256::from(stats.sequence_number) == call.sequence_number
+                    && stats.status == ExecutorStatus::Running
+            })
+            .cloned();
+        if let Some(mut stats) = stats {
+            stats.status = ExecutorStatus::Cancelled;
+            stats.message = "Cancelled through the gRPC API".to_string();
+            stats.remaining_secs = 0;
+            stats.finished = true;
+            self.events.publish(Event::Stats(stats));
+        }
+        Ok(self.control(format!(
+            "The schedule of the call {} of the proxy {:?} is cancelled",
+            request.sequence_number, proxy
+        )))
+    }
+}
+
+fn executor_stats(stats: &TimerExecutorStats) -> ExecutorStats {
+    ExecutorStats {
+        id: stats.id.to_string(),
+        proxy: stats.proxy.map(|proxy| format!("{:?}", proxy)),
+        sequence_number: stats.sequence_number,
+        app: stats.app.clone(),
+        creation_time: stats.creation_time.as_secs(),
+        status: format!("{:?}", stats.status),
+        transaction_status: format!("{:?}", stats.transaction_status),
+        message: stats.message.clone(),
+        remaining_secs: stats.remaining_secs,
+        target_block: stats.target_block,
+        block_deviation: stats.block_deviation,
+    }
+}
+
+fn report(report: proto::Report) -> Result<Report, String> {
+    let decimal = |name: &str, value: &str| {
+        U256::from_dec_str(value).map_err(|err| format!("invalid {} {}: {}", name, value, err))
+    };
+    Ok(Report {
+        account: Address::from_str(&report.account)
+            .map_err(|err| format!("invalid account {}: {}", report.account, err))?,
+        amount: decimal("amount", &report.amount)?,
+        nonce: report
+            .nonce
+            .map(|nonce| decimal("nonce", &nonce))
+            .transpose()?,
+        signature: report
+            .signature
+            .map(|signature| {
+                Bytes::from_str(&signature)
+                    .map_err(|err| format!("invalid signature {}: {}", signature, err))
+            })
+            .transpose()?,
+        report_id: report.report_id,
+    })
+}
+
+pub async fn run_grpc_server(
+    api: GrpcApi,
+    address: SocketAddr,
+    admin: Option<AdminState>,
+) -> Result<(), String> {
+    println!("Starting the gRPC server at {}", address);
+    Server::builder()
+        .add_service(SolverApiServer::with_interceptor(
+            api,
+            AdminInterceptor { admin },
+        ))
+        .serve(address)
+        .await
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,531 @@
+use axum::{
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use ethers::{
+    providers::Middleware,
+    types::{Address, H256, U256},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    future::Future,
+    io::BufReader,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    sync::{Mutex, RwLock},
+    task::AbortHandle,
+};
+
+use crate::{
+    admin::AdminState,
+    contracts_abi::{CallObject, CallPushedFilter, SolverData},
+    dedup::DedupCache,
+    reports_aggr::{PoolSnapshot, ReportsPool},
+};
+
+// Version of the state snapshot, bumped on incompatible changes.
+pub const STATE_VERSION: u32 = 3;
+
+// The state handed over between the instances of a blue-green deployment.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub version: u32,
+    // Time of the export since Unix epoch.
+    pub exported_at: Duration,
+    // Latest block at the export, the importing instance replays the events from it.
+    pub block_number: u64,
+    // The state of the listener of each watched proxy.
+    pub proxies: Vec<ProxyState>,
+    pub reports_pool: PoolSnapshot,
+}
+
+// The state of the listener of a proxy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProxyState {
+    pub proxy: Address,
+    // Calls of the schedules that were running, with their params resolved.
+    pub schedules: Vec<ScheduledCall>,
+    // Params of the latest schedule, reused by the calls pushed without any that come
+    // from no known schedule.
+    pub last_params: Vec<SolverData>,
+    // Params of all the schedules.
+    pub schedule_params: Vec<ScheduleParams>,
+    // The schedules of the disbursements whose pushed calls may not be received yet.
+    pub disbursements: Vec<(H256, U256)>,
+    // The recently received calls.
+    pub dedup: Vec<CallKey>,
+}
+
+// The params of a schedule, set by the call pushed with them. The disbursements pull the
+// call and push its calls again without params, they get the params of the schedule of
+// the disbursement that pushed them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduleParams {
+    pub sequence_number: U256,
+    pub call_objs: Vec<CallObject>,
+    pub data: Vec<SolverData>,
+}
+
+// A call to pull and the schedule it belongs to, by the sequence number of the call that
+// set the params of the schedule.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduledCall {
+    pub schedule: U256,
+    pub call: CallPushedFilter,
+}
+
+struct ActiveSchedule {
+    scheduled: ScheduledCall,
+    abort: AbortHandle,
+}
+
+// The listener state which outlives an instance. Once handed over, the instance
+// doesn't start schedules or disbursements anymore.
+pub struct Handover {
+    // Disbursements hold it for reading while their transactions are in flight, the
+    // export waits for them and sets it.
+    pub handed_over: Arc<RwLock<bool>>,
+    dedup_ttl: Duration,
+    // The state of the listener of each watched proxy, the sequence numbers are per
+    // proxy.
+    proxies: Mutex<BTreeMap<Address, Arc<ProxyHandover>>>,
+}
+
+impl Handover {
+    pub fn new(dedup_ttl: Duration) -> Handover {
+        Handover {
+            handed_over: Arc::new(RwLock::new(false)),
+            dedup_ttl,
+            proxies: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    // The state of the listener of the proxy, created on first use.
+    pub async fn proxy(&self, proxy: Address) -> Arc<ProxyHandover> {
+        self.proxies
+            .lock()
+            .await
+            .entry(proxy)
+            .or_insert_with(|| {
+                Arc::new(ProxyHandover {
+                    handed_over: self.handed_over.clone(),
+                    active: Mutex::new(HashMap::new()),
+                    dedup: Mutex::new(DedupCache::new(self.dedup_ttl)),
+                    last_params: Mutex::new(Vec::new()),
+                    schedule_params: Mutex::new(BTreeMap::new()),
+                    disbursements: Mutex::new(HashMap::new()),
+                })
+            })
+            .clone()
+    }
+
+    // Imports the state of another instance, the schedules are returned by proxy to be
+    // started.
+    pub async fn import(
+        &self,
+        snapshot: StateSnapshot,
+        reports_pool: &Mutex<ReportsPool>,
+    ) -> ImportedSchedules {
+        if let Err(err) = reports_pool.lock().await.restore(snapshot.reports_pool) {
+            println!("Keeping the local reports pool: {}", err);
+        }
+        let mut schedules = BTreeMap::new();
+        for state in snapshot.proxies {
+            let proxy = state.proxy;
+            schedules.insert(proxy, self.proxy(proxy).await.import(state).await);
+        }
+        schedules
+    }
+
+    // Stops the running schedules once the disbursements in flight are over, to start
+    // them again, e.g. with another wallet. Returns them by proxy.
+    pub async fn stop_schedules(&self) -> Result<ImportedSchedules, String> {
+        let handed_over = self.handed_over.write().await;
+        if *handed_over {
+            return Err("The state is handed over to another instance".to_string());
+        }
+        let mut schedules = BTreeMap::new();
+        for (proxy, state) in self.proxies.lock().await.iter() {
+            schedules.insert(*proxy, state.stop().await);
+        }
+        Ok(schedules)
+    }
+
+    #[cfg(feature = "grpc")]
+    pub async fn cancel(
+        &self,
+        proxy: Address,
+        sequence_number: U256,
+    ) -> Result<Option<CallPushedFilter>, String> {
+        let handed_over = self.handed_over.write().await;
+        if *handed_over {
+            return Err("The state is handed over to another instance".to_string());
+        }
+        let state = match self.proxies.lock().await.get(&proxy) {
+            Some(state) => state.clone(),
+            None => return Ok(None),
+        };
+        Ok(state.cancel(sequence_number).await)
+    }
+}
+
+// The imported schedules by proxy.
+pub type ImportedSchedules = BTreeMap<Address, Vec<ScheduledCall>>;
+
+// Calls by (app, proxy, sequence number).
+pub type CallKey = (String, Address, U256);
+
+// The state of the listener of a proxy.
+pub struct ProxyHandover {
+    handed_over: Arc<RwLock<bool>>,
+    // Running schedules by sequence number.
+    active: Mutex<HashMap<U256, ActiveSchedule>>,
+    pub dedup: Mutex<DedupCache<CallKey>>,
+    pub last_params: Mutex<Vec<SolverData>>,
+    // Params of the schedules by the sequence number of the call that set them.
+    schedule_params: Mutex<BTreeMap<U256, ScheduleParams>>,
+    // The schedules of the sent disbursements by transaction, until the calls they push
+    // again are received.
+    disbursements: Mutex<HashMap<H256, U256>>,
+}
+
+impl ProxyHandover {
+    // Sets the params of the schedule started by the call.
+    pub async fn set_schedule_params(&self, call: &CallPushedFilter) {
+        self.schedule_params.lock().await.insert(
+            call.sequence_number,
+            ScheduleParams {
+                sequence_number: call.sequence_number,
+                call_objs: call.call_objs.clone(),
+                data: call.data.clone(),
+            },
+        );
+        *self.last_params.lock().await = call.data.clone();
+    }
+
+    // Records the schedule of a disbursement before it is sent, so that the calls it
+    // pushes again are told apart from the same calls of the other schedules.
+    pub async fn disbursed(&self, tx_hash: H256, schedule: U256) {
+        self.disbursements.lock().await.insert(tx_hash, schedule);
+    }
+
+    // The schedule of the disbursement which pushed a call, None for the calls pushed
+    // by the users.
+    pub async fn pushed_by(&self, tx_hash: Option<H256>) -> Option<U256> {
+        self.disbursements.lock().await.remove(&tx_hash?)
+    }
+
+    // The params of the schedule, the latest params if it isn't known.
+    pub async fn schedule_params(&self, schedule: Option<U256>) -> Vec<SolverData> {
+        let known = match schedule {
+            Some(schedule) => self
+                .schedule_params
+                .lock()
+                .await
+                .get(&schedule)
+                .map(|schedule| schedule.data.clone()),
+            None => None,
+        };
+        match known {
+            Some(data) => data,
+            None => self.last_params.lock().await.clone(),
+        }
+    }
+
+    pub async fn is_handed_over(&self) -> bool {
+        *self.handed_over.read().await
+    }
+
+    // Tracks a running schedule, spawn is called with the active schedules locked so
+    // that a schedule finishing right away is removed after it was added.
+    pub async fn track<F, T>(&self, scheduled: ScheduledCall, spawn: F)
+    where
+        F: FnOnce() -> T,
+        T: Future<Output = AbortHandle>,
+    {
+        let mut active = self.active.lock().await;
+        let abort = spawn().await;
+        active.insert(
+            scheduled.call.sequence_number,
+            ActiveSchedule { scheduled, abort },
+        );
+    }
+
+    // Whether the schedule is running.
+    pub async fn is_scheduled(&self, schedule: U256) -> bool {
+        self.active
+            .lock()
+            .await
+            .values()
+            .any(|active| active.scheduled.schedule == schedule)
+    }
+
+    // Updates the call of a recurring schedule to the one to pull next.
+    pub async fn rearmed(&self, sequence_number: U256, call: CallPushedFilter) {
+        if let Some(active) = self.active.lock().await.get_mut(&sequence_number) {
+            active.scheduled.call = call;
+        }
+    }
+
+    pub async fn finished(&self, sequence_number: U256) {
+        self.active.lock().await.remove(&sequence_number);
+    }
+
+    #[cfg(feature = "grpc")]
+    async fn cancel(&self, sequence_number: U256) -> Option<CallPushedFilter> {
+        let active = self.active.lock().await.remove(&sequence_number)?;
+        active.abort.abort();
+        Some(active.scheduled.call)
+    }
+
+    async fn import(&self, state: ProxyState) -> Vec<ScheduledCall> {
+        let mut dedup = self.dedup.lock().await;
+        for key in state.dedup {
+            dedup.is_duplicate(key);
+        }
+        *self.last_params.lock().await = state.last_params;
+        *self.schedule_params.lock().await = state
+            .schedule_params
+            .into_iter()
+            .map(|schedule| (schedule.sequence_number, schedule))
+            .collect();
+        *self.disbursements.lock().await = state.disbursements.into_iter().collect();
+        state.schedules
+    }
+
+    // Stops the running schedules, returns the calls they were at.
+    async fn stop(&self) -> Vec<ScheduledCall> {
+        self.active
+            .lock()
+            .await
+            .drain()
+            .map(|(_, active)| {
+                active.abort.abort();
+                active.scheduled
+            })
+            .collect()
+    }
+
+    // Stops the running schedules and returns the state for the next instance.
+    async fn export(&self, proxy: Address) -> ProxyState {
+        ProxyState {
+            proxy,
+            schedules: self.stop().await,
+            last_params: self.last_params.lock().await.clone(),
+            schedule_params: self
+                .schedule_params
+                .lock()
+                .await
+                .values()
+                .cloned()
+                .collect(),
+            disbursements: self
+                .disbursements
+                .lock()
+                .await
+                .iter()
+                .map(|(tx_hash, schedule)| (*tx_hash, *schedule))
+                .collect(),
+            dedup: self.dedup.lock().await.keys(),
+        }
+    }
+}
+
+pub fn read_snapshot(path: &str) -> Result<StateSnapshot, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let snapshot: StateSnapshot =
+        serde_json::from_reader(BufReader::new(file)).map_err(|err| err.to_string())?;
+    if snapshot.version != STATE_VERSION {
+        return Err(format!(
+            "Unsupported state version {}, expected {}",
+            snapshot.version, STATE_VERSION
+        ));
+    }
+    Ok(snapshot)
+}
+
+// Hands the state over to another instance. Waits for the disbursements in flight, then
+// stops the running schedules, so that nothing is disbursed twice. Can be done once, by
+// the holder of the admin token.
+#[utoipa::path(
+    post,
+    path = "/admin/export-state",
+    responses(
+        (status = 200, description = "The state snapshot to import", body = Object),
+        (status = 401, description = "Missing or wrong admin token", body = String),
+        (status = 409, description = "The state was already exported", body = String),
+        (status = 503, description = "The chain can't be reached", body = String),
+    )
+)]
+pub async fn export_state<M: Middleware>(
+    admin: AdminState,
+    headers: HeaderMap,
+    handover: Arc<Handover>,
+    reports_pool: Arc<Mutex<ReportsPool>>,
+    middleware: Arc<M>,
+) -> Result<Json<StateSnapshot>, (StatusCode, String)> {
+    admin.authorize(&headers)?;
+    let mut handed_over = handover.handed_over.write().await;
+    if *handed_over {
+        return Err((
+            StatusCode::CONFLICT,
+            "The state was already exported".to_string(),
+        ));
+    }
+    let block_number = middleware
+        .get_block_number()
+        .await
+        .map_err(|err| (StatusCode::SERVICE_UNAVAILABLE, err.to_string()))?;
+    *handed_over = true;
+    let mut proxies = Vec::new();
+    for (proxy, state) in handover.proxies.lock().await.iter() {
+        proxies.push(state.export(*proxy).await);
+    }
+    let snapshot = StateSnapshot {
+        version: STATE_VERSION,
+        exported_at: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default(),
+        block_number: block_number.as_u64(),
+        proxies,
+        reports_pool: reports_pool.lock().await.snapshot(),
+    };
+    println!(
+        "State exported at block {}, {} schedules stopped",
+        snapshot.block_number,
+        snapshot
+            .proxies
+            .iter()
+            .map(|state| state.schedules.len())
+            .sum::<usize>()
+    );
+    Ok(Json(snapshot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(name: &str, value: &str) -> SolverData {
+        SolverData {
+            name: name.to_string(),
+            value: value.to_string(),
+            datatype: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn pushed_calls_get_the_params_of_their_schedule() {
+        let handover = Handover::new(Duration::from_secs(60))
+            .proxy(Address::repeat_byte(0x11))
+            .await;
+        // Two schedules of the same calls, with their own params.
+        let call_objs = vec![CallObject::default(); 3];
+        for (sequence_number, min_amount) in [(1, "10"), (2, "20")] {
+            handover
+                .set_schedule_params(&CallPushedFilter {
+                    call_objs: call_objs.clone(),
+                    sequence_number: sequence_number.into(),
+                    data: vec![
+                        param("CRON", "0 0 * * * *"),
+                        param("MIN_AMOUNT", min_amount),
+                    ],
+                })
+                .await;
+        }
+        let tx_hash = H256::repeat_byte(0x22);
+        handover.disbursed(tx_hash, 1.into()).await;
+        let schedule = handover.pushed_by(Some(tx_hash)).await;
+        assert_eq!(schedule, Some(1.into()));
+        assert_eq!(handover.schedule_params(schedule).await[1].value, "10");
+        // Received once, and the calls pushed by the users get the latest params.
+        assert_eq!(handover.pushed_by(Some(tx_hash)).await, None);
+        assert_eq!(handover.pushed_by(None).await, None);
+        assert_eq!(handover.schedule_params(None).await[1].value, "20");
+
+        // The schedule is carried through a re-arm and a handover.
+        let scheduled = ScheduledCall {
+            schedule: 1.into(),
+            call: CallPushedFilter {
+                call_objs: call_objs.clone(),
+                sequence_number: 3.into(),
+                data: Vec::new(),
+            },
+        };
+        handover
+            .track(scheduled, || async {
+                tokio::spawn(std::future::pending::<()>()).abort_handle()
+            })
+            .await;
+        assert!(handover.is_scheduled(1.into()).await);
+        assert!(!handover.is_scheduled(2.into()).await);
+        handover
+            .rearmed(
+                3.into(),
+                CallPushedFilter {
+                    call_objs,
+                    sequence_number: 4.into(),
+                    data: Vec::new(),
+                },
+            )
+            .await;
+        handover.disbursed(tx_hash, 1.into()).await;
+        let state = handover.export(Address::repeat_byte(0x11)).await;
+        assert_eq!(state.schedules[0].schedule, 1.into());
+        assert_eq!(state.schedules[0].call.sequence_number, 4.into());
+        let imported = Handover::new(Duration::from_secs(60))
+            .proxy(Address::repeat_byte(0x11))
+            .await;
+        imported.import(state).await;
+        let schedule = imported.pushed_by(Some(tx_hash)).await;
+        assert_eq!(imported.schedule_params(schedule).await[1].value, "10");
+    }
+
+    #[tokio::test]
+    async fn schedules_are_stopped_to_be_started_again() {
+        let handover = Handover::new(Duration::from_secs(60));
+        let proxy = Address::repeat_byte(0x11);
+        let task = tokio::spawn(std::future::pending::<()>());
+        let abort = task.abort_handle();
+        handover
+            .proxy(proxy)
+            .await
+            .track(
+                ScheduledCall {
+                    schedule: 1.into(),
+                    call: CallPushedFilter {
+                        sequence_number: 2.into(),
+                        ..Default::default()
+                    },
+                },
+                || async { abort },
+            )
+            .await;
+        let schedules = handover.stop_schedules().await.ok().unwrap();
+        assert_eq!(schedules[&proxy][0].call.sequence_number, 2.into());
+        assert!(task.await.is_err());
+        assert!(!handover.proxy(proxy).await.is_scheduled(1.into()).await);
+        // Nothing is started again once handed over.
+        *handover.handed_over.write().await = true;
+        assert!(handover.stop_schedules().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn received_calls_are_handed_over_by_app_and_proxy() {
+        let proxy = Address::repeat_byte(0x11);
+        let key = |app: &str| (app.to_string(), proxy, U256::one());
+        let handover = Handover::new(Duration::from_secs(60)).proxy(proxy).await;
+        assert!(!handover.dedup.lock().await.is_duplicate(key("APP")));
+        let state = handover.export(proxy).await;
+        let next = Handover::new(Duration::from_secs(60)).proxy(proxy).await;
+        next.import(state).await;
+        let mut dedup = next.dedup.lock().await;
+        assert!(dedup.is_duplicate(key("APP")));
+        // The same sequence number of another app isn't a duplicate.
+        assert!(!dedup.is_duplicate(key("OTHER")));
+    }
+}
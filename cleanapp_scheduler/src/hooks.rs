@@ -0,0 +1,205 @@
+use std::{process::Stdio, str::FromStr, time::Duration};
+use tokio::{io::AsyncWriteExt, process::Command, sync::mpsc::UnboundedReceiver, time::timeout};
+
+use crate::event_bus::Event;
+
+// How long a hook may run before it is given up.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Points of the executor lifecycle the hooks are fired on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HookPoint {
+    ExecutorStarted,
+    TriggerFired,
+    // The executor finished, whatever its final status.
+    Terminal,
+}
+
+impl HookPoint {
+    fn name(&self) -> &'static str {
+        match self {
+            HookPoint::ExecutorStarted => "executor_started",
+            HookPoint::TriggerFired => "trigger_fired",
+            HookPoint::Terminal => "terminal",
+        }
+    }
+
+    fn of(event: &Event) -> Option<HookPoint> {
+        match event {
+            Event::ExecutorStarted { .. } => Some(HookPoint::ExecutorStarted),
+            Event::TriggerFired { .. } => Some(HookPoint::TriggerFired),
+            Event::Stats(stats) if stats.finished => Some(HookPoint::Terminal),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for HookPoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "executor_started" => Ok(HookPoint::ExecutorStarted),
+            "trigger_fired" => Ok(HookPoint::TriggerFired),
+            "terminal" => Ok(HookPoint::Terminal),
+            _ => Err(format!("unknown hook point \"{}\"", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum HookAction {
+    // Shell command getting the event JSON on its stdin and the hook point in
+    // STXN_HOOK_POINT.
+    Exec(String),
+    // URL the event JSON is posted to, with the hook point in X-Stxn-Hook-Point.
+    Webhook(String),
+}
+
+// A hook as POINT=exec:COMMAND or POINT=webhook:URL.
+#[derive(Clone, Debug)]
+pub struct Hook {
+    pub point: HookPoint,
+    pub action: HookAction,
+}
+
+impl FromStr for Hook {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (point, action) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected POINT=ACTION, got \"{}\"", s))?;
+        let action = match action.split_once(':') {
+            Some(("exec", command)) if !command.is_empty() => HookAction::Exec(command.to_string()),
+            Some(("webhook", url)) if !url.is_empty() => HookAction::Webhook(url.to_string()),
+            _ => {
+                return Err(format!(
+                    "expected exec:COMMAND or webhook:URL, got \"{}\"",
+                    action
+                ))
+            }
+        };
+        Ok(Hook {
+            point: point.parse()?,
+            action,
+        })
+    }
+}
+
+// Fires the hooks matching the events of the bus. Hooks run concurrently and don't hold
+// up the executors, failures are only logged.
+pub async fn run_hooks(mut rx: UnboundedReceiver<Event>, hooks: Vec<Hook>) {
+    // Bounded by the hook timeout as well, so that the requests don't outlive it.
+    let client = match reqwest::Client::builder().timeout(HOOK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(err) => {
+            println!(
+                "Error building the HTTP client, the hooks aren't fired: {}",
+                err
+            );
+            return;
+        }
+    };
+    while let Some(event) = rx.recv().await {
+        let point = match HookPoint::of(&event) {
+            Some(point) => point,
+            None => continue,
+        };
+        let payload = match serde_json::to_string(&event) {
+            Ok(payload) => payload,
+            Err(err) => {
+                println!("Error serializing the hook payload: {}", err);
+                continue;
+            }
+        };
+        for hook in hooks.iter().filter(|hook| hook.point == point) {
+            let action = hook.action.clone();
+            let payload = payload.clone();
+            let client = client.clone();
+            tokio::spawn(async move {
+                let res = match timeout(HOOK_TIMEOUT, fire(&client, &action, point, payload)).await
+                {
+                    Ok(res) => res,
+                    Err(_) => Err("timed out".to_string()),
+                };
+                if let Err(err) = res {
+                    println!("The {} hook {:?} failed: {}", point.name(), action, err);
+                }
+            });
+        }
+    }
+}
+
+async fn fire(
+    client: &reqwest::Client,
+    action: &HookAction,
+    point: HookPoint,
+    payload: String,
+) -> Result<(), String> {
+    match action {
+        HookAction::Exec(command) => {
+            let mut child = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .env("STXN_HOOK_POINT", point.name())
+                .stdin(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(|err| err.to_string())?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin
+                    .write_all(payload.as_bytes())
+                    .await
+                    .map_err(|err| err.to_string())?;
+            }
+            let status = child.wait().await.map_err(|err| err.to_string())?;
+            if !status.success() {
+                return Err(format!("exited with {}", status));
+            }
+            Ok(())
+        }
+        HookAction::Webhook(url) => {
+            let response = client
+                .post(url.as_str())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header("X-Stxn-Hook-Point", point.name())
+                .body(payload)
+                .send()
+                .await
+                .map_err(|err| err.to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("rejected with {}", response.status()));
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{Status, TimerExecutorStats};
+    use ethers::types::{Address, U256};
+
+    #[test]
+    fn terminal_hooks_wait_for_the_executor_to_finish() {
+        let duplicate = TimerExecutorStats::duplicate(
+            Address::zero(),
+            U256::one(),
+            "APP".to_string(),
+            Vec::new(),
+        );
+        // The succeeded stats of a re-armed schedule.
+        let rearmed = TimerExecutorStats {
+            status: Status::Succeeded,
+            finished: false,
+            ..duplicate.clone()
+        };
+        assert_eq!(HookPoint::of(&Event::Stats(rearmed)), None);
+        assert_eq!(
+            HookPoint::of(&Event::Stats(duplicate)),
+            Some(HookPoint::Terminal)
+        );
+    }
+}
@@ -1,50 +1,97 @@
 use ethers::{
     abi::Address,
     providers::{Middleware, StreamExt},
-    types::{BlockNumber, U256},
+    types::{BlockNumber, H256, U256},
 };
 use fatal::fatal;
-use std::{collections::HashMap, sync::Arc, time::Duration};
-use tokio::{
-    sync::{mpsc::Sender, Mutex},
-    task::JoinSet,
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+    time::Duration,
 };
+use tokio::{sync::Mutex, time::sleep};
 
 use crate::{
+    connectivity::{ConnectionState, Connectivity},
     contracts_abi::{CallPushedFilter, LaminatedProxy, SolverData},
+    event_bus::{Event, EventBus},
+    executor_queue::ExecutorQueue,
+    handover::{Handover, ImportedSchedules, ProxyHandover, ScheduledCall},
+    objective_matcher::ObjectiveMatcher,
+    reports_aggr::ReportsPool,
     solver::SolverParams,
-    solvers::cleanapp_scheduler::CleanAppSchedulerSolver,
+    solvers::cleanapp_scheduler::{self, CleanAppSchedulerSolver},
     stats::TimerExecutorStats,
+    supervisor::Supervisor,
     timer_executor::TimerRequestExecutor,
 };
 
+// Delay before subscribing to the events again, doubled with each failed attempt.
+const RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(30);
+// Failed subscriptions in a row before giving up, unless set.
+pub const MAX_RESUBSCRIBE_ATTEMPTS: u32 = 10;
+
+// Execution tick duration unless set.
+const TICK_DURATION: Duration = Duration::from_secs(1);
+
+// The state shared by the listeners of all the proxies.
+pub struct ListenerShared {
+    pub supervisor: Supervisor,
+    pub events: EventBus,
+    pub reports_pool: Arc<Mutex<ReportsPool>>,
+    pub queue: Arc<ExecutorQueue>,
+    pub connectivity: Arc<Mutex<Connectivity>>,
+}
+
+#[derive(Clone)]
 pub struct LaminatorListener<M: Clone> {
-    // The address of the laminator contract.
+    // The address of the laminated proxy.
     laminated_proxy_address: Address,
 
     // KITN disbursement scheduler address.
     kitn_disbursement_scheduler_address: Address,
 
+    // Tells the apps of the pushed calls.
+    matcher: Arc<ObjectiveMatcher>,
+
     // The middleware to be used
     middleware: Arc<M>,
 
     // Mapping of app selectors to solver params.
     solver_params: SolverParams<M>,
 
-    // JoinSet for using for executors spawning.
-    exec_set: Arc<Mutex<JoinSet<()>>>,
+    // The middleware the executors sign with, shared by the listeners of all the proxies
+    // and swapped when the wallet is rotated.
+    signer: Arc<RwLock<Arc<M>>>,
+
+    // Spawns the listeners of the proxies and the executors.
+    supervisor: Supervisor,
 
     // Execution tick duration
     tick_duration: Duration,
 
-    // The channel for sending current stats
-    stats_tx: Sender<TimerExecutorStats>,
+    // The bus for publishing current stats and notifications
+    events: EventBus,
 
     // CleanApp reports pool
-    reports_pool: Arc<Mutex<HashMap<Address, U256>>>,
+    reports_pool: Arc<Mutex<ReportsPool>>,
+
+    // Limits the number of concurrently running executors.
+    queue: Arc<ExecutorQueue>,
+
+    // Recently received calls, the params of the last schedule and the running
+    // schedules of the proxy, handed over to the next instance.
+    handover: Arc<ProxyHandover>,
 
-    // Temporaty stores the cron string from the event
-    params: Vec<SolverData>,
+    // Imported schedules and the block of the export, started before listening.
+    imported: Option<(u64, Vec<ScheduledCall>)>,
+
+    // State of the chain connection.
+    connectivity: Arc<Mutex<Connectivity>>,
+
+    // Failed subscriptions in a row before giving up.
+    max_resubscribe_attempts: u32,
 }
 
 impl<M: Middleware + Clone + 'static> LaminatorListener<M> {
@@ -53,111 +100,363 @@ impl<M: Middleware + Clone + 'static> LaminatorListener<M> {
         kitn_disbursement_scheduler_address: Address,
         middleware: Arc<M>,
         solver_params: SolverParams<M>,
-        exec_set: Arc<Mutex<JoinSet<()>>>,
-        tick_duration: Duration,
-        stats_tx: Sender<TimerExecutorStats>,
-        reports_pool: Arc<Mutex<HashMap<Address, U256>>>,
+        shared: ListenerShared,
+        handover: Arc<ProxyHandover>,
     ) -> LaminatorListener<M> {
         LaminatorListener::<M> {
             laminated_proxy_address,
             kitn_disbursement_scheduler_address,
+            matcher: Arc::new(ObjectiveMatcher::cleanapp(
+                kitn_disbursement_scheduler_address,
+            )),
             middleware,
+            signer: Arc::new(RwLock::new(solver_params.middleware.clone())),
             solver_params,
-            exec_set,
-            tick_duration,
-            stats_tx,
-            reports_pool,
-            params: Vec::new(),
+            supervisor: shared.supervisor,
+            tick_duration: TICK_DURATION,
+            events: shared.events,
+            reports_pool: shared.reports_pool,
+            queue: shared.queue,
+            handover,
+            imported: None,
+            connectivity: shared.connectivity,
+            max_resubscribe_attempts: MAX_RESUBSCRIBE_ATTEMPTS,
         }
     }
 
-    fn is_cleanapp_event(&self, event: &CallPushedFilter) -> bool {
-        if event.call_objs.len() != 3 {
-            return false;
+    pub fn with_matcher(mut self, matcher: Arc<ObjectiveMatcher>) -> Self {
+        self.matcher = matcher;
+        self
+    }
+
+    pub fn with_tick_duration(mut self, tick_duration: Duration) -> Self {
+        self.tick_duration = tick_duration;
+        self
+    }
+
+    pub fn with_max_resubscribe_attempts(mut self, max_resubscribe_attempts: u32) -> Self {
+        self.max_resubscribe_attempts = max_resubscribe_attempts;
+        self
+    }
+
+    // The same listener for another proxy.
+    pub fn for_proxy(
+        &self,
+        laminated_proxy_address: Address,
+        handover: Arc<ProxyHandover>,
+        imported: Option<(u64, Vec<ScheduledCall>)>,
+    ) -> LaminatorListener<M> {
+        LaminatorListener {
+            laminated_proxy_address,
+            handover,
+            imported,
+            ..self.clone()
         }
-        return event.call_objs[0].addr == self.kitn_disbursement_scheduler_address;
     }
 
     pub async fn listen(&mut self) {
         let laminated_proxy_contract =
             LaminatedProxy::new(self.laminated_proxy_address, self.middleware.clone());
+        if let Some((block_number, schedules)) = self.imported.take() {
+            // The imported schedules are in the dedup cache already.
+            for scheduled in schedules {
+                self.start(scheduled).await;
+            }
+            // Calls pushed since the export, the ones seen by the exporting instance
+            // are skipped as duplicates.
+            match laminated_proxy_contract
+                .event::<CallPushedFilter>()
+                .from_block(block_number)
+                .query_with_meta()
+                .await
+            {
+                Ok(calls) => {
+                    println!(
+                        "Replaying {} calls since block {}",
+                        calls.len(),
+                        block_number
+                    );
+                    for (call_pushed, meta) in calls {
+                        self.handle_call(call_pushed, Some(meta.transaction_hash), true)
+                            .await;
+                    }
+                }
+                Err(err) => println!(
+                    "Error reading the calls since block {}: {}",
+                    block_number, err
+                ),
+            }
+        }
         let events = laminated_proxy_contract
             .event::<CallPushedFilter>()
             .from_block(BlockNumber::Latest);
+        let mut attempts = 0;
         loop {
-            match events.stream().await {
+            match events.stream_with_meta().await {
                 Ok(stream) => {
+                    attempts = 0;
+                    self.connectivity
+                        .lock()
+                        .await
+                        .recover(ConnectionState::Reconnecting);
                     let mut stream_take = stream.take(10);
-                    println!("Listening the event CallPushed ...");
-                    while let Some(Ok(mut call_pushed)) = stream_take.next().await {
-                        if !self.is_cleanapp_event(&call_pushed) {
-                            continue;
-                        }
-                        let mut exec_set = self.exec_set.lock().await;
-                        let tick_duration = self.tick_duration.clone();
-                        let stats_tx = self.stats_tx.clone();
-                        let reports_pool = self.reports_pool.clone();
-                        let solver_params = self.solver_params.clone();
-                        let laminated_proxy_address = self.laminated_proxy_address;
-                        let kitn_disbursement_scheduler_address =
-                            self.kitn_disbursement_scheduler_address;
-
-                        let mut cron = String::new();
-                        if !call_pushed.data.is_empty() {
-                            for ad in &call_pushed.data {
-                                match ad.name.as_str() {
-                                    "CRON" => {
-                                        cron = ad.value.clone();
-                                    }
-                                    &_ => {}
-                                }
-                            }
-                            if !cron.is_empty() {
-                                self.params = call_pushed.data.clone();
+                    println!(
+                        "Listening the event CallPushed of the proxy {:?} ...",
+                        self.laminated_proxy_address
+                    );
+                    while let Some(event) = stream_take.next().await {
+                        match event {
+                            Ok((call_pushed, meta)) => {
+                                self.handle_call(call_pushed, Some(meta.transaction_hash), true)
+                                    .await
                             }
-                        } else {
-                            call_pushed.data = self.params.clone();
-                            for ad in &call_pushed.data {
-                                match ad.name.as_str() {
-                                    "CRON" => {
-                                        cron = ad.value.clone();
-                                    }
-                                    &_ => {}
-                                }
+                            Err(err) => {
+                                self.connectivity.lock().await.degrade(err.to_string());
+                                break;
                             }
                         }
-                        if !cron.is_empty() {
-                            exec_set.spawn(async move {
-                                match CleanAppSchedulerSolver::new(
-                                    call_pushed.clone(),
-                                    solver_params,
-                                    laminated_proxy_address,
-                                    kitn_disbursement_scheduler_address,
-                                    reports_pool,
-                                    cron,
-                                ) {
-                                    Ok(clean_app_scheduler_solver) => {
-                                        let executor = TimerRequestExecutor::<
-                                            CleanAppSchedulerSolver<M>,
-                                        >::new(
-                                            clean_app_scheduler_solver,
-                                            tick_duration,
-                                            stats_tx,
-                                        );
-                                        executor.execute(call_pushed).await;
-                                    }
-                                    Err(err) => {
-                                        println!("Error creating the solver: {}", err);
-                                    }
-                                }
-                            });
-                        }
                     }
                 }
                 Err(err) => {
-                    fatal!("Error reading events from stream: {}", err);
+                    attempts += 1;
+                    let mut connectivity = self.connectivity.lock().await;
+                    if attempts > self.max_resubscribe_attempts {
+                        connectivity.transition(ConnectionState::Down, Some(err.to_string()));
+                        fatal!("Error reading events from stream: {}", err);
+                    }
+                    connectivity.transition(ConnectionState::Reconnecting, Some(err.to_string()));
+                    drop(connectivity);
+                    let backoff = RESUBSCRIBE_BACKOFF
+                        .saturating_mul(2u32.saturating_pow(attempts - 1))
+                        .min(MAX_RESUBSCRIBE_BACKOFF);
+                    println!(
+                        "Error reading events from stream, attempt {} of {}, retrying in {:?}: {}",
+                        attempts, self.max_resubscribe_attempts, backoff, err
+                    );
+                    sleep(backoff).await;
                 }
             }
         }
     }
+
+    // Starts the executor of a schedule call, the calls without params pushed again by a
+    // disbursement reuse the params of its schedule. Several schedules run side by side,
+    // each with its own cron and selection of the pool.
+    async fn handle_call(
+        &mut self,
+        mut call_pushed: CallPushedFilter,
+        tx_hash: Option<H256>,
+        check_duplicate: bool,
+    ) {
+        let app = match self.matcher.app(&call_pushed) {
+            Some(app @ cleanapp_scheduler::APP_SELECTOR) => app.to_string(),
+            Some(app) => {
+                println!(
+                    "Skipping call {} of the app {}, not solved here",
+                    call_pushed.sequence_number, app
+                );
+                return;
+            }
+            None => return,
+        };
+        if self.handover.is_handed_over().await {
+            println!(
+                "The state is handed over, skipping call {}",
+                call_pushed.sequence_number
+            );
+            return;
+        }
+        if check_duplicate
+            && self.handover.dedup.lock().await.is_duplicate((
+                app.clone(),
+                self.laminated_proxy_address,
+                call_pushed.sequence_number,
+            ))
+        {
+            println!(
+                "Skipping duplicate call {} of the proxy {:?}",
+                call_pushed.sequence_number, self.laminated_proxy_address
+            );
+            self.events
+                .publish(Event::Stats(TimerExecutorStats::duplicate(
+                    self.laminated_proxy_address,
+                    call_pushed.sequence_number,
+                    app,
+                    call_pushed.data,
+                )));
+            return;
+        }
+        let schedule = if !call_pushed.data.is_empty() {
+            if !cron_of(&call_pushed.data).is_empty() {
+                self.handover.set_schedule_params(&call_pushed).await;
+            }
+            call_pushed.sequence_number
+        } else {
+            let pushed_by = self.handover.pushed_by(tx_hash).await;
+            // A recurring schedule pulls the calls it pushes again on its own.
+            if self.solver_params.recurring {
+                if let Some(pushed_by) = pushed_by {
+                    if self.handover.is_scheduled(pushed_by).await {
+                        return;
+                    }
+                }
+            }
+            call_pushed.data = self.handover.schedule_params(pushed_by).await;
+            pushed_by.unwrap_or(call_pushed.sequence_number)
+        };
+        self.start(ScheduledCall {
+            schedule,
+            call: call_pushed,
+        })
+        .await;
+    }
+
+    // Starts the executor of the call of a schedule, unless the call has no cron.
+    async fn start(&self, scheduled: ScheduledCall) {
+        if cron_of(&scheduled.call.data).is_empty() {
+            return;
+        }
+        let tick_duration = self.tick_duration;
+        let event_bus = self.events.clone();
+        let reports_pool = self.reports_pool.clone();
+        let queue = self.queue.clone();
+        let solver_params = SolverParams {
+            middleware: self.signer.read().unwrap().clone(),
+            ..self.solver_params.clone()
+        };
+        let laminated_proxy_address = self.laminated_proxy_address;
+        let kitn_disbursement_scheduler_address = self.kitn_disbursement_scheduler_address;
+        let handover = self.handover.clone();
+        let sequence_number = scheduled.call.sequence_number;
+        self.handover
+            .track(scheduled.clone(), || {
+                let context = format!(
+                    "schedule {} of the proxy {:?}",
+                    sequence_number, laminated_proxy_address
+                );
+                self.supervisor
+                    .spawn("executor", Some(context), async move {
+                        // Schedules carry no tip, they run in arrival order.
+                        let _permit = queue.acquire(U256::zero()).await;
+                        let call_pushed = scheduled.call.clone();
+                        match CleanAppSchedulerSolver::new(
+                            scheduled,
+                            solver_params,
+                            laminated_proxy_address,
+                            kitn_disbursement_scheduler_address,
+                            reports_pool,
+                            handover.clone(),
+                        ) {
+                            Ok(clean_app_scheduler_solver) => {
+                                let executor =
+                                    TimerRequestExecutor::<CleanAppSchedulerSolver<M>>::new(
+                                        clean_app_scheduler_solver,
+                                        tick_duration,
+                                        event_bus,
+                                    );
+                                executor.execute(call_pushed).await;
+                            }
+                            Err(err) => {
+                                println!("Error creating the solver: {}", err);
+                            }
+                        }
+                        handover.finished(sequence_number).await;
+                    })
+            })
+            .await;
+    }
+}
+
+// The CRON param of a schedule, empty if it has none.
+fn cron_of(data: &[SolverData]) -> String {
+    data.iter()
+        .find(|ad| ad.name == "CRON")
+        .map(|ad| ad.value.clone())
+        .unwrap_or_default()
+}
+
+// Starts a listener for each watched proxy, the proxies of several users can be watched.
+pub struct ProxyListeners<M: Clone> {
+    // Listener of the own proxy, the listeners of the other proxies are made from it.
+    listener: LaminatorListener<M>,
+    handover: Arc<Handover>,
+    // Imported schedules by proxy and the block of the export.
+    imported: Mutex<Option<(u64, ImportedSchedules)>>,
+    watched: Mutex<HashSet<Address>>,
+}
+
+impl<M: Middleware + Clone + 'static> ProxyListeners<M> {
+    pub fn new(
+        listener: LaminatorListener<M>,
+        handover: Arc<Handover>,
+        imported: Option<(u64, ImportedSchedules)>,
+    ) -> ProxyListeners<M> {
+        ProxyListeners {
+            listener,
+            handover,
+            imported: Mutex::new(imported),
+            watched: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // Starts listening to the calls of the proxy, unless already done.
+    pub async fn watch(&self, proxy: Address) {
+        if !self.watched.lock().await.insert(proxy) {
+            return;
+        }
+        let imported = self
+            .imported
+            .lock()
+            .await
+            .as_mut()
+            .map(|(block_number, schedules)| {
+                (*block_number, schedules.remove(&proxy).unwrap_or_default())
+            });
+        // A restarted listener keeps its state, the imported schedules are started once.
+        let listener = Arc::new(Mutex::new(self.listener.for_proxy(
+            proxy,
+            self.handover.proxy(proxy).await,
+            imported,
+        )));
+        println!("Watching the proxy {:?}", proxy);
+        self.listener
+            .supervisor
+            .spawn_critical("listener", Some(format!("{:?}", proxy)), move || {
+                let listener = listener.clone();
+                async move {
+                    listener.lock().await.listen().await;
+                }
+            })
+            .await;
+    }
+
+    // Switches the executors to the wallet signing with the middleware and watches its
+    // proxy. The running schedules are started again with it once their disbursements in
+    // flight are over, from the calls they were at. Returns the number of the schedules.
+    pub async fn rotate(&self, middleware: Arc<M>, proxy: Address) -> Result<usize, String> {
+        *self.listener.signer.write().unwrap() = middleware;
+        let schedules = self.handover.stop_schedules().await?;
+        let mut restarted = 0;
+        for (schedule_proxy, schedules) in schedules {
+            let listener = self.listener.for_proxy(
+                schedule_proxy,
+                self.handover.proxy(schedule_proxy).await,
+                None,
+            );
+            for scheduled in schedules {
+                listener.start(scheduled).await;
+                restarted += 1;
+            }
+        }
+        self.watch(proxy).await;
+        Ok(restarted)
+    }
+
+    // The proxies of the imported state, watched by the previous instance.
+    pub async fn imported_proxies(&self) -> Vec<Address> {
+        match self.imported.lock().await.as_ref() {
+            Some((_, schedules)) => schedules.keys().copied().collect(),
+            None => Vec::new(),
+        }
+    }
 }
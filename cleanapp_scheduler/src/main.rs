@@ -1,39 +1,92 @@
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post, Router},
-    serve,
 };
 use clap::Parser;
 use contracts_abi::Laminator;
 use ethers::{
-    core::types::Address,
+    core::types::{transaction::eip712::EIP712Domain, Address},
     middleware::MiddlewareBuilder,
     providers::{Provider, Ws},
     signers::{LocalWallet, Signer},
-    types::U256,
 };
 use fatal::fatal;
-use reports_aggr::{aggregate_report, get_reports_stats};
+use reports_aggr::{
+    aggregate_report, get_account_reports, get_attestations, get_disbursements,
+    get_expired_reports, get_reports, get_reports_stats, run_pool_expiry, Attester, ReportsPool,
+};
+use signature_scheme::{signature_scheme, SchemeName};
 use solver::SolverParams;
-use std::{collections::HashMap, sync::Arc, time::Duration};
-use tokio::{
-    net::TcpListener,
-    sync::{
-        mpsc::{self, Receiver, Sender},
-        Mutex,
-    },
-    task::JoinSet,
+use solvers::cleanapp_scheduler;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    num::NonZeroUsize,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
 };
+use tokio::sync::Mutex;
 
-use crate::laminator_listener::LaminatorListener;
-use crate::stats::{get_stats_json, run_stats_receive, TimerExecutorStats};
+use crate::admin::{rotate_wallet, AdminState, WalletRotation};
+use crate::config_check::{get_readiness, DeployedConfig};
+use crate::connectivity::{
+    get_healthz, get_metrics, run_connectivity_probe, Connectivity, MetricsState,
+};
+use crate::disbursal_signer::DisbursalSigner;
+use crate::event_bus::{run_event_log, EventBus};
+use crate::executor_queue::ExecutorQueue;
+#[cfg(feature = "grpc")]
+use crate::grpc::{run_grpc_server, GrpcApi};
+use crate::handover::{export_state, read_snapshot, Handover};
+#[cfg(feature = "hooks")]
+use crate::hooks::{run_hooks, Hook};
+use crate::laminator_listener::{
+    LaminatorListener, ListenerShared, ProxyListeners, MAX_RESUBSCRIBE_ATTEMPTS,
+};
+use crate::objective_matcher::ObjectiveMatcher;
+use crate::openapi::{get_openapi_json, API_PREFIX};
+use crate::proxy_discovery::run_proxy_discovery;
+use crate::rate_limit::{rate_limited, RateLimit, RateLimiter};
+use crate::schedule_preview::get_schedule_preview;
+use crate::server::{run_server, RouteTimeout, ServerConfig};
+use crate::stats::{get_stats_json, run_stats_receive, stats_router};
+use crate::stats_retention::{run_stats_gc, StatsRetention};
+use crate::supervisor::{get_task_counts_json, Supervisor, TaskCounts};
 
+mod admin;
+mod call_plan;
+mod config_check;
+mod connectivity;
 mod contracts_abi;
+mod dedup;
+mod disbursal_signer;
+mod display;
 mod encoded_data;
+mod event_bus;
+mod executor_queue;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod handover;
+#[cfg(feature = "hooks")]
+mod hooks;
 mod laminator_listener;
+mod objective_matcher;
+mod openapi;
+mod param_schema;
+mod proxy_discovery;
+mod rate_limit;
 mod reports_aggr;
+mod schedule_preview;
+mod server;
+mod signature_scheme;
+#[cfg(feature = "ledger")]
+mod signer;
 mod solver;
 mod solvers;
 mod stats;
+mod stats_retention;
+mod supervisor;
+mod target_block;
 mod timer_executor;
 
 #[derive(Parser, Debug)]
@@ -41,6 +94,64 @@ pub struct Args {
     #[arg(long, default_value_t = 3030)]
     pub port: u16,
 
+    // Address the API is bound to.
+    #[arg(long, default_value = "0.0.0.0")]
+    pub bind_address: IpAddr,
+
+    // PEM certificate chain and private key of the API, served over HTTPS if given.
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "tls_key")]
+    pub tls_cert: Option<String>,
+
+    #[cfg(feature = "tls")]
+    #[arg(long, requires = "tls_cert")]
+    pub tls_key: Option<String>,
+
+    // Origin allowed to call the API from a browser, "*" for any. Can be repeated, no
+    // CORS headers are sent if not given.
+    #[arg(long)]
+    pub cors_origin: Vec<String>,
+
+    // Max size of the body of POST /report in bytes.
+    #[arg(long, default_value_t = 16384)]
+    pub report_body_limit: usize,
+
+    // Timeout of the API requests.
+    #[arg(long, default_value_t = 30)]
+    pub request_timeout_secs: u64,
+
+    // Timeout of the requests to a path instead, as PATH=SECS, e.g. /report=5. Can be
+    // repeated.
+    #[arg(long)]
+    pub route_timeout: Vec<RouteTimeout>,
+
+    // Requests per client IP to POST /report, as REQUESTS/SECS, e.g. 10/60. The requests
+    // over the limit are answered 429. Not limited if not given.
+    #[arg(long)]
+    pub report_rate_limit: Option<RateLimit>,
+
+    // Requests per client IP to the /stats routes, as REQUESTS/SECS.
+    #[arg(long)]
+    pub stats_rate_limit: Option<RateLimit>,
+
+    // The client IP is the first address of X-Forwarded-For, for a proxy in front of the
+    // API. Only set if the proxy overwrites the header.
+    #[arg(long, default_value_t = false)]
+    pub rate_limit_forwarded_for: bool,
+
+    // Port of the gRPC API of proto/solver.proto: the executor stats stream, the reports
+    // and the pause, resume and cancel controls. Not served if not set. The controls need
+    // the admin token as a bearer token in the authorization metadata, the reports are
+    // limited as POST /report.
+    #[cfg(feature = "grpc")]
+    #[arg(long)]
+    pub grpc_port: Option<u16>,
+
+    // Address the gRPC API is bound to.
+    #[cfg(feature = "grpc")]
+    #[arg(long, default_value = "127.0.0.1")]
+    pub grpc_bind_address: IpAddr,
+
     #[arg(long)]
     pub chain_id: u64,
 
@@ -56,29 +167,217 @@ pub struct Args {
     #[arg(long)]
     pub kitn_disbursement_scheduler_address: Address,
 
+    // The expected owner of the KITN disbursement scheduler, not checked if not set.
+    #[arg(long)]
+    pub kitn_owner_address: Option<Address>,
+
+    // Not needed if the transactions are signed with a Ledger device.
     #[arg(long)]
-    pub cleanapp_wallet_private_key: LocalWallet,
+    pub cleanapp_wallet_private_key: Option<LocalWallet>,
+
+    // Key the disbursal data verified by the KITN disbursement scheduler is signed with,
+    // the CleanApp wallet key if not set.
+    #[arg(long)]
+    pub disbursal_signing_key: Option<LocalWallet>,
+
+    // Sign the transactions with this account of a connected Ledger device, for
+    // high-value disbursements.
+    #[cfg(feature = "ledger")]
+    #[arg(long)]
+    pub ledger_account_index: Option<usize>,
+
+    // How long to wait for a transaction to be signed and sent, e.g. confirmed on
+    // the Ledger device, before giving up on this attempt.
+    #[arg(long, default_value_t = 120)]
+    pub signing_timeout_secs: u64,
+
+    // Maximum number of receivers in one disbursement transaction. Larger pools are
+    // disbursed in sequential transactions, a full batch triggers the disbursement
+    // before the schedule time.
+    #[arg(long, default_value_t = 10)]
+    pub disbursement_batch_size: usize,
 
     #[arg(long, default_value_t = 1)]
     pub tick_secs: u64,
 
     #[arg(long, default_value_t = 0)]
     pub tick_nanos: u32,
+
+    // Maximum number of concurrently running executors, the other schedules wait in
+    // the queue. Unlimited if not set.
+    #[arg(long)]
+    pub max_concurrent_executors: Option<NonZeroUsize>,
+
+    // How long received calls are remembered to skip re-delivered events.
+    #[arg(long, default_value_t = 3600)]
+    pub dedup_ttl_secs: u64,
+
+    // JSON file of the rules routing the pushed calls to the solver apps, e.g.
+    // [{"app": "CLEANAPP.SCHEDULER", "calls": 3, "targets": ["0x..."], "selectors":
+    // ["0x12345678"], "data_keys": ["CRON"]}]. The calls of 3 calls including one to the
+    // KITN disbursement scheduler are the CleanApp schedules if not set.
+    #[arg(long)]
+    pub objective_matchers: Option<String>,
+
+    // Users whose laminated proxies are watched besides the own one. Can be repeated.
+    #[arg(long)]
+    pub watch_proxy_owner: Vec<Address>,
+
+    // Watch every proxy created through the laminator from now on.
+    #[arg(long, default_value_t = false)]
+    pub discover_proxies: bool,
+
+    // Also watch the proxies created since this block when discovering the proxies.
+    #[arg(long)]
+    pub proxy_discovery_from_block: Option<u64>,
+
+    // Failed subscriptions to the proxy events in a row before the solver exits,
+    // retried with a growing delay.
+    #[arg(long, default_value_t = MAX_RESUBSCRIBE_ATTEMPTS)]
+    pub max_resubscribe_attempts: u32,
+
+    // Stats of finished executors are evicted above this number of entries or
+    // after this age, unlimited if not set.
+    #[arg(long)]
+    pub stats_max_entries: Option<usize>,
+
+    #[arg(long)]
+    pub stats_max_age_secs: Option<u64>,
+
+    // File the evicted executor stats are appended to, as JSON lines.
+    #[arg(long)]
+    pub stats_archive: Option<String>,
+
+    // Upper bound of the random delay added to each cron trigger time, spreads the
+    // disbursement transactions of many scheduler instances over several blocks.
+    #[arg(long, default_value_t = 0)]
+    pub max_trigger_jitter_secs: u64,
+
+    // Reports pool entries not disbursed within this time are moved to the expired
+    // ledger, 0 keeps them forever.
+    #[arg(long, default_value_t = 0)]
+    pub report_ttl_secs: u64,
+
+    // File journaling the reports pool, replayed on startup. The pool is kept in
+    // memory only if not set.
+    #[arg(long)]
+    pub reports_journal: Option<String>,
+
+    // How long the report ids are remembered, a report retried with the same id within
+    // it is acknowledged but not added again.
+    #[arg(long, default_value_t = 86400)]
+    pub report_id_ttl_secs: u64,
+
+    // Minimum amount disbursed to an account in wei. The accounts owed less stay in the
+    // pool until their reports add up to it.
+    #[arg(long, default_value_t = 0)]
+    pub min_disbursement_wei: u128,
+
+    // Accept only reports signed by this key of the reporting backend, over
+    // (account, amount, nonce).
+    #[arg(long)]
+    pub report_attester_address: Option<Address>,
+
+    // How the reports are signed, eip191 over abi.encode(account, amount, nonce) or
+    // eip712 typed data ReportAttestation(address account,uint256 amount,uint256 nonce).
+    #[arg(long, default_value = "eip191")]
+    pub report_signature_scheme: SchemeName,
+
+    // EIP-712 domain of the report attestations, on the configured chain with the KITN
+    // disbursement scheduler as the verifying contract.
+    #[arg(long, default_value = "CleanApp")]
+    pub eip712_domain_name: String,
+
+    #[arg(long, default_value = "1")]
+    pub eip712_domain_version: String,
+
+    // Execute at the block estimated for the schedule time, submitting the transaction
+    // one block ahead.
+    #[arg(long, default_value_t = false)]
+    pub target_block_execution: bool,
+
+    // Block time used for the target block estimation, measured on chain if not set.
+    #[arg(long)]
+    pub block_time_millis: Option<u64>,
+
+    // Priority fee of the disbursement transaction in wei.
+    #[arg(long)]
+    pub priority_fee_wei: Option<u128>,
+
+    // Each schedule keeps disbursing at its next time, pulling the call its last
+    // disbursement pushed again. Otherwise the calls pushed again start a new schedule.
+    #[arg(long, default_value_t = false)]
+    pub recurring_schedules: bool,
+
+    // Bearer token of the POST /admin/export-state endpoint and the gRPC controls, which
+    // are refused if not set.
+    #[arg(long)]
+    pub admin_token: Option<String>,
+
+    // State exported by POST /admin/export-state of the previous instance, its
+    // schedules are resumed and the calls pushed since the export are replayed.
+    #[arg(long)]
+    pub import_state: Option<String>,
+
+    // Hook fired on an executor lifecycle point with the event as JSON, as
+    // POINT=exec:COMMAND or POINT=webhook:URL, where POINT is executor_started,
+    // trigger_fired or terminal. Can be repeated.
+    #[cfg(feature = "hooks")]
+    #[arg(long)]
+    pub hook: Vec<Hook>,
 }
 
 #[tokio::main]
 async fn main() {
     // Get args
     let args = Args::parse();
-    let cleanapp_wallet = args
-        .cleanapp_wallet_private_key
-        .with_chain_id(args.chain_id);
+    #[cfg(feature = "ledger")]
+    if let Some(account_index) = args.ledger_account_index {
+        match signer::connect_ledger(account_index, args.chain_id).await {
+            Ok(ledger) => run(args, ledger, None).await,
+            Err(err) => fatal!("Cannot connect to the Ledger device: {}", err),
+        }
+        return;
+    }
+    match args.cleanapp_wallet_private_key.clone() {
+        Some(cleanapp_wallet) => {
+            let chain_id = args.chain_id;
+            run(
+                args,
+                cleanapp_wallet.with_chain_id(chain_id),
+                Some(|wallet| wallet),
+            )
+            .await;
+        }
+        None => fatal!("Missing the parameter cleanapp-wallet-private-key"),
+    }
+}
+
+// The wallet can be rotated if a signer is made from the new keys, i.e. unless it's on a
+// hardware device.
+async fn run<S: Signer + Clone + 'static>(
+    args: Args,
+    cleanapp_wallet: S,
+    rotated_signer: Option<fn(LocalWallet) -> S>,
+) {
     let stats_map = Arc::new(Mutex::new(HashMap::new()));
-    let (stats_tx, mut stats_rx): (Sender<TimerExecutorStats>, Receiver<TimerExecutorStats>) =
-        mpsc::channel(100);
-    let exec_set = Arc::new(Mutex::new(JoinSet::new()));
-    let reports_pool: Arc<Mutex<HashMap<Address, U256>>> =
-        Arc::new(Mutex::new(HashMap::new()));
+    let events = EventBus::new(1000);
+    // Consumers subscribe before anything is published. The stats and the hooks get the
+    // final stats of every executor.
+    let stats_rx = events.subscribe_lossless();
+    let event_log_rx = events.subscribe();
+    #[cfg(feature = "hooks")]
+    let hooks_rx = (!args.hook.is_empty()).then(|| events.subscribe_lossless());
+    let task_counts = Arc::new(Mutex::new(TaskCounts::default()));
+    let supervisor = Supervisor::new(Arc::clone(&task_counts));
+    let reports_pool = match ReportsPool::open(args.reports_journal.clone()) {
+        Ok(reports_pool) => Arc::new(Mutex::new(
+            reports_pool
+                .with_min_disbursement(args.min_disbursement_wei.into())
+                .with_report_id_ttl(Duration::from_secs(args.report_id_ttl_secs)),
+        )),
+        Err(err) => fatal!("Cannot restore the reports pool: {}", err),
+    };
 
     println!(
         "Connecting to the chain with URL {} ...",
@@ -92,13 +391,90 @@ async fn main() {
         );
     }
     println!("Connected successfully!");
+    let cleanapp_provider = cleanapp_provider.ok().unwrap();
+    if let Err(err) = config_check::verify_chain_id(&cleanapp_provider, args.chain_id).await {
+        fatal!("{}", err);
+    }
 
     let cleanapp_wallet_address = cleanapp_wallet.address();
-    let cleanapp_provider = Arc::new(cleanapp_provider.ok().unwrap().with_signer(cleanapp_wallet));
+    // The keys rotated in sign on the same connection.
+    let wallet_signer = rotated_signer.map(|rotated_signer| {
+        let provider = cleanapp_provider.clone();
+        let chain_id = args.chain_id;
+        Arc::new(move |wallet: LocalWallet| {
+            provider
+                .clone()
+                .with_signer(rotated_signer(wallet.with_chain_id(chain_id)))
+        })
+    });
+    let cleanapp_provider = Arc::new(cleanapp_provider.with_signer(cleanapp_wallet));
+
+    // Readiness fails while the configuration doesn't match the deployed contracts.
+    let config_mismatches = config_check::validate(
+        cleanapp_provider.clone(),
+        &DeployedConfig {
+            laminator_address: args.laminator_address,
+            call_breaker_address: args.call_breaker_address,
+            kitn_disbursement_scheduler_address: args.kitn_disbursement_scheduler_address,
+            kitn_owner: args.kitn_owner_address,
+        },
+    )
+    .await;
+    for mismatch in &config_mismatches {
+        println!(
+            "Config mismatch in {}: configured {}, deployed {}",
+            mismatch.field, mismatch.configured, mismatch.deployed
+        );
+    }
+
+    let handover = Arc::new(Handover::new(Duration::from_secs(args.dedup_ttl_secs)));
+    let imported = match &args.import_state {
+        Some(path) => match read_snapshot(path) {
+            Ok(snapshot) => {
+                let block_number = snapshot.block_number;
+                let schedules = handover.import(snapshot, &reports_pool).await;
+                println!(
+                    "Imported the state of block {} with {} schedules of {} proxies",
+                    block_number,
+                    schedules.values().map(Vec::len).sum::<usize>(),
+                    schedules.len()
+                );
+                Some((block_number, schedules))
+            }
+            Err(err) => fatal!("Cannot import the state from {}: {}", path, err),
+        },
+        None => None,
+    };
+
+    let disbursal_signer = match args
+        .disbursal_signing_key
+        .clone()
+        .or(args.cleanapp_wallet_private_key.clone())
+    {
+        Some(key) => Arc::new(DisbursalSigner::new(key)),
+        None => fatal!("Missing the parameter disbursal-signing-key"),
+    };
+    println!(
+        "Signing the disbursal data with the address {:?}",
+        disbursal_signer.address()
+    );
 
+    let paused = Arc::new(AtomicBool::new(false));
     let solver_params = SolverParams {
         call_breaker_address: args.call_breaker_address,
         middleware: cleanapp_provider.clone(),
+        max_trigger_jitter: Duration::from_secs(args.max_trigger_jitter_secs),
+        signing_timeout: Duration::from_secs(args.signing_timeout_secs),
+        disbursal_signer,
+        disbursement_batch_size: args.disbursement_batch_size.max(1),
+        target_block_execution: args.target_block_execution,
+        block_time: args.block_time_millis.map(Duration::from_millis),
+        priority_fee: args.priority_fee_wei.map(|fee| fee.into()),
+        recurring: args.recurring_schedules,
+        events: events.clone(),
+        handed_over: handover.handed_over.clone(),
+        disbursing: Arc::new(Mutex::new(())),
+        paused: paused.clone(),
     };
 
     // Extract laminated proxy address
@@ -115,46 +491,263 @@ async fn main() {
         laminated_proxy_address
     );
 
-    let mut listener = LaminatorListener::new(
+    let matcher = match &args.objective_matchers {
+        Some(path) => match ObjectiveMatcher::read(path) {
+            Ok(matcher) => matcher,
+            Err(err) => fatal!("Cannot read the objective matchers from {}: {}", path, err),
+        },
+        None => ObjectiveMatcher::cleanapp(args.kitn_disbursement_scheduler_address),
+    };
+
+    let mut watched_proxies = Vec::new();
+    for owner in &args.watch_proxy_owner {
+        match laminator_contract.compute_proxy_address(*owner).await {
+            Ok(proxy) => watched_proxies.push(proxy),
+            Err(err) => fatal!(
+                "Cannot get the laminated proxy address of {:?}: {}",
+                owner,
+                err
+            ),
+        }
+    }
+
+    let connectivity = Arc::new(Mutex::new(Connectivity::new()));
+
+    // The listeners of the other proxies are made from the one of the own proxy.
+    let listener = LaminatorListener::new(
         laminated_proxy_address,
         args.kitn_disbursement_scheduler_address,
         cleanapp_provider.clone(),
         solver_params,
-        exec_set.clone(),
-        Duration::new(args.tick_secs, args.tick_nanos),
-        stats_tx.clone(),
-        reports_pool.clone(),
-    );
+        ListenerShared {
+            supervisor: supervisor.clone(),
+            events: events.clone(),
+            reports_pool: reports_pool.clone(),
+            queue: Arc::new(ExecutorQueue::new(
+                args.max_concurrent_executors.map(NonZeroUsize::get),
+            )),
+            connectivity: connectivity.clone(),
+        },
+        handover.proxy(laminated_proxy_address).await,
+    )
+    .with_matcher(Arc::new(matcher))
+    .with_tick_duration(Duration::new(args.tick_secs, args.tick_nanos))
+    .with_max_resubscribe_attempts(args.max_resubscribe_attempts);
+    let listeners = Arc::new(ProxyListeners::new(listener, handover.clone(), imported));
+
+    let attester = args.report_attester_address.map(|address| Attester {
+        address,
+        scheme: signature_scheme(
+            args.report_signature_scheme,
+            EIP712Domain {
+                name: Some(args.eip712_domain_name.clone()),
+                version: Some(args.eip712_domain_version.clone()),
+                chain_id: Some(args.chain_id.into()),
+                verifying_contract: Some(args.kitn_disbursement_scheduler_address),
+                salt: None,
+            },
+        ),
+    });
+
+    let rate_limiter = |limit: Option<RateLimit>| {
+        limit.map(|limit| Arc::new(RateLimiter::new(limit, args.rate_limit_forwarded_for)))
+    };
+    // The reports are limited per client across the HTTP and the gRPC API.
+    let report_limiter = rate_limiter(args.report_rate_limit);
+    let admin = args.admin_token.clone().map(|token| AdminState { token });
+
+    #[cfg(feature = "grpc")]
+    let grpc_api = args.grpc_port.map(|port| {
+        (
+            SocketAddr::new(args.grpc_bind_address, port),
+            GrpcApi {
+                stats_map: Arc::clone(&stats_map),
+                events: events.clone(),
+                reports_pool: Arc::clone(&reports_pool),
+                attester: attester.clone(),
+                handover: Arc::clone(&handover),
+                paused,
+                report_limiter: report_limiter.clone(),
+            },
+        )
+    });
 
     // Axum setup
-    let app = Router::new()
-        .route("/", get(|| async { "Smart Transactions Solver" }))
-        .route("/stats/cleanapp", get(get_stats_json))
-        .with_state(Arc::clone(&stats_map))
+    // The API is served under /api/v1 and, for the existing dashboards, unprefixed.
+    let mut api = Router::new()
+        .route("/ready", get(get_readiness))
+        .with_state(Arc::new(config_mismatches))
+        .route("/healthz", get(get_healthz))
+        .with_state(connectivity.clone())
+        .route("/metrics", get(get_metrics))
+        .with_state(MetricsState {
+            connectivity: connectivity.clone(),
+            tasks: Arc::clone(&task_counts),
+        })
         .route("/reportstats", get(get_reports_stats))
+        .route("/reports", get(get_reports))
+        .route("/reports/:address", get(get_account_reports))
+        .route("/reports/expired", get(get_expired_reports))
+        .route("/reports/attestations", get(get_attestations))
+        .route("/disbursements", get(get_disbursements))
         .with_state(Arc::clone(&reports_pool))
-        .route(
-            "/report",
+        .route("/schedule/preview", get(get_schedule_preview))
+        .merge(rate_limited(
+            Router::new()
+                // Kept for the existing dashboards, the same as /stats/cleanapp_scheduler.
+                .route("/stats/cleanapp", get(get_stats_json))
+                .with_state(Arc::clone(&stats_map))
+                .route("/stats/tasks", get(get_task_counts_json))
+                .with_state(Arc::clone(&task_counts))
+                .merge(stats_router(
+                    vec![cleanapp_scheduler::APP_SELECTOR.to_string()],
+                    Arc::clone(&stats_map),
+                )),
+            rate_limiter(args.stats_rate_limit),
+        ))
+        .merge(rate_limited(
+            Router::new().route(
+                "/report",
+                post({
+                    let shared_state = Arc::clone(&reports_pool);
+                    move |body| aggregate_report(body, shared_state, attester.clone())
+                })
+                .layer(DefaultBodyLimit::max(args.report_body_limit)),
+            ),
+            report_limiter,
+        ));
+    if let Some(admin) = admin.clone() {
+        api = api.route(
+            "/admin/export-state",
             post({
-                let shared_state = Arc::clone(&reports_pool);
-                move |body| aggregate_report(body, shared_state)
+                let admin = admin.clone();
+                let handover = Arc::clone(&handover);
+                let reports_pool = Arc::clone(&reports_pool);
+                let middleware = cleanapp_provider.clone();
+                move |headers| export_state(admin, headers, handover, reports_pool, middleware)
             }),
         );
+        if let Some(signer) = wallet_signer {
+            let rotation = WalletRotation {
+                admin,
+                laminator_address: args.laminator_address,
+                listeners: listeners.clone(),
+                signer,
+            };
+            api = api.route(
+                "/admin/wallet/rotate",
+                post(move |headers, body| rotate_wallet(rotation.clone(), headers, body)),
+            );
+        }
+    }
+    let app = Router::new()
+        .route("/", get(|| async { "Smart Transactions Solver" }))
+        .nest(
+            API_PREFIX,
+            api.clone().route("/openapi.json", get(get_openapi_json)),
+        )
+        .merge(api);
 
-    let tcp_listener = TcpListener::bind(format!("0.0.0.0:{}", args.port))
-        .await
-        .unwrap();
+    let server_config = ServerConfig {
+        address: SocketAddr::new(args.bind_address, args.port),
+        #[cfg(feature = "tls")]
+        tls: args.tls_cert.clone().zip(args.tls_key.clone()),
+        cors_origins: args.cors_origin.clone(),
+        request_timeout: Duration::from_secs(args.request_timeout_secs),
+        route_timeouts: args.route_timeout.clone(),
+    };
     // Start all services
-    println!("Starting server at port {}", args.port);
 
+    if args.discover_proxies {
+        let listeners = listeners.clone();
+        let from_block = args.proxy_discovery_from_block;
+        supervisor
+            .spawn("proxy_discovery", None, async move {
+                run_proxy_discovery(laminator_contract, from_block, listeners).await;
+            })
+            .await;
+    }
     {
-        let mut exec_set = exec_set.lock().await;
-        exec_set.spawn(async move {
-            listener.listen().await;
-        });
-        exec_set.spawn(async move {
-            run_stats_receive(&mut stats_rx, Arc::clone(&stats_map)).await;
-        });
+        let middleware = cleanapp_provider.clone();
+        supervisor
+            .spawn("connectivity_probe", None, async move {
+                run_connectivity_probe(middleware, connectivity).await;
+            })
+            .await;
+    }
+    let stats_retention = StatsRetention {
+        max_entries: args.stats_max_entries,
+        max_age: args.stats_max_age_secs.map(Duration::from_secs),
+        archive_path: args.stats_archive.clone(),
     };
-    serve(tcp_listener, app).await.unwrap();
+    if stats_retention.is_enabled() {
+        let stats_map = Arc::clone(&stats_map);
+        supervisor
+            .spawn("stats_gc", None, async move {
+                run_stats_gc(stats_map, stats_retention).await;
+            })
+            .await;
+    }
+    // The stats receiver is restarted if it panics, the events it didn't read yet are
+    // kept.
+    let stats_rx = Arc::new(Mutex::new(stats_rx));
+    {
+        let stats_map = Arc::clone(&stats_map);
+        supervisor
+            .spawn_critical("stats_receiver", None, move || {
+                let stats_rx = Arc::clone(&stats_rx);
+                let stats_map = Arc::clone(&stats_map);
+                async move {
+                    run_stats_receive(&mut *stats_rx.lock().await, stats_map).await;
+                }
+            })
+            .await;
+    }
+    supervisor
+        .spawn("event_log", None, async move {
+            run_event_log(event_log_rx).await;
+        })
+        .await;
+    #[cfg(feature = "hooks")]
+    if let Some(hooks_rx) = hooks_rx {
+        let hooks = args.hook.clone();
+        supervisor
+            .spawn("hooks", None, async move {
+                run_hooks(hooks_rx, hooks).await;
+            })
+            .await;
+    }
+    #[cfg(feature = "grpc")]
+    if let Some((address, grpc_api)) = grpc_api {
+        supervisor
+            .spawn("grpc_server", None, async move {
+                if let Err(err) = run_grpc_server(grpc_api, address, admin).await {
+                    fatal!("Cannot serve the gRPC API: {}", err);
+                }
+            })
+            .await;
+    }
+    if args.report_ttl_secs > 0 {
+        let reports_pool = Arc::clone(&reports_pool);
+        let ttl = Duration::from_secs(args.report_ttl_secs);
+        let events = events.clone();
+        supervisor
+            .spawn("pool_expiry", None, async move {
+                run_pool_expiry(reports_pool, ttl, events).await;
+            })
+            .await;
+    }
+    listeners.watch(laminated_proxy_address).await;
+    for proxy in watched_proxies {
+        listeners.watch(proxy).await;
+    }
+    // The proxies watched by the previous instance.
+    for proxy in listeners.imported_proxies().await {
+        listeners.watch(proxy).await;
+    }
+    // Finished tasks are collected outside of the set.
+    tokio::spawn(supervisor.run());
+    if let Err(err) = run_server(app, server_config).await {
+        fatal!("Cannot serve the API: {}", err);
+    }
 }
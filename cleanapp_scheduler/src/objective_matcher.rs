@@ -0,0 +1,138 @@
+use ethers::types::{Address, Bytes};
+use serde::Deserialize;
+use std::{fs::File, io::BufReader};
+
+use crate::{contracts_abi::CallPushedFilter, solvers::cleanapp_scheduler};
+
+// Recognizes the objectives of a solver app among the pushed calls. All the criteria
+// set have to match.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MatchRule {
+    pub app: String,
+    // Number of calls of the objective.
+    #[serde(default)]
+    pub calls: Option<usize>,
+    // Contracts which have to be called, in any order.
+    #[serde(default)]
+    pub targets: Vec<Address>,
+    // Function selectors which have to start the callvalue of one of the calls.
+    #[serde(default)]
+    pub selectors: Vec<Bytes>,
+    // Keys of the objective data which have to be set. The calls pushed again by the
+    // disbursements carry no data.
+    #[serde(default)]
+    pub data_keys: Vec<String>,
+}
+
+impl MatchRule {
+    fn matches(&self, call: &CallPushedFilter) -> bool {
+        self.calls
+            .iter()
+            .all(|calls| call.call_objs.len() == *calls)
+            && self.targets.iter().all(|target| {
+                call.call_objs
+                    .iter()
+                    .any(|call_obj| call_obj.addr == *target)
+            })
+            && self.selectors.iter().all(|selector| {
+                call.call_objs
+                    .iter()
+                    .any(|call_obj| call_obj.callvalue.starts_with(selector))
+            })
+            && self
+                .data_keys
+                .iter()
+                .all(|key| call.data.iter().any(|data| data.name == *key))
+    }
+}
+
+// Routes the pushed calls to the solver apps by the first matching rule.
+pub struct ObjectiveMatcher {
+    rules: Vec<MatchRule>,
+}
+
+impl ObjectiveMatcher {
+    // The schedules of the CleanApp solver, three calls including one to the KITN
+    // disbursement scheduler.
+    pub fn cleanapp(kitn_disbursement_scheduler_address: Address) -> ObjectiveMatcher {
+        ObjectiveMatcher {
+            rules: vec![MatchRule {
+                app: cleanapp_scheduler::APP_SELECTOR.to_string(),
+                calls: Some(3),
+                targets: vec![kitn_disbursement_scheduler_address],
+                selectors: Vec::new(),
+                data_keys: Vec::new(),
+            }],
+        }
+    }
+
+    // Reads the rules from a JSON file, a list of rules.
+    pub fn read(path: &str) -> Result<ObjectiveMatcher, String> {
+        let file = File::open(path).map_err(|err| err.to_string())?;
+        let rules: Vec<MatchRule> =
+            serde_json::from_reader(BufReader::new(file)).map_err(|err| err.to_string())?;
+        if rules.is_empty() {
+            return Err("no rule is given".to_string());
+        }
+        Ok(ObjectiveMatcher { rules })
+    }
+
+    // The app of the objective, None if no rule matches.
+    pub fn app(&self, call: &CallPushedFilter) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(call))
+            .map(|rule| rule.app.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contracts_abi::{CallObject, SolverData};
+
+    fn call(targets: &[Address], data: &[&str]) -> CallPushedFilter {
+        CallPushedFilter {
+            call_objs: targets
+                .iter()
+                .map(|target| CallObject {
+                    addr: *target,
+                    callvalue: Bytes::from(vec![0xa9, 0x05, 0x9c, 0xbb, 0x00]),
+                    ..Default::default()
+                })
+                .collect(),
+            sequence_number: 1.into(),
+            data: data
+                .iter()
+                .map(|name| SolverData {
+                    name: name.to_string(),
+                    value: String::new(),
+                    datatype: 0,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn objectives_go_to_the_first_matching_app() {
+        let kitn = Address::repeat_byte(0x11);
+        let other = Address::repeat_byte(0x22);
+        let cleanapp = ObjectiveMatcher::cleanapp(kitn);
+        assert_eq!(
+            cleanapp.app(&call(&[other, kitn, other], &[])),
+            Some(cleanapp_scheduler::APP_SELECTOR)
+        );
+        assert_eq!(cleanapp.app(&call(&[other, other, other], &[])), None);
+        assert_eq!(cleanapp.app(&call(&[kitn, other], &[])), None);
+
+        let rules: Vec<MatchRule> = serde_json::from_value(serde_json::json!([
+            {"app": "TRANSFER", "selectors": ["0xa9059cbb"], "data_keys": ["CRON"]},
+            {"app": "ANY"}
+        ]))
+        .ok()
+        .unwrap();
+        let matcher = ObjectiveMatcher { rules };
+        assert_eq!(matcher.app(&call(&[other], &["CRON"])), Some("TRANSFER"));
+        assert_eq!(matcher.app(&call(&[other], &[])), Some("ANY"));
+    }
+}
@@ -0,0 +1,59 @@
+use axum::response::Json;
+use utoipa::{
+    openapi::{schema::SchemaType, ObjectBuilder, Type},
+    OpenApi,
+};
+
+// Prefix of the versioned API routes, the unprefixed routes are kept for the existing
+// dashboards.
+pub const API_PREFIX: &str = "/api/v1";
+
+// Schema of a std Duration as serialized by serde.
+pub fn duration() -> ObjectBuilder {
+    ObjectBuilder::new()
+        .property(
+            "secs",
+            ObjectBuilder::new().schema_type(SchemaType::Type(Type::Integer)),
+        )
+        .property(
+            "nanos",
+            ObjectBuilder::new().schema_type(SchemaType::Type(Type::Integer)),
+        )
+        .required("secs")
+        .required("nanos")
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "CleanApp solver API"),
+    servers((url = "/api/v1")),
+    paths(
+        crate::admin::rotate_wallet,
+        crate::config_check::get_readiness,
+        crate::connectivity::get_healthz,
+        crate::connectivity::get_metrics,
+        crate::stats::get_stats_json,
+        crate::stats::get_app_stats_json,
+        crate::supervisor::get_task_counts_json,
+        crate::reports_aggr::aggregate_report,
+        crate::reports_aggr::get_reports_stats,
+        crate::reports_aggr::get_reports,
+        crate::reports_aggr::get_account_reports,
+        crate::reports_aggr::get_expired_reports,
+        crate::reports_aggr::get_attestations,
+        crate::reports_aggr::get_disbursements,
+        crate::handover::export_state,
+        crate::schedule_preview::get_schedule_preview,
+        get_openapi_json,
+    )
+)]
+struct ApiDoc;
+
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    responses((status = 200, description = "This document", body = Object))
+)]
+pub async fn get_openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
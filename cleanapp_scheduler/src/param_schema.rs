@@ -0,0 +1,203 @@
+use cron::Schedule;
+use ethers::types::U256;
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use crate::{contracts_abi::SolverData, solver::SolverError};
+
+// Longest duration parsed, parse_duration takes very long on huge numbers.
+const MAX_DURATION_LEN: usize = 32;
+
+// Whether the duration has a number in the exponent notation, e.g. 1e9, which
+// parse_duration may take very long to expand.
+fn has_exponent(value: &str) -> bool {
+    value.as_bytes().windows(2).any(|pair| {
+        (pair[0].is_ascii_digit() || pair[0] == b'.') && pair[1].eq_ignore_ascii_case(&b'e')
+    })
+}
+
+#[derive(Clone, Debug)]
+pub enum ParamType {
+    // A cron expression with the seconds, e.g. "0 0 * * * *".
+    Cron,
+    // A decimal unsigned integer.
+    Uint,
+    // A positive duration, e.g. 7 days.
+    Duration,
+}
+
+impl ParamType {
+    fn parse(&self, value: &str) -> Result<ParamValue, String> {
+        match self {
+            ParamType::Cron => Schedule::from_str(value)
+                .map(|_| ParamValue::Cron(value.to_string()))
+                .map_err(|err| format!("\"{}\" isn't a cron expression: {}", value, err)),
+            ParamType::Uint => U256::from_dec_str(value)
+                .map(ParamValue::Uint)
+                .map_err(|err| format!("\"{}\" isn't an unsigned integer: {:?}", value, err)),
+            ParamType::Duration => {
+                if value.len() > MAX_DURATION_LEN {
+                    return Err(format!(
+                        "durations are up to {} characters long",
+                        MAX_DURATION_LEN
+                    ));
+                }
+                if has_exponent(value) {
+                    return Err("durations can't use the exponent notation".to_string());
+                }
+                match parse_duration::parse(value) {
+                    Ok(duration) if duration.is_zero() => Err("the duration is zero".to_string()),
+                    Ok(duration) => Ok(ParamValue::Duration(duration)),
+                    Err(err) => Err(format!("\"{}\" isn't a duration: {}", value, err)),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ParamValue {
+    Cron(String),
+    Uint(U256),
+    Duration(Duration),
+}
+
+struct ParamSpec {
+    name: &'static str,
+    param_type: ParamType,
+    required: bool,
+}
+
+// The params a schedule expects, the keys not declared are ignored.
+pub struct ParamSchema {
+    params: Vec<ParamSpec>,
+}
+
+impl ParamSchema {
+    pub fn new() -> ParamSchema {
+        ParamSchema { params: Vec::new() }
+    }
+
+    pub fn required(mut self, name: &'static str, param_type: ParamType) -> ParamSchema {
+        self.params.push(ParamSpec {
+            name,
+            param_type,
+            required: true,
+        });
+        self
+    }
+
+    pub fn optional(mut self, name: &'static str, param_type: ParamType) -> ParamSchema {
+        self.params.push(ParamSpec {
+            name,
+            param_type,
+            required: false,
+        });
+        self
+    }
+
+    // Parses the declared params, the problems with all of them are reported at once. A
+    // key given more than once takes the last value.
+    pub fn validate(&self, data: &[SolverData]) -> Result<Params, SolverError> {
+        let mut values = HashMap::new();
+        let mut problems = Vec::new();
+        for spec in &self.params {
+            match data.iter().rev().find(|ad| ad.name == spec.name) {
+                Some(ad) => match spec.param_type.parse(ad.value.as_str()) {
+                    Ok(value) => {
+                        values.insert(spec.name, value);
+                    }
+                    Err(err) => problems.push(format!("{}: {}", spec.name, err)),
+                },
+                None if spec.required => problems.push(format!("{} is missing", spec.name)),
+                None => {}
+            }
+        }
+        if !problems.is_empty() {
+            return Err(SolverError::Param(format!(
+                "Invalid parameters: {}",
+                problems.join("; ")
+            )));
+        }
+        Ok(Params { values })
+    }
+}
+
+// The params that passed the schema.
+pub struct Params {
+    values: HashMap<&'static str, ParamValue>,
+}
+
+impl Params {
+    pub fn cron(&self, name: &str) -> Option<&str> {
+        match self.values.get(name) {
+            Some(ParamValue::Cron(cron)) => Some(cron.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn uint(&self, name: &str) -> Option<U256> {
+        match self.values.get(name) {
+            Some(ParamValue::Uint(uint)) => Some(*uint),
+            _ => None,
+        }
+    }
+
+    pub fn duration(&self, name: &str) -> Option<Duration> {
+        match self.values.get(name) {
+            Some(ParamValue::Duration(duration)) => Some(*duration),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(values: &[(&str, &str)]) -> Vec<SolverData> {
+        values
+            .iter()
+            .map(|(name, value)| SolverData {
+                name: name.to_string(),
+                datatype: 0,
+                value: value.to_string(),
+            })
+            .collect()
+    }
+
+    fn schema() -> ParamSchema {
+        ParamSchema::new()
+            .required("CRON", ParamType::Cron)
+            .optional("MIN_AMOUNT", ParamType::Uint)
+            .optional("MIN_AGE", ParamType::Duration)
+    }
+
+    #[test]
+    fn problems_are_reported_together() {
+        let err = schema()
+            .validate(&data(&[("MIN_AMOUNT", "a lot"), ("MIN_AGE", "1e9 days")]))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Parameter error, \"Invalid parameters: CRON is missing; MIN_AMOUNT: \"a lot\" \
+             isn't an unsigned integer: InvalidCharacter; MIN_AGE: durations can't use the \
+             exponent notation\""
+        );
+    }
+
+    #[test]
+    fn valid_values_are_parsed() {
+        let params = schema()
+            .validate(&data(&[
+                ("CRON", "0 0 * * * *"),
+                ("MIN_AMOUNT", "100"),
+                ("MIN_AMOUNT", "200"),
+            ]))
+            .ok()
+            .unwrap();
+        assert_eq!(params.cron("CRON"), Some("0 0 * * * *"));
+        assert_eq!(params.uint("MIN_AMOUNT"), Some(200.into()));
+        assert_eq!(params.duration("MIN_AGE"), None);
+    }
+}
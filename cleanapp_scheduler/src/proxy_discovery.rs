@@ -0,0 +1,73 @@
+use ethers::{
+    providers::{Middleware, StreamExt},
+    types::BlockNumber,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::time::sleep;
+
+use crate::{
+    contracts_abi::{Laminator, ProxyCreatedFilter},
+    laminator_listener::ProxyListeners,
+};
+
+// Delay before subscribing to the created proxies again.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+// Watches the proxies created through the laminator, the ones created since the given
+// block first if set.
+pub async fn run_proxy_discovery<M: Middleware + Clone + 'static>(
+    laminator: Laminator<M>,
+    from_block: Option<u64>,
+    listeners: Arc<ProxyListeners<M>>,
+) {
+    if let Some(from_block) = from_block {
+        match laminator
+            .event::<ProxyCreatedFilter>()
+            .from_block(from_block)
+            .query()
+            .await
+        {
+            Ok(created) => {
+                println!(
+                    "Discovered {} proxies created since block {}",
+                    created.len(),
+                    from_block
+                );
+                for proxy_created in created {
+                    listeners.watch(proxy_created.proxy_address).await;
+                }
+            }
+            Err(err) => println!(
+                "Error reading the proxies created since block {}: {}",
+                from_block, err
+            ),
+        }
+    }
+    let events = laminator
+        .event::<ProxyCreatedFilter>()
+        .from_block(BlockNumber::Latest);
+    loop {
+        match events.stream().await {
+            Ok(mut stream) => {
+                println!("Listening the event ProxyCreated ...");
+                while let Some(event) = stream.next().await {
+                    match event {
+                        Ok(proxy_created) => {
+                            println!(
+                                "Proxy {:?} created for {:?}",
+                                proxy_created.proxy_address, proxy_created.owner
+                            );
+                            listeners.watch(proxy_created.proxy_address).await;
+                        }
+                        Err(err) => {
+                            println!("Error reading the event ProxyCreated: {}", err);
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(err) => println!("Error subscribing to the event ProxyCreated: {}", err),
+        }
+        sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
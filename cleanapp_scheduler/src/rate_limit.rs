@@ -0,0 +1,175 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// Clients tracked before the buckets refilled to their capacity are dropped.
+const MAX_TRACKED_CLIENTS: usize = 10000;
+
+// Requests allowed per client in a period, passed as REQUESTS/SECS, e.g. 10/60. Up to
+// REQUESTS can be made at once, the allowance refills evenly over the period.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (requests, secs) = match s.split_once('/') {
+            Some(parts) => parts,
+            None => return Err(format!("expected REQUESTS/SECS, got \"{}\"", s)),
+        };
+        let requests: u32 = requests
+            .parse()
+            .map_err(|err| format!("invalid number of requests {}: {}", requests, err))?;
+        let secs: u64 = secs
+            .parse()
+            .map_err(|err| format!("invalid period {}: {}", secs, err))?;
+        if requests == 0 || secs == 0 {
+            return Err(format!("the rate limit {} allows no requests", s));
+        }
+        Ok(RateLimit {
+            requests,
+            period: Duration::from_secs(secs),
+        })
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// Token buckets of the clients by IP address.
+pub struct RateLimiter {
+    limit: RateLimit,
+    // The client is the first address of X-Forwarded-For, set by the proxy in front.
+    forwarded_for: bool,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit, forwarded_for: bool) -> RateLimiter {
+        RateLimiter {
+            limit,
+            forwarded_for,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn refill_rate(&self) -> f64 {
+        self.limit.requests as f64 / self.limit.period.as_secs_f64()
+    }
+
+    // Takes a token of the client, or tells how long until one is available.
+    fn take(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let capacity = self.limit.requests as f64;
+        let rate = self.refill_rate();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate < capacity
+            });
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        bucket.tokens =
+            (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    fn client(&self, request: &Request) -> Option<IpAddr> {
+        self.client_of(
+            request
+                .headers()
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok()),
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| address.ip()),
+        )
+    }
+
+    // The client from the X-Forwarded-For value if it's trusted, from the address of the
+    // connection otherwise.
+    fn client_of(&self, forwarded_for: Option<&str>, address: Option<IpAddr>) -> Option<IpAddr> {
+        if self.forwarded_for {
+            let forwarded = forwarded_for
+                .and_then(|value| value.split(',').next())
+                .and_then(|value| value.trim().parse().ok());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        address
+    }
+
+    // Takes a token of the client of a request outside of the HTTP API, e.g. of the gRPC
+    // API. The clients that can't be told apart aren't limited.
+    #[cfg(feature = "grpc")]
+    pub fn check(
+        &self,
+        forwarded_for: Option<&str>,
+        address: Option<IpAddr>,
+    ) -> Result<(), Duration> {
+        match self.client_of(forwarded_for, address) {
+            Some(client) => self.take(client, Instant::now()),
+            None => Ok(()),
+        }
+    }
+}
+
+async fn limit_requests(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = match limiter.client(&request) {
+        Some(client) => client,
+        None => return next.run(request).await,
+    };
+    match limiter.take(client, Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(
+                header::RETRY_AFTER,
+                retry_after.as_secs_f64().ceil().to_string(),
+            )],
+            "Too many requests",
+        )
+            .into_response(),
+    }
+}
+
+// The routes of the router limited per client, if there is a limiter.
+pub fn rate_limited(router: Router, limiter: Option<Arc<RateLimiter>>) -> Router {
+    match limiter {
+        Some(limiter) => {
+            router.route_layer(middleware::from_fn_with_state(limiter, limit_requests))
+        }
+        None => router,
+    }
+}
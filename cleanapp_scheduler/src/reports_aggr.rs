@@ -1,50 +1,937 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{rename, File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Write},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
-use axum::{extract::State, response::Json};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
 
-use ethers::types::{Address, U256};
+use ethers::types::{Address, Bytes, Signature, H256, U256};
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
+use tokio::{sync::Mutex, time::sleep};
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+use crate::{
+    event_bus::{Event, EventBus},
+    openapi::duration,
+    param_schema::Params,
+    signature_scheme::SignatureScheme,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct Report {
-    account: Address,
-    amount: U256,
+    #[schema(value_type = String)]
+    pub account: Address,
+    #[schema(value_type = String)]
+    pub amount: U256,
+    // Attestation by the reporting backend, required if an attester is configured.
+    #[schema(value_type = Option<String>)]
+    pub nonce: Option<U256>,
+    #[schema(value_type = Option<String>)]
+    pub signature: Option<Bytes>,
+    // Idempotency key of the client, a retried report with the same id isn't added again.
+    pub report_id: Option<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+// A report signed by the reporting backend, kept for audits.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct Attestation {
+    #[schema(value_type = String)]
+    pub account: Address,
+    #[schema(value_type = String)]
+    pub amount: U256,
+    #[schema(value_type = String)]
+    pub nonce: U256,
+    #[schema(value_type = String)]
+    pub signature: Bytes,
+    #[schema(schema_with = duration)]
+    pub time: Duration,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct ReportStats {
     accounts: usize,
+    #[schema(value_type = String)]
     total_amount: U256,
+    expired_accounts: usize,
+    #[schema(value_type = String)]
+    expired_amount: U256,
+    // Accounts owed less than the minimum disbursement, left in the pool.
+    below_minimum_accounts: usize,
+    #[schema(value_type = String)]
+    below_minimum_amount: U256,
+    // Report ids remembered, and the reports acknowledged as duplicates since the start.
+    report_ids: usize,
+    duplicate_reports: u64,
+}
+
+// Amount pending disbursement for an account.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoolEntry {
+    pub amount: U256,
+    // Time since Unix epoch of the oldest report not disbursed yet.
+    pub first_reported: Duration,
+}
+
+// The amount pending for an account, as listed by /reports.
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct AccountReports {
+    #[schema(value_type = String)]
+    pub account: Address,
+    #[schema(value_type = String)]
+    pub amount: U256,
+    #[schema(schema_with = duration)]
+    pub first_reported: Duration,
+    // Owed less than the minimum disbursement, not disbursed yet.
+    pub below_minimum: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReportsQuery {
+    offset: Option<usize>,
+    limit: Option<usize>,
+    // Only the entry of this account.
+    #[param(value_type = Option<String>)]
+    address: Option<Address>,
+}
+
+// A page of the pool entries, ordered by account.
+#[derive(Serialize, ToSchema)]
+pub struct ReportsPage {
+    // Number of entries matching the query.
+    pub total: usize,
+    pub offset: usize,
+    pub entries: Vec<AccountReports>,
+}
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+// An entry that stayed in the pool longer than allowed and was never disbursed.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExpiredEntry {
+    #[schema(value_type = String)]
+    pub account: Address,
+    #[schema(value_type = String)]
+    pub amount: U256,
+    #[schema(schema_with = duration)]
+    pub first_reported: Duration,
+    #[schema(schema_with = duration)]
+    pub expired: Duration,
+}
+
+// The state of the reports pool handed over to another instance.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PoolSnapshot {
+    pub entries: HashMap<Address, PoolEntry>,
+    pub expired: Vec<ExpiredEntry>,
+    pub attestations: Vec<Attestation>,
+    #[serde(default)]
+    pub report_ids: HashMap<String, Duration>,
+    #[serde(default)]
+    pub disbursements: Vec<Disbursement>,
+}
+
+// A confirmed disbursement transaction, kept for accounting.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct Disbursement {
+    #[schema(value_type = String)]
+    pub tx_hash: H256,
+    #[schema(schema_with = duration)]
+    pub time: Duration,
+    #[schema(value_type = Vec<String>)]
+    pub receivers: Vec<Address>,
+    #[schema(value_type = Vec<String>)]
+    pub amounts: Vec<U256>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DisbursementsQuery {
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+// A page of the disbursement history, oldest first.
+#[derive(Serialize, ToSchema)]
+pub struct DisbursementsPage {
+    pub total: usize,
+    pub offset: usize,
+    pub disbursements: Vec<Disbursement>,
+}
+
+// A change of the reports pool, appended to the journal.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "op")]
+enum JournalEntry {
+    Report {
+        account: Address,
+        amount: U256,
+        time: Duration,
+    },
+    Disbursed {
+        account: Address,
+        amount: U256,
+    },
+    Expired(ExpiredEntry),
+    Attested(Attestation),
+    Paid(Disbursement),
+    // A report id received at the given time.
+    Seen {
+        report_id: String,
+        time: Duration,
+    },
+}
+
+// The pool entries a schedule disburses, all of them if no criteria are set. Given in
+// the schedule params as MIN_AMOUNT in wei and MIN_AGE as a duration, e.g. "7 days".
+#[derive(Clone, Debug, Default)]
+pub struct PoolSelection {
+    // Only the accounts owed at least this amount.
+    pub min_amount: Option<U256>,
+    // Only the entries whose oldest report is at least this old.
+    pub min_age: Option<Duration>,
+}
+
+impl PoolSelection {
+    pub fn from_params(params: &Params) -> PoolSelection {
+        PoolSelection {
+            min_amount: params.uint("MIN_AMOUNT"),
+            min_age: params.duration("MIN_AGE"),
+        }
+    }
+
+    fn matches(&self, entry: &PoolEntry, now: Duration) -> bool {
+        self.min_amount
+            .iter()
+            .all(|min_amount| entry.amount >= *min_amount)
+            && self
+                .min_age
+                .iter()
+                .all(|min_age| now.saturating_sub(entry.first_reported) >= *min_age)
+    }
 }
 
+// CleanApp reports pool, the amounts to disburse per account.
+#[derive(Default)]
+pub struct ReportsPool {
+    pub entries: HashMap<Address, PoolEntry>,
+    // Ledger of expired entries, kept for export.
+    pub expired: Vec<ExpiredEntry>,
+    // Attestations of the accepted reports and their nonces, which can't be reused.
+    pub attestations: Vec<Attestation>,
+    // History of the disbursements, oldest first.
+    pub disbursements: Vec<Disbursement>,
+    nonces: HashSet<U256>,
+    // The report ids received within the TTL, and the time they were received at.
+    report_ids: HashMap<String, Duration>,
+    report_id_ttl: Duration,
+    duplicate_reports: u64,
+    // The accounts owed less are never disbursed, their reports accumulate.
+    min_disbursement: U256,
+    // Append-only journal of the pool changes, replayed on startup.
+    journal: Option<File>,
+}
+
+impl ReportsPool {
+    // Restores the pool from the journal at the given path, if any, and keeps appending
+    // to it.
+    pub fn open(journal_path: Option<String>) -> Result<ReportsPool, String> {
+        let mut pool = ReportsPool::default();
+        let journal_path = match journal_path {
+            Some(journal_path) => journal_path,
+            None => return Ok(pool),
+        };
+        match File::open(journal_path.as_str()) {
+            Ok(file) => {
+                for (i, line) in BufReader::new(file).lines().enumerate() {
+                    let line = line.map_err(|err| err.to_string())?;
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let entry: JournalEntry = serde_json::from_str(line.as_str())
+                        .map_err(|err| format!("{}:{}: {}", journal_path, i + 1, err))?;
+                    pool.apply(entry);
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err.to_string()),
+        }
+        println!(
+            "Restored {} reports pool entries from {}",
+            pool.entries.len(),
+            journal_path
+        );
+        pool.compact(journal_path.as_str())?;
+        Ok(pool)
+    }
+
+    pub fn with_min_disbursement(mut self, min_disbursement: U256) -> ReportsPool {
+        self.min_disbursement = min_disbursement;
+        self
+    }
+
+    pub fn with_report_id_ttl(mut self, report_id_ttl: Duration) -> ReportsPool {
+        self.report_id_ttl = report_id_ttl;
+        self
+    }
+
+    // Returns true if the report id was received within the TTL, counting the duplicate.
+    fn is_duplicate(&mut self, report_id: &str) -> bool {
+        let (now, ttl) = (now(), self.report_id_ttl);
+        self.report_ids
+            .retain(|_, time| now.saturating_sub(*time) < ttl);
+        let duplicate = self.report_ids.contains_key(report_id);
+        if duplicate {
+            self.duplicate_reports += 1;
+        }
+        duplicate
+    }
+
+    fn mark_seen(&mut self, report_id: String) -> Result<(), String> {
+        self.record(JournalEntry::Seen {
+            report_id,
+            time: now(),
+        })
+    }
+
+    fn below_minimum(&self, entry: &PoolEntry) -> bool {
+        entry.amount < self.min_disbursement
+    }
+
+    fn account_reports(&self, account: Address) -> Option<AccountReports> {
+        self.entries.get(&account).map(|entry| AccountReports {
+            account,
+            amount: entry.amount,
+            first_reported: entry.first_reported,
+            below_minimum: self.below_minimum(entry),
+        })
+    }
+
+    pub fn add(&mut self, account: Address, amount: U256) -> Result<(), String> {
+        self.record(JournalEntry::Report {
+            account,
+            amount,
+            time: now(),
+        })
+    }
+
+    // Adds a report along with its attestation.
+    pub fn add_attested(&mut self, attestation: Attestation) -> Result<(), String> {
+        let (account, amount) = (attestation.account, attestation.amount);
+        self.record(JournalEntry::Attested(attestation))?;
+        self.add(account, amount)
+    }
+
+    // The (account, amount) of the entries of the selection, owed at least the minimum
+    // disbursement.
+    pub fn selected(&self, selection: &PoolSelection) -> Vec<(Address, U256)> {
+        let now = now();
+        self.entries
+            .iter()
+            .filter(|(_, entry)| !self.below_minimum(entry) && selection.matches(entry, now))
+            .map(|(account, entry)| (*account, entry.amount))
+            .collect()
+    }
+
+    // Removes the amounts included into a successful disbursement, and adds it to the
+    // history.
+    pub fn disburse(&mut self, disbursed: &[(Address, U256)], tx_hash: H256) -> Result<(), String> {
+        for (account, amount) in disbursed {
+            self.record(JournalEntry::Disbursed {
+                account: *account,
+                amount: *amount,
+            })?;
+        }
+        self.record(JournalEntry::Paid(Disbursement {
+            tx_hash,
+            time: now(),
+            receivers: disbursed.iter().map(|(account, _)| *account).collect(),
+            amounts: disbursed.iter().map(|(_, amount)| *amount).collect(),
+        }))
+    }
+
+    // Moves entries older than ttl into the expired ledger, returns their number.
+    pub fn expire(&mut self, ttl: Duration) -> Result<usize, String> {
+        let now = now();
+        let expired: Vec<ExpiredEntry> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| now.saturating_sub(entry.first_reported) > ttl)
+            .map(|(account, entry)| ExpiredEntry {
+                account: *account,
+                amount: entry.amount,
+                first_reported: entry.first_reported,
+                expired: now,
+            })
+            .collect();
+        let count = expired.len();
+        for entry in expired {
+            self.record(JournalEntry::Expired(entry))?;
+        }
+        Ok(count)
+    }
+
+    // The pool as exported for a handover.
+    pub fn snapshot(&self) -> PoolSnapshot {
+        PoolSnapshot {
+            entries: self.entries.clone(),
+            expired: self.expired.clone(),
+            attestations: self.attestations.clone(),
+            report_ids: self.report_ids.clone(),
+            disbursements: self.disbursements.clone(),
+        }
+    }
+
+    // Takes over the pool of another instance, only into an empty pool so that the
+    // entries restored from a shared journal aren't counted twice.
+    pub fn restore(&mut self, snapshot: PoolSnapshot) -> Result<(), String> {
+        if !self.entries.is_empty()
+            || !self.expired.is_empty()
+            || !self.attestations.is_empty()
+            || !self.disbursements.is_empty()
+        {
+            return Err("the reports pool isn't empty".to_string());
+        }
+        for attestation in snapshot.attestations {
+            self.record(JournalEntry::Attested(attestation))?;
+        }
+        for disbursement in snapshot.disbursements {
+            self.record(JournalEntry::Paid(disbursement))?;
+        }
+        for (report_id, time) in snapshot.report_ids {
+            self.record(JournalEntry::Seen { report_id, time })?;
+        }
+        for expired in snapshot.expired {
+            self.record(JournalEntry::Expired(expired))?;
+        }
+        for (account, entry) in snapshot.entries {
+            self.record(JournalEntry::Report {
+                account,
+                amount: entry.amount,
+                time: entry.first_reported,
+            })?;
+        }
+        Ok(())
+    }
+
+    // Persists the change before applying it.
+    fn record(&mut self, entry: JournalEntry) -> Result<(), String> {
+        if let Some(journal) = &mut self.journal {
+            let line = serde_json::to_string(&entry).map_err(|err| err.to_string())?;
+            writeln!(journal, "{}", line).map_err(|err| err.to_string())?;
+        }
+        self.apply(entry);
+        Ok(())
+    }
+
+    fn apply(&mut self, entry: JournalEntry) {
+        match entry {
+            JournalEntry::Report {
+                account,
+                amount,
+                time,
+            } => match self.entries.get_mut(&account) {
+                Some(entry) => {
+                    entry.amount += amount;
+                }
+                None => {
+                    self.entries.insert(
+                        account,
+                        PoolEntry {
+                            amount,
+                            first_reported: time,
+                        },
+                    );
+                }
+            },
+            JournalEntry::Disbursed { account, amount } => {
+                if let Some(entry) = self.entries.get_mut(&account) {
+                    entry.amount = entry.amount.saturating_sub(amount);
+                    if entry.amount.is_zero() {
+                        self.entries.remove(&account);
+                    }
+                }
+            }
+            JournalEntry::Expired(expired) => {
+                self.entries.remove(&expired.account);
+                self.expired.push(expired);
+            }
+            JournalEntry::Attested(attestation) => {
+                self.nonces.insert(attestation.nonce);
+                self.attestations.push(attestation);
+            }
+            JournalEntry::Paid(disbursement) => {
+                self.disbursements.push(disbursement);
+            }
+            JournalEntry::Seen { report_id, time } => {
+                self.report_ids.insert(report_id, time);
+            }
+        }
+    }
+
+    // Rewrites the journal with the current state only, so that it doesn't grow forever.
+    fn compact(&mut self, journal_path: &str) -> Result<(), String> {
+        let tmp_path = format!("{}.tmp", journal_path);
+        {
+            let mut tmp = File::create(tmp_path.as_str()).map_err(|err| err.to_string())?;
+            let mut entries: Vec<JournalEntry> = self
+                .attestations
+                .iter()
+                .map(|attestation| JournalEntry::Attested(attestation.clone()))
+                .collect();
+            entries.extend(
+                self.disbursements
+                    .iter()
+                    .map(|disbursement| JournalEntry::Paid(disbursement.clone())),
+            );
+            entries.extend(
+                self.report_ids
+                    .iter()
+                    .map(|(report_id, time)| JournalEntry::Seen {
+                        report_id: report_id.clone(),
+                        time: *time,
+                    }),
+            );
+            entries.extend(
+                self.expired
+                    .iter()
+                    .map(|expired| JournalEntry::Expired(expired.clone())),
+            );
+            entries.extend(
+                self.entries
+                    .iter()
+                    .map(|(account, entry)| JournalEntry::Report {
+                        account: *account,
+                        amount: entry.amount,
+                        time: entry.first_reported,
+                    }),
+            );
+            for entry in entries {
+                let line = serde_json::to_string(&entry).map_err(|err| err.to_string())?;
+                writeln!(tmp, "{}", line).map_err(|err| err.to_string())?;
+            }
+            tmp.sync_all().map_err(|err| err.to_string())?;
+        }
+        rename(tmp_path.as_str(), journal_path).map_err(|err| err.to_string())?;
+        self.journal = Some(
+            OpenOptions::new()
+                .append(true)
+                .open(journal_path)
+                .map_err(|err| err.to_string())?,
+        );
+        Ok(())
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+// The key of the reporting backend the reports are signed with, and how they are signed.
+#[derive(Clone)]
+pub struct Attester {
+    pub address: Address,
+    pub scheme: Arc<dyn SignatureScheme>,
+}
+
+// Checks the signature of the attester over (account, amount, nonce).
+fn verify_attestation(
+    attester: &Attester,
+    report: &Report,
+) -> Result<Attestation, (StatusCode, String)> {
+    let (nonce, signature) = match (report.nonce, &report.signature) {
+        (Some(nonce), Some(signature)) => (nonce, signature.clone()),
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "missing the report attestation".to_string(),
+            ))
+        }
+    };
+    let hash = attester
+        .scheme
+        .signing_hash(report.account, report.amount, nonce);
+    Signature::try_from(signature.as_ref())
+        .map_err(|err| err.to_string())
+        .and_then(|parsed| {
+            parsed
+                .verify(hash, attester.address)
+                .map_err(|err| err.to_string())
+        })
+        .map_err(|err| (StatusCode::UNAUTHORIZED, err))?;
+    Ok(Attestation {
+        account: report.account,
+        amount: report.amount,
+        nonce,
+        signature,
+        time: now(),
+    })
+}
+
+// A retried report is acknowledged without being added again.
+fn is_duplicate(reports: &mut ReportsPool, report: &Report) -> bool {
+    match &report.report_id {
+        Some(report_id) if reports.is_duplicate(report_id) => {
+            println!("Report {} already received", report_id);
+            true
+        }
+        _ => false,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/report",
+    request_body = Report,
+    responses(
+        (status = 200, description = "Added to the pool, or already received with the same id"),
+        (status = 400, description = "The attestation is missing"),
+        (status = 401, description = "The attestation doesn't verify"),
+        (status = 409, description = "The attestation nonce was used"),
+        (status = 429, description = "Over the rate limit of the client"),
+    )
+)]
 pub async fn aggregate_report(
     Json(body): Json<Report>,
-    reports: Arc<Mutex<HashMap<Address, U256>>>,
-) {
+    reports: Arc<Mutex<ReportsPool>>,
+    attester: Option<Attester>,
+) -> StatusCode {
+    match add_report(body, &reports, attester.as_ref()).await {
+        Ok(()) => StatusCode::OK,
+        Err((status, _)) => status,
+    }
+}
+
+// Adds the report to the pool, for POST /report and the gRPC API. A report already
+// received with the same id is acknowledged.
+pub async fn add_report(
+    body: Report,
+    reports: &Mutex<ReportsPool>,
+    attester: Option<&Attester>,
+) -> Result<(), (StatusCode, String)> {
     println!("Report: {:#?}", body);
     let mut reports = reports.lock().await;
-    match reports.get_mut(&body.account) {
-        Some(amount) => {
-            *amount += body.amount;
-        }
-        None => {
-            reports.insert(body.account, body.amount);
+    let res = match attester {
+        Some(attester) => match verify_attestation(attester, &body) {
+            Ok(_) if is_duplicate(&mut reports, &body) => return Ok(()),
+            Ok(attestation) => {
+                if reports.nonces.contains(&attestation.nonce) {
+                    println!("Report rejected, nonce {} reused", attestation.nonce);
+                    return Err((
+                        StatusCode::CONFLICT,
+                        format!("nonce {} reused", attestation.nonce),
+                    ));
+                }
+                reports.add_attested(attestation)
+            }
+            Err((status, err)) => {
+                println!("Report rejected: {}", err);
+                return Err((status, err));
+            }
+        },
+        None if is_duplicate(&mut reports, &body) => return Ok(()),
+        None => reports.add(body.account, body.amount),
+    };
+    if let Err(err) = res {
+        println!("Error persisting report: {}", err);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, err));
+    }
+    if let Some(report_id) = body.report_id {
+        // The report is added anyway, only a retry after a restart would add it again.
+        if let Err(err) = reports.mark_seen(report_id) {
+            println!("Error persisting report id: {}", err);
         }
     }
-    println!("{:#?}", reports);
+    println!("{:#?}", reports.entries);
+    Ok(())
 }
 
-pub async fn get_reports_stats(
-    reports: State<Arc<Mutex<HashMap<Address, U256>>>>,
-) -> Json<ReportStats> {
+#[utoipa::path(get, path = "/reportstats", responses((status = 200, body = ReportStats)))]
+pub async fn get_reports_stats(reports: State<Arc<Mutex<ReportsPool>>>) -> Json<ReportStats> {
     let reports = reports.lock().await;
     let total = reports
+        .entries
+        .values()
+        .fold(U256::zero(), |acc, v| acc + v.amount);
+    let expired_total = reports
+        .expired
         .iter()
-        .fold(U256::zero(), |acc, v| acc + *v.1);
+        .fold(U256::zero(), |acc, v| acc + v.amount);
+    let below_minimum: Vec<&PoolEntry> = reports
+        .entries
+        .values()
+        .filter(|entry| reports.below_minimum(entry))
+        .collect();
 
     Json(ReportStats {
-        accounts: reports.len(),
+        accounts: reports.entries.len(),
         total_amount: total,
+        expired_accounts: reports.expired.len(),
+        expired_amount: expired_total,
+        below_minimum_accounts: below_minimum.len(),
+        below_minimum_amount: below_minimum
+            .iter()
+            .fold(U256::zero(), |acc, v| acc + v.amount),
+        report_ids: reports.report_ids.len(),
+        duplicate_reports: reports.duplicate_reports,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports",
+    params(ReportsQuery),
+    responses((status = 200, body = ReportsPage))
+)]
+pub async fn get_reports(
+    reports: State<Arc<Mutex<ReportsPool>>>,
+    Query(query): Query<ReportsQuery>,
+) -> Json<ReportsPage> {
+    let reports = reports.lock().await;
+    let mut accounts: Vec<Address> = match query.address {
+        Some(address) => vec![address],
+        None => reports.entries.keys().copied().collect(),
+    };
+    accounts.retain(|account| reports.entries.contains_key(account));
+    accounts.sort();
+    let offset = query.offset.unwrap_or(0);
+    Json(ReportsPage {
+        total: accounts.len(),
+        offset,
+        entries: accounts
+            .into_iter()
+            .skip(offset)
+            .take(query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE))
+            .filter_map(|account| reports.account_reports(account))
+            .collect(),
     })
 }
+
+#[utoipa::path(
+    get,
+    path = "/reports/{address}",
+    params(("address" = String, Path, description = "Account address")),
+    responses(
+        (status = 200, body = AccountReports),
+        (status = 404, description = "No pending amount for the account", body = String),
+    )
+)]
+pub async fn get_account_reports(
+    reports: State<Arc<Mutex<ReportsPool>>>,
+    Path(account): Path<Address>,
+) -> Result<Json<AccountReports>, (StatusCode, String)> {
+    reports
+        .lock()
+        .await
+        .account_reports(account)
+        .map(Json)
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            format!("No pending amount for {:?}", account),
+        ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/disbursements",
+    params(DisbursementsQuery),
+    responses((status = 200, body = DisbursementsPage))
+)]
+pub async fn get_disbursements(
+    reports: State<Arc<Mutex<ReportsPool>>>,
+    Query(query): Query<DisbursementsQuery>,
+) -> Json<DisbursementsPage> {
+    let reports = reports.lock().await;
+    let offset = query.offset.unwrap_or(0);
+    Json(DisbursementsPage {
+        total: reports.disbursements.len(),
+        offset,
+        disbursements: reports
+            .disbursements
+            .iter()
+            .skip(offset)
+            .take(query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE))
+            .cloned()
+            .collect(),
+    })
+}
+
+#[utoipa::path(get, path = "/reports/expired", responses((status = 200, body = Vec<ExpiredEntry>)))]
+pub async fn get_expired_reports(
+    reports: State<Arc<Mutex<ReportsPool>>>,
+) -> Json<Vec<ExpiredEntry>> {
+    let reports = reports.lock().await;
+    Json(reports.expired.clone())
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/attestations",
+    responses((status = 200, body = Vec<Attestation>))
+)]
+pub async fn get_attestations(reports: State<Arc<Mutex<ReportsPool>>>) -> Json<Vec<Attestation>> {
+    let reports = reports.lock().await;
+    Json(reports.attestations.clone())
+}
+
+// Periodically expires entries older than ttl.
+pub async fn run_pool_expiry(reports: Arc<Mutex<ReportsPool>>, ttl: Duration, events: EventBus) {
+    // Check often enough for the entries not to overstay much.
+    let interval = (ttl / 10).clamp(Duration::from_secs(1), Duration::from_secs(60));
+    loop {
+        sleep(interval).await;
+        let mut reports = reports.lock().await;
+        let message = match reports.expire(ttl) {
+            Ok(0) => continue,
+            Ok(expired) => format!("Expired {} reports pool entries", expired),
+            Err(err) => format!("Error persisting expired entries: {}", err),
+        };
+        println!("{}", message);
+        events.publish(Event::PoolWarning { message });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{contracts_abi::SolverData, solvers::cleanapp_scheduler::schedule_schema};
+
+    fn param(name: &str, value: &str) -> SolverData {
+        SolverData {
+            name: name.to_string(),
+            value: value.to_string(),
+            datatype: 0,
+        }
+    }
+
+    fn report(account: Address, amount: u64, report_id: Option<&str>) -> Report {
+        Report {
+            account,
+            amount: amount.into(),
+            nonce: None,
+            signature: None,
+            report_id: report_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn schedules_select_by_amount_and_age() {
+        let params = schedule_schema()
+            .validate(&[
+                param("CRON", "0 0 * * * *"),
+                param("MIN_AMOUNT", "100"),
+                param("MIN_AGE", "1 day"),
+            ])
+            .ok()
+            .unwrap();
+        let selection = PoolSelection::from_params(&params);
+        assert_eq!(selection.min_amount, Some(100.into()));
+        assert_eq!(selection.min_age, Some(Duration::from_secs(86400)));
+
+        let mut pool = ReportsPool::default();
+        let day = Duration::from_secs(86400);
+        for (byte, amount, age) in [(1, 100, day * 2), (2, 50, day * 2), (3, 100, day / 2)] {
+            pool.apply(JournalEntry::Report {
+                account: Address::repeat_byte(byte),
+                amount: amount.into(),
+                time: now() - age,
+            });
+        }
+        assert_eq!(
+            pool.selected(&selection),
+            vec![(Address::repeat_byte(1), 100.into())]
+        );
+        assert_eq!(pool.selected(&PoolSelection::default()).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn accounts_below_the_minimum_disbursement_wait() {
+        let pool = ReportsPool::default().with_min_disbursement(100.into());
+        let reports = Arc::new(Mutex::new(pool));
+        for (byte, amount) in [(1, 100), (2, 99)] {
+            add_report(
+                report(Address::repeat_byte(byte), amount, None),
+                &reports,
+                None,
+            )
+            .await
+            .ok()
+            .unwrap();
+        }
+        let selected = reports.lock().await.selected(&PoolSelection::default());
+        assert_eq!(selected, vec![(Address::repeat_byte(1), 100.into())]);
+        let Json(page) = get_reports(
+            State(reports.clone()),
+            Query(ReportsQuery {
+                offset: None,
+                limit: None,
+                address: Some(Address::repeat_byte(2)),
+            }),
+        )
+        .await;
+        assert!(page.entries[0].below_minimum);
+    }
+
+    #[tokio::test]
+    async fn retried_reports_are_added_once() {
+        let pool = ReportsPool::default().with_report_id_ttl(Duration::from_secs(60));
+        let reports = Arc::new(Mutex::new(pool));
+        let account = Address::repeat_byte(1);
+        for report_id in [Some("a"), Some("a"), Some("b"), None, None] {
+            add_report(report(account, 10, report_id), &reports, None)
+                .await
+                .ok()
+                .unwrap();
+        }
+        let reports = reports.lock().await;
+        assert_eq!(reports.entries[&account].amount, 40.into());
+        assert_eq!(reports.duplicate_reports, 1);
+    }
+
+    #[tokio::test]
+    async fn reports_are_paged_by_account() {
+        let mut pool = ReportsPool::default();
+        for byte in (1..=5).rev() {
+            pool.add(Address::repeat_byte(byte), 10.into())
+                .ok()
+                .unwrap();
+        }
+        let reports = Arc::new(Mutex::new(pool));
+        let page = |offset, limit| {
+            get_reports(
+                State(reports.clone()),
+                Query(ReportsQuery {
+                    offset,
+                    limit,
+                    address: None,
+                }),
+            )
+        };
+        let Json(first) = page(None, Some(2)).await;
+        assert_eq!(first.total, 5);
+        assert_eq!(
+            first
+                .entries
+                .iter()
+                .map(|entry| entry.account)
+                .collect::<Vec<_>>(),
+            vec![Address::repeat_byte(1), Address::repeat_byte(2)]
+        );
+        let Json(last) = page(Some(4), Some(2)).await;
+        assert_eq!(last.offset, 4);
+        assert_eq!(last.entries.len(), 1);
+        assert_eq!(last.entries[0].account, Address::repeat_byte(5));
+        let Json(all) = page(None, None).await;
+        assert_eq!(all.entries.len(), 5);
+    }
+}
@@ -0,0 +1,56 @@
+use axum::{extract::Query, http::StatusCode, response::Json};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::solvers::cleanapp_scheduler::parse_schedule;
+
+const DEFAULT_COUNT: usize = 5;
+const MAX_COUNT: usize = 100;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PreviewQuery {
+    cron: String,
+    // Number of trigger times to return.
+    count: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SchedulePreview {
+    pub cron: String,
+    // The next cron times in UTC as RFC 3339, the solver adds its random delay to each one.
+    pub trigger_times: Vec<String>,
+}
+
+// Validates a CRON parameter the way the solver does before it is pushed on chain.
+#[utoipa::path(
+    get,
+    path = "/schedule/preview",
+    params(PreviewQuery),
+    responses(
+        (status = 200, body = SchedulePreview),
+        (status = 400, description = "Invalid schedule", body = String),
+    )
+)]
+pub async fn get_schedule_preview(
+    Query(query): Query<PreviewQuery>,
+) -> Result<Json<SchedulePreview>, (StatusCode, String)> {
+    let schedule =
+        parse_schedule(&query.cron).map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    let trigger_times: Vec<String> = schedule
+        .upcoming(Utc)
+        .take(query.count.unwrap_or(DEFAULT_COUNT).min(MAX_COUNT))
+        .map(|trigger_time| trigger_time.to_rfc3339())
+        .collect();
+    if trigger_times.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The schedule has no upcoming time, the solver won't run".to_string(),
+        ));
+    }
+    Ok(Json(SchedulePreview {
+        cron: query.cron,
+        trigger_times,
+    }))
+}
@@ -0,0 +1,167 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    serve, Router,
+};
+use std::{net::SocketAddr, str::FromStr, sync::Arc, time::Duration};
+use tokio::net::TcpListener;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+use crate::openapi::API_PREFIX;
+
+// Timeout of the requests to a path, passed as PATH=SECS. The longest path the request
+// starts with applies, e.g. /reports=5 to the reports of all accounts, with or without
+// the API prefix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteTimeout {
+    pub path: String,
+    pub timeout: Duration,
+}
+
+impl FromStr for RouteTimeout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, secs) = match s.split_once('=') {
+            Some((path, secs)) if path.starts_with('/') => (path, secs),
+            _ => return Err(format!("expected PATH=SECS, got \"{}\"", s)),
+        };
+        let secs = secs
+            .parse()
+            .map_err(|err| format!("invalid timeout {}: {}", secs, err))?;
+        Ok(RouteTimeout {
+            path: path.to_string(),
+            timeout: Duration::from_secs(secs),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Timeouts {
+    default: Duration,
+    routes: Vec<RouteTimeout>,
+}
+
+impl Timeouts {
+    fn of(&self, path: &str) -> Duration {
+        let path = match path.strip_prefix(API_PREFIX) {
+            Some(unprefixed) if unprefixed.starts_with('/') => unprefixed,
+            _ => path,
+        };
+        self.routes
+            .iter()
+            .filter(|route| path.starts_with(route.path.as_str()))
+            .max_by_key(|route| route.path.len())
+            .map(|route| route.timeout)
+            .unwrap_or(self.default)
+    }
+}
+
+async fn timeout_requests(
+    State(timeouts): State<Arc<Timeouts>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let timeout = timeouts.of(request.uri().path());
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => (StatusCode::REQUEST_TIMEOUT, "Request timed out").into_response(),
+    }
+}
+
+// The origins allowed to call the API from a browser, "*" for any. No CORS headers are
+// sent if there are none.
+fn cors_layer(origins: &[String]) -> Result<Option<CorsLayer>, String> {
+    if origins.is_empty() {
+        return Ok(None);
+    }
+    let allow_origin = match origins.iter().any(|origin| origin == "*") {
+        true => AllowOrigin::from(Any),
+        false => AllowOrigin::list(
+            origins
+                .iter()
+                .map(|origin| {
+                    HeaderValue::from_str(origin)
+                        .map_err(|err| format!("invalid CORS origin {}: {}", origin, err))
+                })
+                .collect::<Result<Vec<HeaderValue>, String>>()?,
+        ),
+    };
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(Any)
+            .allow_headers(Any),
+    ))
+}
+
+// How the API is exposed.
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    pub address: SocketAddr,
+    // PEM certificate chain and private key, the API is served over HTTPS if given.
+    #[cfg(feature = "tls")]
+    pub tls: Option<(String, String)>,
+    pub cors_origins: Vec<String>,
+    pub request_timeout: Duration,
+    pub route_timeouts: Vec<RouteTimeout>,
+}
+
+// Serves the app with the CORS policy and the request timeouts of the config.
+pub async fn run_server(app: Router, config: ServerConfig) -> Result<(), String> {
+    let timeouts = Arc::new(Timeouts {
+        default: config.request_timeout,
+        routes: config.route_timeouts.clone(),
+    });
+    let mut app = app.layer(middleware::from_fn_with_state(timeouts, timeout_requests));
+    if let Some(cors) = cors_layer(&config.cors_origins)? {
+        app = app.layer(cors);
+    }
+    #[cfg(feature = "tls")]
+    if let Some((cert_path, key_path)) = &config.tls {
+        let tls_config = axum_server::tls_rustls::RustlsConfig::from_pem_file(cert_path, key_path)
+            .await
+            .map_err(|err| format!("Error reading the TLS certificate and key: {}", err))?;
+        println!("Starting server at {} over TLS", config.address);
+        return axum_server::bind_rustls(config.address, tls_config)
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .map_err(|err| err.to_string());
+    }
+    let tcp_listener = TcpListener::bind(config.address)
+        .await
+        .map_err(|err| format!("Error binding {}: {}", config.address, err))?;
+    println!("Starting server at {}", config.address);
+    serve(
+        tcp_listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixed_paths_get_the_timeout_of_their_route() {
+        let timeouts = Timeouts {
+            default: Duration::from_secs(30),
+            routes: vec![
+                RouteTimeout::from_str("/reports=5").ok().unwrap(),
+                RouteTimeout::from_str("/reports/expired=10").ok().unwrap(),
+            ],
+        };
+        assert_eq!(timeouts.of("/reports/0x11"), Duration::from_secs(5));
+        assert_eq!(timeouts.of("/api/v1/reports/0x11"), Duration::from_secs(5));
+        assert_eq!(
+            timeouts.of("/api/v1/reports/expired"),
+            Duration::from_secs(10)
+        );
+        assert_eq!(timeouts.of("/api/v1"), Duration::from_secs(30));
+        assert_eq!(timeouts.of("/report"), Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,138 @@
+use ethers::{
+    abi::{encode, Token},
+    types::{transaction::eip712::EIP712Domain, Address, H256, U256},
+    utils::{hash_message, keccak256},
+};
+use std::{str::FromStr, sync::Arc};
+
+// Type of the report attestation in the EIP-712 scheme.
+const REPORT_ATTESTATION_TYPE: &str =
+    "ReportAttestation(address account,uint256 amount,uint256 nonce)";
+
+// How the report attestations are signed, selected per deployment.
+pub trait SignatureScheme: Send + Sync {
+    // The hash the attester's signature is over.
+    fn signing_hash(&self, account: Address, amount: U256, nonce: U256) -> H256;
+}
+
+// Legacy scheme, keccak256(abi.encode(account, amount, nonce)) signed as an Ethereum
+// message.
+pub struct Eip191;
+
+impl SignatureScheme for Eip191 {
+    fn signing_hash(&self, account: Address, amount: U256, nonce: U256) -> H256 {
+        hash_message(keccak256(encode(&[
+            Token::Address(account),
+            Token::Uint(amount),
+            Token::Uint(nonce),
+        ])))
+    }
+}
+
+// Typed data ReportAttestation signed under the domain of the deployment.
+pub struct Eip712 {
+    domain_separator: [u8; 32],
+}
+
+impl Eip712 {
+    pub fn new(domain: EIP712Domain) -> Eip712 {
+        Eip712 {
+            domain_separator: domain.separator(),
+        }
+    }
+}
+
+// The EIP-712 hash of the ReportAttestation.
+fn struct_hash(account: Address, amount: U256, nonce: U256) -> [u8; 32] {
+    keccak256(encode(&[
+        Token::FixedBytes(keccak256(REPORT_ATTESTATION_TYPE).to_vec()),
+        Token::Address(account),
+        Token::Uint(amount),
+        Token::Uint(nonce),
+    ]))
+}
+
+impl SignatureScheme for Eip712 {
+    fn signing_hash(&self, account: Address, amount: U256, nonce: U256) -> H256 {
+        let struct_hash = struct_hash(account, amount, nonce);
+        H256(keccak256(
+            [
+                &[0x19, 0x01][..],
+                &self.domain_separator[..],
+                &struct_hash[..],
+            ]
+            .concat(),
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SchemeName {
+    Eip191,
+    Eip712,
+}
+
+impl FromStr for SchemeName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "eip191" => Ok(SchemeName::Eip191),
+            "eip712" => Ok(SchemeName::Eip712),
+            _ => Err(format!("unknown signature scheme \"{}\"", s)),
+        }
+    }
+}
+
+// The scheme by its name, the domain is used by EIP-712 only.
+pub fn signature_scheme(name: SchemeName, domain: EIP712Domain) -> Arc<dyn SignatureScheme> {
+    match name {
+        SchemeName::Eip191 => Arc::new(Eip191),
+        SchemeName::Eip712 => Arc::new(Eip712::new(domain)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The domain of the example of the EIP-712 spec.
+    fn domain() -> EIP712Domain {
+        EIP712Domain {
+            name: Some("Ether Mail".to_string()),
+            version: Some("1".to_string()),
+            chain_id: Some(1.into()),
+            verifying_contract: Address::from_str("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC")
+                .ok(),
+            salt: None,
+        }
+    }
+
+    fn hash(hex: &str) -> H256 {
+        H256::from_str(hex).ok().unwrap()
+    }
+
+    // The separator is the one given in the spec, the hashes of the attestation are the
+    // ones of ethers' TypedData for the same domain and message.
+    #[test]
+    fn eip712_hashes_match_the_known_answers() {
+        let scheme = Eip712::new(domain());
+        assert_eq!(
+            H256(scheme.domain_separator),
+            hash("0xf2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+        let account = Address::from_str("0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB")
+            .ok()
+            .unwrap();
+        let amount = U256::exp10(18);
+        let nonce = U256::from(7);
+        assert_eq!(
+            H256(struct_hash(account, amount, nonce)),
+            hash("0x1f2d243c09ac9d00bb7c874b53060a07bb3da4508d04cadb46b84bd93769a7ad")
+        );
+        assert_eq!(
+            scheme.signing_hash(account, amount, nonce),
+            hash("0x37cae127b84ed93593e3bb459490f320944a77a1495eb468b4fbfae7595d7b06")
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use ethers::{
+    signers::{HDPath, Ledger, Signer},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip712::Eip712},
+        Address, Signature,
+    },
+};
+use std::sync::Arc;
+
+// Makes a signer that cannot be cloned, like a hardware wallet, shareable between the
+// executors.
+#[derive(Debug)]
+pub struct SharedSigner<S>(Arc<S>);
+
+impl<S> Clone for SharedSigner<S> {
+    fn clone(&self) -> Self {
+        SharedSigner(self.0.clone())
+    }
+}
+
+#[async_trait]
+impl<S: Signer> Signer for SharedSigner<S> {
+    type Error = S::Error;
+
+    async fn sign_message<T: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: T,
+    ) -> Result<Signature, Self::Error> {
+        self.0.sign_message(message).await
+    }
+
+    async fn sign_transaction(&self, message: &TypedTransaction) -> Result<Signature, Self::Error> {
+        self.0.sign_transaction(message).await
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        self.0.sign_typed_data(payload).await
+    }
+
+    fn address(&self) -> Address {
+        self.0.address()
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.0.chain_id()
+    }
+
+    // The chain ID is set when the device is connected, it can only be changed while
+    // the signer isn't shared yet.
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match Arc::try_unwrap(self.0) {
+            Ok(signer) => SharedSigner(Arc::new(signer.with_chain_id(chain_id))),
+            Err(shared) => SharedSigner(shared),
+        }
+    }
+}
+
+// Connects to the Ethereum app of a Ledger device, every transaction is to be confirmed
+// on the device.
+pub async fn connect_ledger(
+    account_index: usize,
+    chain_id: u64,
+) -> Result<SharedSigner<Ledger>, String> {
+    let ledger = Ledger::new(HDPath::LedgerLive(account_index), chain_id)
+        .await
+        .map_err(|err| err.to_string())?;
+    println!(
+        "Connected to the Ledger account {} at the address {:?}",
+        account_index,
+        ledger.address()
+    );
+    Ok(SharedSigner(Arc::new(ledger)))
+}
@@ -1,9 +1,16 @@
 use chrono::{DateTime, Utc};
-use ethers::types::Address;
+use ethers::{
+    providers::JsonRpcError,
+    types::{Address, U256},
+};
 use std::{
-    fmt::{self, Display},
-    sync::Arc,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
 };
+use thiserror::Error;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::{disbursal_signer::DisbursalSigner, event_bus::EventBus};
 
 #[derive(Clone)]
 pub struct SolverParams<M>
@@ -12,6 +19,33 @@ where
 {
     pub call_breaker_address: Address,
     pub middleware: Arc<M>,
+    pub max_trigger_jitter: Duration,
+    pub signing_timeout: Duration,
+    // Signs the disbursal data verified by the KITN scheduler.
+    pub disbursal_signer: Arc<DisbursalSigner>,
+    // Maximum number of receivers in one disbursement transaction, larger pools are
+    // disbursed in several transactions.
+    pub disbursement_batch_size: usize,
+    // Execute at the block estimated for the schedule time instead of the time itself.
+    pub target_block_execution: bool,
+    // Block time for the estimation, averaged over the recent blocks if not given.
+    pub block_time: Option<Duration>,
+    // Priority fee of the final transaction, to land in the target block.
+    pub priority_fee: Option<U256>,
+    // The schedules are re-armed for their next time after each disbursement, instead of
+    // by the calls pushed again.
+    pub recurring: bool,
+    // Transaction notifications are published here.
+    pub events: EventBus,
+    // Set once the state is handed over to another instance, held for reading while
+    // disbursing.
+    pub handed_over: Arc<RwLock<bool>>,
+    // The disbursements of the schedules run one at a time, each one reads the pool after
+    // the previous one removed its amounts.
+    pub disbursing: Arc<Mutex<()>>,
+    // Set while the disbursements are paused through the gRPC API, the schedules aren't
+    // triggered.
+    pub paused: Arc<AtomicBool>,
 }
 
 pub struct SolverResponse {
@@ -20,28 +54,58 @@ pub struct SolverResponse {
     pub remaining_secs: i64,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Error)]
 pub enum SolverError {
-    ParamError(String),
-    ExecError(String),
+    #[error("Parameter error, \"{0}\"")]
+    Param(String),
+    #[error("Execution error, {0}")]
+    Exec(String),
+    // The chain node couldn't be reached or didn't answer, e.g. timed out.
+    #[error("RPC error, {0}")]
+    Rpc(String),
+}
+
+// JSON-RPC error codes of the nodes limiting the rate of the requests.
+const RATE_LIMITED_CODES: [i64; 2] = [-32005, 429];
+
+// Whether the node didn't answer, or answered that it couldn't serve the request for now:
+// rate limited, timed out, or behind the block asked for.
+pub fn transient(response: Option<&JsonRpcError>) -> bool {
+    let response = match response {
+        Some(response) => response,
+        None => return true,
+    };
+    let message = response.message.to_lowercase();
+    RATE_LIMITED_CODES.contains(&response.code)
+        || ["rate limit", "timeout", "timed out", "header not found"]
+            .iter()
+            .any(|pattern| message.contains(pattern))
 }
 
-impl Display for SolverError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
+impl SolverError {
+    // Whether the call may succeed if repeated later. Bad parameters and failed
+    // executions don't change by retrying, the schedule is given up on them.
+    pub fn is_retryable(&self) -> bool {
         match self {
-            SolverError::ParamError(s) => {
-                write!(f, "Parameter error, \"{}\"", s)
-            }
-            SolverError::ExecError(s) => {
-                write!(f, "Execution error, {}", s)
-            }
+            SolverError::Rpc(_) => true,
+            SolverError::Param(_) | SolverError::Exec(_) => false,
         }
     }
 }
 
 pub trait Solver {
     fn app(&self) -> String;
+    // The proxy the calls are pulled from.
+    fn proxy(&self) -> Address;
     fn schedule_time(&self) -> Result<DateTime<Utc>, SolverError>;
     async fn exec_solver_step(&self) -> Result<SolverResponse, SolverError>;
+    // Why the final transaction would revert whatever it is sent with, e.g. the call was
+    // already pulled by another instance, None if it may land.
+    async fn superseded(&self) -> Result<Option<String>, SolverError>;
     async fn final_exec(&self) -> Result<SolverResponse, SolverError>;
+    // Re-arms a recurring schedule after a successful final execution, returns the
+    // sequence number of the call to pull next, None if the schedule is over.
+    async fn rearm(&self) -> Option<(U256, String)>;
+    // The target block and the deviation of the block the transaction landed in.
+    async fn target_block_stats(&self) -> (Option<u64>, Option<i64>);
 }
@@ -1,19 +1,41 @@
 use crate::{
+    call_plan::CallPlan,
     contracts_abi::{
-        CallBreaker, CallObject, CallPushedFilter, LaminatedProxyCalls, PullCall,
-        ReturnObject,
-    }, encoded_data::{get_associated_data, get_disbursed_data}, solver::{Solver, SolverError, SolverParams, SolverResponse}
+        CallBreaker, CallObject, CallPushedFilter, LaminatedProxy, LaminatedProxyCalls, PullCall,
+        SolverData,
+    },
+    disbursal_signer::DisbursalSigner,
+    display,
+    encoded_data::{get_associated_data, hint_indices},
+    event_bus::{Event, EventBus},
+    handover::{ProxyHandover, ScheduledCall},
+    param_schema::{ParamSchema, ParamType},
+    reports_aggr::{PoolSelection, ReportsPool},
+    solver::{transient, Solver, SolverError, SolverParams, SolverResponse},
+    target_block,
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, TimeDelta, Utc};
 use cron::Schedule;
 use ethers::{
-    abi::{self, AbiEncode, Token},
-    contract::abigen,
-    providers::Middleware,
-    types::{Address, Bytes, U256},
+    abi::AbiEncode,
+    contract::{abigen, parse_log, ContractError},
+    providers::{Middleware, MiddlewareError, RpcError},
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256},
+};
+use rand::Rng;
+use std::{
+    future::Future,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    sync::{Mutex, RwLock},
+    time::timeout,
 };
-use std::{collections::HashMap, str::FromStr, sync::Arc, time::SystemTime};
-use tokio::sync::Mutex;
 
 abigen!(
   KITNDisburmentScheduler,
@@ -24,12 +46,24 @@ abigen!(
 pub const APP_SELECTOR: &str = "CLEANAPP.SCHEDULER";
 
 pub struct CleanAppSchedulerSolver<M> {
-    // Sequence number for laminator proxy call
-    sequence_number: U256,
+    // Sequence number for laminator proxy call, the one pushed again by the last
+    // disbursement once a recurring schedule is re-armed
+    sequence_number: Mutex<U256>,
+
+    // Sequence number of the call that started the executor, and the params of its
+    // schedule
+    schedule_sequence_number: U256,
+    schedule_params: Vec<SolverData>,
+
+    // Sequence number of the call that set the params of the schedule
+    schedule: U256,
 
     // Proxy Address
     proxy_address: Address,
 
+    // The calls pulled from the proxy.
+    pushed_calls: Vec<CallObject>,
+
     // KITN Disbursement Address
     kitn_disbursement_scheduler_address: Address,
 
@@ -40,60 +74,376 @@ pub struct CleanAppSchedulerSolver<M> {
     schedule_string: String,
 
     // Trigger time
-    trigger_time: Result<DateTime<Utc>, SolverError>,
+    trigger_time: std::sync::Mutex<Result<DateTime<Utc>, SolverError>>,
+    max_trigger_jitter: Duration,
+
+    // Re-armed for the next time of the schedule after each disbursement
+    recurring: bool,
+    // Sequence number of the call pushed again by the last disbursement
+    pushed_sequence_number: Mutex<Option<U256>>,
+    handover: Arc<ProxyHandover>,
 
     // Reports Pool
-    reports_pool: Arc<Mutex<HashMap<Address, U256>>>,
+    reports_pool: Arc<Mutex<ReportsPool>>,
+
+    // The entries of the pool disbursed by this schedule.
+    selection: PoolSelection,
+
+    // Time limit for signing and sending the transaction
+    signing_timeout: Duration,
+
+    // Signs the disbursal data
+    disbursal_signer: Arc<DisbursalSigner>,
+
+    // Maximum number of receivers disbursed in one transaction
+    batch_size: usize,
+
+    // Transaction notifications
+    events: EventBus,
+
+    // Set once the state is handed over, no disbursements after that
+    handed_over: Arc<RwLock<bool>>,
+
+    // Held while disbursing, shared by the schedules
+    disbursing: Arc<Mutex<()>>,
+
+    // Set while the disbursements are paused
+    paused: Arc<AtomicBool>,
+
+    // Target block execution params
+    target_block_execution: bool,
+    block_time: Option<Duration>,
+    priority_fee: Option<U256>,
+
+    // The block estimated for the trigger time, and the block the transaction landed in.
+    target_block: Mutex<Option<u64>>,
+    landed_block: Mutex<Option<u64>>,
 }
 
 impl<M: Middleware + Clone> CleanAppSchedulerSolver<M> {
     pub fn new(
-        event: CallPushedFilter,
+        scheduled: ScheduledCall,
         params: SolverParams<M>,
         proxy_address: Address,
         kitn_disbursement_scheduler_address: Address,
-        reports_pool: Arc<Mutex<HashMap<Address, U256>>>,
-        cron: String,
+        reports_pool: Arc<Mutex<ReportsPool>>,
+        handover: Arc<ProxyHandover>,
     ) -> Result<CleanAppSchedulerSolver<M>, SolverError> {
-        println!("Event received: {}", event);
-        let mut ret = CleanAppSchedulerSolver {
-            sequence_number: event.sequence_number,
+        let event = scheduled.call;
+        println!(
+            "Event received: {}",
+            display::pushed_call(&event, proxy_address)
+        );
+        let schedule_params = schedule_schema().validate(&event.data)?;
+        let cron = schedule_params.cron("CRON").unwrap_or_default().to_string();
+        let selection = PoolSelection::from_params(&schedule_params);
+        let trigger_time = next_trigger_time(cron.as_str(), params.max_trigger_jitter)?;
+        Ok(CleanAppSchedulerSolver {
+            sequence_number: Mutex::new(event.sequence_number),
+            schedule_sequence_number: event.sequence_number,
+            schedule_params: event.data.clone(),
+            schedule: scheduled.schedule,
             proxy_address,
+            pushed_calls: event.call_objs.clone(),
             kitn_disbursement_scheduler_address,
             call_breaker_contract: CallBreaker::new(
                 params.call_breaker_address,
                 params.middleware.clone(),
             ),
             schedule_string: cron,
-            trigger_time: Err(SolverError::ParamError(
-                "Missing CRON parameter".to_string(),
-            )),
+            trigger_time: std::sync::Mutex::new(Ok(trigger_time)),
+            max_trigger_jitter: params.max_trigger_jitter,
+            recurring: params.recurring,
+            pushed_sequence_number: Mutex::new(None),
+            handover,
             reports_pool,
-        };
+            selection,
+            signing_timeout: params.signing_timeout,
+            disbursal_signer: params.disbursal_signer.clone(),
+            batch_size: params.disbursement_batch_size,
+            events: params.events.clone(),
+            handed_over: params.handed_over.clone(),
+            disbursing: params.disbursing.clone(),
+            paused: params.paused.clone(),
+            target_block_execution: params.target_block_execution,
+            block_time: params.block_time,
+            priority_fee: params.priority_fee,
+            target_block: Mutex::new(None),
+            landed_block: Mutex::new(None),
+        })
+    }
+}
 
-        let mut schedule_extracted = false;
-        // Check that all parameters are successfully extracted.
-        match Schedule::from_str(ret.schedule_string.as_str()) {
-            Ok(schedule) => {
-                for trigger_time in schedule.upcoming(Utc).take(1) {
-                    ret.trigger_time = Ok(trigger_time);
-                }
-                schedule_extracted = true;
-            }
-            Err(err) => {
-                ret.trigger_time = Err(SolverError::ParamError(format!(
-                    "Error parsing CRON parameter: {}",
-                    err
-                )));
+// The params of a schedule, the pool entries it disburses are selected with MIN_AMOUNT
+// and MIN_AGE.
+pub fn schedule_schema() -> ParamSchema {
+    ParamSchema::new()
+        .required("CRON", ParamType::Cron)
+        .optional("MIN_AMOUNT", ParamType::Uint)
+        .optional("MIN_AGE", ParamType::Duration)
+}
+
+// Disburses the batches in turn, each pulling the call pushed by the previous one. The
+// call to pull is stored after each batch, so that a retry resumes at the batch that
+// failed instead of finding the first call pulled.
+async fn disburse_batches<'a, F, Fut>(
+    next_call: &Mutex<U256>,
+    batches: &[&'a [(Address, U256)]],
+    mut exec_batch: F,
+) -> Result<SolverResponse, SolverError>
+where
+    F: FnMut(U256, &'a [(Address, U256)]) -> Fut,
+    Fut: Future<Output = Result<(SolverResponse, Option<U256>), SolverError>>,
+{
+    let mut response = SolverResponse {
+        succeeded: false,
+        message: "No entry of the pool is selected".to_string(),
+        remaining_secs: 0,
+    };
+    for (i, batch) in batches.iter().enumerate() {
+        let sequence_number = *next_call.lock().await;
+        let pushed;
+        (response, pushed) = exec_batch(sequence_number, batch).await?;
+        if !response.succeeded {
+            response.message = format!(
+                "Batch {} of {} failed: {}",
+                i + 1,
+                batches.len(),
+                response.message
+            );
+            return Ok(response);
+        }
+        match pushed {
+            Some(pushed) => *next_call.lock().await = pushed,
+            None if i + 1 < batches.len() => {
+                return Ok(SolverResponse {
+                    succeeded: false,
+                    message: format!(
+                        "Batch {} of {} pushed no call to pull next",
+                        i + 1,
+                        batches.len()
+                    ),
+                    remaining_secs: 0,
+                })
             }
+            None => {}
         }
-        if !schedule_extracted {
-            return Err(SolverError::ParamError(
-                "Missing schedule, the solver won't run".to_string(),
-            ));
+    }
+    if response.succeeded {
+        response.message = format!("{} batches disbursed, {}", batches.len(), response.message);
+    }
+    Ok(response)
+}
+
+// Parses the CRON parameter of a schedule.
+pub fn parse_schedule(cron: &str) -> Result<Schedule, SolverError> {
+    Schedule::from_str(cron)
+        .map_err(|err| SolverError::Param(format!("Error parsing CRON parameter: {}", err)))
+}
+
+// The next time of the cron schedule, with a random delay within the allowed window.
+fn next_trigger_time(cron: &str, max_jitter: Duration) -> Result<DateTime<Utc>, SolverError> {
+    let schedule = parse_schedule(cron)?;
+    let jitter_millis = rand::thread_rng().gen_range(0..=max_jitter.as_millis() as i64);
+    schedule
+        .upcoming(Utc)
+        .next()
+        .map(|trigger_time| trigger_time + TimeDelta::milliseconds(jitter_millis))
+        .ok_or(SolverError::Param(
+            "Missing schedule, the solver won't run".to_string(),
+        ))
+}
+
+impl<M: Middleware> CleanAppSchedulerSolver<M> {
+    // Returns the target block, estimated on first use, and the current block.
+    async fn target_block(&self, trigger_time: DateTime<Utc>) -> Result<(u64, u64), SolverError> {
+        let rpc_error = |err: String| SolverError::Rpc(format!("Target block error: {}", err));
+        let middleware = self.call_breaker_contract.client();
+        let current_block = middleware
+            .get_block_number()
+            .await
+            .map_err(|err| rpc_error(err.to_string()))?
+            .as_u64();
+        let mut target_block = self.target_block.lock().await;
+        if let Some(target_block) = *target_block {
+            return Ok((target_block, current_block));
         }
+        let block_time = match self.block_time {
+            Some(block_time) => block_time,
+            None => target_block::estimate_block_time(middleware.as_ref())
+                .await
+                .map_err(rpc_error)?,
+        };
+        let target = target_block::block_at(middleware.as_ref(), trigger_time, block_time)
+            .await
+            .map_err(rpc_error)?;
+        println!(
+            "Target block {} for {} with the block time {:?}",
+            target, trigger_time, block_time
+        );
+        *target_block = Some(target);
+        Ok((target, current_block))
+    }
 
-        Ok(ret)
+    // Disburses the batch in a transaction pulling the call with the given sequence
+    // number, and removes it from the pool once confirmed.
+    async fn exec_batch(
+        &self,
+        sequence_number: U256,
+        batch: &[(Address, U256)],
+    ) -> Result<SolverResponse, SolverError> {
+        let receivers: Vec<Address> = batch.iter().map(|(account, _)| *account).collect();
+        let amounts: Vec<U256> = batch.iter().map(|(_, amount)| *amount).collect();
+        let disbursal = self
+            .disbursal_signer
+            .sign(&receivers, &amounts)
+            .map_err(SolverError::Exec)?;
+
+        // Every pull pushes the same calls again, the ones of the schedule.
+        let plan = CallPlan::new(self.call_breaker_contract.address())
+            .pull(
+                CallObject {
+                    amount: 0.into(),
+                    addr: self.proxy_address,
+                    gas: 10000000.into(),
+                    callvalue: LaminatedProxyCalls::Pull(PullCall {
+                        seq_number: sequence_number,
+                    })
+                    .encode()
+                    .into(),
+                },
+                self.pushed_calls.clone(),
+            )
+            .call(CallObject {
+                amount: 0.into(),
+                addr: self.kitn_disbursement_scheduler_address,
+                gas: 1000000.into(),
+                callvalue: KITNDisburmentSchedulerCalls::VerifySignature(VerifySignatureCall {
+                    data: disbursal.data.clone(),
+                })
+                .encode()
+                .into(),
+            });
+        let call_objects = plan.call_objects();
+        let return_objects = plan
+            .return_objects(self.call_breaker_contract.client().as_ref())
+            .await?;
+
+        let associated_data = get_associated_data(sequence_number, &disbursal);
+        let hintindices = hint_indices(&call_objects);
+
+        let call_bytes: Bytes = call_objects.encode().into();
+        let return_bytes: Bytes = return_objects.encode().into();
+        {
+            let mut call = self
+                .call_breaker_contract
+                .execute_and_verify(call_bytes, return_bytes, associated_data, hintindices)
+                .gas(10000000);
+            if let Some(priority_fee) = self.priority_fee {
+                let (max_fee, _) = self
+                    .call_breaker_contract
+                    .client()
+                    .estimate_eip1559_fees(None)
+                    .await
+                    .map_err(|err| SolverError::Rpc(format!("Fee estimation error: {}", err)))?;
+                if let TypedTransaction::Eip1559(tx) = &mut call.tx {
+                    tx.max_priority_fee_per_gas = Some(priority_fee);
+                    tx.max_fee_per_gas = Some(max_fee + priority_fee);
+                }
+            }
+            // The signer may wait for a confirmation on a hardware wallet.
+            let sent = match timeout(self.signing_timeout, call.send()).await {
+                Ok(sent) => sent,
+                Err(_) => {
+                    return Ok(SolverResponse {
+                        succeeded: false,
+                        message: format!(
+                            "The transaction wasn't signed within {} secs, retrying",
+                            self.signing_timeout.as_secs()
+                        ),
+                        remaining_secs: 0,
+                    });
+                }
+            };
+            match sent {
+                Ok(pending) => {
+                    println!("Transaction is sent, txhash: {}", pending.tx_hash());
+                    self.handover
+                        .disbursed(pending.tx_hash(), self.schedule)
+                        .await;
+                    self.events.publish(Event::TxSubmitted {
+                        sequence_number,
+                        tx_hash: pending.tx_hash(),
+                    });
+                    match pending.await {
+                        Ok(receipt) => {
+                            if let Some(receipt) = receipt {
+                                *self.landed_block.lock().await =
+                                    receipt.block_number.map(|number| number.as_u64());
+                                self.events.publish(Event::TxConfirmed {
+                                    sequence_number,
+                                    tx_hash: receipt.transaction_hash,
+                                    succeeded: receipt
+                                        .status
+                                        .is_some_and(|status| !status.is_zero()),
+                                    block: receipt.block_number.map(|number| number.as_u64()),
+                                });
+                                // The pull pushes the calls of the schedule again, the
+                                // next disbursement pulls them.
+                                *self.pushed_sequence_number.lock().await = receipt
+                                    .logs
+                                    .iter()
+                                    .filter(|log| log.address == self.proxy_address)
+                                    .find_map(|log| parse_log::<CallPushedFilter>(log.clone()).ok())
+                                    .map(|call_pushed| call_pushed.sequence_number);
+                                if let Some(status) = receipt.status {
+                                    if status > 0.into() {
+                                        if let Err(err) = self
+                                            .reports_pool
+                                            .lock()
+                                            .await
+                                            .disburse(batch, receipt.transaction_hash)
+                                        {
+                                            println!("Error persisting disbursement: {}", err);
+                                        }
+                                    }
+                                    let mut message = format!("Transaction status: {}", status);
+                                    if let (Some(target), Some(deviation)) =
+                                        self.target_block_stats().await
+                                    {
+                                        message.push_str(
+                                            format!(
+                                                ", target block {}, deviation {}",
+                                                target, deviation
+                                            )
+                                            .as_str(),
+                                        );
+                                    }
+                                    return Ok(SolverResponse {
+                                        succeeded: status != 0.into(),
+                                        message,
+                                        remaining_secs: 0,
+                                    });
+                                }
+                            }
+                            // Whether it landed isn't known, it isn't sent again either.
+                            return Err(SolverError::Exec(
+                                "The status of the sent transaction wasn't received".to_string(),
+                            ));
+                        }
+                        // The transaction may still land, it isn't sent again so that
+                        // the batch isn't disbursed twice.
+                        Err(err) => {
+                            return Err(SolverError::Exec(format!(
+                                "Final execution error: {}",
+                                err
+                            )));
+                        }
+                    }
+                }
+                Err(err) => return Err(contract_error("Final execution error", err)),
+            }
+        };
     }
 }
 
@@ -102,25 +452,43 @@ impl<M: Middleware> Solver for CleanAppSchedulerSolver<M> {
         APP_SELECTOR.to_string()
     }
 
+    fn proxy(&self) -> Address {
+        self.proxy_address
+    }
+
     fn schedule_time(&self) -> Result<DateTime<Utc>, SolverError> {
-        self.trigger_time.clone()
+        self.trigger_time.lock().unwrap().clone()
     }
 
     async fn exec_solver_step(&self) -> Result<SolverResponse, SolverError> {
-        if let Err(err) = self.trigger_time.clone() {
-            return Err(err);
+        let trigger_time = self.schedule_time()?;
+        // A paused schedule keeps its time, it's triggered once resumed if it passed.
+        if self.paused.load(Ordering::Relaxed) {
+            return Ok(SolverResponse {
+                succeeded: false,
+                message: "Not triggered, the disbursements are paused".to_string(),
+                remaining_secs: 0,
+            });
         }
-        let trigger_time = self.trigger_time.clone().unwrap();
         // Check if the schedule is triggered.
-        const MAX_BATCH_SIZE: usize = 10;
         match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
             Ok(now) => {
                 let now =
                     DateTime::from_timestamp(i64::from_ne_bytes(now.as_secs().to_ne_bytes()), 0)
-                        .unwrap();
-                if trigger_time <= now {
-                    let reports = self.reports_pool.lock().await;
-                    if !reports.is_empty() {
+                        .ok_or(SolverError::Exec(
+                            "The system time is out of range".to_string(),
+                        ))?;
+                // In the target block mode the transaction is sent one block ahead to
+                // land in the target block.
+                let reached = if self.target_block_execution {
+                    let (target_block, current_block) = self.target_block(trigger_time).await?;
+                    current_block + 1 >= target_block
+                } else {
+                    trigger_time <= now
+                };
+                if reached {
+                    let selected = self.reports_pool.lock().await.selected(&self.selection);
+                    if !selected.is_empty() {
                         return Ok(SolverResponse {
                             succeeded: true,
                             message: format!("Triggered at {}", now),
@@ -129,13 +497,13 @@ impl<M: Middleware> Solver for CleanAppSchedulerSolver<M> {
                     } else {
                         return Ok(SolverResponse {
                             succeeded: false,
-                            message: "Not triggered, the pool is empty".to_string(),
+                            message: "Not triggered, no entry of the pool is selected".to_string(),
                             remaining_secs: 0,
                         });
                     }
                 } else {
-                    let reports = self.reports_pool.lock().await;
-                    if reports.len() >= MAX_BATCH_SIZE {
+                    let selected = self.reports_pool.lock().await.selected(&self.selection);
+                    if selected.len() >= self.batch_size {
                         return Ok(SolverResponse {
                             succeeded: true,
                             message: format!("Triggered at {} as the batch is complete", now),
@@ -152,7 +520,7 @@ impl<M: Middleware> Solver for CleanAppSchedulerSolver<M> {
                 }
             }
             Err(err) => {
-                return Err(SolverError::ExecError(format!(
+                return Err(SolverError::Exec(format!(
                     "Solver execution error: {}",
                     err
                 )));
@@ -160,111 +528,192 @@ impl<M: Middleware> Solver for CleanAppSchedulerSolver<M> {
         }
     }
 
+    async fn superseded(&self) -> Result<Option<String>, SolverError> {
+        let sequence_number = *self.sequence_number.lock().await;
+        let (initialized, executed, _, _) =
+            LaminatedProxy::new(self.proxy_address, self.call_breaker_contract.client())
+                .view_deferred_call(sequence_number)
+                .call()
+                .await
+                .map_err(|err| contract_error("Error reading the call of the proxy", err))?;
+        if !initialized {
+            Ok(Some(format!(
+                "The call {} isn't pushed to the proxy",
+                sequence_number
+            )))
+        } else if executed {
+            Ok(Some(format!(
+                "The call {} was already pulled",
+                sequence_number
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+
     async fn final_exec(&self) -> Result<SolverResponse, SolverError> {
-        let mut receivers: Vec<Address> = Vec::new();
-        let mut amounts: Vec<U256> = Vec::new();
+        // The export of the state waits for the disbursement to finish.
+        let handed_over = self.handed_over.read().await;
+        if *handed_over {
+            return Ok(SolverResponse {
+                succeeded: false,
+                message: "The state is handed over to another instance".to_string(),
+                remaining_secs: 0,
+            });
+        }
+        let _disbursing = self.disbursing.lock().await;
+        // Reports keep coming while the transactions are pending, only the amounts
+        // included into confirmed batches are removed from the pool.
+        let entries = self.reports_pool.lock().await.selected(&self.selection);
+        let batches: Vec<&[(Address, U256)]> = entries.chunks(self.batch_size).collect();
+        disburse_batches(
+            &self.sequence_number,
+            &batches,
+            |sequence_number, batch| async move {
+                let response = self.exec_batch(sequence_number, batch).await?;
+                Ok((response, *self.pushed_sequence_number.lock().await))
+            },
+        )
+        .await
+    }
 
-        let mut reports = self.reports_pool.lock().await;
-        for (account, amount) in reports.iter() {
-            receivers.push(*account);
-            amounts.push(*amount);
+    async fn rearm(&self) -> Option<(U256, String)> {
+        if !self.recurring {
+            return None;
         }
+        let sequence_number = match self.pushed_sequence_number.lock().await.take() {
+            Some(sequence_number) => sequence_number,
+            None => {
+                println!("The disbursement pushed no call to pull next, the schedule is over");
+                return None;
+            }
+        };
+        let trigger_time =
+            match next_trigger_time(self.schedule_string.as_str(), self.max_trigger_jitter) {
+                Ok(trigger_time) => trigger_time,
+                Err(err) => {
+                    println!("The schedule is over: {}", err);
+                    return None;
+                }
+            };
+        *self.sequence_number.lock().await = sequence_number;
+        *self.trigger_time.lock().unwrap() = Ok(trigger_time);
+        *self.target_block.lock().await = None;
+        *self.landed_block.lock().await = None;
+        // A handover resumes the schedule from the call to pull next.
+        self.handover
+            .rearmed(
+                self.schedule_sequence_number,
+                CallPushedFilter {
+                    call_objs: self.pushed_calls.clone(),
+                    sequence_number,
+                    data: self.schedule_params.clone(),
+                },
+            )
+            .await;
+        Some((
+            sequence_number,
+            format!(
+                "Re-armed for {} to pull the call {}",
+                trigger_time, sequence_number
+            ),
+        ))
+    }
 
-        let disbursal_data = get_disbursed_data(receivers.clone(), amounts.clone());
+    async fn target_block_stats(&self) -> (Option<u64>, Option<i64>) {
+        let target_block = *self.target_block.lock().await;
+        let landed_block = *self.landed_block.lock().await;
+        match (target_block, landed_block) {
+            (Some(target), Some(landed)) => (Some(target), Some(landed as i64 - target as i64)),
+            _ => (target_block, None),
+        }
+    }
+}
 
-        let call_objects = vec![
-            CallObject {
-                amount: 0.into(),
-                addr: self.proxy_address,
-                gas: 10000000.into(),
-                callvalue: LaminatedProxyCalls::Pull(PullCall {
-                    seq_number: self.sequence_number,
-                })
-                .encode()
-                .into(),
-            },
-            CallObject {
-                amount: 0.into(),
-                addr: self.kitn_disbursement_scheduler_address,
-                gas: 1000000.into(),
-                callvalue: KITNDisburmentSchedulerCalls::VerifySignature(VerifySignatureCall {
-                    data: disbursal_data.clone(),
-                })
-                .encode()
-                .into(),
-            },
-        ];
-        let next_sequence_number = self.sequence_number + 1;
-        let return_objects_from_pull = vec![
-            ReturnObject {
-                returnvalue: Bytes::new(),
-            },
-            ReturnObject {
-                returnvalue: next_sequence_number.encode().into(),
-            },
-            ReturnObject {
-                returnvalue: Bytes::new(),
-            },
-        ];
-        let return_objects = vec![
-            ReturnObject {
-                returnvalue: abi::encode(&[Token::Bytes(return_objects_from_pull.encode())]).into(),
-            },
-            ReturnObject {
-                returnvalue: Bytes::new(),
-            },
-        ];
+// Errors answered by the node, e.g. a revert, are final. The call or the transaction
+// may be made again if the node didn't answer or couldn't serve it for now.
+fn contract_error<M: Middleware>(context: &str, err: ContractError<M>) -> SolverError {
+    let transient = match (err.as_middleware_error(), err.as_provider_error()) {
+        (Some(middleware_err), _) => transient(middleware_err.as_error_response()),
+        (None, Some(provider_err)) => transient(RpcError::as_error_response(provider_err)),
+        (None, None) => false,
+    };
+    let message = format!("{}: {}", context, err);
+    if transient {
+        SolverError::Rpc(message)
+    } else {
+        SolverError::Exec(message)
+    }
+}
 
-        let associated_data = get_associated_data(self.sequence_number, receivers, amounts);
-        let hintindices = Bytes::from_str("0x00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000c0baed237ba5681f7a9e0892d5d807f7bddae6ccb06e0a053b4b358cad56dfc2b1000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000000b09eb645b7de126aeb2d91436e34148ebde4ff228768eb684ecb19bd1524ac06000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000001").unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
 
-        let call_bytes: Bytes = call_objects.encode().into();
-        let return_bytes: Bytes = return_objects.encode().into();
-        {
-            match self
-                .call_breaker_contract
-                .execute_and_verify(call_bytes, return_bytes, associated_data, hintindices)
-                .gas(10000000)
-                .send()
-                .await
-            {
-                Ok(pending) => {
-                    println!("Transaction is sent, txhash: {}", pending.tx_hash());
-                    match pending.await {
-                        Ok(receipt) => {
-                            if let Some(receipt) = receipt {
-                                if let Some(status) = receipt.status {
-                                    if status > 0.into() {
-                                        reports.clear();
-                                    }
-                                    return Ok(SolverResponse {
-                                        succeeded: status != 0.into(),
-                                        message: format!("Transaction status: {}", status),
-                                        remaining_secs: 0,
-                                    });
-                                }
-                            }
-                            return Ok(SolverResponse {
-                                succeeded: false,
-                                message: "transaction status wasn't received".to_string(),
-                                remaining_secs: 0,
-                            });
-                        }
-                        Err(err) => {
-                            return Err(SolverError::ExecError(format!(
-                                "Final execution error: {}",
-                                err
-                            )));
-                        }
-                    }
-                }
-                Err(err) => {
-                    return Err(SolverError::ExecError(format!(
-                        "Final execution error: {}",
-                        err
-                    )));
+    fn succeeded() -> SolverResponse {
+        SolverResponse {
+            succeeded: true,
+            message: "Transaction status: 1".to_string(),
+            remaining_secs: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn batches_pull_the_calls_pushed_by_the_previous_ones() {
+        let entries: Vec<(Address, U256)> = (1..=3)
+            .map(|byte| (Address::repeat_byte(byte), U256::one()))
+            .collect();
+        let batches: Vec<&[(Address, U256)]> = entries.chunks(1).collect();
+        // The calls pushed by the pulls, not the next sequence numbers: other
+        // schedules push to the proxy in between.
+        let pushed: HashMap<U256, U256> = HashMap::from([
+            (5.into(), 8.into()),
+            (8.into(), 12.into()),
+            (12.into(), 20.into()),
+        ]);
+        let next_call = Mutex::new(U256::from(5));
+        let pulled = std::sync::Mutex::new(Vec::new());
+        // The second batch fails with a transient error.
+        let exec_batch = |sequence_number: U256, _: &[(Address, U256)]| {
+            pulled.lock().unwrap().push(sequence_number);
+            let result = match sequence_number.as_u64() {
+                8 if pulled.lock().unwrap().len() == 2 => {
+                    Err(SolverError::Rpc("timed out".to_string()))
                 }
-            }
+                _ => Ok((succeeded(), pushed.get(&sequence_number).copied())),
+            };
+            async move { result }
         };
+        assert!(disburse_batches(&next_call, &batches, exec_batch)
+            .await
+            .is_err());
+        // The retry resumes at the call pushed by the first batch.
+        assert_eq!(*next_call.lock().await, 8.into());
+        let response = disburse_batches(&next_call, &batches[1..], exec_batch)
+            .await
+            .ok()
+            .unwrap();
+        assert!(response.succeeded);
+        assert_eq!(*next_call.lock().await, 20.into());
+        assert_eq!(
+            *pulled.lock().unwrap(),
+            vec![5.into(), 8.into(), 8.into(), 12.into()]
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_pushing_no_call_stops_the_disbursement() {
+        let entries = [(Address::repeat_byte(1), U256::one()); 2];
+        let batches: Vec<&[(Address, U256)]> = entries.chunks(1).collect();
+        let next_call = Mutex::new(U256::from(5));
+        let response = disburse_batches(&next_call, &batches, |_, _| async {
+            Ok((succeeded(), None))
+        })
+        .await
+        .ok()
+        .unwrap();
+        assert!(!response.succeeded);
+        assert_eq!(*next_call.lock().await, 5.into());
     }
 }
@@ -1,25 +1,32 @@
-use axum::{extract::State, response::Json};
+use axum::{extract::State, response::Json, routing::get, Router};
+use ethers::types::{Address, U256};
 use serde::{Deserialize, Serialize};
-use tokio::sync::{mpsc::Receiver, Mutex};
 use std::{
     collections::HashMap,
     sync::Arc,
-    time::Duration,
+    time::{Duration, SystemTime},
 };
+use tokio::sync::{mpsc::UnboundedReceiver, Mutex};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::contracts_abi::SolverData;
+use crate::{contracts_abi::SolverData, event_bus::Event, openapi::duration};
 
 // Executor statistics
-#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize, ToSchema)]
 pub enum Status {
     Running,
     Succeeded,
     Failed,
     Timeout,
+    Duplicate,
+    // The call was already pulled or is gone, nothing was sent.
+    Superseded,
+    // Stopped through the gRPC API.
+    Cancelled,
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize, ToSchema)]
 pub enum TransactionStatus {
     Succeeded,
     StepFailed,
@@ -29,19 +36,63 @@ pub enum TransactionStatus {
     NotExecuted,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct TimerExecutorStats {
     pub id: Uuid,
+    // The proxy the call was pushed to, missing in the stats of older instances.
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub proxy: Option<Address>,
     pub sequence_number: u32,
     pub app: String,
+    #[schema(schema_with = duration)]
     pub creation_time: Duration,
     pub status: Status,
     pub transaction_status: TransactionStatus,
     pub message: String,
+    #[schema(value_type = Vec<Object>)]
     pub params: Vec<SolverData>,
     pub remaining_secs: i64,
+    pub target_block: Option<u64>,
+    pub block_deviation: Option<i64>,
+    // The executor is over, unlike the succeeded stats of a schedule which is re-armed.
+    #[serde(default)]
+    pub finished: bool,
 }
 
+impl TimerExecutorStats {
+    // Stats of an objective that was received again and not executed.
+    pub fn duplicate(
+        proxy: Address,
+        sequence_number: U256,
+        app: String,
+        params: Vec<SolverData>,
+    ) -> TimerExecutorStats {
+        TimerExecutorStats {
+            id: Uuid::new_v4(),
+            proxy: Some(proxy),
+            sequence_number: sequence_number.as_u32(),
+            app,
+            creation_time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default(),
+            status: Status::Duplicate,
+            transaction_status: TransactionStatus::NotExecuted,
+            message: "Duplicate objective, already being executed".to_string(),
+            params,
+            remaining_secs: 0,
+            target_block: None,
+            block_deviation: None,
+            finished: true,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats",
+    responses((status = 200, description = "All executors, oldest first", body = Vec<TimerExecutorStats>))
+)]
 pub async fn get_stats_json(
     stats: State<Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>>,
 ) -> Json<Vec<TimerExecutorStats>> {
@@ -54,12 +105,57 @@ pub async fn get_stats_json(
     Json(filtered)
 }
 
+// Stats of the executors of one app.
+#[utoipa::path(
+    get,
+    path = "/stats/{app}",
+    params(("app" = String, Path, description = "App slug, e.g. cleanapp_scheduler")),
+    responses((status = 200, body = Vec<TimerExecutorStats>))
+)]
+pub async fn get_app_stats_json(
+    stats: State<Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>>,
+    app: String,
+) -> Json<Vec<TimerExecutorStats>> {
+    let Json(stats) = get_stats_json(stats).await;
+    Json(stats.into_iter().filter(|el| el.app == app).collect())
+}
+
+// Route path segment of an app, e.g. "cleanapp_scheduler" for "CLEANAPP.SCHEDULER".
+pub fn app_slug(app: &str) -> String {
+    app.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// Stats routes of the solver apps, /stats/{app slug} per app and /stats for all of them.
+pub fn stats_router(
+    apps: Vec<String>,
+    stats_map: Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>,
+) -> Router {
+    let mut router = Router::new().route("/stats", get(get_stats_json));
+    for app in apps {
+        router = router.route(
+            format!("/stats/{}", app_slug(app.as_str())).as_str(),
+            get(move |stats| get_app_stats_json(stats, app)),
+        );
+    }
+    router.with_state(stats_map)
+}
+
 pub async fn run_stats_receive(
-    rx: &mut Receiver<TimerExecutorStats>,
+    rx: &mut UnboundedReceiver<Event>,
     stats_map: Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>,
 ) {
-    while let Some(stats) = rx.recv().await {
-        let mut stats_map = stats_map.lock().await;
-        stats_map.insert(stats.id, stats);
+    while let Some(event) = rx.recv().await {
+        if let Event::Stats(stats) = event {
+            let mut stats_map = stats_map.lock().await;
+            stats_map.insert(stats.id, stats);
+        }
     }
 }
@@ -0,0 +1,251 @@
+use axum::{extract::State, response::Json};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::Mutex,
+    task::{AbortHandle, Id, JoinSet},
+    time::sleep,
+};
+use utoipa::ToSchema;
+
+// How often finished tasks are collected.
+const REAP_INTERVAL: Duration = Duration::from_secs(10);
+
+// A critical task is restarted after a backoff doubled on each failure in a row.
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+// A critical task running that long before failing starts over from the first backoff.
+const HEALTHY_RUN: Duration = Duration::from_secs(300);
+
+// Outcomes of the tasks spawned into the JoinSet.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct TaskCounts {
+    pub running: usize,
+    pub finished: u64,
+    pub panicked: u64,
+    pub cancelled: u64,
+    // Panics and restarts by task name.
+    #[serde(default)]
+    pub panics: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub restarts: BTreeMap<String, u64>,
+}
+
+pub type TaskCountsState = Arc<Mutex<TaskCounts>>;
+
+// Builds the future of a critical task, once more on each restart.
+type TaskFactory = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+struct SupervisedTask {
+    name: &'static str,
+    // What the task works on, for the logs.
+    context: Option<String>,
+    // Only the critical tasks are restarted.
+    restart: Option<TaskFactory>,
+    // Failures in a row.
+    failures: u32,
+    started: Instant,
+}
+
+impl SupervisedTask {
+    fn label(&self) -> String {
+        match &self.context {
+            Some(context) => format!("{} ({})", self.name, context),
+            None => self.name.to_string(),
+        }
+    }
+}
+
+// Spawns the tasks into the JoinSet and watches them. Panics are logged with the task
+// they happened in and counted, the critical tasks are restarted.
+#[derive(Clone)]
+pub struct Supervisor {
+    exec_set: Arc<Mutex<JoinSet<()>>>,
+    tasks: Arc<std::sync::Mutex<HashMap<Id, SupervisedTask>>>,
+    counts: TaskCountsState,
+}
+
+impl Supervisor {
+    pub fn new(counts: TaskCountsState) -> Supervisor {
+        Supervisor {
+            exec_set: Arc::new(Mutex::new(JoinSet::new())),
+            tasks: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            counts,
+        }
+    }
+
+    pub async fn spawn<F>(
+        &self,
+        name: &'static str,
+        context: Option<String>,
+        task: F,
+    ) -> AbortHandle
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut exec_set = self.exec_set.lock().await;
+        self.start(
+            &mut exec_set,
+            SupervisedTask {
+                name,
+                context,
+                restart: None,
+                failures: 0,
+                started: Instant::now(),
+            },
+            task,
+        )
+    }
+
+    // Spawns a task that is started again from the factory when it panics.
+    pub async fn spawn_critical<F, T>(
+        &self,
+        name: &'static str,
+        context: Option<String>,
+        factory: F,
+    ) where
+        F: Fn() -> T + Send + Sync + 'static,
+        T: Future<Output = ()> + Send + 'static,
+    {
+        let restart: TaskFactory = Arc::new(move || Box::pin(factory()));
+        let task = restart();
+        let mut exec_set = self.exec_set.lock().await;
+        self.start(
+            &mut exec_set,
+            SupervisedTask {
+                name,
+                context,
+                restart: Some(restart),
+                failures: 0,
+                started: Instant::now(),
+            },
+            task,
+        );
+    }
+
+    // The task is known before the set is unlocked, so before it can be collected.
+    fn start<F>(
+        &self,
+        exec_set: &mut JoinSet<()>,
+        supervised: SupervisedTask,
+        task: F,
+    ) -> AbortHandle
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let abort = exec_set.spawn(task);
+        self.tasks.lock().unwrap().insert(abort.id(), supervised);
+        abort
+    }
+
+    // Collects the finished tasks, so that their results are observed and their
+    // resources are freed.
+    pub async fn run(self) {
+        loop {
+            sleep(REAP_INTERVAL).await;
+            let mut exec_set = self.exec_set.lock().await;
+            let mut counts = self.counts.lock().await;
+            while let Some(res) = exec_set.try_join_next_with_id() {
+                let (id, err) = match res {
+                    Ok((id, ())) => {
+                        self.tasks.lock().unwrap().remove(&id);
+                        counts.finished += 1;
+                        continue;
+                    }
+                    Err(err) => (err.id(), err),
+                };
+                let supervised = self.tasks.lock().unwrap().remove(&id);
+                let label = match &supervised {
+                    Some(supervised) => supervised.label(),
+                    None => id.to_string(),
+                };
+                if !err.is_panic() {
+                    println!("Task {} cancelled: {}", label, err);
+                    counts.cancelled += 1;
+                    continue;
+                }
+                println!("Task {} panicked: {}", label, err);
+                counts.panicked += 1;
+                let mut supervised = match supervised {
+                    Some(supervised) => supervised,
+                    None => continue,
+                };
+                *counts
+                    .panics
+                    .entry(supervised.name.to_string())
+                    .or_default() += 1;
+                let restart = match supervised.restart.clone() {
+                    Some(restart) => restart,
+                    None => continue,
+                };
+                if supervised.started.elapsed() >= HEALTHY_RUN {
+                    supervised.failures = 0;
+                }
+                let backoff = RESTART_BACKOFF
+                    .saturating_mul(2u32.saturating_pow(supervised.failures))
+                    .min(MAX_RESTART_BACKOFF);
+                println!("Restarting the task {} in {:?}", label, backoff);
+                *counts
+                    .restarts
+                    .entry(supervised.name.to_string())
+                    .or_default() += 1;
+                supervised.failures += 1;
+                supervised.started = Instant::now() + backoff;
+                let task = restart();
+                self.start(&mut exec_set, supervised, async move {
+                    sleep(backoff).await;
+                    task.await;
+                });
+            }
+            counts.running = exec_set.len();
+        }
+    }
+}
+
+#[utoipa::path(get, path = "/stats/tasks", responses((status = 200, body = TaskCounts)))]
+pub async fn get_task_counts_json(counts: State<TaskCountsState>) -> Json<TaskCounts> {
+    let counts = counts.lock().await;
+    Json(counts.clone())
+}
+
+// The running tasks, the panics and the restarts in the Prometheus text format.
+pub fn write_metrics(counts: &TaskCounts, body: &mut String) {
+    let _ = writeln!(
+        body,
+        "# HELP solver_tasks_running Tasks running in the JoinSet."
+    );
+    let _ = writeln!(body, "# TYPE solver_tasks_running gauge");
+    let _ = writeln!(body, "solver_tasks_running {}", counts.running);
+    let _ = writeln!(
+        body,
+        "# HELP solver_task_panics_total Tasks that panicked, by task name."
+    );
+    let _ = writeln!(body, "# TYPE solver_task_panics_total counter");
+    for (name, count) in &counts.panics {
+        let _ = writeln!(
+            body,
+            "solver_task_panics_total{{task=\"{}\"}} {}",
+            name, count
+        );
+    }
+    let _ = writeln!(
+        body,
+        "# HELP solver_task_restarts_total Critical tasks restarted after a panic."
+    );
+    let _ = writeln!(body, "# TYPE solver_task_restarts_total counter");
+    for (name, count) in &counts.restarts {
+        let _ = writeln!(
+            body,
+            "solver_task_restarts_total{{task=\"{}\"}} {}",
+            name, count
+        );
+    }
+}
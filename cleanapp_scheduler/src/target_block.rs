@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use ethers::{providers::Middleware, types::BlockNumber};
+use std::time::Duration;
+
+// Number of recent blocks the block time is averaged over.
+const BLOCK_TIME_SAMPLE: u64 = 100;
+
+// Average block time over the recent blocks.
+pub async fn estimate_block_time<M: Middleware>(middleware: &M) -> Result<Duration, String> {
+    let latest = middleware
+        .get_block(BlockNumber::Latest)
+        .await
+        .map_err(|err| err.to_string())?
+        .ok_or("latest block not found".to_string())?;
+    let latest_number = latest.number.unwrap_or_default().as_u64();
+    let sample = BLOCK_TIME_SAMPLE.min(latest_number);
+    if sample == 0 {
+        return Err("not enough blocks to estimate the block time".to_string());
+    }
+    let earlier = middleware
+        .get_block(latest_number - sample)
+        .await
+        .map_err(|err| err.to_string())?
+        .ok_or(format!("block {} not found", latest_number - sample))?;
+    let elapsed_secs = (latest.timestamp - earlier.timestamp).as_u64();
+    Ok(Duration::from_millis(elapsed_secs * 1000 / sample))
+}
+
+// The first block expected to be produced at or after the given time.
+pub async fn block_at<M: Middleware>(
+    middleware: &M,
+    time: DateTime<Utc>,
+    block_time: Duration,
+) -> Result<u64, String> {
+    let latest = middleware
+        .get_block(BlockNumber::Latest)
+        .await
+        .map_err(|err| err.to_string())?
+        .ok_or("latest block not found".to_string())?;
+    let latest_number = latest.number.unwrap_or_default().as_u64();
+    let latest_millis = latest.timestamp.as_u64() as i64 * 1000;
+    let ahead_millis = time.timestamp_millis() - latest_millis;
+    let block_millis = block_time.as_millis().max(1) as i64;
+    if ahead_millis <= 0 {
+        return Ok(latest_number + 1);
+    }
+    Ok(latest_number + ((ahead_millis + block_millis - 1) / block_millis) as u64)
+}
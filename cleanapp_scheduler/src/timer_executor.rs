@@ -1,15 +1,20 @@
 use ethers::types::U256;
 use fatal::fatal;
 use std::time::{Duration, SystemTime};
-use tokio::{sync::mpsc::Sender, time::sleep};
+use tokio::time::sleep;
 use uuid::Uuid;
 
 use crate::{
     contracts_abi::{laminator::SolverData, CallPushedFilter},
+    event_bus::{Event, EventBus},
     solver::Solver,
     stats::{Status, TimerExecutorStats, TransactionStatus},
 };
 
+// Upper bound of the delay before retrying after transient errors in a row, the delay
+// starts at the tick and doubles with each one.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
 // The executor combined with a timer, PoC version.
 // For real prod version the timer is to be moved into its own thread to reduce a number of
 // contract read calls.
@@ -26,16 +31,12 @@ pub struct TimerRequestExecutor<S> {
     // Execution tick duration
     tick_duration: Duration,
 
-    // The channel for sending current stats
-    stats_tx: Sender<TimerExecutorStats>,
+    // The bus for publishing current stats and notifications
+    events: EventBus,
 }
 
 impl<S: Solver> TimerRequestExecutor<S> {
-    pub fn new(
-        solver: S,
-        tick_duration: Duration,
-        stats_tx: Sender<TimerExecutorStats>,
-    ) -> TimerRequestExecutor<S> {
+    pub fn new(solver: S, tick_duration: Duration, events: EventBus) -> TimerRequestExecutor<S> {
         let creation_time_res = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH);
         if creation_time_res.is_err() {
             fatal!(
@@ -48,32 +49,68 @@ impl<S: Solver> TimerRequestExecutor<S> {
             id: Uuid::new_v4(),
             creation_time: creation_time_res.ok().unwrap(),
             tick_duration,
-            stats_tx,
+            events,
         };
 
         ret
     }
 
     // Execute the FlashLiquidity executor with given params.
-    pub async fn execute(&self, event: CallPushedFilter) {
+    pub async fn execute(&self, mut event: CallPushedFilter) {
         println!("Executor {} started", self.id);
+        self.events.publish(Event::ExecutorStarted {
+            id: self.id,
+            sequence_number: event.sequence_number,
+        });
         // Create a solver of a given type
-        if self.solver.schedule_time().is_err() {
-            print!(
-                "Error getting time limit: {}",
-                &self.solver.schedule_time().err().unwrap()
-            );
+        if let Err(err) = self.solver.schedule_time() {
+            print!("Error getting time limit: {}", err);
             return;
         }
+        // Transient errors in a row.
+        let mut retries = 0;
         // Tokens reading.
         loop {
             // Actions
             match self.solver.exec_solver_step().await {
                 Ok(response) => {
                     if response.succeeded {
+                        // A call pulled since it was pushed would only waste the gas.
+                        match self.solver.superseded().await {
+                            Ok(Some(message)) => {
+                                println!("Executor {} superseded: {}", self.id, message);
+                                self.send_stats(
+                                    event.sequence_number,
+                                    Status::Superseded,
+                                    TransactionStatus::NotExecuted,
+                                    message,
+                                    response.remaining_secs,
+                                    &event.data,
+                                )
+                                .await;
+                                return;
+                            }
+                            Ok(None) => {}
+                            Err(err) if err.is_retryable() => {
+                                retries += 1;
+                                let backoff = self.retry_backoff(retries);
+                                println!(
+                                    "Error checking the call of the proxy, retrying in {:?}: {}",
+                                    backoff, err
+                                );
+                                sleep(backoff).await;
+                                continue;
+                            }
+                            // The final transaction is sent anyway, it is checked on chain.
+                            Err(err) => println!("Error checking the call of the proxy: {}", err),
+                        }
+                        self.events.publish(Event::TriggerFired {
+                            id: self.id,
+                            sequence_number: event.sequence_number,
+                            message: response.message.clone(),
+                        });
                         self.send_stats(
                             event.sequence_number,
-                            self.solver.app(),
                             Status::Running,
                             TransactionStatus::TransactionPending,
                             response.message.clone(),
@@ -84,21 +121,43 @@ impl<S: Solver> TimerRequestExecutor<S> {
                         match self.solver.final_exec().await {
                             Ok(response) => {
                                 if response.succeeded {
-                                    self.send_stats(
-                                        event.sequence_number,
-                                        self.solver.app(),
-                                        Status::Succeeded,
-                                        TransactionStatus::Succeeded,
-                                        response.message.clone(),
-                                        response.remaining_secs,
-                                        &event.data,
-                                    )
-                                    .await;
+                                    let stats = self
+                                        .stats(
+                                            event.sequence_number,
+                                            Status::Succeeded,
+                                            TransactionStatus::Succeeded,
+                                            response.message.clone(),
+                                            response.remaining_secs,
+                                            &event.data,
+                                        )
+                                        .await;
+                                    // A recurring schedule waits for its next time, the
+                                    // executor isn't finished.
+                                    let rearmed = self.solver.rearm().await;
+                                    self.events.publish(Event::Stats(TimerExecutorStats {
+                                        finished: rearmed.is_none(),
+                                        ..stats
+                                    }));
                                     println!("Executor {} successfully finished", self.id);
+                                    if let Some((sequence_number, message)) = rearmed {
+                                        println!("Executor {}: {}", self.id, message);
+                                        event.sequence_number = sequence_number;
+                                        self.send_stats(
+                                            event.sequence_number,
+                                            Status::Running,
+                                            TransactionStatus::StepPending,
+                                            message,
+                                            0,
+                                            &event.data,
+                                        )
+                                        .await;
+                                        retries = 0;
+                                        sleep(self.tick_duration).await;
+                                        continue;
+                                    }
                                 } else {
                                     self.send_stats(
                                         event.sequence_number,
-                                        self.solver.app(),
                                         Status::Failed,
                                         TransactionStatus::TransactionFailed,
                                         response.message.clone(),
@@ -113,11 +172,29 @@ impl<S: Solver> TimerRequestExecutor<S> {
                                     );
                                 }
                             }
+                            Err(err) if err.is_retryable() => {
+                                retries += 1;
+                                let backoff = self.retry_backoff(retries);
+                                println!(
+                                    "Error in solver final exec, retrying in {:?}: {}",
+                                    backoff, err
+                                );
+                                self.send_stats(
+                                    event.sequence_number,
+                                    Status::Running,
+                                    TransactionStatus::TransactionFailed,
+                                    err.to_string(),
+                                    response.remaining_secs,
+                                    &event.data,
+                                )
+                                .await;
+                                sleep(backoff).await;
+                                continue;
+                            }
                             Err(err) => {
                                 println!("Error in solver final exec: {}", err);
                                 self.send_stats(
                                     event.sequence_number,
-                                    self.solver.app(),
                                     Status::Failed,
                                     TransactionStatus::TransactionFailed,
                                     err.to_string(),
@@ -131,7 +208,6 @@ impl<S: Solver> TimerRequestExecutor<S> {
                     } else {
                         self.send_stats(
                             event.sequence_number,
-                            self.solver.app(),
                             Status::Running,
                             TransactionStatus::StepPending,
                             response.message.clone(),
@@ -141,11 +217,30 @@ impl<S: Solver> TimerRequestExecutor<S> {
                         .await;
                     }
                 }
+                Err(err) if err.is_retryable() => {
+                    retries += 1;
+                    let backoff = self.retry_backoff(retries);
+                    println!(
+                        "Error in solver step call, retrying in {:?}: {}",
+                        backoff, err
+                    );
+                    self.send_stats(
+                        event.sequence_number,
+                        Status::Running,
+                        TransactionStatus::StepFailed,
+                        err.to_string(),
+                        0,
+                        &event.data,
+                    )
+                    .await;
+                    sleep(backoff).await;
+                    continue;
+                }
+                // Permanent errors, e.g. bad parameters, fail the schedule right away.
                 Err(err) => {
                     println!("Error in solver step call: {}", err);
                     self.send_stats(
                         event.sequence_number,
-                        self.solver.app(),
                         Status::Failed,
                         TransactionStatus::StepFailed,
                         err.to_string(),
@@ -153,40 +248,71 @@ impl<S: Solver> TimerRequestExecutor<S> {
                         &event.data,
                     )
                     .await;
+                    return;
                 }
             }
+            retries = 0;
             // Wait for the next tick
             sleep(self.tick_duration).await;
         }
     }
 
-    // Send statistics into the stats channel
+    // Delay before the next attempt after the given number of transient errors in a row.
+    fn retry_backoff(&self, retries: u32) -> Duration {
+        self.tick_duration
+            .saturating_mul(2u32.saturating_pow(retries.saturating_sub(1)))
+            .min(MAX_RETRY_BACKOFF)
+    }
+
+    // Publish statistics on the event bus
     async fn send_stats(
         &self,
         sequence_number: U256,
-        app: String,
         status: Status,
         transaction_status: TransactionStatus,
         message: String,
         remaining_secs: i64,
-        params: &Vec<SolverData>,
+        params: &[SolverData],
     ) {
-        let res = self
-            .stats_tx
-            .send(TimerExecutorStats {
-                id: self.id,
-                sequence_number: sequence_number.as_u32(),
-                app,
-                creation_time: self.creation_time,
+        let stats = self
+            .stats(
+                sequence_number,
                 status,
                 transaction_status,
                 message,
-                params: params.clone(),
                 remaining_secs,
-            })
+                params,
+            )
             .await;
-        if let Some(err) = res.err() {
-            println!("Error sending stats: {}", err);
+        self.events.publish(Event::Stats(stats));
+    }
+
+    // Statistics of the executor, which is finished once it isn't running anymore.
+    async fn stats(
+        &self,
+        sequence_number: U256,
+        status: Status,
+        transaction_status: TransactionStatus,
+        message: String,
+        remaining_secs: i64,
+        params: &[SolverData],
+    ) -> TimerExecutorStats {
+        let (target_block, block_deviation) = self.solver.target_block_stats().await;
+        let finished = status != Status::Running;
+        TimerExecutorStats {
+            id: self.id,
+            proxy: Some(self.solver.proxy()),
+            sequence_number: sequence_number.as_u32(),
+            app: self.solver.app(),
+            creation_time: self.creation_time,
+            status,
+            transaction_status,
+            message,
+            params: params.to_vec(),
+            remaining_secs,
+            target_block,
+            block_deviation,
+            finished,
         }
     }
 }
@@ -7,15 +7,23 @@ FLASH_LOAN_ADDRESS=0xA04bABcCbcf9B9E51eE4954DB223E34691F5F65D
 SWAP_POOL_ADDRESS=0xD68B5dd90022f9871913198285cce9d90AAcCD62
 TICK_SECS=5
 TICK_NANOS=0
+LIMIT_ORDER_WALLET_SECRET="LOCAL_LESTNET_WALLET_PRIVATE_KEY_DEV"
 
-PROJECT_NAME="solver-438012"
-CURRENT_PROJECT=$(gcloud config get project)
-if [ "${PROJECT_NAME}" != "${CURRENT_PROJECT}" ]; then
-  gcloud auth login
-  gcloud config set project ${PROJECT_NAME}
+# Settings written by `cargo run -- init` override the defaults above.
+if [ -f solver.env ]; then
+  . ./solver.env
 fi
 
-LIMIT_ORDER_WALLET_PRIVATE_KEY=$(gcloud secrets versions access 1 --secret="LOCAL_LESTNET_WALLET_PRIVATE_KEY_DEV")
+if [ -z "${LIMIT_ORDER_WALLET_PRIVATE_KEY}" ]; then
+  PROJECT_NAME="solver-438012"
+  CURRENT_PROJECT=$(gcloud config get project)
+  if [ "${PROJECT_NAME}" != "${CURRENT_PROJECT}" ]; then
+    gcloud auth login
+    gcloud config set project ${PROJECT_NAME}
+  fi
+
+  LIMIT_ORDER_WALLET_PRIVATE_KEY=$(gcloud secrets versions access 1 --secret="${LIMIT_ORDER_WALLET_SECRET}")
+fi
 
 cargo run \
   -- \
@@ -0,0 +1,164 @@
+use axum::{extract::State, response::Json};
+use chrono::DateTime;
+use ethers::types::{Address, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+use crate::solver::selector;
+
+// Amounts of a token the solver wallet received and sent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenFlow {
+    pub received: U256,
+    pub sent: U256,
+}
+
+impl TokenFlow {
+    fn add(&mut self, other: &TokenFlow) {
+        self.received = self.received.saturating_add(other.received);
+        self.sent = self.sent.saturating_add(other.sent);
+    }
+}
+
+// What executing an objective cost and earned the solver, over all its final
+// transactions, including the reverted ones.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ObjectiveAccounting {
+    pub gas_spent_wei: U256,
+    pub tips_earned_wei: U256,
+    // Changes of the solver wallet balances across the final transactions, by token.
+    pub token_flows: BTreeMap<Address, TokenFlow>,
+}
+
+impl ObjectiveAccounting {
+    pub fn add_gas(&mut self, gas_used: U256, gas_price: U256) {
+        self.gas_spent_wei = self
+            .gas_spent_wei
+            .saturating_add(gas_used.saturating_mul(gas_price));
+    }
+
+    pub fn add_tip(&mut self, tip: U256) {
+        self.tips_earned_wei = self.tips_earned_wei.saturating_add(tip);
+    }
+
+    // Records the change of the wallet balance of the token.
+    pub fn add_balance_change(&mut self, token: Address, before: U256, after: U256) {
+        let flow = if after >= before {
+            TokenFlow {
+                received: after - before,
+                sent: U256::zero(),
+            }
+        } else {
+            TokenFlow {
+                received: U256::zero(),
+                sent: before - after,
+            }
+        };
+        self.token_flows.entry(token).or_default().add(&flow);
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct AccountingTotals {
+    // Objectives that sent at least one final transaction.
+    pub objectives: u64,
+    pub gas_spent_wei: U256,
+    pub tips_earned_wei: U256,
+    // The tips less the gas in wei, negative at a loss.
+    pub net_wei: String,
+    pub token_flows: BTreeMap<Address, TokenFlow>,
+}
+
+impl AccountingTotals {
+    fn add(&mut self, accounting: &ObjectiveAccounting) {
+        self.objectives += 1;
+        self.gas_spent_wei = self.gas_spent_wei.saturating_add(accounting.gas_spent_wei);
+        self.tips_earned_wei = self
+            .tips_earned_wei
+            .saturating_add(accounting.tips_earned_wei);
+        self.net_wei = if self.tips_earned_wei >= self.gas_spent_wei {
+            (self.tips_earned_wei - self.gas_spent_wei).to_string()
+        } else {
+            format!("-{}", self.gas_spent_wei - self.tips_earned_wei)
+        };
+        for (token, flow) in &accounting.token_flows {
+            self.token_flows.entry(*token).or_default().add(flow);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AppAccounting {
+    pub selector: H256,
+    pub total: AccountingTotals,
+    // Totals by UTC day, as YYYY-MM-DD.
+    pub days: BTreeMap<String, AccountingTotals>,
+}
+
+// Gas and earnings of the executed objectives, by app.
+#[derive(Default)]
+pub struct Accounting {
+    apps: BTreeMap<String, AppAccounting>,
+}
+
+impl Accounting {
+    // Adds an objective finished at the given time since Unix epoch.
+    pub fn add(&mut self, app: &str, finished: Duration, accounting: &ObjectiveAccounting) {
+        let day = DateTime::from_timestamp(finished.as_secs() as i64, 0)
+            .map(|time| time.date_naive().to_string())
+            .unwrap_or_default();
+        let app_accounting = self
+            .apps
+            .entry(app.to_string())
+            .or_insert_with(|| AppAccounting {
+                selector: selector(app.to_string()),
+                total: AccountingTotals::default(),
+                days: BTreeMap::new(),
+            });
+        app_accounting.total.add(accounting);
+        app_accounting.days.entry(day).or_default().add(accounting);
+    }
+}
+
+pub async fn get_accounting_json(
+    accounting: State<Arc<Mutex<Accounting>>>,
+) -> Json<BTreeMap<String, AppAccounting>> {
+    Json(accounting.lock().await.apps.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objectives_add_up_per_app_and_day() {
+        let token = Address::repeat_byte(0xda);
+        let mut objective = ObjectiveAccounting::default();
+        objective.add_gas(100000.into(), 10.into());
+        objective.add_tip(300000.into());
+        objective.add_balance_change(token, 50.into(), 20.into());
+        objective.add_balance_change(token, 20.into(), 25.into());
+        let mut reverted = ObjectiveAccounting::default();
+        reverted.add_gas(100000.into(), 10.into());
+
+        let mut accounting = Accounting::default();
+        let day = Duration::from_secs(86400 * 365);
+        accounting.add("APP", day, &objective);
+        accounting.add("APP", day + Duration::from_secs(86400), &reverted);
+
+        let app = &accounting.apps["APP"];
+        assert_eq!(app.total.objectives, 2);
+        assert_eq!(app.total.gas_spent_wei, 2000000.into());
+        assert_eq!(app.total.net_wei, "-1700000");
+        assert_eq!(app.days["1971-01-01"].net_wei, "-700000");
+        assert_eq!(app.days["1971-01-02"].net_wei, "-1000000");
+        assert_eq!(
+            app.total.token_flows[&token],
+            TokenFlow {
+                received: 5.into(),
+                sent: 30.into(),
+            }
+        );
+    }
+}
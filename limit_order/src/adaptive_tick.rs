@@ -0,0 +1,35 @@
+use ethers::types::U256;
+use std::time::Duration;
+
+// Relative distance to the trigger price in basis points from which the longest tick is
+// used, closer to the trigger the tick shortens linearly.
+const FAR_DISTANCE_BPS: u64 = 1000;
+
+// Bounds of the tick of price triggered objectives.
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveTick {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl AdaptiveTick {
+    // The interval before the next price check, the shortest at the trigger price.
+    pub fn tick(&self, current_price: U256, trigger_price: U256) -> Duration {
+        if trigger_price.is_zero() {
+            return self.min;
+        }
+        let difference = if current_price > trigger_price {
+            current_price - trigger_price
+        } else {
+            trigger_price - current_price
+        };
+        let distance_bps = difference
+            .saturating_mul(10000.into())
+            .checked_div(trigger_price)
+            .unwrap_or_default()
+            .min(FAR_DISTANCE_BPS.into())
+            .as_u64();
+        let range = self.max.saturating_sub(self.min);
+        self.min + range.mul_f64(distance_bps as f64 / FAR_DISTANCE_BPS as f64)
+    }
+}
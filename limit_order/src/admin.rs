@@ -0,0 +1,156 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+use tokio::sync::watch;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PauseState {
+    // No executors are started while paused, the received objectives are held.
+    pub paused: bool,
+    // The running executors don't send their final transactions while set.
+    pub broadcast_suspended: bool,
+    pub reason: Option<String>,
+    // Time of the pause since Unix epoch.
+    pub since: Option<Duration>,
+}
+
+// Kill switch of the solving, the events are still received and the stats served.
+pub struct SolvingSwitch {
+    state: watch::Sender<PauseState>,
+    // Objectives waiting for the solving to be resumed.
+    held: AtomicUsize,
+}
+
+impl SolvingSwitch {
+    pub fn new() -> SolvingSwitch {
+        SolvingSwitch {
+            state: watch::channel(PauseState::default()).0,
+            held: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn broadcast_suspended(&self) -> bool {
+        self.state.borrow().broadcast_suspended
+    }
+
+    // Returns once the solving isn't paused, the objective is held meanwhile.
+    pub async fn wait_resumed(&self) {
+        let mut state = self.state.subscribe();
+        if !state.borrow_and_update().paused {
+            return;
+        }
+        self.held.fetch_add(1, Ordering::Relaxed);
+        // The sender lives as long as the switch.
+        let _ = state.wait_for(|state| !state.paused).await;
+        self.held.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn status(&self) -> AdminStatus {
+        AdminStatus {
+            state: self.state.borrow().clone(),
+            held_objectives: self.held.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminStatus {
+    #[serde(flatten)]
+    pub state: PauseState,
+    pub held_objectives: usize,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PauseRequest {
+    #[serde(default)]
+    pub suspend_broadcast: bool,
+    pub reason: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct AdminState {
+    pub switch: Arc<SolvingSwitch>,
+    // Expected as a bearer token in the Authorization header.
+    pub token: String,
+}
+
+impl AdminState {
+    pub fn authorize(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match token {
+            Some(token) if constant_time_eq(token.as_bytes(), self.token.as_bytes()) => Ok(()),
+            _ => Err((
+                StatusCode::UNAUTHORIZED,
+                "Missing or wrong admin token".to_string(),
+            )),
+        }
+    }
+}
+
+// Compares the tokens in a time that doesn't depend on the matching prefix.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// Pauses the solving, the body is optional.
+pub async fn pause(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+    request: Option<Json<PauseRequest>>,
+) -> Result<Json<AdminStatus>, (StatusCode, String)> {
+    admin.authorize(&headers)?;
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    admin.switch.state.send_modify(|state| {
+        if !state.paused {
+            state.since = Some(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default(),
+            );
+        }
+        state.paused = true;
+        state.broadcast_suspended = request.suspend_broadcast;
+        state.reason = request.reason;
+    });
+    println!(
+        "Solving paused{}",
+        if request.suspend_broadcast {
+            ", final transactions suspended"
+        } else {
+            ""
+        }
+    );
+    Ok(Json(admin.switch.status()))
+}
+
+// Resumes the solving, the held objectives are started.
+pub async fn resume(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+) -> Result<Json<AdminStatus>, (StatusCode, String)> {
+    admin.authorize(&headers)?;
+    admin.switch.state.send_replace(PauseState::default());
+    println!("Solving resumed");
+    Ok(Json(admin.switch.status()))
+}
+
+pub async fn get_status(
+    State(admin): State<AdminState>,
+    headers: HeaderMap,
+) -> Result<Json<AdminStatus>, (StatusCode, String)> {
+    admin.authorize(&headers)?;
+    Ok(Json(admin.switch.status()))
+}
@@ -0,0 +1,298 @@
+use ethers::types::{Address, Bytes, U256};
+use serde::Deserialize;
+use std::str::FromStr;
+
+use crate::{
+    contracts_abi::call_breaker::CallObject, http_client::http_client, solver::SolverError,
+};
+
+// Gas limit of the routed swap if the aggregator doesn't estimate it.
+const SWAP_GAS: u64 = 10000000;
+
+// The off-chain aggregator the swaps are routed through, 0x or 1inch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AggregatorKind {
+    ZeroX,
+    OneInch,
+}
+
+impl FromStr for AggregatorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "0x" => Ok(AggregatorKind::ZeroX),
+            "1inch" => Ok(AggregatorKind::OneInch),
+            _ => Err(format!(
+                "unknown aggregator \"{}\", expected 0x or 1inch",
+                s
+            )),
+        }
+    }
+}
+
+impl AggregatorKind {
+    // The public API of the aggregator.
+    pub fn default_url(&self) -> &'static str {
+        match self {
+            AggregatorKind::ZeroX => "https://api.0x.org",
+            AggregatorKind::OneInch => "https://api.1inch.dev",
+        }
+    }
+}
+
+// A swap quoted by the aggregator, the call to its router with the amounts it was
+// quoted for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregatorQuote {
+    pub router: Address,
+    pub calldata: Bytes,
+    pub value: U256,
+    pub gas: U256,
+    pub sell_amount: U256,
+    pub buy_amount: U256,
+    // The router reverts if the swap delivers less.
+    pub min_buy_amount: U256,
+}
+
+impl AggregatorQuote {
+    pub fn call(&self) -> CallObject {
+        CallObject {
+            amount: self.value,
+            addr: self.router,
+            gas: self.gas,
+            callvalue: self.calldata.clone(),
+        }
+    }
+
+    // Why the quote doesn't hold the slippage of the order, if it doesn't. The swap must
+    // deliver at least the expected amount less the slippage percentage, whatever the
+    // route.
+    pub fn min_out_violation(&self, expected_out: U256, slippage_percent: U256) -> Option<String> {
+        let slippage = slippage_percent.min(100.into());
+        let needed = expected_out * (U256::from(100) - slippage) / 100;
+        (self.min_buy_amount < needed).then(|| {
+            format!(
+                "The aggregator guarantees {} out, less than the {} expected within the slippage of {}%",
+                self.min_buy_amount, needed, slippage
+            )
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ZeroXQuote {
+    buy_amount: String,
+    min_buy_amount: String,
+    transaction: ZeroXTransaction,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZeroXTransaction {
+    to: Address,
+    data: Bytes,
+    value: String,
+    gas: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OneInchSwap {
+    dst_amount: String,
+    tx: OneInchTransaction,
+}
+
+#[derive(Debug, Deserialize)]
+struct OneInchTransaction {
+    to: Address,
+    data: Bytes,
+    value: String,
+    gas: Option<u64>,
+}
+
+fn amount(name: &str, value: &str) -> Result<U256, SolverError> {
+    U256::from_dec_str(value)
+        .map_err(|err| SolverError::ExecError(format!("invalid {} {}: {}", name, value, err)))
+}
+
+// Requests swap calldata from the aggregator API for the call breaker to execute. The
+// quotes are only used if they call the configured router, the API isn't trusted with
+// the target of the call.
+pub struct Aggregator {
+    kind: AggregatorKind,
+    url: String,
+    api_key: Option<String>,
+    chain_id: u64,
+    router: Address,
+    client: reqwest::Client,
+}
+
+impl Aggregator {
+    pub fn new(
+        kind: AggregatorKind,
+        url: Option<String>,
+        api_key: Option<String>,
+        chain_id: u64,
+        router: Address,
+    ) -> Result<Aggregator, String> {
+        Ok(Aggregator {
+            kind,
+            url: url
+                .unwrap_or(kind.default_url().to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            api_key,
+            chain_id,
+            router,
+            client: http_client()?,
+        })
+    }
+
+    // Quotes the swap of the sell amount made by the taker, the router enforces the
+    // slippage percentage on the quoted amount.
+    pub async fn quote(
+        &self,
+        sell_token: Address,
+        buy_token: Address,
+        sell_amount: U256,
+        taker: Address,
+        slippage_percent: U256,
+    ) -> Result<AggregatorQuote, SolverError> {
+        let request = match self.kind {
+            AggregatorKind::ZeroX => self
+                .client
+                .get(format!("{}/swap/allowance-holder/quote", self.url))
+                .query(&[
+                    ("chainId", self.chain_id.to_string()),
+                    ("sellToken", format!("{:?}", sell_token)),
+                    ("buyToken", format!("{:?}", buy_token)),
+                    ("sellAmount", sell_amount.to_string()),
+                    ("taker", format!("{:?}", taker)),
+                    (
+                        "slippageBps",
+                        (slippage_percent * U256::from(100)).to_string(),
+                    ),
+                ])
+                .header("0x-version", "v2")
+                .header("0x-api-key", self.api_key.clone().unwrap_or_default()),
+            AggregatorKind::OneInch => self
+                .client
+                .get(format!("{}/swap/v6.0/{}/swap", self.url, self.chain_id))
+                .query(&[
+                    ("src", format!("{:?}", sell_token)),
+                    ("dst", format!("{:?}", buy_token)),
+                    ("amount", sell_amount.to_string()),
+                    ("from", format!("{:?}", taker)),
+                    ("slippage", slippage_percent.to_string()),
+                    // The taker only holds the tokens during the final transaction.
+                    ("disableEstimate", "true".to_string()),
+                ])
+                .bearer_auth(self.api_key.clone().unwrap_or_default()),
+        };
+        let response = request
+            .send()
+            .await
+            .map_err(|err| SolverError::RpcError(format!("aggregator request error: {}", err)))?;
+        if !response.status().is_success() {
+            return Err(SolverError::RpcError(format!(
+                "the aggregator answered {}",
+                response.status()
+            )));
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|err| SolverError::RpcError(format!("aggregator response error: {}", err)))?;
+        let quote = parse_quote(self.kind, &body, sell_amount, slippage_percent)?;
+        if quote.router != self.router {
+            return Err(SolverError::ExecError(format!(
+                "the aggregator routes the swap through {:?}, not the router {:?}",
+                quote.router, self.router
+            )));
+        }
+        Ok(quote)
+    }
+}
+
+fn parse_quote(
+    kind: AggregatorKind,
+    body: &str,
+    sell_amount: U256,
+    slippage_percent: U256,
+) -> Result<AggregatorQuote, SolverError> {
+    let invalid = |err: serde_json::Error| {
+        SolverError::ExecError(format!("invalid aggregator response: {}", err))
+    };
+    match kind {
+        AggregatorKind::ZeroX => {
+            let quote: ZeroXQuote = serde_json::from_str(body).map_err(invalid)?;
+            Ok(AggregatorQuote {
+                router: quote.transaction.to,
+                calldata: quote.transaction.data,
+                value: amount("value", &quote.transaction.value)?,
+                gas: match quote.transaction.gas {
+                    Some(gas) => amount("gas", &gas)?,
+                    None => SWAP_GAS.into(),
+                },
+                sell_amount,
+                buy_amount: amount("buyAmount", &quote.buy_amount)?,
+                min_buy_amount: amount("minBuyAmount", &quote.min_buy_amount)?,
+            })
+        }
+        // The minimum return is built into the calldata from the slippage of the request.
+        AggregatorKind::OneInch => {
+            let swap: OneInchSwap = serde_json::from_str(body).map_err(invalid)?;
+            let buy_amount = amount("dstAmount", &swap.dst_amount)?;
+            let slippage = slippage_percent.min(100.into());
+            Ok(AggregatorQuote {
+                router: swap.tx.to,
+                calldata: swap.tx.data,
+                value: amount("value", &swap.tx.value)?,
+                // Not estimated without the taker balances.
+                gas: swap
+                    .tx
+                    .gas
+                    .filter(|gas| *gas > 0)
+                    .unwrap_or(SWAP_GAS)
+                    .into(),
+                sell_amount,
+                buy_amount,
+                min_buy_amount: buy_amount * (U256::from(100) - slippage) / 100,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_are_parsed_and_checked() {
+        let zero_x = r#"{
+            "buyAmount": "1010",
+            "minBuyAmount": "990",
+            "transaction": {"to": "0x0000000000001ff3684f28c67538d4d072c22734", "data": "0x2213bc0b", "value": "0", "gas": "210000"}
+        }"#;
+        let quote = parse_quote(AggregatorKind::ZeroX, zero_x, 10.into(), 2.into())
+            .ok()
+            .unwrap();
+        assert_eq!(quote.min_buy_amount, 990.into());
+        assert_eq!(quote.gas, 210000.into());
+        assert!(quote.min_out_violation(1010.into(), 1.into()).is_some());
+        assert!(quote.min_out_violation(1010.into(), 2.into()).is_none());
+
+        let one_inch = r#"{
+            "dstAmount": "1000",
+            "tx": {"from": "0xcbcbcbcbcbcbcbcbcbcbcbcbcbcbcbcbcbcbcbcb", "to": "0x111111125421ca6dc452d289314280a0f8842a65", "data": "0x07ed2379", "value": "0", "gas": 0, "gasPrice": "1"}
+        }"#;
+        let quote = parse_quote(AggregatorKind::OneInch, one_inch, 10.into(), 3.into())
+            .ok()
+            .unwrap();
+        assert_eq!(quote.min_buy_amount, 970.into());
+        assert_eq!(quote.gas, SWAP_GAS.into());
+
+        assert!(parse_quote(AggregatorKind::ZeroX, one_inch, 10.into(), 3.into()).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use ethers::types::U256;
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use crate::{executor_queue::ExecutorQueue, solver::SolverParams};
+
+// Executor settings of an app, as APP:KEY=VALUE[,KEY=VALUE...] with the keys tick,
+// time_limit, max_concurrent_executors and gas_budget. The global ones are used for the
+// unset keys.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AppConfig {
+    pub app: String,
+    pub tick: Option<Duration>,
+    // Time limit of the objectives not giving one.
+    pub time_limit: Option<Duration>,
+    // Running executors of the app, within the global maximum.
+    pub max_concurrent_executors: Option<usize>,
+    // Wei the failed final transactions of an objective may spend.
+    pub gas_budget: Option<U256>,
+}
+
+impl FromStr for AppConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (app, settings) = match s.split_once(':') {
+            Some((app, settings)) if !app.is_empty() && !settings.is_empty() => (app, settings),
+            _ => {
+                return Err(format!(
+                    "expected APP:KEY=VALUE[,KEY=VALUE...], got \"{}\"",
+                    s
+                ))
+            }
+        };
+        let duration = |value: &str| {
+            parse_duration::parse(value)
+                .map_err(|err| format!("invalid duration {}: {}", value, err))
+        };
+        let mut config = AppConfig {
+            app: app.to_string(),
+            ..Default::default()
+        };
+        for setting in settings.split(',') {
+            match setting.split_once('=') {
+                Some(("tick", value)) => config.tick = Some(duration(value)?),
+                Some(("time_limit", value)) => config.time_limit = Some(duration(value)?),
+                Some(("max_concurrent_executors", value)) => {
+                    config.max_concurrent_executors =
+                        Some(value.parse().map_err(|err| format!("{}", err))?)
+                }
+                Some(("gas_budget", value)) => {
+                    config.gas_budget = Some(
+                        U256::from_dec_str(value)
+                            .map_err(|err| format!("invalid gas budget {}: {}", value, err))?,
+                    )
+                }
+                _ => return Err(format!("unknown app setting \"{}\"", setting)),
+            }
+        }
+        if config.tick.is_some_and(|tick| tick.is_zero()) {
+            return Err(format!("the tick of the app {} is zero", app));
+        }
+        if config.max_concurrent_executors == Some(0) {
+            return Err(format!(
+                "the max concurrent executors of the app {} is zero",
+                app
+            ));
+        }
+        Ok(config)
+    }
+}
+
+impl AppConfig {
+    // The params of the app, the queued objectives of the app wait at most max_wait
+    // before the ones of higher value.
+    pub fn apply<M: Clone>(
+        &self,
+        params: SolverParams<M>,
+        max_wait: Option<Duration>,
+    ) -> SolverParams<M> {
+        SolverParams {
+            tick: self.tick.unwrap_or(params.tick),
+            default_time_limit: self.time_limit.or(params.default_time_limit),
+            app_queue: match self.max_concurrent_executors {
+                Some(max) => Some(Arc::new(ExecutorQueue::new(Some(max), max_wait))),
+                None => params.app_queue.clone(),
+            },
+            gas_budget: self.gas_budget.or(params.gas_budget),
+            ..params
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_configs_are_parsed() {
+        assert_eq!(
+            AppConfig::from_str("LIMIT_ORDER:tick=250ms,max_concurrent_executors=4")
+                .ok()
+                .unwrap(),
+            AppConfig {
+                app: "LIMIT_ORDER".to_string(),
+                tick: Some(Duration::from_millis(250)),
+                time_limit: None,
+                max_concurrent_executors: Some(4),
+                gas_budget: None,
+            }
+        );
+        assert_eq!(
+            AppConfig::from_str("CRON:tick=1m,time_limit=1h")
+                .ok()
+                .unwrap()
+                .time_limit,
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(
+            AppConfig::from_str("LIMIT_ORDER:gas_budget=1000000000000000")
+                .ok()
+                .unwrap()
+                .gas_budget,
+            Some(U256::exp10(15))
+        );
+        assert!(AppConfig::from_str("LIMIT_ORDER").is_err());
+        assert!(AppConfig::from_str("LIMIT_ORDER:tick=0s").is_err());
+        assert!(AppConfig::from_str("LIMIT_ORDER:max_concurrent_executors=0").is_err());
+        assert!(AppConfig::from_str("LIMIT_ORDER:ticks=1s").is_err());
+    }
+}
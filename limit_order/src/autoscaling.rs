@@ -0,0 +1,112 @@
+use axum::{extract::State, response::Json};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, collections::HashMap, sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+#[cfg(feature = "webhooks")]
+use tokio::time::sleep;
+use uuid::Uuid;
+
+#[cfg(feature = "webhooks")]
+use crate::http_client::http_client;
+use crate::{
+    executor_queue::ExecutorQueue,
+    stats::{Status, TimerExecutorStats},
+};
+
+// Number of the latest finished executors the solve latency is measured over.
+const LATENCY_SAMPLES: usize = 100;
+
+// Load signals for scaling out solver instances.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AutoscalingSignals {
+    // Objectives waiting for a free executor slot.
+    pub queue_depth: usize,
+    pub running: usize,
+    pub max_running: Option<usize>,
+    // Share of the executor slots in use, not set if the executors aren't limited.
+    pub saturation: Option<f64>,
+    // Execution time of the latest finished executors.
+    pub solve_latency_avg: Option<Duration>,
+    pub solve_latency_p95: Option<Duration>,
+    pub latency_samples: usize,
+}
+
+#[derive(Clone)]
+pub struct AutoscalingState {
+    pub queue: Arc<ExecutorQueue>,
+    pub stats_map: Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>,
+}
+
+impl AutoscalingState {
+    pub async fn signals(&self) -> AutoscalingSignals {
+        let (running, queue_depth) = self.queue.depth();
+        let max_running = self.queue.max_running();
+        let mut finished = self
+            .stats_map
+            .lock()
+            .await
+            .values()
+            .filter(|stats| {
+                matches!(
+                    stats.status,
+                    Status::Succeeded | Status::Failed | Status::Timeout | Status::BudgetExhausted
+                )
+            })
+            .map(|stats| (stats.creation_time, stats.elapsed))
+            .collect::<Vec<(Duration, Duration)>>();
+        finished.sort_by_key(|(creation_time, _)| Reverse(*creation_time));
+        let mut latencies = finished
+            .into_iter()
+            .take(LATENCY_SAMPLES)
+            .map(|(_, elapsed)| elapsed)
+            .collect::<Vec<Duration>>();
+        latencies.sort();
+        let (solve_latency_avg, solve_latency_p95) = if latencies.is_empty() {
+            (None, None)
+        } else {
+            (
+                Some(latencies.iter().sum::<Duration>() / latencies.len() as u32),
+                Some(latencies[(latencies.len() * 95).div_ceil(100) - 1]),
+            )
+        };
+        AutoscalingSignals {
+            queue_depth,
+            running,
+            max_running,
+            saturation: max_running.map(|max| running as f64 / max.max(1) as f64),
+            solve_latency_avg,
+            solve_latency_p95,
+            latency_samples: latencies.len(),
+        }
+    }
+}
+
+pub async fn get_autoscaling_json(state: State<AutoscalingState>) -> Json<AutoscalingSignals> {
+    Json(state.signals().await)
+}
+
+// Periodically pushes the signals to an external autoscaler.
+#[cfg(feature = "webhooks")]
+pub async fn run_autoscaler_push(state: AutoscalingState, url: String, interval: Duration) {
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(err) => {
+            println!("{}, the autoscaling signals aren't pushed", err);
+            return;
+        }
+    };
+    loop {
+        sleep(interval).await;
+        let signals = state.signals().await;
+        match client.post(url.as_str()).json(&signals).send().await {
+            Ok(response) => {
+                if !response.status().is_success() {
+                    println!("Autoscaler rejected the signals: {}", response.status());
+                }
+            }
+            Err(err) => {
+                println!("Error pushing autoscaling signals: {}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use ethers::providers::{Middleware, PubsubClient, StreamExt};
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::watch, time::sleep};
+
+// Delay before subscribing to the blocks again after the subscription failed.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+// Publishes the number of every new block from a single newHeads subscription, shared
+// by the executors ticking once per block.
+pub async fn run_block_ticker<M>(middleware: Arc<M>, tx: watch::Sender<u64>)
+where
+    M: Middleware,
+    M::Provider: PubsubClient,
+{
+    loop {
+        match middleware.subscribe_blocks().await {
+            Ok(mut blocks) => {
+                while let Some(block) = blocks.next().await {
+                    if let Some(number) = block.number {
+                        tx.send_replace(number.as_u64());
+                    }
+                }
+                println!("The block subscription of the block ticker ended");
+            }
+            Err(err) => println!(
+                "Error subscribing to the blocks for the block ticker: {}",
+                err
+            ),
+        }
+        sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
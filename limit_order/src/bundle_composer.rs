@@ -0,0 +1,219 @@
+use ethers::types::{Address, U256};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+use tokio::{sync::oneshot, time::timeout};
+
+use crate::{call_plan::CallPlan, chain_client::Execution, solver::SolverError};
+
+// Delivers the execution of a bundle to an order executed in it.
+pub struct OutcomeSender(oneshot::Sender<Result<Execution, String>>);
+
+impl OutcomeSender {
+    pub fn send(self, outcome: Result<&Execution, &SolverError>) {
+        // The order may have stopped waiting, e.g. its executor was cancelled.
+        self.0
+            .send(outcome.cloned().map_err(|err| err.to_string()))
+            .ok();
+    }
+}
+
+// An order ready for its final execution, waiting for a complementary one.
+pub struct Offer {
+    id: u64,
+    pub sequence_number: U256,
+    pub plan: CallPlan,
+    pub outcome: OutcomeSender,
+}
+
+pub enum Composition {
+    // No complementary order came within the window, the order is executed alone.
+    Alone(CallPlan),
+    // The order executes the bundle, the calls of the offered order run after its own.
+    Leading(CallPlan, Offer),
+    // The order was executed in the bundle of another one.
+    Joined(Result<Execution, String>),
+}
+
+type Offers = HashMap<(Address, Address), Vec<Offer>>;
+
+// Withdraws the offer once its order stops waiting, e.g. its executor is cancelled, so
+// that no bundle executes the calls of an order that is gone.
+struct Withdrawal<'a> {
+    offers: &'a Mutex<Offers>,
+    pair: (Address, Address),
+    id: u64,
+}
+
+impl Drop for Withdrawal<'_> {
+    fn drop(&mut self) {
+        take_offer(&mut self.offers.lock().unwrap(), self.pair, |offer| {
+            offer.id == self.id
+        });
+    }
+}
+
+// Matches the orders of opposite directions on the same pair which are ready within the
+// window of each other, to be executed in one transaction sharing its gas. The order
+// ready last leads the bundle.
+pub struct BundleComposer {
+    window: Duration,
+    // Waiting offers by the (give, take) tokens of their orders, the oldest first.
+    offers: Mutex<Offers>,
+    next_id: AtomicU64,
+}
+
+impl BundleComposer {
+    pub fn new(window: Duration) -> BundleComposer {
+        BundleComposer {
+            window,
+            offers: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) async fn compose(
+        &self,
+        give_token: Address,
+        take_token: Address,
+        sequence_number: U256,
+        plan: CallPlan,
+    ) -> Composition {
+        let (outcome_tx, mut outcome_rx) = oneshot::channel();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut offers = self.offers.lock().unwrap();
+            if let Some(offer) = take_offer(&mut offers, (take_token, give_token), |_| true) {
+                return Composition::Leading(plan, offer);
+            }
+            offers
+                .entry((give_token, take_token))
+                .or_default()
+                .push(Offer {
+                    id,
+                    sequence_number,
+                    plan,
+                    outcome: OutcomeSender(outcome_tx),
+                });
+        }
+        let _withdrawal = Withdrawal {
+            offers: &self.offers,
+            pair: (give_token, take_token),
+            id,
+        };
+        let outcome = match timeout(self.window, &mut outcome_rx).await {
+            Ok(outcome) => outcome,
+            Err(_) => {
+                let offer = take_offer(
+                    &mut self.offers.lock().unwrap(),
+                    (give_token, take_token),
+                    |offer| offer.id == id,
+                );
+                if let Some(offer) = offer {
+                    return Composition::Alone(offer.plan);
+                }
+                // Taken by another order as the window ended.
+                outcome_rx.await
+            }
+        };
+        Composition::Joined(
+            outcome.unwrap_or_else(|_| Err("the bundle was dropped unexecuted".to_string())),
+        )
+    }
+}
+
+fn take_offer<F: Fn(&Offer) -> bool>(
+    offers: &mut Offers,
+    pair: (Address, Address),
+    matches: F,
+) -> Option<Offer> {
+    let waiting = offers.get_mut(&pair)?;
+    let offer = waiting
+        .iter()
+        .position(matches)
+        .map(|position| waiting.remove(position));
+    if waiting.is_empty() {
+        offers.remove(&pair);
+    }
+    offer
+}
+
+// The share of an order of the execution of a bundle of two, each pays half of the gas.
+pub fn gas_share(execution: Execution) -> Execution {
+    match execution {
+        Execution::Sent {
+            tx_hash,
+            status,
+            block_number,
+            gas_used,
+            effective_gas_price,
+            bundle,
+        } => Execution::Sent {
+            tx_hash,
+            status,
+            block_number,
+            gas_used: gas_used.map(|gas_used| gas_used / 2),
+            effective_gas_price,
+            bundle,
+        },
+        simulated => simulated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn opposite_orders_are_bundled() {
+        let (dai, weth) = (Address::repeat_byte(0xda), Address::repeat_byte(0xee));
+        let composer = Arc::new(BundleComposer::new(Duration::from_millis(100)));
+        let plan = || CallPlan::new(Address::repeat_byte(0xcb));
+        let offer = || {
+            let composer = composer.clone();
+            tokio::spawn(async move { composer.compose(dai, weth, 1.into(), plan()).await })
+        };
+
+        // An order of the same direction doesn't complement the waiting one.
+        let waiting = offer();
+        tokio::task::yield_now().await;
+        assert!(matches!(
+            composer.compose(dai, weth, 2.into(), plan()).await,
+            Composition::Alone(_)
+        ));
+        assert!(matches!(waiting.await.ok().unwrap(), Composition::Alone(_)));
+
+        let waiting = offer();
+        tokio::task::yield_now().await;
+        match composer.compose(weth, dai, 3.into(), plan()).await {
+            Composition::Leading(_, offer) => {
+                assert_eq!(offer.sequence_number, 1.into());
+                offer
+                    .outcome
+                    .send(Ok(&Execution::Simulated("Dry run".to_string())));
+            }
+            _ => panic!("the opposite order should lead the bundle"),
+        }
+        assert!(matches!(
+            waiting.await.ok().unwrap(),
+            Composition::Joined(Ok(Execution::Simulated(_)))
+        ));
+
+        // The offer of a cancelled order is withdrawn.
+        let waiting = offer();
+        tokio::task::yield_now().await;
+        waiting.abort();
+        let _ = waiting.await;
+        assert!(composer.offers.lock().unwrap().is_empty());
+        assert!(matches!(
+            composer.compose(weth, dai, 4.into(), plan()).await,
+            Composition::Alone(_)
+        ));
+    }
+}
@@ -0,0 +1,344 @@
+use ethers::{
+    abi::{self, AbiEncode, Token},
+    types::{Address, Bytes},
+};
+
+use crate::{
+    chain_client::ChainClient,
+    contracts_abi::call_breaker::{CallObject, ReturnObject},
+    solver::SolverError,
+};
+
+// How the expected return of a planned call is found out.
+enum PlannedReturn {
+    // Observed by simulating the call from the call breaker.
+    Simulated,
+    // The returns of the calls pushed to the proxy, simulated from the proxy.
+    Pull(Vec<CallObject>),
+    // Given for the calls that only pass within the execution, e.g. on flash loaned funds.
+    // They are left out of the simulation.
+    Known(Bytes),
+}
+
+struct PlannedCall {
+    call: CallObject,
+    planned_return: PlannedReturn,
+    name: Option<&'static str>,
+    // Names of the calls that must run before this one.
+    after: Vec<&'static str>,
+}
+
+// The calls of a final transaction with their expected returns, derived by simulating
+// the calls in the planned order rather than written by hand. The calls are simulated in
+// sequence on the latest state, each sees the effects of the calls before it, e.g. a swap
+// the approval of its input.
+//
+// The calls are ordered by their declared dependencies, e.g. an approval before the swap
+// spending it, otherwise they run in the order they were added.
+pub struct CallPlan {
+    call_breaker: Address,
+    calls: Vec<PlannedCall>,
+}
+
+impl CallPlan {
+    pub fn new(call_breaker: Address) -> CallPlan {
+        CallPlan {
+            call_breaker,
+            calls: Vec::new(),
+        }
+    }
+
+    pub fn call(self, call: CallObject) -> CallPlan {
+        self.push(call, PlannedReturn::Simulated)
+    }
+
+    // The pull of the calls pushed to the proxy the call is made to.
+    pub fn pull(self, call: CallObject, pushed: Vec<CallObject>) -> CallPlan {
+        self.push(call, PlannedReturn::Pull(pushed))
+    }
+
+    pub fn call_returning(self, call: CallObject, returnvalue: Bytes) -> CallPlan {
+        self.push(call, PlannedReturn::Known(returnvalue))
+    }
+
+    fn push(mut self, call: CallObject, planned_return: PlannedReturn) -> CallPlan {
+        self.calls.push(PlannedCall {
+            call,
+            planned_return,
+            name: None,
+            after: Vec::new(),
+        });
+        self
+    }
+
+    // Names the last added call, for other calls to depend on.
+    pub fn named(mut self, name: &'static str) -> CallPlan {
+        if let Some(last) = self.calls.last_mut() {
+            last.name = Some(name);
+        }
+        self
+    }
+
+    // The last added call runs after all the calls of the given names.
+    pub fn after(mut self, names: &[&'static str]) -> CallPlan {
+        if let Some(last) = self.calls.last_mut() {
+            last.after.extend_from_slice(names);
+        }
+        self
+    }
+
+    // Positions of the calls in the execution order. Each call is placed as soon as its
+    // dependencies are, so the independent calls keep the order they were added in.
+    fn order(&self) -> Result<Vec<usize>, SolverError> {
+        for (i, planned) in self.calls.iter().enumerate() {
+            if let Some(name) = planned
+                .after
+                .iter()
+                .find(|name| !self.calls.iter().any(|other| other.name == Some(**name)))
+            {
+                return Err(SolverError::ExecError(format!(
+                    "the call {} depends on the unknown call \"{}\"",
+                    i, name
+                )));
+            }
+        }
+        let mut placed = vec![false; self.calls.len()];
+        let mut order = Vec::new();
+        while order.len() < self.calls.len() {
+            let next = (0..self.calls.len()).find(|&i| {
+                !placed[i]
+                    && self.calls[i].after.iter().all(|name| {
+                        self.calls
+                            .iter()
+                            .zip(&placed)
+                            .all(|(other, placed)| other.name != Some(*name) || *placed)
+                    })
+            });
+            match next {
+                Some(i) => {
+                    placed[i] = true;
+                    order.push(i);
+                }
+                None => {
+                    return Err(SolverError::ExecError(
+                        "the dependencies of the calls are circular".to_string(),
+                    ))
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    // The calls of both plans in one, those of the other plan after all of these. The
+    // calls keep their order within each plan, the names of either don't apply to the
+    // other.
+    pub fn then(self, other: CallPlan) -> Result<CallPlan, SolverError> {
+        let call_breaker = self.call_breaker;
+        let mut calls = self.into_ordered()?;
+        calls.extend(other.into_ordered()?);
+        Ok(CallPlan {
+            call_breaker,
+            calls,
+        })
+    }
+
+    // The calls in the execution order, without the names they were ordered by.
+    fn into_ordered(self) -> Result<Vec<PlannedCall>, SolverError> {
+        let order = self.order()?;
+        let mut calls: Vec<Option<PlannedCall>> = self.calls.into_iter().map(Some).collect();
+        Ok(order
+            .into_iter()
+            .filter_map(|i| calls[i].take())
+            .map(|planned| PlannedCall {
+                name: None,
+                after: Vec::new(),
+                ..planned
+            })
+            .collect())
+    }
+
+    pub fn call_objects(&self) -> Result<Vec<CallObject>, SolverError> {
+        Ok(self
+            .order()?
+            .into_iter()
+            .map(|i| self.calls[i].call.clone())
+            .collect())
+    }
+
+    pub async fn return_objects<C: ChainClient>(
+        &self,
+        chain: &C,
+    ) -> Result<Vec<ReturnObject>, SolverError> {
+        let order = self.order()?;
+        // The simulated calls by their sender, the pulls are simulated as the calls they
+        // make from the proxy.
+        let mut simulated = Vec::new();
+        for &i in &order {
+            let PlannedCall {
+                call,
+                planned_return,
+                ..
+            } = &self.calls[i];
+            match planned_return {
+                PlannedReturn::Simulated => simulated.push((self.call_breaker, call.clone())),
+                PlannedReturn::Pull(pushed) => simulated.extend(
+                    pushed
+                        .iter()
+                        .map(|pushed_call| (call.addr, pushed_call.clone())),
+                ),
+                PlannedReturn::Known(_) => {}
+            }
+        }
+        let mut outputs = chain
+            .simulate_calls(&simulated)
+            .await
+            .map_err(|err| err.context("Simulation of the calls"))?
+            .into_iter();
+        let mut return_objects = Vec::new();
+        for i in order {
+            let returnvalue = match &self.calls[i].planned_return {
+                PlannedReturn::Simulated => outputs.next().unwrap_or_default(),
+                PlannedReturn::Pull(pushed) => {
+                    let pushed_returns = outputs
+                        .by_ref()
+                        .take(pushed.len())
+                        .map(|returnvalue| ReturnObject { returnvalue })
+                        .collect::<Vec<ReturnObject>>();
+                    abi::encode(&[Token::Bytes(pushed_returns.encode())]).into()
+                }
+                PlannedReturn::Known(returnvalue) => returnvalue.clone(),
+            };
+            return_objects.push(ReturnObject { returnvalue });
+        }
+        Ok(return_objects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chain_client::mock::MockChainClient,
+        contracts_abi::ierc20::{ApproveCall, IERC20Calls, TransferFromCall},
+    };
+
+    fn call(addr: Address) -> CallObject {
+        CallObject {
+            amount: 0.into(),
+            addr,
+            gas: 0.into(),
+            callvalue: Bytes::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn returns_follow_the_planned_calls() {
+        let (token, proxy, pool) = (
+            Address::repeat_byte(0xda),
+            Address::repeat_byte(0x01),
+            Address::repeat_byte(0x77),
+        );
+        let mut chain = MockChainClient::default();
+        chain.call_outputs.insert(token, true.encode().into());
+        let plan = CallPlan::new(Address::repeat_byte(0xcb))
+            .call(call(token))
+            .pull(call(proxy), vec![call(token), call(pool)])
+            .call_returning(call(pool), Bytes::from(vec![1]));
+        let returns = plan
+            .return_objects(&chain)
+            .await
+            .ok()
+            .unwrap()
+            .into_iter()
+            .map(|return_object| return_object.returnvalue)
+            .collect::<Vec<Bytes>>();
+
+        let pushed_returns = vec![
+            ReturnObject {
+                returnvalue: true.encode().into(),
+            },
+            ReturnObject {
+                returnvalue: Bytes::new(),
+            },
+        ];
+        assert_eq!(
+            returns,
+            vec![
+                true.encode().into(),
+                abi::encode(&[Token::Bytes(pushed_returns.encode())]).into(),
+                Bytes::from(vec![1]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn pushed_calls_see_the_calls_before_them() {
+        let (token, proxy, owner) = (
+            Address::repeat_byte(0xda),
+            Address::repeat_byte(0x01),
+            Address::repeat_byte(0x02),
+        );
+        let erc20_call = |data: IERC20Calls| CallObject {
+            callvalue: data.encode().into(),
+            ..call(token)
+        };
+        let approve = erc20_call(IERC20Calls::Approve(ApproveCall {
+            spender: proxy,
+            amount: 10.into(),
+        }));
+        let transfer_from = erc20_call(IERC20Calls::TransferFrom(TransferFromCall {
+            from: proxy,
+            to: owner,
+            amount: 10.into(),
+        }));
+        let chain = MockChainClient::default();
+        // Alone the transfer would miss the approval.
+        assert!(chain.simulate_call(proxy, &transfer_from).await.is_err());
+        let plan = CallPlan::new(Address::repeat_byte(0xcb))
+            .pull(call(proxy), vec![approve, transfer_from]);
+        let returns = plan.return_objects(&chain).await.ok().unwrap();
+        let pushed_returns = vec![
+            ReturnObject {
+                returnvalue: true.encode().into(),
+            },
+            ReturnObject {
+                returnvalue: true.encode().into(),
+            },
+        ];
+        assert_eq!(
+            returns[0].returnvalue,
+            Bytes::from(abi::encode(&[Token::Bytes(pushed_returns.encode())]))
+        );
+    }
+
+    #[test]
+    fn calls_run_after_their_dependencies() {
+        let addrs = (1..=4).map(Address::repeat_byte).collect::<Vec<Address>>();
+        let plan = CallPlan::new(Address::repeat_byte(0xcb))
+            .call(call(addrs[0]))
+            .named("swap")
+            .after(&["approve", "pull"])
+            .call(call(addrs[1]))
+            .named("pull")
+            .call(call(addrs[2]))
+            .named("approve")
+            .call(call(addrs[3]));
+        let order = plan
+            .call_objects()
+            .ok()
+            .unwrap()
+            .into_iter()
+            .map(|call| call.addr)
+            .collect::<Vec<Address>>();
+        assert_eq!(order, vec![addrs[1], addrs[2], addrs[0], addrs[3]]);
+
+        let circular = CallPlan::new(Address::repeat_byte(0xcb))
+            .call(call(addrs[0]))
+            .named("a")
+            .after(&["b"])
+            .call(call(addrs[1]))
+            .named("b")
+            .after(&["a"]);
+        assert!(circular.call_objects().is_err());
+    }
+}
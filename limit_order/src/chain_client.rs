@@ -0,0 +1,675 @@
+use ethers::{
+    contract::ContractError,
+    providers::{JsonRpcError, Middleware, MiddlewareError, RpcError},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Bytes, TransactionRequest, H256, U256, U64,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::watch, time::sleep};
+
+use crate::{
+    contracts_abi::{call_breaker::CallObject, ierc20::IERC20, laminated_proxy::LaminatedProxy},
+    price_feed::PriceFeed,
+    profitability::{self, ProfitabilityEstimate},
+    solver::SolverError,
+    solvers::limit_order::SwapPool,
+    submission::{BundleStatus, SubmissionPolicy},
+    token_metadata::{TokenMetadata, TokenMetadataCache},
+};
+
+// Interval of the block number reads while waiting for the confirmations.
+const CONFIRMATION_POLL: Duration = Duration::from_secs(2);
+
+// JSON-RPC error codes of the nodes limiting the rate of the requests.
+const RATE_LIMITED_CODES: [i64; 2] = [-32005, 429];
+
+// Outcome of the final transaction.
+#[derive(Clone, Debug)]
+pub enum Execution {
+    // The transaction was sent, with the hash and the status of its receipt if it was
+    // received.
+    Sent {
+        tx_hash: Option<H256>,
+        status: Option<U64>,
+        block_number: Option<U64>,
+        gas_used: Option<U256>,
+        effective_gas_price: Option<U256>,
+        // Whether the bundle landed, if the transaction was sent in a private bundle.
+        bundle: Option<BundleStatus>,
+    },
+    // The transaction was only simulated in the dry run mode.
+    Simulated(String),
+}
+
+// The chain reads and writes of the limit order solver, so that its decisions can be
+// checked without a chain. Failures to reach the node are RpcError, to be retried.
+pub trait ChainClient {
+    async fn price_of_weth(&self, pool: Address) -> Result<U256, SolverError>;
+    // Changes of the pool price if they are pushed, the price is polled otherwise.
+    fn price_updates(&self, _pool: Address) -> Option<watch::Receiver<Option<U256>>> {
+        None
+    }
+    // The (token 0, token 1) pair traded by the pool.
+    async fn pool_tokens(&self, pool: Address) -> Result<(Address, Address), SolverError>;
+    async fn token_balance(&self, token: Address, holder: Address) -> Result<U256, SolverError>;
+    // The amount of the token the spender may transfer from the owner.
+    async fn allowance(
+        &self,
+        token: Address,
+        owner: Address,
+        spender: Address,
+    ) -> Result<U256, SolverError>;
+    // The decimals and the symbol of the token.
+    async fn token_metadata(&self, token: Address) -> Result<TokenMetadata, SolverError>;
+    async fn balance(&self, account: Address) -> Result<U256, SolverError>;
+    async fn gas_price(&self) -> Result<U256, SolverError>;
+    async fn estimate_profitability(
+        &self,
+        tx: &TypedTransaction,
+        tip: U256,
+        surplus: U256,
+    ) -> Result<ProfitabilityEstimate, SolverError>;
+    // The output of the call made from the given account, with eth_call.
+    async fn simulate_call(&self, from: Address, call: &CallObject) -> Result<Bytes, SolverError>;
+    // The outputs of the calls made in sequence from their given accounts, each seeing
+    // the effects of the ones before it, e.g. an approval before the transfer spending it.
+    async fn simulate_calls(
+        &self,
+        calls: &[(Address, CallObject)],
+    ) -> Result<Vec<Bytes>, SolverError>;
+    // Whether the call pushed to the proxy with the sequence number was executed.
+    async fn call_executed(
+        &self,
+        proxy: Address,
+        sequence_number: U256,
+    ) -> Result<bool, SolverError>;
+    // Gets the execute_and_verify transaction of the objective on chain.
+    async fn execute_and_verify(
+        &self,
+        app: &str,
+        amount: U256,
+        tx: TypedTransaction,
+    ) -> Result<Execution, SolverError>;
+    // Waits until the successful transaction included at the block is buried under the
+    // confirmations, false if a reorg dropped it or it doesn't succeed anymore.
+    async fn confirmed(
+        &self,
+        tx_hash: H256,
+        block_number: U64,
+        confirmations: u64,
+    ) -> Result<bool, SolverError>;
+    // Whether the gas of the final transaction may be paid by the solver wallet rather
+    // than by a relayer.
+    fn wallet_pays_gas(&self, app: &str, amount: U256) -> bool;
+}
+
+// The chain behind the middleware, the transactions go through the submission policy.
+// The pool prices come from the price feed if there is one.
+pub struct EthersClient<M> {
+    middleware: Arc<M>,
+    submission_policy: Arc<SubmissionPolicy>,
+    price_feed: Option<Arc<PriceFeed<M>>>,
+    token_metadata: Arc<TokenMetadataCache>,
+}
+
+impl<M> EthersClient<M> {
+    pub fn new(
+        middleware: Arc<M>,
+        submission_policy: Arc<SubmissionPolicy>,
+        price_feed: Option<Arc<PriceFeed<M>>>,
+        token_metadata: Arc<TokenMetadataCache>,
+    ) -> EthersClient<M> {
+        EthersClient {
+            middleware,
+            submission_policy,
+            price_feed,
+            token_metadata,
+        }
+    }
+}
+
+impl<M: Middleware> ChainClient for EthersClient<M> {
+    async fn price_of_weth(&self, pool: Address) -> Result<U256, SolverError> {
+        if let Some(price_feed) = &self.price_feed {
+            return price_feed.price(pool).await;
+        }
+        SwapPool::new(pool, self.middleware.clone())
+            .get_price_of_weth()
+            .call()
+            .await
+            .map_err(contract_error)
+    }
+
+    fn price_updates(&self, pool: Address) -> Option<watch::Receiver<Option<U256>>> {
+        self.price_feed
+            .as_ref()
+            .map(|price_feed| price_feed.subscribe(pool))
+    }
+
+    async fn pool_tokens(&self, pool: Address) -> Result<(Address, Address), SolverError> {
+        let pool = SwapPool::new(pool, self.middleware.clone());
+        let token_0 = pool
+            .dai()
+            .call()
+            .await
+            .map_err(|err| contract_error(err).context("Error reading pool token 0"))?;
+        let token_1 = pool
+            .weth()
+            .call()
+            .await
+            .map_err(|err| contract_error(err).context("Error reading pool token 1"))?;
+        Ok((token_0, token_1))
+    }
+
+    async fn token_balance(&self, token: Address, holder: Address) -> Result<U256, SolverError> {
+        IERC20::new(token, self.middleware.clone())
+            .balance_of(holder)
+            .call()
+            .await
+            .map_err(contract_error)
+    }
+
+    async fn allowance(
+        &self,
+        token: Address,
+        owner: Address,
+        spender: Address,
+    ) -> Result<U256, SolverError> {
+        IERC20::new(token, self.middleware.clone())
+            .allowance(owner, spender)
+            .call()
+            .await
+            .map_err(contract_error)
+    }
+
+    async fn token_metadata(&self, token: Address) -> Result<TokenMetadata, SolverError> {
+        self.token_metadata
+            .read(self.middleware.clone(), token)
+            .await
+    }
+
+    async fn balance(&self, account: Address) -> Result<U256, SolverError> {
+        self.middleware
+            .get_balance(account, None)
+            .await
+            .map_err(middleware_error)
+    }
+
+    async fn gas_price(&self) -> Result<U256, SolverError> {
+        self.middleware
+            .get_gas_price()
+            .await
+            .map_err(middleware_error)
+    }
+
+    async fn estimate_profitability(
+        &self,
+        tx: &TypedTransaction,
+        tip: U256,
+        surplus: U256,
+    ) -> Result<ProfitabilityEstimate, SolverError> {
+        profitability::estimate(self.middleware.as_ref(), tx, tip, surplus)
+            .await
+            .map_err(SolverError::ExecError)
+    }
+
+    async fn simulate_call(&self, from: Address, call: &CallObject) -> Result<Bytes, SolverError> {
+        let tx: TypedTransaction = TransactionRequest::new()
+            .from(from)
+            .to(call.addr)
+            .value(call.amount)
+            .gas(call.gas)
+            .data(call.callvalue.clone())
+            .into();
+        self.middleware
+            .call(&tx, None)
+            .await
+            .map_err(middleware_error)
+    }
+
+    // The calls are simulated in one block with eth_simulateV1.
+    async fn simulate_calls(
+        &self,
+        calls: &[(Address, CallObject)],
+    ) -> Result<Vec<Bytes>, SolverError> {
+        if calls.is_empty() {
+            return Ok(Vec::new());
+        }
+        let calls = calls
+            .iter()
+            .map(|(from, call)| {
+                serde_json::json!({
+                    "from": from,
+                    "to": call.addr,
+                    "value": call.amount,
+                    "gas": call.gas,
+                    "data": call.callvalue,
+                })
+            })
+            .collect::<Vec<_>>();
+        let blocks: Vec<SimulatedBlock> = self
+            .middleware
+            .provider()
+            .request(
+                "eth_simulateV1",
+                (
+                    serde_json::json!({ "blockStateCalls": [{ "calls": calls }] }),
+                    "latest",
+                ),
+            )
+            .await
+            .map_err(middleware_error)?;
+        let simulated = blocks
+            .into_iter()
+            .next()
+            .map(|block| block.calls)
+            .unwrap_or_default();
+        if simulated.len() != calls.len() {
+            return Err(SolverError::ExecError(format!(
+                "the simulation returned {} outputs for {} calls",
+                simulated.len(),
+                calls.len()
+            )));
+        }
+        simulated
+            .into_iter()
+            .enumerate()
+            .map(|(i, call)| match call.error {
+                Some(error) if call.status.is_zero() => Err(SolverError::ExecError(format!(
+                    "the call {} reverted: {}",
+                    i, error.message
+                ))),
+                _ if call.status.is_zero() => {
+                    Err(SolverError::ExecError(format!("the call {} reverted", i)))
+                }
+                _ => Ok(call.return_data),
+            })
+            .collect()
+    }
+
+    async fn call_executed(
+        &self,
+        proxy: Address,
+        sequence_number: U256,
+    ) -> Result<bool, SolverError> {
+        let (_, executed, _) = LaminatedProxy::new(proxy, self.middleware.clone())
+            .view_deferred_call(sequence_number)
+            .call()
+            .await
+            .map_err(|err| contract_error(err).context("Error reading the call of the proxy"))?;
+        Ok(executed)
+    }
+
+    async fn execute_and_verify(
+        &self,
+        app: &str,
+        amount: U256,
+        tx: TypedTransaction,
+    ) -> Result<Execution, SolverError> {
+        if self.submission_policy.dry_run() {
+            return self
+                .submission_policy
+                .simulate(self.middleware.as_ref(), tx)
+                .await
+                .map(Execution::Simulated)
+                .map_err(SolverError::TxError);
+        }
+        let submitted = self
+            .submission_policy
+            .submit(app, amount, self.middleware.as_ref(), tx)
+            .await
+            .map_err(SolverError::TxError)?;
+        // A transaction sent without a receipt may still land, it isn't sent again.
+        if submitted.receipt.is_none() && submitted.bundle.is_none() {
+            return Err(SolverError::ExecError(format!(
+                "the receipt of the sent transaction {:?} isn't known",
+                submitted.tx_hash.unwrap_or_default()
+            )));
+        }
+        let receipt = submitted.receipt;
+        Ok(Execution::Sent {
+            tx_hash: receipt.as_ref().map(|receipt| receipt.transaction_hash),
+            status: receipt.as_ref().and_then(|receipt| receipt.status),
+            block_number: receipt.as_ref().and_then(|receipt| receipt.block_number),
+            gas_used: receipt.as_ref().and_then(|receipt| receipt.gas_used),
+            effective_gas_price: receipt.and_then(|receipt| receipt.effective_gas_price),
+            bundle: submitted.bundle,
+        })
+    }
+
+    async fn confirmed(
+        &self,
+        tx_hash: H256,
+        block_number: U64,
+        confirmations: u64,
+    ) -> Result<bool, SolverError> {
+        // The transaction is landed, the node errors are waited out rather than failing
+        // the executor into sending it again.
+        let mut block_number = block_number;
+        loop {
+            sleep(CONFIRMATION_POLL).await;
+            let head = match self.middleware.get_block_number().await {
+                Ok(head) => head,
+                Err(err) => {
+                    println!("Error reading the block number: {}", err);
+                    continue;
+                }
+            };
+            if head < block_number + confirmations {
+                continue;
+            }
+            let receipt = match self.middleware.get_transaction_receipt(tx_hash).await {
+                Ok(receipt) => receipt,
+                Err(err) => {
+                    println!("Error reading the receipt of {:?}: {}", tx_hash, err);
+                    continue;
+                }
+            };
+            match receipt {
+                Some(receipt) if receipt.status != Some(1.into()) => return Ok(false),
+                // Included again in a later block after a reorg.
+                Some(receipt) => match receipt.block_number {
+                    Some(included) if included != block_number => block_number = included,
+                    _ => return Ok(true),
+                },
+                None => return Ok(false),
+            }
+        }
+    }
+
+    fn wallet_pays_gas(&self, app: &str, amount: U256) -> bool {
+        self.submission_policy.wallet_pays_gas(app, amount)
+    }
+}
+
+// The outputs of the calls of a block simulated with eth_simulateV1.
+#[derive(Debug, Deserialize, Serialize)]
+struct SimulatedBlock {
+    calls: Vec<SimulatedCall>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SimulatedCall {
+    return_data: Bytes,
+    status: U64,
+    error: Option<SimulatedCallError>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct SimulatedCallError {
+    message: String,
+}
+
+// Errors answered by the node, e.g. reverts, are final. The call may be retried if the
+// node didn't answer or couldn't serve it for now.
+pub fn contract_error<M: Middleware>(err: ContractError<M>) -> SolverError {
+    let transient = match (err.as_middleware_error(), err.as_provider_error()) {
+        (Some(middleware_err), _) => transient(middleware_err.as_error_response()),
+        (None, Some(provider_err)) => transient(RpcError::as_error_response(provider_err)),
+        (None, None) => false,
+    };
+    if transient {
+        SolverError::RpcError(err.to_string())
+    } else {
+        SolverError::ExecError(err.to_string())
+    }
+}
+
+fn middleware_error<E: MiddlewareError>(err: E) -> SolverError {
+    if transient(err.as_error_response()) {
+        SolverError::RpcError(err.to_string())
+    } else {
+        SolverError::ExecError(err.to_string())
+    }
+}
+
+// Whether the node didn't answer, or answered that it couldn't serve the request for now:
+// rate limited, timed out, or behind the block asked for.
+fn transient(response: Option<&JsonRpcError>) -> bool {
+    let response = match response {
+        Some(response) => response,
+        None => return true,
+    };
+    let message = response.message.to_lowercase();
+    RATE_LIMITED_CODES.contains(&response.code)
+        || ["rate limit", "timeout", "timed out", "header not found"]
+            .iter()
+            .any(|pattern| message.contains(pattern))
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use crate::contracts_abi::ierc20::IERC20Calls;
+    use ethers::abi::{AbiDecode, AbiEncode};
+    use std::{collections::HashMap, sync::Mutex};
+
+    // A chain held in memory, the final transactions are recorded and answered with a
+    // receipt of the given status.
+    #[derive(Default)]
+    pub struct MockChainClient {
+        pub price_of_weth: Mutex<U256>,
+        pub pool_tokens: (Address, Address),
+        // Token balances by (token, holder).
+        pub token_balances: HashMap<(Address, Address), U256>,
+        // Token allowances by (token, owner, spender), approved and spent by the
+        // simulated calls.
+        pub allowances: HashMap<(Address, Address, Address), U256>,
+        // The tokens not set have 18 decimals.
+        pub token_metadata: HashMap<Address, TokenMetadata>,
+        pub balance: U256,
+        pub gas_price: U256,
+        pub gas: U256,
+        // Status of the receipt of the final transaction, no receipt if not set.
+        pub receipt_status: Option<u64>,
+        pub dry_run: bool,
+        pub sent: Mutex<Vec<TypedTransaction>>,
+        // Outputs of the simulated calls by the called contract, empty if not set.
+        pub call_outputs: HashMap<Address, Bytes>,
+        // Pushes the pool price to the solver if set.
+        pub price_updates: Option<watch::Sender<Option<U256>>>,
+        // The final transactions are relayed, the wallet doesn't pay for the gas.
+        pub relayed: bool,
+        // Status of the bundle of the final transaction, not sent in a bundle if not set.
+        pub bundle: Option<BundleStatus>,
+        // The call of the proxy was executed by another solver.
+        pub call_executed: bool,
+        // A reorg drops the final transaction before its confirmations.
+        pub dropped: bool,
+    }
+
+    impl MockChainClient {
+        // The output of the call if it approves or spends an allowance, reverting the
+        // transfers beyond it.
+        fn allowance_call(
+            allowances: &mut HashMap<(Address, Address, Address), U256>,
+            from: Address,
+            call: &CallObject,
+        ) -> Option<Result<Bytes, SolverError>> {
+            match IERC20Calls::decode(&call.callvalue) {
+                Ok(IERC20Calls::Approve(approve)) => {
+                    allowances.insert((call.addr, from, approve.spender), approve.amount);
+                    Some(Ok(true.encode().into()))
+                }
+                Ok(IERC20Calls::TransferFrom(transfer)) => {
+                    let allowance = allowances
+                        .entry((call.addr, transfer.from, from))
+                        .or_default();
+                    if *allowance < transfer.amount {
+                        return Some(Err(SolverError::ExecError(
+                            "execution reverted: insufficient allowance".to_string(),
+                        )));
+                    }
+                    *allowance -= transfer.amount;
+                    Some(Ok(true.encode().into()))
+                }
+                _ => None,
+            }
+        }
+    }
+
+    impl ChainClient for MockChainClient {
+        async fn price_of_weth(&self, _pool: Address) -> Result<U256, SolverError> {
+            Ok(*self.price_of_weth.lock().unwrap())
+        }
+
+        fn price_updates(&self, _pool: Address) -> Option<watch::Receiver<Option<U256>>> {
+            self.price_updates.as_ref().map(|tx| tx.subscribe())
+        }
+
+        async fn pool_tokens(&self, _pool: Address) -> Result<(Address, Address), SolverError> {
+            Ok(self.pool_tokens)
+        }
+
+        async fn token_balance(
+            &self,
+            token: Address,
+            holder: Address,
+        ) -> Result<U256, SolverError> {
+            Ok(self
+                .token_balances
+                .get(&(token, holder))
+                .copied()
+                .unwrap_or_default())
+        }
+
+        async fn allowance(
+            &self,
+            token: Address,
+            owner: Address,
+            spender: Address,
+        ) -> Result<U256, SolverError> {
+            Ok(self
+                .allowances
+                .get(&(token, owner, spender))
+                .copied()
+                .unwrap_or_default())
+        }
+
+        async fn token_metadata(&self, token: Address) -> Result<TokenMetadata, SolverError> {
+            Ok(self
+                .token_metadata
+                .get(&token)
+                .cloned()
+                .unwrap_or(TokenMetadata {
+                    decimals: 18,
+                    symbol: "TKN".to_string(),
+                }))
+        }
+
+        async fn balance(&self, _account: Address) -> Result<U256, SolverError> {
+            Ok(self.balance)
+        }
+
+        async fn gas_price(&self) -> Result<U256, SolverError> {
+            Ok(self.gas_price)
+        }
+
+        async fn estimate_profitability(
+            &self,
+            _tx: &TypedTransaction,
+            tip: U256,
+            surplus: U256,
+        ) -> Result<ProfitabilityEstimate, SolverError> {
+            Ok(ProfitabilityEstimate {
+                gas: self.gas,
+                gas_price: self.gas_price,
+                tip,
+                surplus,
+            })
+        }
+
+        async fn simulate_call(
+            &self,
+            from: Address,
+            call: &CallObject,
+        ) -> Result<Bytes, SolverError> {
+            let mut outputs = self.simulate_calls(&[(from, call.clone())]).await?;
+            Ok(outputs.remove(0))
+        }
+
+        async fn simulate_calls(
+            &self,
+            calls: &[(Address, CallObject)],
+        ) -> Result<Vec<Bytes>, SolverError> {
+            let mut allowances = self.allowances.clone();
+            calls
+                .iter()
+                .map(
+                    |(from, call)| match Self::allowance_call(&mut allowances, *from, call) {
+                        Some(output) => output,
+                        None => Ok(self
+                            .call_outputs
+                            .get(&call.addr)
+                            .cloned()
+                            .unwrap_or_default()),
+                    },
+                )
+                .collect()
+        }
+
+        async fn call_executed(
+            &self,
+            _proxy: Address,
+            _sequence_number: U256,
+        ) -> Result<bool, SolverError> {
+            Ok(self.call_executed)
+        }
+
+        async fn execute_and_verify(
+            &self,
+            _app: &str,
+            _amount: U256,
+            tx: TypedTransaction,
+        ) -> Result<Execution, SolverError> {
+            if self.dry_run {
+                return Ok(Execution::Simulated("Dry run".to_string()));
+            }
+            self.sent.lock().unwrap().push(tx);
+            Ok(Execution::Sent {
+                tx_hash: self.receipt_status.map(|_| H256::repeat_byte(0x7e)),
+                status: self.receipt_status.map(U64::from),
+                block_number: self.receipt_status.map(|_| U64::from(1)),
+                gas_used: self.receipt_status.map(|_| self.gas),
+                effective_gas_price: self.receipt_status.map(|_| self.gas_price),
+                bundle: self.bundle.clone(),
+            })
+        }
+
+        async fn confirmed(
+            &self,
+            _tx_hash: H256,
+            _block_number: U64,
+            _confirmations: u64,
+        ) -> Result<bool, SolverError> {
+            Ok(!self.dropped)
+        }
+
+        fn wallet_pays_gas(&self, _app: &str, _amount: U256) -> bool {
+            !self.relayed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_errors_are_retried_if_transient() {
+        let response = |code: i64, message: &str| JsonRpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        };
+        assert!(transient(None));
+        assert!(transient(Some(&response(-32005, "limit exceeded"))));
+        assert!(transient(Some(&response(429, "Too Many Requests"))));
+        assert!(transient(Some(&response(-32000, "header not found"))));
+        assert!(transient(Some(&response(-32000, "request timed out"))));
+        assert!(!transient(Some(&response(3, "execution reverted"))));
+    }
+}
@@ -0,0 +1,183 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::admin::AdminState;
+
+#[derive(Default)]
+struct Circuit {
+    // Final transactions that failed in a row.
+    failures: u32,
+    opened_at: Option<Instant>,
+}
+
+// Whether the final transactions of an app are held.
+#[derive(Clone, Debug, Serialize)]
+pub struct CircuitState {
+    pub app: String,
+    pub open: bool,
+    pub failures: u32,
+    // Until the next final transaction is let through, if open.
+    pub remaining: Option<Duration>,
+}
+
+// Holds the final transactions of an app for a cool-down after failures in a row, e.g.
+// with a misconfigured CallBreaker every transaction reverts. Once the cool-down is over
+// the next failure opens the circuit again, a success closes it.
+pub struct CircuitBreaker {
+    max_failures: u32,
+    cool_down: Duration,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(max_failures: u32, cool_down: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            max_failures: max_failures.max(1),
+            cool_down,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record_success(&self, app: &str) {
+        self.circuits.lock().unwrap().remove(app);
+    }
+
+    pub fn record_failure(&self, app: &str) {
+        let mut circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.entry(app.to_string()).or_default();
+        circuit.failures += 1;
+        if circuit.failures >= self.max_failures {
+            println!(
+                "Final transactions of {} held for {:?} after {} failures in a row",
+                app, self.cool_down, circuit.failures
+            );
+            circuit.opened_at = Some(Instant::now());
+        }
+    }
+
+    fn remaining(&self, circuit: &Circuit) -> Option<Duration> {
+        circuit
+            .opened_at
+            .map(|opened_at| self.cool_down.saturating_sub(opened_at.elapsed()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    // Why the final transactions of the app are held, if they are.
+    pub fn open_message(&self, app: &str) -> Option<String> {
+        let circuits = self.circuits.lock().unwrap();
+        let circuit = circuits.get(app)?;
+        self.remaining(circuit).map(|remaining| {
+            format!(
+                "The final transactions of the app are held for {}s after {} failures in a row",
+                remaining.as_secs(),
+                circuit.failures
+            )
+        })
+    }
+
+    // Closes the circuit of the app, or of all the apps.
+    pub fn reset(&self, app: Option<&str>) {
+        let mut circuits = self.circuits.lock().unwrap();
+        match app {
+            Some(app) => {
+                circuits.remove(app);
+            }
+            None => circuits.clear(),
+        }
+    }
+
+    // The apps with failures, by name.
+    pub fn states(&self) -> Vec<CircuitState> {
+        let mut states = self
+            .circuits
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(app, circuit)| {
+                let remaining = self.remaining(circuit);
+                CircuitState {
+                    app: app.clone(),
+                    open: remaining.is_some(),
+                    failures: circuit.failures,
+                    remaining,
+                }
+            })
+            .collect::<Vec<_>>();
+        states.sort_by(|a, b| a.app.cmp(&b.app));
+        states
+    }
+
+    // The open circuits in the Prometheus text format.
+    pub fn write_metrics(&self, body: &mut String) {
+        let _ = writeln!(
+            body,
+            "# HELP solver_circuit_open Whether the final transactions of the app are held."
+        );
+        let _ = writeln!(body, "# TYPE solver_circuit_open gauge");
+        for state in self.states() {
+            let _ = writeln!(
+                body,
+                "solver_circuit_open{{app=\"{}\"}} {}",
+                state.app, state.open as u8
+            );
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ResetRequest {
+    // All the apps if not given.
+    pub app: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct CircuitBreakerState {
+    pub admin: AdminState,
+    pub circuit_breaker: Arc<CircuitBreaker>,
+}
+
+// Lets the final transactions through again, the body is optional.
+pub async fn reset_circuit(
+    State(state): State<CircuitBreakerState>,
+    headers: HeaderMap,
+    request: Option<Json<ResetRequest>>,
+) -> Result<Json<Vec<CircuitState>>, (StatusCode, String)> {
+    state.admin.authorize(&headers)?;
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    state.circuit_breaker.reset(request.app.as_deref());
+    println!(
+        "Circuit of {} reset",
+        request.app.as_deref().unwrap_or("all the apps")
+    );
+    Ok(Json(state.circuit_breaker.states()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circuit_opens_after_failures_in_a_row() {
+        let circuit_breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        circuit_breaker.record_failure("APP");
+        circuit_breaker.record_success("APP");
+        circuit_breaker.record_failure("APP");
+        assert_eq!(circuit_breaker.open_message("APP"), None);
+        circuit_breaker.record_failure("APP");
+        assert!(circuit_breaker.open_message("APP").is_some());
+        assert_eq!(circuit_breaker.open_message("OTHER"), None);
+        circuit_breaker.reset(Some("APP"));
+        assert_eq!(circuit_breaker.open_message("APP"), None);
+        assert!(circuit_breaker.states().is_empty());
+    }
+}
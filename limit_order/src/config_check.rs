@@ -0,0 +1,169 @@
+use axum::{extract::State, http::StatusCode, response::Json};
+use ethers::{providers::Middleware, types::Address};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Debug, sync::Arc};
+
+use crate::solvers::limit_order::{FlashLoan, PairPool, SwapPool};
+
+// A configuration value which doesn't match the parameters of the deployed contracts.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfigMismatch {
+    pub field: String,
+    pub configured: String,
+    pub deployed: String,
+}
+
+// The contracts the solver is configured with.
+pub struct DeployedConfig {
+    pub laminator_address: Address,
+    pub call_breaker_address: Address,
+    pub flash_loan_address: Address,
+    pub swap_pool_address: Option<Address>,
+    pub pair_pools: Vec<PairPool>,
+}
+
+struct Diff {
+    mismatches: Vec<ConfigMismatch>,
+}
+
+impl Diff {
+    fn expect<T: Debug + PartialEq, E: ToString>(
+        &mut self,
+        field: String,
+        configured: T,
+        deployed: Result<T, E>,
+    ) {
+        match deployed {
+            Ok(deployed) if deployed == configured => {}
+            Ok(deployed) => self.mismatches.push(ConfigMismatch {
+                field,
+                configured: format!("{:?}", configured),
+                deployed: format!("{:?}", deployed),
+            }),
+            Err(err) => self.mismatches.push(ConfigMismatch {
+                field,
+                configured: format!("{:?}", configured),
+                deployed: format!("error: {}", err.to_string()),
+            }),
+        }
+    }
+
+    async fn expect_code<M: Middleware>(&mut self, middleware: &M, field: &str, address: Address) {
+        let deployed = match middleware.get_code(address, None).await {
+            Ok(code) => Ok(if code.is_empty() {
+                "no contract"
+            } else {
+                "contract"
+            }),
+            Err(err) => Err(err.to_string()),
+        };
+        self.expect(format!("{} {:?}", field, address), "contract", deployed);
+    }
+}
+
+// Reads the parameters of the deployed contracts and compares them with the
+// configuration, field by field.
+pub async fn validate<M: Middleware>(
+    middleware: Arc<M>,
+    config: &DeployedConfig,
+) -> Vec<ConfigMismatch> {
+    let mut diff = Diff {
+        mismatches: Vec::new(),
+    };
+    diff.expect_code(middleware.as_ref(), "laminator", config.laminator_address)
+        .await;
+    diff.expect_code(
+        middleware.as_ref(),
+        "call_breaker",
+        config.call_breaker_address,
+    )
+    .await;
+    diff.expect_code(middleware.as_ref(), "flash_loan", config.flash_loan_address)
+        .await;
+
+    // The flash loan provides the liquidity of the default pool.
+    let flash_loan = FlashLoan::new(config.flash_loan_address, middleware.clone());
+    if let Some(swap_pool_address) = config.swap_pool_address {
+        diff.expect_code(middleware.as_ref(), "swap_pool", swap_pool_address)
+            .await;
+        let swap_pool = SwapPool::new(swap_pool_address, middleware.clone());
+        diff.expect(
+            "swap_pool.callbreaker".to_string(),
+            config.call_breaker_address,
+            swap_pool.callbreaker().call().await,
+        );
+        if let (Ok(token_0), Ok(token_1)) = (
+            flash_loan.dai().call().await,
+            flash_loan.weth().call().await,
+        ) {
+            diff.expect(
+                "swap_pool.dai".to_string(),
+                token_0,
+                swap_pool.dai().call().await,
+            );
+            diff.expect(
+                "swap_pool.weth".to_string(),
+                token_1,
+                swap_pool.weth().call().await,
+            );
+        }
+    }
+
+    for pair_pool in &config.pair_pools {
+        diff.expect_code(middleware.as_ref(), "pair_pool", pair_pool.pool)
+            .await;
+        let swap_pool = SwapPool::new(pair_pool.pool, middleware.clone());
+        diff.expect(
+            format!("pair_pool {:?} callbreaker", pair_pool.pool),
+            config.call_breaker_address,
+            swap_pool.callbreaker().call().await,
+        );
+        // The pool may list the pair in any order.
+        let tokens = match (swap_pool.dai().call().await, swap_pool.weth().call().await) {
+            (Ok(token_0), Ok(token_1)) if token_0 > token_1 => Ok((token_1, token_0)),
+            (Ok(token_0), Ok(token_1)) => Ok((token_0, token_1)),
+            (Err(err), _) | (_, Err(err)) => Err(err),
+        };
+        let configured = if pair_pool.token_a > pair_pool.token_b {
+            (pair_pool.token_b, pair_pool.token_a)
+        } else {
+            (pair_pool.token_a, pair_pool.token_b)
+        };
+        diff.expect(
+            format!("pair_pool {:?} tokens", pair_pool.pool),
+            configured,
+            tokens,
+        );
+    }
+    diff.mismatches
+}
+
+// The transactions are signed for the configured chain with EIP-155, a node of another
+// chain would reject them, or replay them on a chain they weren't meant for.
+pub async fn verify_chain_id<M: Middleware>(middleware: &M, chain_id: u64) -> Result<(), String> {
+    let actual = middleware
+        .get_chainid()
+        .await
+        .map_err(|err| format!("Error reading the chain ID of the node: {}", err))?;
+    if actual != chain_id.into() {
+        return Err(format!(
+            "The node is on chain {}, the solver is configured for chain {}",
+            actual, chain_id
+        ));
+    }
+    Ok(())
+}
+
+// Fails while the configuration doesn't match the deployed contracts.
+pub async fn get_readiness(
+    mismatches: State<Arc<Vec<ConfigMismatch>>>,
+) -> (StatusCode, Json<Vec<ConfigMismatch>>) {
+    if mismatches.is_empty() {
+        (StatusCode::OK, Json(Vec::new()))
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(mismatches.as_ref().clone()),
+        )
+    }
+}
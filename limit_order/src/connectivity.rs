@@ -0,0 +1,229 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json},
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    circuit_breaker::{CircuitBreaker, CircuitState},
+    sender_filter::SenderFilter,
+    sharding::Sharding,
+    stats_channel::StatsSender,
+    supervisor::{self, TaskCountsState},
+    wallet_monitor::{self, WalletBalances, WalletBalancesMap},
+};
+
+// Connectivity of the chain provider. Degraded while the provider is slow or the event
+// stream fails, Reconnecting while the events can't be subscribed to, Down when the
+// subscription is given up.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize)]
+pub enum ConnectionState {
+    Connected,
+    Degraded,
+    Reconnecting,
+    Down,
+}
+
+impl ConnectionState {
+    const ALL: [ConnectionState; 4] = [
+        ConnectionState::Connected,
+        ConnectionState::Degraded,
+        ConnectionState::Reconnecting,
+        ConnectionState::Down,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            ConnectionState::Connected => "connected",
+            ConnectionState::Degraded => "degraded",
+            ConnectionState::Reconnecting => "reconnecting",
+            ConnectionState::Down => "down",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Connectivity {
+    pub state: ConnectionState,
+    // Time of the last transition since Unix epoch.
+    pub since: Duration,
+    // Number of times each state was entered.
+    pub transitions: HashMap<ConnectionState, u64>,
+    pub last_error: Option<String>,
+}
+
+impl Connectivity {
+    // The provider is connected at startup.
+    pub fn new() -> Connectivity {
+        Connectivity {
+            state: ConnectionState::Connected,
+            since: now(),
+            transitions: HashMap::new(),
+            last_error: None,
+        }
+    }
+
+    pub fn transition(&mut self, state: ConnectionState, error: Option<String>) {
+        if error.is_some() {
+            self.last_error = error;
+        }
+        if state == self.state {
+            return;
+        }
+        println!(
+            "Chain connection {:?} -> {:?}{}",
+            self.state,
+            state,
+            match &self.last_error {
+                Some(err) if state != ConnectionState::Connected => format!(": {}", err),
+                _ => String::new(),
+            }
+        );
+        self.state = state;
+        self.since = now();
+        *self.transitions.entry(state).or_default() += 1;
+    }
+
+    // Degrades a healthy connection, keeps the worse states.
+    pub fn degrade(&mut self, error: String) {
+        if self.state == ConnectionState::Connected {
+            self.transition(ConnectionState::Degraded, Some(error));
+        } else {
+            self.last_error = Some(error);
+        }
+    }
+
+    // Back to connected if the connection is still in the given state.
+    pub fn recover(&mut self, from: ConnectionState) {
+        if self.state == from {
+            self.transition(ConnectionState::Connected, None);
+        }
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+#[derive(Clone)]
+pub struct HealthState {
+    pub connectivity: Arc<Mutex<Connectivity>>,
+    // The balances of the wallets of the pool, the primary one first.
+    pub wallets: Vec<WalletBalancesMap>,
+    pub sender_filter: Arc<SenderFilter>,
+    pub stats: StatsSender,
+    pub tasks: TaskCountsState,
+    pub circuit_breaker: Option<Arc<CircuitBreaker>>,
+    pub sharding: Arc<Sharding>,
+}
+
+impl HealthState {
+    async fn wallets(&self) -> Vec<WalletBalances> {
+        let mut wallets = Vec::new();
+        for wallet in &self.wallets {
+            wallets.push(wallet.lock().await.clone());
+        }
+        wallets
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Health {
+    #[serde(flatten)]
+    pub connectivity: Connectivity,
+    pub wallet: WalletBalances,
+    // The other wallets of the pool.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pool_wallets: Vec<WalletBalances>,
+    // The apps whose final transactions failed last.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub circuits: Vec<CircuitState>,
+}
+
+// Healthy while connected or degraded. Low balances and open circuits are reported but
+// don't fail the check, a restart wouldn't refill the wallet or fix the contracts.
+pub async fn get_healthz(health: State<HealthState>) -> (StatusCode, Json<Health>) {
+    let mut wallets = health.wallets().await.into_iter();
+    let health = Health {
+        connectivity: health.connectivity.lock().await.clone(),
+        wallet: wallets.next().unwrap_or_default(),
+        pool_wallets: wallets.collect(),
+        circuits: health
+            .circuit_breaker
+            .as_ref()
+            .map(|circuit_breaker| circuit_breaker.states())
+            .unwrap_or_default(),
+    };
+    match health.connectivity.state {
+        ConnectionState::Connected | ConnectionState::Degraded => (StatusCode::OK, Json(health)),
+        _ => (StatusCode::SERVICE_UNAVAILABLE, Json(health)),
+    }
+}
+
+// The connectivity, the wallet balances, the rejected senders, the stats overflow, the
+// supervised tasks, the shards and the open circuits in the Prometheus text format.
+pub async fn get_metrics(health: State<HealthState>) -> impl IntoResponse {
+    let connectivity = health.connectivity.lock().await.clone();
+    let mut body = String::new();
+    let _ = writeln!(
+        body,
+        "# HELP solver_chain_connection_state Current state of the chain connection."
+    );
+    let _ = writeln!(body, "# TYPE solver_chain_connection_state gauge");
+    for state in ConnectionState::ALL {
+        let _ = writeln!(
+            body,
+            "solver_chain_connection_state{{state=\"{}\"}} {}",
+            state.label(),
+            (state == connectivity.state) as u8
+        );
+    }
+    let _ = writeln!(
+        body,
+        "# HELP solver_chain_connection_state_since_seconds Time of the last state transition."
+    );
+    let _ = writeln!(
+        body,
+        "# TYPE solver_chain_connection_state_since_seconds gauge"
+    );
+    let _ = writeln!(
+        body,
+        "solver_chain_connection_state_since_seconds {}",
+        connectivity.since.as_secs()
+    );
+    let _ = writeln!(
+        body,
+        "# HELP solver_chain_connection_transitions_total Transitions into each state."
+    );
+    let _ = writeln!(
+        body,
+        "# TYPE solver_chain_connection_transitions_total counter"
+    );
+    for state in ConnectionState::ALL {
+        let _ = writeln!(
+            body,
+            "solver_chain_connection_transitions_total{{state=\"{}\"}} {}",
+            state.label(),
+            connectivity.transitions.get(&state).copied().unwrap_or(0)
+        );
+    }
+    wallet_monitor::write_metrics(&health.wallets().await, &mut body);
+    health.sender_filter.write_metrics(&mut body);
+    health.stats.write_metrics(&mut body);
+    supervisor::write_metrics(&health.tasks.lock().await.clone(), &mut body);
+    health.sharding.write_metrics(&mut body);
+    if let Some(circuit_breaker) = &health.circuit_breaker {
+        circuit_breaker.write_metrics(&mut body);
+    }
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
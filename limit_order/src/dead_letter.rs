@@ -0,0 +1,374 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::{mpsc::Sender, Mutex};
+use uuid::Uuid;
+
+use crate::{admin::AdminState, contracts_abi::laminator::ProxyPushedFilter, stats::Status};
+
+// Number of failed objectives kept, the oldest are dropped first.
+const MAX_DEAD_LETTERS: usize = 1000;
+
+// An objective whose executor didn't succeed, kept to be retried.
+pub struct DeadLetter {
+    pub id: Uuid,
+    pub app: String,
+    pub event: ProxyPushedFilter,
+    pub status: Status,
+    pub message: String,
+    pub time: Duration,
+}
+
+// What is shown of a dead letter, without the objective parameters.
+#[derive(Clone, Debug, Serialize)]
+pub struct DeadLetterSummary {
+    pub id: Uuid,
+    pub app: String,
+    pub proxy_address: Address,
+    pub sequence_number: U256,
+    pub status: Status,
+    pub message: String,
+    pub time: Duration,
+}
+
+impl DeadLetter {
+    fn summary(&self) -> DeadLetterSummary {
+        DeadLetterSummary {
+            id: self.id,
+            app: self.app.clone(),
+            proxy_address: self.event.proxy_address,
+            sequence_number: self.event.sequence_number,
+            status: self.status.clone(),
+            message: self.message.clone(),
+            time: self.time,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DeadLetters {
+    entries: VecDeque<DeadLetter>,
+}
+
+impl DeadLetters {
+    pub fn add(&mut self, app: String, event: ProxyPushedFilter, status: Status, message: String) {
+        if self.entries.len() >= MAX_DEAD_LETTERS {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(DeadLetter {
+            id: Uuid::new_v4(),
+            app,
+            event,
+            status,
+            message,
+            time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default(),
+        });
+    }
+}
+
+// Selects the dead letters to retry, all the given conditions must match.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct RetryFilter {
+    pub app: Option<String>,
+    pub status: Option<Status>,
+    pub proxy_address: Option<Address>,
+    // Substring of the failure message.
+    pub message: Option<String>,
+    // Failed at or after this time, in seconds since Unix epoch.
+    pub since: Option<u64>,
+    // Only show what would be retried.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl RetryFilter {
+    fn matches(&self, dead_letter: &DeadLetter) -> bool {
+        self.app.iter().all(|app| *app == dead_letter.app)
+            && self
+                .status
+                .iter()
+                .all(|status| *status == dead_letter.status)
+            && self
+                .proxy_address
+                .iter()
+                .all(|proxy| *proxy == dead_letter.event.proxy_address)
+            && self
+                .message
+                .iter()
+                .all(|message| dead_letter.message.contains(message.as_str()))
+            && self
+                .since
+                .iter()
+                .all(|since| dead_letter.time.as_secs() >= *since)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetryResult {
+    pub dry_run: bool,
+    pub retried: Vec<DeadLetterSummary>,
+}
+
+#[derive(Clone)]
+pub struct DeadLetterState {
+    pub admin: AdminState,
+    pub dead_letters: Arc<Mutex<DeadLetters>>,
+    // Objectives fed back into the listener.
+    pub retry_tx: Sender<ProxyPushedFilter>,
+}
+
+// Requeues the matching failed objectives into the listener, where they are validated and
+// executed as newly received ones.
+pub async fn retry_all(
+    State(state): State<DeadLetterState>,
+    headers: HeaderMap,
+    Json(filter): Json<RetryFilter>,
+) -> Result<(StatusCode, Json<RetryResult>), (StatusCode, String)> {
+    state.admin.authorize(&headers)?;
+    let matching = {
+        let mut dead_letters = state.dead_letters.lock().await;
+        if filter.dry_run {
+            return Ok((
+                StatusCode::OK,
+                Json(RetryResult {
+                    dry_run: true,
+                    retried: dead_letters
+                        .entries
+                        .iter()
+                        .filter(|dead_letter| filter.matches(dead_letter))
+                        .map(DeadLetter::summary)
+                        .collect(),
+                }),
+            ));
+        }
+        let (matching, rest) = dead_letters
+            .entries
+            .drain(..)
+            .partition::<Vec<DeadLetter>, _>(|dead_letter| filter.matches(dead_letter));
+        dead_letters.entries = rest.into();
+        matching
+    };
+    let mut retried = Vec::new();
+    let mut matching = matching.into_iter();
+    while let Some(dead_letter) = matching.next() {
+        if state
+            .retry_tx
+            .send(dead_letter.event.clone())
+            .await
+            .is_err()
+        {
+            // The listener is gone, the objectives stay in the dead letters.
+            let mut dead_letters = state.dead_letters.lock().await;
+            dead_letters.entries.push_back(dead_letter);
+            dead_letters.entries.extend(matching);
+            return Ok((
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(RetryResult {
+                    dry_run: false,
+                    retried,
+                }),
+            ));
+        }
+        retried.push(dead_letter.summary());
+    }
+    Ok((
+        StatusCode::OK,
+        Json(RetryResult {
+            dry_run: false,
+            retried,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::SolvingSwitch;
+    use axum::http::header;
+    use tokio::sync::mpsc;
+
+    const TOKEN: &str = "secret";
+
+    fn proxy(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn dead_letter(app: &str, proxy_address: Address, status: Status, time: u64) -> DeadLetter {
+        DeadLetter {
+            id: Uuid::new_v4(),
+            app: app.to_string(),
+            event: ProxyPushedFilter {
+                proxy_address,
+                ..Default::default()
+            },
+            message: format!("{:?}", status).to_lowercase(),
+            status,
+            time: Duration::from_secs(time),
+        }
+    }
+
+    // A failed and a timed out objective of the same proxy, a failed one of another.
+    fn state(retry_tx: Sender<ProxyPushedFilter>) -> DeadLetterState {
+        DeadLetterState {
+            admin: AdminState {
+                switch: Arc::new(SolvingSwitch::new()),
+                token: TOKEN.to_string(),
+            },
+            dead_letters: Arc::new(Mutex::new(DeadLetters {
+                entries: VecDeque::from([
+                    dead_letter("LIMIT_ORDER", proxy(1), Status::Failed, 100),
+                    dead_letter("LIMIT_ORDER", proxy(1), Status::Timeout, 200),
+                    dead_letter("LIMIT_ORDER", proxy(2), Status::Failed, 300),
+                ]),
+            })),
+            retry_tx,
+        }
+    }
+
+    fn headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            format!("Bearer {}", TOKEN).parse().ok().unwrap(),
+        );
+        headers
+    }
+
+    fn failed() -> RetryFilter {
+        RetryFilter {
+            status: Some(Status::Failed),
+            ..Default::default()
+        }
+    }
+
+    async fn remaining(state: &DeadLetterState) -> Vec<(Address, Status)> {
+        state
+            .dead_letters
+            .lock()
+            .await
+            .entries
+            .iter()
+            .map(|dead_letter| (dead_letter.event.proxy_address, dead_letter.status.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn all_the_filter_conditions_must_match() {
+        let dead_letter = dead_letter("LIMIT_ORDER", proxy(1), Status::Failed, 100);
+        assert!(RetryFilter::default().matches(&dead_letter));
+        let filter = RetryFilter {
+            app: Some("LIMIT_ORDER".to_string()),
+            status: Some(Status::Failed),
+            proxy_address: Some(proxy(1)),
+            message: Some("failed".to_string()),
+            since: Some(100),
+            dry_run: false,
+        };
+        assert!(filter.matches(&dead_letter));
+        for other in [
+            RetryFilter {
+                app: Some("CLEANAPP".to_string()),
+                ..filter.clone()
+            },
+            RetryFilter {
+                status: Some(Status::Timeout),
+                ..filter.clone()
+            },
+            RetryFilter {
+                proxy_address: Some(proxy(2)),
+                ..filter.clone()
+            },
+            RetryFilter {
+                message: Some("timeout".to_string()),
+                ..filter.clone()
+            },
+            RetryFilter {
+                since: Some(101),
+                ..filter.clone()
+            },
+        ] {
+            assert!(!other.matches(&dead_letter), "{:?}", other);
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_requeues_nothing() {
+        let (retry_tx, mut retry_rx) = mpsc::channel(10);
+        let state = state(retry_tx);
+        let filter = RetryFilter {
+            dry_run: true,
+            ..failed()
+        };
+        let (status, Json(result)) = retry_all(State(state.clone()), headers(), Json(filter))
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert!(result.dry_run);
+        assert_eq!(result.retried.len(), 2);
+        assert!(retry_rx.try_recv().is_err());
+        assert_eq!(remaining(&state).await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn matching_dead_letters_are_requeued() {
+        let (retry_tx, mut retry_rx) = mpsc::channel(10);
+        let state = state(retry_tx);
+        let (status, Json(result)) = retry_all(State(state.clone()), headers(), Json(failed()))
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(result.retried.len(), 2);
+        assert_eq!(retry_rx.try_recv().ok().unwrap().proxy_address, proxy(1));
+        assert_eq!(retry_rx.try_recv().ok().unwrap().proxy_address, proxy(2));
+        assert!(retry_rx.try_recv().is_err());
+        assert_eq!(remaining(&state).await, [(proxy(1), Status::Timeout)]);
+    }
+
+    #[tokio::test]
+    async fn dead_letters_are_put_back_when_the_listener_is_gone() {
+        let (retry_tx, retry_rx) = mpsc::channel(10);
+        drop(retry_rx);
+        let state = state(retry_tx);
+        let (status, Json(result)) = retry_all(State(state.clone()), headers(), Json(failed()))
+            .await
+            .ok()
+            .unwrap();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(result.retried.is_empty());
+        let mut remaining = remaining(&state).await;
+        remaining.sort_by_key(|(proxy_address, _)| *proxy_address);
+        assert_eq!(
+            remaining,
+            [
+                (proxy(1), Status::Timeout),
+                (proxy(1), Status::Failed),
+                (proxy(2), Status::Failed),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_need_the_admin_token() {
+        let (retry_tx, _retry_rx) = mpsc::channel(10);
+        let state = state(retry_tx);
+        let err = retry_all(State(state.clone()), HeaderMap::new(), Json(failed()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(remaining(&state).await.len(), 3);
+    }
+}
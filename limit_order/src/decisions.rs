@@ -0,0 +1,109 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::{
+    solver::DecisionInput,
+    stats::{TimerExecutorStats, TransactionStatus},
+};
+
+// Decisions kept per executor, the oldest ones are dropped first.
+const MAX_DECISIONS: usize = 100;
+
+// What a step of an executor was decided on, and what came of it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Decision {
+    // Time of the stats the decision came with since Unix epoch.
+    pub time: Duration,
+    pub transaction_status: TransactionStatus,
+    pub message: String,
+    pub inputs: Vec<DecisionInput>,
+}
+
+// The decisions of the executors, recorded from their stats.
+pub type DecisionHistories = Arc<Mutex<HashMap<Uuid, VecDeque<Decision>>>>;
+
+// Appends the decision of the stats to the history of its executor, unless neither the
+// inputs nor the transaction status changed since the last one.
+pub fn record(histories: &mut HashMap<Uuid, VecDeque<Decision>>, stats: &TimerExecutorStats) {
+    if stats.decision.is_empty() {
+        return;
+    }
+    let history = histories.entry(stats.id).or_default();
+    if history.back().is_some_and(|last| {
+        last.inputs == stats.decision && last.transaction_status == stats.transaction_status
+    }) {
+        return;
+    }
+    if history.len() == MAX_DECISIONS {
+        history.pop_front();
+    }
+    history.push_back(Decision {
+        time: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default(),
+        transaction_status: stats.transaction_status.clone(),
+        message: stats.message.clone(),
+        inputs: stats.decision.clone(),
+    });
+}
+
+pub async fn get_decisions_json(
+    State(histories): State<DecisionHistories>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<VecDeque<Decision>>, (StatusCode, String)> {
+    match histories.lock().await.get(&id) {
+        Some(history) => Ok(Json(history.clone())),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            format!("No decisions of the executor {}", id),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::DecisionValue;
+
+    #[test]
+    fn changed_decisions_are_recorded() {
+        let mut stats = TimerExecutorStats::duplicate(1.into(), "APP".to_string(), vec![]);
+        let mut histories = HashMap::new();
+        record(&mut histories, &stats);
+        assert!(histories.is_empty());
+        for price in ["1500", "1500", "1600"] {
+            stats.transaction_status = TransactionStatus::StepPending;
+            stats.decision = vec![DecisionInput::new(
+                "current_price",
+                DecisionValue::Price(price.to_string()),
+            )];
+            record(&mut histories, &stats);
+        }
+        stats.transaction_status = TransactionStatus::TransactionPending;
+        record(&mut histories, &stats);
+        let history = &histories[&stats.id];
+        assert_eq!(history.len(), 3);
+        let inputs = serde_json::to_value(&history[2].inputs).ok().unwrap();
+        assert_eq!(
+            inputs,
+            serde_json::json!([{"name": "current_price", "type": "price", "value": "1600"}])
+        );
+        assert_eq!(
+            serde_json::from_value::<Vec<DecisionInput>>(inputs)
+                .ok()
+                .unwrap(),
+            stats.decision
+        );
+    }
+}
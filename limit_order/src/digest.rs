@@ -0,0 +1,225 @@
+use ethers::types::U256;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::{
+    http_client::http_client,
+    stats::{Status, TimerExecutorStats},
+    submission::SubmissionStatsMap,
+};
+
+// Number of the most frequent failure messages listed in a digest.
+const MAX_INCIDENTS: usize = 5;
+
+#[derive(Clone, Copy, Debug)]
+pub enum DigestPeriod {
+    Daily,
+    Weekly,
+}
+
+impl DigestPeriod {
+    fn name(&self) -> &'static str {
+        match self {
+            DigestPeriod::Daily => "daily",
+            DigestPeriod::Weekly => "weekly",
+        }
+    }
+
+    fn duration(&self) -> Duration {
+        match self {
+            DigestPeriod::Daily => Duration::from_secs(24 * 3600),
+            DigestPeriod::Weekly => Duration::from_secs(7 * 24 * 3600),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct AppDigest {
+    pub solved: u64,
+    pub failed: u64,
+    pub timed_out: u64,
+    pub solved_externally: u64,
+    pub tips_earned_wei: U256,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Incident {
+    pub message: String,
+    pub count: u64,
+}
+
+// Summary of the executions over a period, for the stakeholders.
+#[derive(Clone, Debug, Serialize)]
+pub struct Digest {
+    pub period: String,
+    // Start and end of the period in seconds since Unix epoch.
+    pub from: u64,
+    pub to: u64,
+    pub solved: u64,
+    pub failed: u64,
+    pub timed_out: u64,
+    pub duplicates: u64,
+    // Objectives executed by another solver first.
+    pub solved_externally: u64,
+    // Tips of the solved objectives, redacted tips aren't counted.
+    pub tips_earned_wei: U256,
+    // Gas of the submitted transactions since the previous digest of the period.
+    pub gas_spent_wei: U256,
+    pub apps: HashMap<String, AppDigest>,
+    // The most frequent failure messages.
+    pub incidents: Vec<Incident>,
+}
+
+// Builds the digests from the executor stats in memory and in the stats archive, and
+// posts them to a webhook.
+pub struct DigestReporter {
+    pub url: String,
+    pub stats_map: Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>,
+    pub archive_path: Option<String>,
+    pub submission_stats: SubmissionStatsMap,
+    // Total gas spent at the previous digest of each period.
+    pub gas_spent_at: Mutex<HashMap<&'static str, U256>>,
+}
+
+impl DigestReporter {
+    async fn build(&self, period: DigestPeriod) -> Digest {
+        let to = now();
+        let from = to.saturating_sub(period.duration());
+        let mut stats = self
+            .stats_map
+            .lock()
+            .await
+            .values()
+            .filter(|stats| stats.creation_time >= from)
+            .cloned()
+            .collect::<Vec<TimerExecutorStats>>();
+        // Evicted executors are in the archive, the ones in memory may be there too.
+        let in_memory = stats.iter().map(|stats| stats.id).collect::<Vec<Uuid>>();
+        stats.extend(
+            read_archive(self.archive_path.as_deref(), from)
+                .into_iter()
+                .filter(|stats| !in_memory.contains(&stats.id)),
+        );
+
+        let mut digest = Digest {
+            period: period.name().to_string(),
+            from: from.as_secs(),
+            to: to.as_secs(),
+            solved: 0,
+            failed: 0,
+            timed_out: 0,
+            duplicates: 0,
+            solved_externally: 0,
+            tips_earned_wei: U256::zero(),
+            gas_spent_wei: self.gas_spent(period).await,
+            apps: HashMap::new(),
+            incidents: Vec::new(),
+        };
+        let mut incidents: HashMap<String, u64> = HashMap::new();
+        for stats in stats {
+            let app = digest.apps.entry(stats.app.clone()).or_default();
+            match stats.status {
+                Status::Succeeded => {
+                    let tip = tip(&stats);
+                    digest.solved += 1;
+                    digest.tips_earned_wei += tip;
+                    app.solved += 1;
+                    app.tips_earned_wei += tip;
+                }
+                Status::Failed | Status::BudgetExhausted => {
+                    digest.failed += 1;
+                    app.failed += 1;
+                    *incidents.entry(stats.message).or_default() += 1;
+                }
+                Status::Timeout => {
+                    digest.timed_out += 1;
+                    app.timed_out += 1;
+                    *incidents.entry(stats.message).or_default() += 1;
+                }
+                Status::SolvedExternally => {
+                    digest.solved_externally += 1;
+                    app.solved_externally += 1;
+                }
+                Status::Duplicate => digest.duplicates += 1,
+                Status::Running => {}
+            }
+        }
+        let mut incidents = incidents
+            .into_iter()
+            .map(|(message, count)| Incident { message, count })
+            .collect::<Vec<Incident>>();
+        incidents.sort_by(|a, b| b.count.cmp(&a.count).then(a.message.cmp(&b.message)));
+        incidents.truncate(MAX_INCIDENTS);
+        digest.incidents = incidents;
+        digest
+    }
+
+    async fn gas_spent(&self, period: DigestPeriod) -> U256 {
+        let total = self
+            .submission_stats
+            .lock()
+            .await
+            .values()
+            .fold(U256::zero(), |acc, stats| acc + stats.gas_spent_wei);
+        let mut gas_spent_at = self.gas_spent_at.lock().await;
+        let previous = gas_spent_at
+            .insert(period.name(), total)
+            .unwrap_or_default();
+        total.saturating_sub(previous)
+    }
+
+    // Builds the digest of the period and posts it to the webhook.
+    pub async fn send(&self, period: DigestPeriod) -> Result<String, String> {
+        let digest = self.build(period).await;
+        let response = http_client()?
+            .post(self.url.as_str())
+            .json(&digest)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("Digest rejected: {}", response.status()));
+        }
+        Ok(format!(
+            "Sent the {} digest, {} solved, {} failed, {} timed out",
+            digest.period, digest.solved, digest.failed, digest.timed_out
+        ))
+    }
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+// Archived stats created since the given time, unreadable lines are skipped.
+fn read_archive(archive_path: Option<&str>, from: Duration) -> Vec<TimerExecutorStats> {
+    let file = match archive_path.map(File::open) {
+        Some(Ok(file)) => file,
+        _ => return Vec::new(),
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<TimerExecutorStats>(line.as_str()).ok())
+        .filter(|stats| stats.creation_time >= from)
+        .collect()
+}
+
+fn tip(stats: &TimerExecutorStats) -> U256 {
+    stats
+        .params
+        .iter()
+        .find(|ad| ad.name == "tip")
+        .and_then(|ad| U256::from_dec_str(ad.value.as_str()).ok())
+        .unwrap_or_default()
+}
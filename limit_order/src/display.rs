@@ -0,0 +1,111 @@
+use ethers::{
+    abi::AbiDecode,
+    types::{Bytes, H256},
+};
+use std::fmt;
+
+use crate::{
+    contracts_abi::{
+        call_breaker::CallBreakerCalls,
+        ierc20::IERC20Calls,
+        laminated_proxy::LaminatedProxyCalls,
+        laminator::{LaminatorCalls, ProxyPushedFilter},
+    },
+    solver::selector,
+    solvers::limit_order::{FlashLoanCalls, SwapPoolCalls, APP_SELECTOR},
+};
+
+// A pushed objective as logged, its mev-time data by name and its calls decoded.
+pub struct Objective<'a> {
+    objective: &'a ProxyPushedFilter,
+    app: Option<&'a str>,
+}
+
+// The app is found by the selector of the objective if not given.
+pub fn objective<'a>(objective: &'a ProxyPushedFilter, app: Option<&'a str>) -> Objective<'a> {
+    Objective { objective, app }
+}
+
+impl fmt::Display for Objective<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let objective = self.objective;
+        let app_selector = H256::from(objective.selector);
+        let app = match self.app {
+            Some(app) => app,
+            None if app_selector == selector(APP_SELECTOR.to_string()) => APP_SELECTOR,
+            None => "unknown app",
+        };
+        writeln!(
+            f,
+            "Objective {} of the proxy {:?}, {} (selector {:?})",
+            objective.sequence_number, objective.proxy_address, app, app_selector
+        )?;
+        writeln!(f, "  Data values:")?;
+        for data in &objective.data_values {
+            writeln!(
+                f,
+                "    {} = {} (type {})",
+                data.name, data.value, data.datatype
+            )?;
+        }
+        write!(f, "  Call objects:")?;
+        for (i, call) in objective.call_objs.iter().enumerate() {
+            write!(
+                f,
+                "\n    {}: to {:?}, amount {}, gas {}\n       {}",
+                i,
+                call.addr,
+                call.amount,
+                call.gas,
+                describe_call(&call.callvalue)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// The call decoded with the first of the bundled ABIs that knows its function.
+pub fn describe_call(callvalue: &Bytes) -> String {
+    if let Ok(call) = IERC20Calls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    if let Ok(call) = SwapPoolCalls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    if let Ok(call) = FlashLoanCalls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    if let Ok(call) = LaminatedProxyCalls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    if let Ok(call) = LaminatorCalls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    if let Ok(call) = CallBreakerCalls::decode(callvalue) {
+        return format!("{:?}", call);
+    }
+    format!("undecoded {}", callvalue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contracts_abi::ierc20::ApproveCall;
+    use ethers::{abi::AbiEncode, types::Address};
+
+    #[test]
+    fn calls_are_decoded_with_the_bundled_abis() {
+        let approve = IERC20Calls::Approve(ApproveCall {
+            spender: Address::repeat_byte(0x77),
+            amount: 10.into(),
+        });
+        assert_eq!(
+            describe_call(&approve.clone().encode().into()),
+            format!("{:?}", approve)
+        );
+        assert_eq!(
+            describe_call(&Bytes::from(vec![0xde, 0xad])),
+            "undecoded 0xdead"
+        );
+    }
+}
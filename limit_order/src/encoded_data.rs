@@ -0,0 +1,61 @@
+use ethers::{
+    abi::{self, AbiEncode, Token},
+    types::{Bytes, H256, U256},
+    utils::keccak256,
+};
+
+use crate::contracts_abi::call_breaker::CallObject;
+
+// Entries of the CallBreaker associated data store, encoded as AdditionalData[].
+#[derive(Clone, Debug, Default)]
+pub struct AssociatedData {
+    entries: Vec<(H256, Bytes)>,
+}
+
+impl AssociatedData {
+    pub fn new() -> AssociatedData {
+        AssociatedData::default()
+    }
+
+    // Adds the value under keccak256 of the name, the key the contracts look it up by.
+    pub fn with(mut self, name: &str, value: Bytes) -> AssociatedData {
+        self.entries.push((H256(keccak256(name)), value));
+        self
+    }
+
+    pub fn encode(self) -> Bytes {
+        encode_additional_data(self.entries)
+    }
+}
+
+// Positions of the calls in the call list, keyed by the call id the CallBreaker computes
+// for each call, keccak256(abi.encode(callObj)).
+pub fn hint_indices(calls: &[CallObject]) -> Bytes {
+    encode_additional_data(
+        calls
+            .iter()
+            .enumerate()
+            .map(|(i, call)| {
+                (
+                    H256(keccak256(call.clone().encode())),
+                    U256::from(i).encode().into(),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn encode_additional_data(entries: Vec<(H256, Bytes)>) -> Bytes {
+    abi::encode(&[Token::Array(
+        entries
+            .into_iter()
+            .map(|(key, value)| {
+                Token::Tuple(vec![
+                    Token::FixedBytes(key.as_bytes().to_vec()),
+                    Token::Bytes(value.to_vec()),
+                ])
+            })
+            .collect(),
+    )])
+    .into()
+}
@@ -0,0 +1,238 @@
+use ethers::types::{Address, H256, U256, U64};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::Write,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use uuid::Uuid;
+
+use crate::{
+    contracts_abi::{
+        call_breaker::{CallObject, ReturnObject},
+        laminator::{self, AdditionalData, ProxyPushedFilter},
+    },
+    solver::{SolverError, SolverResponse},
+    stats::Status,
+};
+
+// Stage of the execution a decision was taken at.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Step,
+    Preconditions,
+    Profitability,
+    FinalExec,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum ExecutionRecord {
+    // The objective as received, with the redacted parameters.
+    Objective {
+        data_values: Vec<AdditionalData>,
+        call_objs: Vec<laminator::CallObject>,
+    },
+    // The values a solver took its decision on, e.g. the observed price and the trigger.
+    Observed {
+        stage: Stage,
+        values: BTreeMap<String, String>,
+    },
+    // The outcome of a stage, an error if the stage didn't complete.
+    Decision {
+        stage: Stage,
+        succeeded: bool,
+        message: String,
+    },
+    // The calls of the final transaction with their simulated returns.
+    CallBundle {
+        calls: Vec<CallObject>,
+        returns: Vec<ReturnObject>,
+    },
+    // The final transaction simulated in the dry run mode.
+    Simulated {
+        message: String,
+    },
+    // The final transaction sent, with its receipt if it was received.
+    Transaction {
+        tx_hash: Option<H256>,
+        status: Option<U64>,
+        block_number: Option<U64>,
+        gas_used: Option<U256>,
+    },
+    Finished {
+        status: Status,
+        message: String,
+    },
+}
+
+#[derive(Serialize)]
+struct ExecutionLogEntry<'a> {
+    time: u64,
+    app: &'a str,
+    proxy_address: Address,
+    sequence_number: U256,
+    // Not known to the solvers, their records follow the ones of their executor.
+    executor: Option<Uuid>,
+    #[serde(flatten)]
+    record: &'a ExecutionRecord,
+}
+
+// Append-only file of what was done with each objective, as JSON lines, for post-mortems
+// and for showing users why their objective did or didn't execute.
+pub struct ExecutionLog {
+    path: String,
+    // Keeps the lines of concurrent executors whole.
+    lock: Mutex<()>,
+}
+
+impl ExecutionLog {
+    pub fn new(path: String) -> ExecutionLog {
+        ExecutionLog {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn append(&self, entry: &ExecutionLogEntry) -> Result<(), String> {
+        let line = serde_json::to_string(entry).map_err(|err| err.to_string())?;
+        let _lock = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|err| err.to_string())?;
+        file.write_all(format!("{}\n", line).as_bytes())
+            .map_err(|err| err.to_string())
+    }
+}
+
+// The records of one objective, nothing is recorded if the log isn't set.
+#[derive(Clone)]
+pub struct ExecutionTrace {
+    log: Option<Arc<ExecutionLog>>,
+    app: String,
+    proxy_address: Address,
+    sequence_number: U256,
+    executor: Option<Uuid>,
+}
+
+impl ExecutionTrace {
+    pub fn new(log: Option<Arc<ExecutionLog>>, app: &str, event: &ProxyPushedFilter) -> Self {
+        ExecutionTrace {
+            log,
+            app: app.to_string(),
+            proxy_address: event.proxy_address,
+            sequence_number: event.sequence_number,
+            executor: None,
+        }
+    }
+
+    pub fn with_executor(mut self, executor: Uuid) -> Self {
+        self.executor = Some(executor);
+        self
+    }
+
+    pub fn record(&self, record: ExecutionRecord) {
+        let log = match &self.log {
+            Some(log) => log,
+            None => return,
+        };
+        let entry = ExecutionLogEntry {
+            time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            app: self.app.as_str(),
+            proxy_address: self.proxy_address,
+            sequence_number: self.sequence_number,
+            executor: self.executor,
+            record: &record,
+        };
+        if let Err(err) = log.append(&entry) {
+            println!(
+                "Error recording objective {} in the execution log: {}",
+                self.sequence_number, err
+            );
+        }
+    }
+
+    // Records the values the decision of the stage was taken on.
+    pub fn observed(&self, stage: Stage, values: &[(&str, String)]) {
+        self.record(ExecutionRecord::Observed {
+            stage,
+            values: values
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        });
+    }
+
+    // Records the outcome of the stage.
+    pub fn decision(&self, stage: Stage, res: &Result<SolverResponse, SolverError>) {
+        let (succeeded, message) = match res {
+            Ok(response) => (response.succeeded, response.message.clone()),
+            Err(err) => (false, err.to_string()),
+        };
+        self.record(ExecutionRecord::Decision {
+            stage,
+            succeeded,
+            message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn records_are_appended_as_json_lines() {
+        let path = std::env::temp_dir().join(format!("execution_log_{}.jsonl", Uuid::new_v4()));
+        let log = Arc::new(ExecutionLog::new(path.to_string_lossy().to_string()));
+        let event = ProxyPushedFilter {
+            proxy_address: Address::repeat_byte(0x01),
+            sequence_number: 7.into(),
+            ..Default::default()
+        };
+        let executor = Uuid::new_v4();
+        let trace = ExecutionTrace::new(Some(log), "app", &event).with_executor(executor);
+        trace.observed(Stage::Step, &[("current_price", "1600".to_string())]);
+        trace.decision(
+            Stage::Step,
+            &Err(SolverError::RpcError("timed out".to_string())),
+        );
+        // Nothing is recorded without the log.
+        ExecutionTrace::new(None, "app", &event).decision(
+            Stage::Step,
+            &Ok(SolverResponse {
+                succeeded: true,
+                message: String::new(),
+                decision: Vec::new(),
+            }),
+        );
+
+        let lines = fs::read_to_string(&path).ok().unwrap();
+        fs::remove_file(&path).ok();
+        let entries = lines
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line)
+                    .ok()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["record"], "observed");
+        assert_eq!(entries[0]["values"]["current_price"], "1600");
+        assert_eq!(entries[0]["executor"], executor.to_string());
+        assert_eq!(entries[1]["record"], "decision");
+        assert_eq!(entries[1]["stage"], "step");
+        assert_eq!(entries[1]["succeeded"], false);
+        assert_eq!(entries[1]["message"], "RPC error, timed out");
+    }
+}
@@ -0,0 +1,146 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+use crate::{contracts_abi::laminator::ProxyPushedFilter, stats::TimerExecutorStats};
+
+// An objective as its author knows it. The sender is the proxy the objective was pushed
+// to, the nonce its sequence number there.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+pub struct ObjectiveId {
+    pub app: String,
+    pub sender: Address,
+    pub nonce: u64,
+}
+
+// The executors spawned for each objective, one more each time it's retried or resumed.
+#[derive(Default)]
+pub struct ExecutorIndex {
+    executors: Mutex<HashMap<ObjectiveId, Vec<Uuid>>>,
+}
+
+impl ExecutorIndex {
+    pub fn record(&self, app: &str, event: &ProxyPushedFilter, id: Uuid) {
+        let objective = ObjectiveId {
+            app: app.to_string(),
+            sender: event.proxy_address,
+            nonce: event.sequence_number.low_u64(),
+        };
+        self.executors
+            .lock()
+            .unwrap()
+            .entry(objective)
+            .or_default()
+            .push(id);
+    }
+
+    // Drops the executors whose stats were evicted.
+    pub fn forget(&self, ids: &HashSet<Uuid>) {
+        self.executors.lock().unwrap().retain(|_, executors| {
+            executors.retain(|id| !ids.contains(id));
+            !executors.is_empty()
+        });
+    }
+
+    fn find(&self, query: &ExecutorQuery) -> Vec<(ObjectiveId, Uuid)> {
+        let mut found = self
+            .executors
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(objective, _)| {
+                objective.sender == query.sender
+                    && query.nonce.iter().all(|nonce| objective.nonce == *nonce)
+                    && query.app.iter().all(|app| objective.app == *app)
+            })
+            .flat_map(|(objective, executors)| executors.iter().map(|id| (objective.clone(), *id)))
+            .collect::<Vec<_>>();
+        found.sort_by_key(|(objective, _)| objective.nonce);
+        found
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExecutorQuery {
+    pub sender: Address,
+    pub nonce: Option<u64>,
+    pub app: Option<String>,
+}
+
+// An executor of an objective, with its current stats once it started.
+#[derive(Serialize)]
+pub struct ObjectiveExecutor {
+    #[serde(flatten)]
+    pub objective: ObjectiveId,
+    pub id: Uuid,
+    pub stats: Option<TimerExecutorStats>,
+}
+
+#[derive(Clone)]
+pub struct ExecutorIndexState {
+    pub index: Arc<ExecutorIndex>,
+    pub stats: Arc<tokio::sync::Mutex<HashMap<Uuid, TimerExecutorStats>>>,
+}
+
+// The executors of the objectives of a sender, e.g. /executors?sender=0x...&nonce=3.
+pub async fn get_executors_json(
+    State(state): State<ExecutorIndexState>,
+    Query(query): Query<ExecutorQuery>,
+) -> Json<Vec<ObjectiveExecutor>> {
+    let found = state.index.find(&query);
+    let stats = state.stats.lock().await;
+    Json(
+        found
+            .into_iter()
+            .map(|(objective, id)| ObjectiveExecutor {
+                objective,
+                id,
+                stats: stats.get(&id).cloned(),
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn executors_are_found_by_sender_and_nonce() {
+        let index = ExecutorIndex::default();
+        let event = |proxy_address, sequence_number: u64| ProxyPushedFilter {
+            proxy_address,
+            sequence_number: sequence_number.into(),
+            ..Default::default()
+        };
+        let (alice, bob) = (Address::repeat_byte(0xa1), Address::repeat_byte(0xb0));
+        let (first, retried, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        index.record("APP", &event(alice, 1), first);
+        index.record("APP", &event(alice, 1), retried);
+        index.record("APP", &event(bob, 1), other);
+        let query = |nonce| ExecutorQuery {
+            sender: alice,
+            nonce,
+            app: None,
+        };
+        let ids = |query| {
+            index
+                .find(&query)
+                .into_iter()
+                .map(|(_, id)| id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(query(Some(1))), vec![first, retried]);
+        assert!(ids(query(Some(2))).is_empty());
+        index.forget(&HashSet::from([first]));
+        assert_eq!(ids(query(None)), vec![retried]);
+    }
+}
@@ -0,0 +1,301 @@
+use axum::{extract::State, response::Json};
+use ethers::types::U256;
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{sync::oneshot, time::Instant};
+
+// Economic value of an objective, ordered by the tip, then by the max fee per gas.
+#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Priority {
+    pub tip: U256,
+    pub max_fee_per_gas: U256,
+}
+
+// Limits the number of concurrently running executors. Objectives above the limit wait
+// for a free slot, the highest priority first and FIFO within the same priority.
+// Objectives waiting longer than the max wait are stale and go first, the oldest first,
+// so that low value objectives aren't starved.
+pub struct ExecutorQueue {
+    // None means no limit.
+    max_running: Option<usize>,
+    max_wait: Option<Duration>,
+    state: Mutex<QueueState>,
+}
+
+struct QueueState {
+    running: usize,
+    next_seq: u64,
+    waiting: Vec<Waiting>,
+}
+
+impl QueueState {
+    // Drops the objectives that stopped waiting, e.g. their executor was cancelled.
+    fn prune(&mut self) {
+        self.waiting.retain(|w| !w.wake.is_closed());
+    }
+}
+
+struct Waiting {
+    priority: Priority,
+    seq: u64,
+    app: String,
+    sequence_number: U256,
+    since: Instant,
+    wake: oneshot::Sender<()>,
+}
+
+// A waiting objective as shown in the stats.
+#[derive(Clone, Debug, Serialize)]
+pub struct QueuedObjective {
+    pub app: String,
+    pub sequence_number: U256,
+    pub priority: Priority,
+    pub waited: Duration,
+    pub stale: bool,
+}
+
+// A slot of a running executor, released on drop.
+pub struct QueuePermit {
+    queue: Arc<ExecutorQueue>,
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        self.queue.release();
+    }
+}
+
+impl ExecutorQueue {
+    pub fn new(max_running: Option<usize>, max_wait: Option<Duration>) -> ExecutorQueue {
+        ExecutorQueue {
+            max_running,
+            max_wait,
+            state: Mutex::new(QueueState {
+                running: 0,
+                next_seq: 0,
+                waiting: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn max_running(&self) -> Option<usize> {
+        self.max_running
+    }
+
+    // Numbers of the running and the waiting objectives.
+    pub fn depth(&self) -> (usize, usize) {
+        let mut state = self.state.lock().unwrap();
+        state.prune();
+        (state.running, state.waiting.len())
+    }
+
+    // Waits for a free slot.
+    pub async fn acquire(
+        self: &Arc<Self>,
+        priority: Priority,
+        app: String,
+        sequence_number: U256,
+    ) -> QueuePermit {
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            if self.max_running.iter().all(|max| state.running < *max) {
+                state.running += 1;
+                None
+            } else {
+                state.prune();
+                let (wake, wait) = oneshot::channel();
+                let seq = state.next_seq;
+                state.next_seq += 1;
+                state.waiting.push(Waiting {
+                    priority,
+                    seq,
+                    app,
+                    sequence_number,
+                    since: Instant::now(),
+                    wake,
+                });
+                println!(
+                    "Objective queued, running: {}, waiting: {}",
+                    state.running,
+                    state.waiting.len()
+                );
+                Some(wait)
+            }
+        };
+        if let Some(wait) = wait {
+            // The slot is handed over by the released permit.
+            let _ = wait.await;
+        }
+        QueuePermit {
+            queue: self.clone(),
+        }
+    }
+
+    // Hands the slot over to the next waiting objective, if any.
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        while let Some(next) = self.next(&state.waiting) {
+            if state.waiting.swap_remove(next).wake.send(()).is_ok() {
+                return;
+            }
+        }
+        state.running -= 1;
+    }
+
+    fn is_stale(&self, waiting: &Waiting) -> bool {
+        self.max_wait
+            .is_some_and(|max_wait| waiting.since.elapsed() >= max_wait)
+    }
+
+    // Index of the objective to run next, the oldest stale one or the highest priority.
+    fn next(&self, waiting: &[Waiting]) -> Option<usize> {
+        let stale = waiting
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| self.is_stale(w))
+            .min_by_key(|(_, w)| w.seq);
+        stale
+            .or_else(|| {
+                waiting
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, w)| (w.priority.clone(), Reverse(w.seq)))
+            })
+            .map(|(i, _)| i)
+    }
+
+    // The waiting objectives in the order they will run.
+    pub fn waiting(&self) -> Vec<QueuedObjective> {
+        let mut state = self.state.lock().unwrap();
+        state.prune();
+        let mut waiting = state
+            .waiting
+            .iter()
+            .map(|w| (self.is_stale(w), w))
+            .collect::<Vec<(bool, &Waiting)>>();
+        waiting.sort_by(|(stale_a, a), (stale_b, b)| {
+            stale_b.cmp(stale_a).then(if *stale_a && *stale_b {
+                a.seq.cmp(&b.seq)
+            } else {
+                b.priority.cmp(&a.priority).then(a.seq.cmp(&b.seq))
+            })
+        });
+        waiting
+            .into_iter()
+            .map(|(stale, w)| QueuedObjective {
+                app: w.app.clone(),
+                sequence_number: w.sequence_number,
+                priority: w.priority.clone(),
+                waited: w.since.elapsed(),
+                stale,
+            })
+            .collect()
+    }
+}
+
+pub async fn get_queue_json(queue: State<Arc<ExecutorQueue>>) -> Json<Vec<QueuedObjective>> {
+    Json(queue.waiting())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::task::{yield_now, JoinHandle};
+
+    fn priority(tip: u64) -> Priority {
+        Priority {
+            tip: tip.into(),
+            max_fee_per_gas: 0.into(),
+        }
+    }
+
+    // Queues the objective behind the running ones, the task ends once it has a slot.
+    async fn queued(
+        queue: &Arc<ExecutorQueue>,
+        tip: u64,
+        sequence_number: u64,
+    ) -> JoinHandle<QueuePermit> {
+        let waiting = queue.depth().1;
+        let task = tokio::spawn({
+            let queue = queue.clone();
+            async move {
+                queue
+                    .acquire(priority(tip), "app".to_string(), sequence_number.into())
+                    .await
+            }
+        });
+        while queue.depth().1 == waiting {
+            yield_now().await;
+        }
+        task
+    }
+
+    #[tokio::test]
+    async fn highest_priority_runs_first() {
+        let queue = Arc::new(ExecutorQueue::new(Some(1), None));
+        let permit = queue
+            .acquire(priority(0), "app".to_string(), 0.into())
+            .await;
+        let low = queued(&queue, 1, 1).await;
+        let high = queued(&queue, 2, 2).await;
+        let same = queued(&queue, 2, 3).await;
+        let order = queue
+            .waiting()
+            .iter()
+            .map(|w| w.sequence_number.as_u64())
+            .collect::<Vec<u64>>();
+        assert_eq!(order, [2, 3, 1]);
+
+        drop(permit);
+        let permit = high.await.ok().unwrap();
+        assert_eq!(queue.depth(), (1, 2));
+        drop(permit);
+        let permit = same.await.ok().unwrap();
+        assert_eq!(queue.depth(), (1, 1));
+        drop(permit);
+        let permit = low.await.ok().unwrap();
+        assert_eq!(queue.depth(), (1, 0));
+        drop(permit);
+        assert_eq!(queue.depth(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn released_slot_goes_to_the_waiting_objective() {
+        let queue = Arc::new(ExecutorQueue::new(Some(1), None));
+        let permit = queue
+            .acquire(priority(0), "app".to_string(), 0.into())
+            .await;
+        let waiting = queued(&queue, 0, 1).await;
+        assert_eq!(queue.depth(), (1, 1));
+
+        drop(permit);
+        let permit = waiting.await.ok().unwrap();
+        assert_eq!(queue.depth(), (1, 0));
+        drop(permit);
+        assert_eq!(queue.depth(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn cancelled_objectives_are_not_counted() {
+        let queue = Arc::new(ExecutorQueue::new(Some(1), None));
+        let permit = queue
+            .acquire(priority(0), "app".to_string(), 0.into())
+            .await;
+        let cancelled = queued(&queue, 2, 1).await;
+        let waiting = queued(&queue, 1, 2).await;
+        cancelled.abort();
+        assert!(cancelled.await.is_err());
+        assert_eq!(queue.depth(), (1, 1));
+        assert_eq!(queue.waiting().len(), 1);
+
+        drop(permit);
+        let permit = waiting.await.ok().unwrap();
+        assert_eq!(queue.depth(), (1, 0));
+        drop(permit);
+        assert_eq!(queue.depth(), (0, 0));
+    }
+}
@@ -0,0 +1,136 @@
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::BufReader,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use crate::{contracts_abi::laminator::ProxyPushedFilter, stats::TransactionStatus};
+
+// What is needed to resume the executor of an objective after a restart.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExecutorState {
+    // The raw objective, the redacted parameters included.
+    pub event: ProxyPushedFilter,
+    // Time the objective was received since Unix epoch, its time limit counts from it.
+    pub created: Duration,
+    // End of the time limit since Unix epoch, once the executor has read it.
+    pub deadline: Option<Duration>,
+    pub transaction_status: TransactionStatus,
+    pub message: String,
+}
+
+// Objectives by proxy and sequence number.
+type ObjectiveKey = (Address, U256);
+
+fn key(event: &ProxyPushedFilter) -> ObjectiveKey {
+    (event.proxy_address, event.sequence_number)
+}
+
+// The states of the executors not finished yet, written to a JSON file on every change
+// of their progress so that they are resumed on the next start.
+pub struct ExecutorStateStore {
+    path: String,
+    states: Mutex<HashMap<ObjectiveKey, ExecutorState>>,
+}
+
+impl ExecutorStateStore {
+    // Loads the states left by the previous run, there are none if the file is missing.
+    pub fn load(path: String) -> Result<ExecutorStateStore, String> {
+        let states: Vec<ExecutorState> = match File::open(&path) {
+            Ok(file) => {
+                serde_json::from_reader(BufReader::new(file)).map_err(|err| err.to_string())?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.to_string()),
+        };
+        Ok(ExecutorStateStore {
+            path,
+            states: Mutex::new(
+                states
+                    .into_iter()
+                    .map(|state| (key(&state.event), state))
+                    .collect(),
+            ),
+        })
+    }
+
+    // The states to resume, the ones past their deadline are dropped.
+    pub fn resumable(&self) -> Vec<ExecutorState> {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut states = self.states.lock().unwrap();
+        states.retain(|_, state| !matches!(state.deadline, Some(deadline) if deadline <= now));
+        self.write(&states);
+        let mut resumable = states.values().cloned().collect::<Vec<ExecutorState>>();
+        resumable.sort_by_key(|state| state.created);
+        resumable
+    }
+
+    // Tracks the objective, a resumed one keeps its state.
+    pub fn insert(&self, event: ProxyPushedFilter, created: Duration) {
+        let mut states = self.states.lock().unwrap();
+        if states.contains_key(&key(&event)) {
+            return;
+        }
+        states.insert(
+            key(&event),
+            ExecutorState {
+                event,
+                created,
+                deadline: None,
+                transaction_status: TransactionStatus::NotExecuted,
+                message: String::new(),
+            },
+        );
+        self.write(&states);
+    }
+
+    // Records the progress of the executor, written only when the transaction status
+    // changes rather than on every tick.
+    pub fn progress(
+        &self,
+        event: &ProxyPushedFilter,
+        deadline: Duration,
+        transaction_status: &TransactionStatus,
+        message: &str,
+    ) {
+        let mut states = self.states.lock().unwrap();
+        let state = match states.get_mut(&key(event)) {
+            Some(state) => state,
+            None => return,
+        };
+        if state.deadline == Some(deadline) && state.transaction_status == *transaction_status {
+            return;
+        }
+        state.deadline = Some(deadline);
+        state.transaction_status = transaction_status.clone();
+        state.message = message.to_string();
+        self.write(&states);
+    }
+
+    pub fn remove(&self, event: &ProxyPushedFilter) {
+        let mut states = self.states.lock().unwrap();
+        if states.remove(&key(event)).is_some() {
+            self.write(&states);
+        }
+    }
+
+    // Replaces the file at once, so that a crash while writing leaves the previous one.
+    fn write(&self, states: &HashMap<ObjectiveKey, ExecutorState>) {
+        let res = serde_json::to_vec(&states.values().collect::<Vec<&ExecutorState>>())
+            .map_err(|err| err.to_string())
+            .and_then(|content| {
+                let tmp_path = format!("{}.tmp", self.path);
+                fs::write(&tmp_path, content).map_err(|err| err.to_string())?;
+                fs::rename(&tmp_path, &self.path).map_err(|err| err.to_string())
+            });
+        if let Err(err) = res {
+            println!("Error writing the executor state to {}: {}", self.path, err);
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use ethers::{
+    abi::{self, AbiEncode, Token},
+    types::{Address, Bytes, U256},
+};
+use std::str::FromStr;
+
+use crate::contracts_abi::{
+    call_breaker::CallObject,
+    ierc20::{ApproveCall, IERC20Calls},
+};
+
+// Flash loan premium of the Aave V3 markets, FLASHLOAN_PREMIUM_TOTAL of the pool.
+const AAVE_V3_PREMIUM_BPS: u64 = 5;
+
+// Lends the liquidity of the flash loan strategy for the duration of the final
+// transaction. The loans are given as (token, amount).
+pub trait FlashLoanProvider {
+    fn name(&self) -> &'static str;
+    // Holder of the lent tokens, whose balances bound the loans. None if the provider
+    // doesn't hold them itself.
+    fn liquidity_holder(&self) -> Option<Address>;
+    // Owed on top of the loan of the amount.
+    fn fee(&self, amount: U256) -> U256;
+    // The flash loan data of execute_and_verify the call breaker takes the loans with.
+    fn loan_data(&self, loans: &[(Address, U256)]) -> Bytes;
+    // Calls of the call breaker repaying the loans with their fees, after the calls
+    // using them.
+    fn repay_calls(&self, loans: &[(Address, U256)]) -> Vec<CallObject>;
+}
+
+// A clone of the FlashLoanData onchain structure.
+// Cannot be imported by abigen due to visibility restriction.
+// Should be synchronized with the definition in https://github.com/smart-transaction/stxn-contracts-core/blob/6dc025f53af60a0026aa6a4bb0f1d98a881d978a/src/CallBreakerTypes.sol
+struct FlashLoanData {
+    provider: Address,
+    amount_a: U256,
+    amount_b: U256,
+}
+
+impl AbiEncode for FlashLoanData {
+    fn encode(self) -> Vec<u8> {
+        let mut res = self.provider.encode();
+        res.extend(self.amount_a.encode());
+        res.extend(self.amount_b.encode());
+        res
+    }
+}
+
+// The MockFlashLoan of the test deployments, lending its own DAI and WETH for free. It
+// takes the loans back itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockProvider {
+    pub address: Address,
+}
+
+impl FlashLoanProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn liquidity_holder(&self) -> Option<Address> {
+        Some(self.address)
+    }
+
+    fn fee(&self, _amount: U256) -> U256 {
+        U256::zero()
+    }
+
+    // The mock lends a pair, in the order of the loans.
+    fn loan_data(&self, loans: &[(Address, U256)]) -> Bytes {
+        let amount = |i: usize| loans.get(i).map(|(_, amount)| *amount).unwrap_or_default();
+        FlashLoanData {
+            provider: self.address,
+            amount_a: amount(0),
+            amount_b: amount(1),
+        }
+        .encode()
+        .into()
+    }
+
+    fn repay_calls(&self, _loans: &[(Address, U256)]) -> Vec<CallObject> {
+        Vec::new()
+    }
+}
+
+// The pool of an Aave V3 market. The pool pulls the loans with their premium from the
+// call breaker once the calls using them ran, so the call breaker approves it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AaveV3 {
+    pub pool: Address,
+    pub premium_bps: u64,
+}
+
+impl FlashLoanProvider for AaveV3 {
+    fn name(&self) -> &'static str {
+        "aave_v3"
+    }
+
+    // The reserves are held by the aTokens of the market, the pool checks them.
+    fn liquidity_holder(&self) -> Option<Address> {
+        None
+    }
+
+    // Rounded half up, as percentMul of the pool.
+    fn fee(&self, amount: U256) -> U256 {
+        (amount * self.premium_bps + 5000) / 10000
+    }
+
+    // The pool with the assets and amounts of flashLoan.
+    fn loan_data(&self, loans: &[(Address, U256)]) -> Bytes {
+        abi::encode(&[
+            Token::Address(self.pool),
+            Token::Array(
+                loans
+                    .iter()
+                    .map(|(token, _)| Token::Address(*token))
+                    .collect(),
+            ),
+            Token::Array(
+                loans
+                    .iter()
+                    .map(|(_, amount)| Token::Uint(*amount))
+                    .collect(),
+            ),
+        ])
+        .into()
+    }
+
+    fn repay_calls(&self, loans: &[(Address, U256)]) -> Vec<CallObject> {
+        loans
+            .iter()
+            .map(|(token, amount)| CallObject {
+                amount: 0.into(),
+                addr: *token,
+                gas: 10000000.into(),
+                callvalue: IERC20Calls::Approve(ApproveCall {
+                    spender: self.pool,
+                    amount: *amount + self.fee(*amount),
+                })
+                .encode()
+                .into(),
+            })
+            .collect()
+    }
+}
+
+// All available providers, configured by name.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Provider {
+    Mock(MockProvider),
+    AaveV3(AaveV3),
+}
+
+impl FlashLoanProvider for Provider {
+    fn name(&self) -> &'static str {
+        match self {
+            Provider::Mock(p) => p.name(),
+            Provider::AaveV3(p) => p.name(),
+        }
+    }
+
+    fn liquidity_holder(&self) -> Option<Address> {
+        match self {
+            Provider::Mock(p) => p.liquidity_holder(),
+            Provider::AaveV3(p) => p.liquidity_holder(),
+        }
+    }
+
+    fn fee(&self, amount: U256) -> U256 {
+        match self {
+            Provider::Mock(p) => p.fee(amount),
+            Provider::AaveV3(p) => p.fee(amount),
+        }
+    }
+
+    fn loan_data(&self, loans: &[(Address, U256)]) -> Bytes {
+        match self {
+            Provider::Mock(p) => p.loan_data(loans),
+            Provider::AaveV3(p) => p.loan_data(loans),
+        }
+    }
+
+    fn repay_calls(&self, loans: &[(Address, U256)]) -> Vec<CallObject> {
+        match self {
+            Provider::Mock(p) => p.repay_calls(loans),
+            Provider::AaveV3(p) => p.repay_calls(loans),
+        }
+    }
+}
+
+// The flash loan provider of a token pair, passed as TOKEN_A:TOKEN_B:PROVIDER:ADDRESS
+// with the provider mock or aave_v3. The Aave V3 premium may follow in basis points,
+// e.g. TOKEN_A:TOKEN_B:aave_v3:POOL:9. Pairs without a market of their own borrow from
+// the mock of --flash-loan-address.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlashLoanMarket {
+    pub token_a: Address,
+    pub token_b: Address,
+    pub provider: Provider,
+}
+
+impl FromStr for FlashLoanMarket {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let (token_a, token_b, kind, address, premium) = match parts.as_slice() {
+            [token_a, token_b, kind, address] => (token_a, token_b, kind, address, None),
+            [token_a, token_b, kind, address, premium] => {
+                (token_a, token_b, kind, address, Some(premium))
+            }
+            _ => {
+                return Err(format!(
+                    "expected TOKEN_A:TOKEN_B:PROVIDER:ADDRESS[:PREMIUM_BPS], got \"{}\"",
+                    s
+                ))
+            }
+        };
+        let address_of = |value: &str| {
+            Address::from_str(value).map_err(|err| format!("invalid address {}: {}", value, err))
+        };
+        let address = address_of(address)?;
+        let provider = match (*kind, premium) {
+            ("mock", None) => Provider::Mock(MockProvider { address }),
+            ("aave_v3", premium) => Provider::AaveV3(AaveV3 {
+                pool: address,
+                premium_bps: match premium {
+                    Some(premium) => premium
+                        .parse()
+                        .map_err(|err| format!("invalid premium {}: {}", premium, err))?,
+                    None => AAVE_V3_PREMIUM_BPS,
+                },
+            }),
+            ("mock", Some(_)) => return Err("the mock provider takes no premium".to_string()),
+            (kind, _) => {
+                return Err(format!(
+                    "unknown flash loan provider \"{}\", expected mock or aave_v3",
+                    kind
+                ))
+            }
+        };
+        Ok(FlashLoanMarket {
+            token_a: address_of(token_a)?,
+            token_b: address_of(token_b)?,
+            provider,
+        })
+    }
+}
+
+impl FlashLoanMarket {
+    pub fn trades(&self, token_a: Address, token_b: Address) -> bool {
+        (self.token_a, self.token_b) == (token_a, token_b)
+            || (self.token_a, self.token_b) == (token_b, token_a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aave_loans_are_repaid_with_the_premium() {
+        let market = FlashLoanMarket::from_str(&format!(
+            "{:?}:{:?}:aave_v3:{:?}",
+            Address::repeat_byte(0xda),
+            Address::repeat_byte(0xee),
+            Address::repeat_byte(0xaa)
+        ))
+        .ok()
+        .unwrap();
+        assert!(market.trades(Address::repeat_byte(0xee), Address::repeat_byte(0xda)));
+        let provider = market.provider;
+        assert_eq!(provider.fee(U256::exp10(18)), U256::exp10(14) * 5);
+        assert_eq!(provider.liquidity_holder(), None);
+
+        let loans = [(Address::repeat_byte(0xda), U256::from(10000))];
+        let repay = provider.repay_calls(&loans);
+        assert_eq!(repay.len(), 1);
+        assert_eq!(
+            repay[0].callvalue,
+            Bytes::from(
+                IERC20Calls::Approve(ApproveCall {
+                    spender: Address::repeat_byte(0xaa),
+                    amount: 10005.into(),
+                })
+                .encode()
+            )
+        );
+
+        assert!(FlashLoanMarket::from_str("0xda:0xee:aave_v3").is_err());
+        assert!(FlashLoanMarket::from_str(&format!(
+            "{:?}:{:?}:mock:{:?}:5",
+            Address::zero(),
+            Address::zero(),
+            Address::zero()
+        ))
+        .is_err());
+    }
+}
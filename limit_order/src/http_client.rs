@@ -0,0 +1,12 @@
+use std::time::Duration;
+
+// Bound of a request to a webhook or an external API, so that an unresponsive one
+// doesn't hold the task sending to it.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .map_err(|err| format!("Error building the HTTP client: {}", err))
+}
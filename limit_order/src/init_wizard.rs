@@ -0,0 +1,215 @@
+use ethers::{
+    core::types::Address,
+    providers::{Middleware, Provider, Ws},
+    signers::{LocalWallet, Signer},
+};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+// Known deployments used as defaults, keyed by chain ID.
+struct KnownDeployment {
+    chain_id: u64,
+    ws_chain_url: &'static str,
+    laminator_address: &'static str,
+    call_breaker_address: &'static str,
+    flash_loan_address: &'static str,
+    swap_pool_address: &'static str,
+}
+
+const KNOWN_DEPLOYMENTS: &[KnownDeployment] = &[KnownDeployment {
+    chain_id: 21363,
+    ws_chain_url: "wss://service.lestnet.org:8888/",
+    laminator_address: "0x36aB7A6ad656BC19Da2D5Af5b46f3cf3fc47274D",
+    call_breaker_address: "0x23912387357621473Ff6514a2DC20Df14cd72E7f",
+    flash_loan_address: "0xA04bABcCbcf9B9E51eE4954DB223E34691F5F65D",
+    swap_pool_address: "0xD68B5dd90022f9871913198285cce9d90AAcCD62",
+}];
+
+// Asks for the solver settings, validates them against the chain and writes them into
+// the config file, in the format sourced by run_local.sh.
+pub async fn run(output: String) {
+    println!("Solver configuration, press Enter to accept the value in brackets.");
+    let mut lines = io::stdin().lock().lines();
+
+    let chain_id: u64 = ask_parsed(&mut lines, "Chain ID", Some("21363"));
+    let known = KNOWN_DEPLOYMENTS.iter().find(|d| d.chain_id == chain_id);
+    if known.is_some() {
+        println!(
+            "Using the known deployment on chain {} as defaults",
+            chain_id
+        );
+    }
+
+    // The chain connection, everything else is validated against it.
+    let (ws_chain_url, provider) = loop {
+        let ws_chain_url = ask(
+            &mut lines,
+            "WebSocket chain URL",
+            known.map(|d| d.ws_chain_url),
+        );
+        match Provider::<Ws>::connect(ws_chain_url.as_str()).await {
+            Ok(provider) => match provider.get_chainid().await {
+                Ok(actual) if actual == chain_id.into() => break (ws_chain_url, provider),
+                Ok(actual) => println!(
+                    "The chain at {} has ID {}, expected {}",
+                    ws_chain_url, actual, chain_id
+                ),
+                Err(err) => println!("Error reading the chain ID: {}", err),
+            },
+            Err(err) => println!("Failed connection to the chain: {}", err),
+        }
+    };
+
+    let laminator_address = ask_contract(
+        &mut lines,
+        &provider,
+        "Laminator address",
+        known.map(|d| d.laminator_address),
+    )
+    .await;
+    let call_breaker_address = ask_contract(
+        &mut lines,
+        &provider,
+        "CallBreaker address",
+        known.map(|d| d.call_breaker_address),
+    )
+    .await;
+    let flash_loan_address = ask_contract(
+        &mut lines,
+        &provider,
+        "Flash loan address",
+        known.map(|d| d.flash_loan_address),
+    )
+    .await;
+    let swap_pool_address = ask_contract(
+        &mut lines,
+        &provider,
+        "Default swap pool address",
+        known.map(|d| d.swap_pool_address),
+    )
+    .await;
+
+    // The key itself is never written into the config, only where to take it from.
+    let wallet_secret = loop {
+        let source = ask(
+            &mut lines,
+            "Wallet key source (gcloud or env)",
+            Some("gcloud"),
+        );
+        match source.as_str() {
+            "gcloud" => {
+                break Some(ask(
+                    &mut lines,
+                    "Name of the gcloud secret with the wallet private key",
+                    Some("LOCAL_LESTNET_WALLET_PRIVATE_KEY_DEV"),
+                ))
+            }
+            "env" => {
+                match std::env::var("LIMIT_ORDER_WALLET_PRIVATE_KEY")
+                    .map_err(|err| err.to_string())
+                    .and_then(|key| LocalWallet::from_str(&key).map_err(|err| err.to_string()))
+                {
+                    Ok(wallet) => match provider.get_balance(wallet.address(), None).await {
+                        Ok(balance) => {
+                            println!("Wallet {:?} has balance {}", wallet.address(), balance);
+                            break None;
+                        }
+                        Err(err) => println!("Error reading the wallet balance: {}", err),
+                    },
+                    Err(err) => {
+                        println!("Invalid key in LIMIT_ORDER_WALLET_PRIVATE_KEY: {}", err)
+                    }
+                }
+            }
+            _ => println!("Unknown key source {}", source),
+        }
+    };
+
+    let port: u16 = ask_parsed(&mut lines, "HTTP port", Some("3030"));
+    let tick_secs: u64 = ask_parsed(&mut lines, "Tick seconds", Some("1"));
+    let tick_nanos: u32 = ask_parsed(&mut lines, "Tick nanoseconds", Some("0"));
+
+    let mut config = format!(
+        "PORT={}\nCHAIN_ID={}\nWS_CHAIN_URL={}\nLAMINATOR_ADDRESS={:?}\nCALL_BREAKER_ADDRESS={:?}\nFLASH_LOAN_ADDRESS={:?}\nSWAP_POOL_ADDRESS={:?}\nTICK_SECS={}\nTICK_NANOS={}\n",
+        port,
+        chain_id,
+        ws_chain_url,
+        laminator_address,
+        call_breaker_address,
+        flash_loan_address,
+        swap_pool_address,
+        tick_secs,
+        tick_nanos
+    );
+    if let Some(wallet_secret) = wallet_secret {
+        config.push_str(format!("LIMIT_ORDER_WALLET_SECRET={}\n", wallet_secret).as_str());
+    }
+    match fs::write(&output, config) {
+        Ok(_) => println!("Config is written to {}", output),
+        Err(err) => println!("Error writing the config to {}: {}", output, err),
+    }
+}
+
+// Prompts for a value, returning the default on empty input.
+fn ask(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+    prompt: &str,
+    default: Option<&str>,
+) -> String {
+    loop {
+        match default {
+            Some(default) => print!("{} [{}]: ", prompt, default),
+            None => print!("{}: ", prompt),
+        }
+        let _ = io::stdout().flush();
+        let line = match lines.next() {
+            Some(Ok(line)) => line.trim().to_string(),
+            _ => {
+                println!();
+                std::process::exit(1);
+            }
+        };
+        if !line.is_empty() {
+            return line;
+        }
+        if let Some(default) = default {
+            return default.to_string();
+        }
+    }
+}
+
+fn ask_parsed<T: FromStr>(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+    prompt: &str,
+    default: Option<&str>,
+) -> T
+where
+    T::Err: std::fmt::Display,
+{
+    loop {
+        match ask(lines, prompt, default).parse() {
+            Ok(value) => return value,
+            Err(err) => println!("Invalid value: {}", err),
+        }
+    }
+}
+
+// Prompts for a contract address and checks that there is a contract deployed at it.
+async fn ask_contract(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+    provider: &Provider<Ws>,
+    prompt: &str,
+    default: Option<&str>,
+) -> Address {
+    loop {
+        let address: Address = ask_parsed(lines, prompt, default);
+        match provider.get_code(address, None).await {
+            Ok(code) if !code.is_empty() => return address,
+            Ok(_) => println!("There is no contract at the address {:?}", address),
+            Err(err) => println!("Error reading the contract code: {}", err),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use ethers::{
+    contract::parse_log,
+    providers::{Middleware, Provider, Ws},
+    types::{Log, H256},
+};
+
+use crate::{contracts_abi::laminator::ProxyPushedFilter, display};
+
+// Decodes the objectives pushed in the transaction, or in the given log as JSON, e.g.
+// one returned by eth_getLogs, and prints them.
+pub async fn run(
+    ws_chain_url: Option<String>,
+    tx_hash: Option<H256>,
+    log: Option<String>,
+) -> Result<(), String> {
+    let logs = match (log, tx_hash, ws_chain_url) {
+        (Some(log), _, _) => serde_json::from_str::<Vec<Log>>(&log)
+            .or_else(|_| serde_json::from_str::<Log>(&log).map(|log| vec![log]))
+            .map_err(|err| format!("Invalid log: {}", err))?,
+        (None, Some(tx_hash), Some(ws_chain_url)) => {
+            let provider = Provider::<Ws>::connect(ws_chain_url.as_str())
+                .await
+                .map_err(|err| format!("Failed connection to the chain: {}", err))?;
+            provider
+                .get_transaction_receipt(tx_hash)
+                .await
+                .map_err(|err| format!("Error reading the receipt: {}", err))?
+                .ok_or(format!("No receipt for the transaction {:?}", tx_hash))?
+                .logs
+        }
+        _ => {
+            return Err("Either a log or a transaction hash with a chain URL is needed".to_string())
+        }
+    };
+    let objectives = logs
+        .into_iter()
+        .filter_map(|log| {
+            let (tx_hash, block_number) = (log.transaction_hash, log.block_number);
+            parse_log::<ProxyPushedFilter>(log)
+                .ok()
+                .map(|objective| (objective, tx_hash, block_number))
+        })
+        .collect::<Vec<_>>();
+    if objectives.is_empty() {
+        return Err("No ProxyPushed event found".to_string());
+    }
+    for (objective, tx_hash, block_number) in objectives {
+        println!("{}", display::objective(&objective, None));
+        if let (Some(tx_hash), Some(block_number)) = (tx_hash, block_number) {
+            println!("  Pushed in {:?}, block {}", tx_hash, block_number);
+        }
+    }
+    Ok(())
+}
@@ -1,22 +1,66 @@
 use ethers::{
     abi::Address,
     providers::{Middleware, StreamExt},
-    types::{BlockNumber, H256},
+    types::{BlockNumber, H256, U256},
 };
 use fatal::fatal;
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    future::pending,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 use tokio::{
-    sync::{mpsc::Sender, Mutex},
-    task::JoinSet,
+    sync::{
+        mpsc::{unbounded_channel, Receiver, UnboundedReceiver, UnboundedSender},
+        Mutex,
+    },
+    time::sleep,
 };
+use uuid::Uuid;
 
 use crate::{
+    admin::SolvingSwitch,
+    circuit_breaker::CircuitBreaker,
+    connectivity::{ConnectionState, Connectivity},
     contracts_abi::laminator::{Laminator, ProxyPushedFilter},
+    dead_letter::DeadLetters,
+    dedup::DedupCache,
+    display,
+    executor_index::ExecutorIndex,
+    executor_queue::{ExecutorQueue, Priority},
+    executor_state::ExecutorStateStore,
+    redaction::Redactor,
+    sender_filter::{Rejection, SenderFilter},
+    shadow::Shadow,
+    sharding::Sharding,
     solver::{selector, SolverParams},
-    solvers::limit_order::{self, LimitOrderSolver},
-    stats::TimerExecutorStats,
+    solvers::{
+        limit_order::{self, LimitOrderSolver},
+        order_price,
+    },
+    stats::{Status, TimerExecutorStats},
+    stats_channel::StatsSender,
+    supervisor::Supervisor,
     timer_executor::TimerRequestExecutor,
 };
+#[cfg(feature = "plugins")]
+use crate::{plugins::SolverPlugin, solvers::plugin::PluginSolver};
+
+// Delay before subscribing to the events again, doubled with each failed attempt.
+const RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESUBSCRIBE_BACKOFF: Duration = Duration::from_secs(30);
+// Failed subscriptions in a row before giving up, unless set.
+pub const MAX_RESUBSCRIBE_ATTEMPTS: u32 = 10;
+
+// How long a solver warmed up from a pending push waits for the event of its objective.
+const PREWARM_TTL: Duration = Duration::from_secs(120);
+
+// Objectives by (selector, proxy, sequence number).
+type ObjectiveKey = (H256, Address, U256);
+
+// A solver warmed up from a pending push, with the objective it was built for.
+type Warmed<M> = (ObjectiveKey, ProxyPushedFilter, LimitOrderSolver<M>);
 
 pub struct LaminatorListener<M: Clone> {
     // The address of the laminator contract.
@@ -28,14 +72,70 @@ pub struct LaminatorListener<M: Clone> {
     // Mapping of app selectors to solver params.
     solvers_params: HashMap<H256, SolverParams<M>>,
 
-    // JoinSet for using for executors spawning.
-    exec_set: Arc<Mutex<JoinSet<()>>>,
+    // Solver plugins by the selectors of their apps.
+    #[cfg(feature = "plugins")]
+    plugins: HashMap<H256, Arc<SolverPlugin>>,
 
-    // Execution tick duration
-    tick_duration: Duration,
+    // Spawns the executors.
+    supervisor: Supervisor,
 
     // The channel for sending current stats
-    stats_tx: Sender<TimerExecutorStats>,
+    stats_tx: StatsSender,
+
+    // Limits the number of concurrently running executors.
+    queue: Arc<ExecutorQueue>,
+
+    // Recently received objectives.
+    dedup: DedupCache<ObjectiveKey>,
+
+    // Mirroring of objectives to and from another solver.
+    shadow: Shadow,
+
+    // Redaction of the objective parameters in logs and stats.
+    redactor: Arc<Redactor>,
+
+    // State of the chain connection.
+    connectivity: Arc<Mutex<Connectivity>>,
+
+    // Failed subscriptions in a row before giving up.
+    max_resubscribe_attempts: u32,
+
+    // Objectives that didn't succeed, and the ones requeued from them.
+    dead_letters: Arc<Mutex<DeadLetters>>,
+    retry_rx: Option<Receiver<ProxyPushedFilter>>,
+
+    // States of the running executors, to resume them after a restart.
+    state_store: Option<Arc<ExecutorStateStore>>,
+
+    // Holds the new objectives while the solving is paused.
+    switch: Arc<SolvingSwitch>,
+
+    // Allowed and denied senders by app.
+    sender_filter: Arc<SenderFilter>,
+
+    // The objectives of the previous run are resumed once, not on each restart of the
+    // listener.
+    resumed: bool,
+
+    // Objectives of the pending pushes, if the mempool is watched.
+    pending_rx: Option<Receiver<ProxyPushedFilter>>,
+    // Solvers warmed up from the pending pushes, with the objective and the time they
+    // were warmed up at.
+    prewarmed: HashMap<ObjectiveKey, (Instant, ProxyPushedFilter, LimitOrderSolver<M>)>,
+    // Objectives whose solvers are being warmed up off the listener loop, dropped once
+    // their event comes first.
+    warming: HashSet<ObjectiveKey>,
+    warmed_tx: UnboundedSender<Warmed<M>>,
+    warmed_rx: UnboundedReceiver<Warmed<M>>,
+
+    // The executors of the objectives, for their authors to find them.
+    executor_index: Arc<ExecutorIndex>,
+
+    // Holds the final transactions of an app after failures in a row if set.
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+
+    // The shards of the objectives executed by this instance.
+    sharding: Arc<Sharding>,
 }
 
 impl<M: Middleware + Clone + 'static> LaminatorListener<M> {
@@ -43,67 +143,524 @@ impl<M: Middleware + Clone + 'static> LaminatorListener<M> {
         laminator_address: Address,
         middleware: Arc<M>,
         solvers_params: HashMap<H256, SolverParams<M>>,
-        exec_set: Arc<Mutex<JoinSet<()>>>,
-        tick_duration: Duration,
-        stats_tx: Sender<TimerExecutorStats>,
+        supervisor: Supervisor,
+        stats_tx: StatsSender,
+        queue: Arc<ExecutorQueue>,
+        dedup_ttl: Duration,
     ) -> LaminatorListener<M> {
+        let (warmed_tx, warmed_rx) = unbounded_channel();
         LaminatorListener::<M> {
             laminator_address,
             middleware,
             solvers_params,
-            exec_set,
-            tick_duration,
+            #[cfg(feature = "plugins")]
+            plugins: HashMap::new(),
+            supervisor,
             stats_tx,
+            queue,
+            dedup: DedupCache::new(dedup_ttl),
+            shadow: Shadow::new(None, None),
+            redactor: Arc::new(Redactor::new(Vec::new())),
+            connectivity: Arc::new(Mutex::new(Connectivity::new())),
+            max_resubscribe_attempts: MAX_RESUBSCRIBE_ATTEMPTS,
+            dead_letters: Arc::default(),
+            retry_rx: None,
+            state_store: None,
+            switch: Arc::new(SolvingSwitch::new()),
+            sender_filter: Arc::default(),
+            resumed: false,
+            pending_rx: None,
+            prewarmed: HashMap::new(),
+            warming: HashSet::new(),
+            warmed_tx,
+            warmed_rx,
+            executor_index: Arc::default(),
+            circuit_breaker: None,
+            sharding: Arc::default(),
         }
     }
 
+    #[cfg(feature = "plugins")]
+    pub fn with_plugins(mut self, plugins: HashMap<H256, Arc<SolverPlugin>>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    pub fn with_shadow(mut self, shadow: Shadow) -> Self {
+        self.shadow = shadow;
+        self
+    }
+
+    pub fn with_redactor(mut self, redactor: Arc<Redactor>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    pub fn with_connectivity(
+        mut self,
+        connectivity: Arc<Mutex<Connectivity>>,
+        max_resubscribe_attempts: u32,
+    ) -> Self {
+        self.connectivity = connectivity;
+        self.max_resubscribe_attempts = max_resubscribe_attempts;
+        self
+    }
+
+    // The objectives requeued from the dead letters are executed again.
+    pub fn with_dead_letters(
+        mut self,
+        dead_letters: Arc<Mutex<DeadLetters>>,
+        retry_rx: Receiver<ProxyPushedFilter>,
+    ) -> Self {
+        self.dead_letters = dead_letters;
+        self.retry_rx = Some(retry_rx);
+        self
+    }
+
+    pub fn with_state_store(mut self, state_store: Option<Arc<ExecutorStateStore>>) -> Self {
+        self.state_store = state_store;
+        self
+    }
+
+    pub fn with_switch(mut self, switch: Arc<SolvingSwitch>) -> Self {
+        self.switch = switch;
+        self
+    }
+
+    pub fn with_sender_filter(mut self, sender_filter: Arc<SenderFilter>) -> Self {
+        self.sender_filter = sender_filter;
+        self
+    }
+
+    // The limit order solvers are warmed up from the objectives of the pending pushes.
+    pub fn with_pending(mut self, pending_rx: Receiver<ProxyPushedFilter>) -> Self {
+        self.pending_rx = Some(pending_rx);
+        self
+    }
+
+    pub fn with_executor_index(mut self, executor_index: Arc<ExecutorIndex>) -> Self {
+        self.executor_index = executor_index;
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, circuit_breaker: Option<Arc<CircuitBreaker>>) -> Self {
+        self.circuit_breaker = circuit_breaker;
+        self
+    }
+
+    pub fn with_sharding(mut self, sharding: Arc<Sharding>) -> Self {
+        self.sharding = sharding;
+        self
+    }
+
     pub async fn listen(&mut self) {
+        // Objectives of the previous run that are still within their time limit.
+        let resumable = match &self.state_store {
+            Some(state_store) if !self.resumed => state_store.resumable(),
+            _ => Vec::new(),
+        };
+        self.resumed = true;
+        for state in resumable {
+            println!(
+                "Resuming objective {} of the proxy {:?}",
+                state.event.sequence_number, state.event.proxy_address
+            );
+            self.handle_objective(state.event, true, false, Some(state.created))
+                .await;
+        }
         let laminator_contract = Laminator::new(self.laminator_address, self.middleware.clone());
         let events = laminator_contract
             .event::<ProxyPushedFilter>()
             .from_block(BlockNumber::Latest);
+        let mut attempts = 0;
         loop {
             match events.stream().await {
                 Ok(stream) => {
+                    attempts = 0;
+                    self.connectivity
+                        .lock()
+                        .await
+                        .recover(ConnectionState::Reconnecting);
                     let mut stream_take = stream.take(10);
                     println!("Listening the event ProxyPushed ...");
-                    while let Some(Ok(proxy_pushed)) = stream_take.next().await {
-                        if let Some(solver_params) =
-                            self.solvers_params.get(&proxy_pushed.selector.into())
-                        {
-                            let mut exec_set = self.exec_set.lock().await;
-                            let solver_params = solver_params.clone();
-                            let tick_duration = self.tick_duration.clone();
-                            let stats_tx = self.stats_tx.clone();
-                            exec_set.spawn(async move {
-                                let limit_order_selector =
-                                    selector(limit_order::APP_SELECTOR.to_string());
-                                let event_selector: H256 = proxy_pushed.selector.into();
-                                if event_selector == limit_order_selector {
-                                    let limit_order_solver = LimitOrderSolver::new(
-                                        proxy_pushed.clone(),
-                                        solver_params.clone(),
-                                    );
-                                    if let Ok(limit_order_solver) = limit_order_solver {
-                                        let executor =
-                                            TimerRequestExecutor::<LimitOrderSolver<M>>::new(
-                                                limit_order_solver,
-                                                tick_duration,
-                                                stats_tx,
-                                            );
-                                        executor.execute(proxy_pushed).await;
-                                    } else {
-                                        println!("Error creating solver: Unknown selector");
-                                    }
+                    loop {
+                        tokio::select! {
+                            event = stream_take.next() => match event {
+                                Some(Ok(proxy_pushed)) => {
+                                    self.handle_objective(proxy_pushed, true, false, None)
+                                        .await;
+                                }
+                                Some(Err(err)) => {
+                                    self.connectivity.lock().await.degrade(err.to_string());
+                                    break;
                                 }
-                            });
+                                None => break,
+                            },
+                            Some(proxy_pushed) = self.shadow.next_incoming() => {
+                                println!(
+                                    "Shadow objective {} received",
+                                    proxy_pushed.sequence_number
+                                );
+                                self.handle_objective(proxy_pushed, true, true, None).await;
+                            }
+                            Some(proxy_pushed) = next_objective(&mut self.retry_rx) => {
+                                println!(
+                                    "Retrying objective {} of the proxy {:?}",
+                                    proxy_pushed.sequence_number, proxy_pushed.proxy_address
+                                );
+                                self.handle_objective(proxy_pushed, false, false, None)
+                                    .await;
+                            }
+                            Some(pending) = next_objective(&mut self.pending_rx) => {
+                                self.prewarm(pending);
+                            }
+                            Some((key, objective, solver)) = self.warmed_rx.recv() => {
+                                if self.warming.remove(&key) {
+                                    self.prewarmed
+                                        .insert(key, (Instant::now(), objective, solver));
+                                }
+                            }
                         }
                     }
                 }
                 Err(err) => {
-                    fatal!("Error reading events from stream: {}", err);
+                    attempts += 1;
+                    let mut connectivity = self.connectivity.lock().await;
+                    if attempts > self.max_resubscribe_attempts {
+                        connectivity.transition(ConnectionState::Down, Some(err.to_string()));
+                        fatal!("Error reading events from stream: {}", err);
+                    }
+                    connectivity.transition(ConnectionState::Reconnecting, Some(err.to_string()));
+                    drop(connectivity);
+                    let backoff = RESUBSCRIBE_BACKOFF
+                        .saturating_mul(2u32.saturating_pow(attempts - 1))
+                        .min(MAX_RESUBSCRIBE_BACKOFF);
+                    println!(
+                        "Error reading events from stream, attempt {} of {}, retrying in {:?}: {}",
+                        attempts, self.max_resubscribe_attempts, backoff, err
+                    );
+                    sleep(backoff).await;
+                }
+            }
+        }
+    }
+
+    // Builds the solver of a pending objective and reads ahead what its first step needs.
+    // The solver is kept until the executor of the objective builds its own, so that the
+    // price feed keeps tracking the pool in between. The reads are spawned so that the
+    // events aren't held behind them.
+    fn prewarm(&mut self, objective: ProxyPushedFilter) {
+        self.prewarmed
+            .retain(|_, (warmed_at, _, _)| warmed_at.elapsed() < PREWARM_TTL);
+        let app_selector: H256 = objective.selector.into();
+        let key = (
+            app_selector,
+            objective.proxy_address,
+            objective.sequence_number,
+        );
+        if app_selector != selector(limit_order::APP_SELECTOR.to_string())
+            || self.prewarmed.contains_key(&key)
+            || self.warming.contains(&key)
+            || !self.sharding.contains(&objective)
+        {
+            return;
+        }
+        let solver_params = match self.solvers_params.get(&app_selector) {
+            Some(solver_params) => solver_params.clone(),
+            None => return,
+        };
+        let solver = match LimitOrderSolver::new(objective.clone(), solver_params) {
+            Ok(solver) => solver,
+            Err(err) => {
+                println!(
+                    "Pending objective {} of the proxy {:?} can't be solved: {}",
+                    objective.sequence_number, objective.proxy_address, err
+                );
+                return;
+            }
+        };
+        self.warming.insert(key);
+        let warmed_tx = self.warmed_tx.clone();
+        tokio::spawn(async move {
+            match solver.prewarm().await {
+                Ok(price) => println!(
+                    "Warmed up the solver of the pending objective {} of the proxy {:?}, price {}",
+                    objective.sequence_number,
+                    objective.proxy_address,
+                    order_price::format(price)
+                ),
+                Err(err) => println!(
+                    "Error warming up the solver of the pending objective {} of the proxy {:?}: {}",
+                    objective.sequence_number, objective.proxy_address, err
+                ),
+            }
+            let _ = warmed_tx.send((key, objective, solver));
+        });
+    }
+
+    // Objectives retried from the dead letters skip the duplicate check. The objectives
+    // mirrored by another solver are only simulated, they are neither stored to be
+    // resumed nor kept to be retried. Resumed objectives are given the time they were
+    // received at since Unix epoch. Only the new objectives received from the chain are
+    // mirrored, once they passed the duplicate check.
+    async fn handle_objective(
+        &mut self,
+        proxy_pushed: ProxyPushedFilter,
+        check_duplicate: bool,
+        mirrored: bool,
+        created: Option<Duration>,
+    ) {
+        if let Some(solver_params) = self.solvers_params.get(&proxy_pushed.selector.into()) {
+            let app_selector: H256 = proxy_pushed.selector.into();
+            #[cfg(feature = "plugins")]
+            let app = match self.plugins.get(&app_selector) {
+                Some(plugin) => plugin.app.clone(),
+                None => limit_order::APP_SELECTOR.to_string(),
+            };
+            #[cfg(not(feature = "plugins"))]
+            let app = limit_order::APP_SELECTOR.to_string();
+            // The solver gets the raw parameters, logs and stats the redacted ones.
+            let redacted = self.redactor.redact(app.as_str(), &proxy_pushed);
+            let key = (
+                app_selector,
+                proxy_pushed.proxy_address,
+                proxy_pushed.sequence_number,
+            );
+            if check_duplicate && self.dedup.is_duplicate(key) {
+                println!(
+                    "Skipping duplicate objective {} of the proxy {:?}",
+                    proxy_pushed.sequence_number, proxy_pushed.proxy_address
+                );
+                self.stats_tx
+                    .send(TimerExecutorStats::duplicate(
+                        proxy_pushed.sequence_number,
+                        app,
+                        redacted.data_values,
+                    ))
+                    .await;
+                return;
+            }
+            if check_duplicate && !mirrored && created.is_none() {
+                self.shadow.mirror(&redacted);
+            }
+            if !self.sharding.check(app.as_str(), &proxy_pushed) {
+                println!(
+                    "Skipping objective {} of the proxy {:?}, left to the instances of its shard",
+                    proxy_pushed.sequence_number, proxy_pushed.proxy_address
+                );
+                return;
+            }
+            // Objectives whose sender couldn't be read are kept to be retried, unless
+            // mirrored.
+            if let Err((rejection, message)) = self
+                .sender_filter
+                .check(self.middleware.clone(), app.as_str(), &proxy_pushed)
+                .await
+            {
+                println!(
+                    "Skipping objective {} of the proxy {:?}: {}",
+                    proxy_pushed.sequence_number, proxy_pushed.proxy_address, message
+                );
+                if rejection == Rejection::UnknownSender && !mirrored {
+                    self.dead_letters
+                        .lock()
+                        .await
+                        .add(app, proxy_pushed, Status::Failed, message);
                 }
+                return;
+            }
+            #[cfg(feature = "audit-store")]
+            self.redactor.audit(app.as_str(), &proxy_pushed);
+            self.warming.remove(&key);
+            let prewarmed = self
+                .prewarmed
+                .remove(&key)
+                .filter(|(_, objective, _)| *objective == proxy_pushed);
+            if prewarmed.is_some() {
+                println!(
+                    "Objective {} of the proxy {:?} was warmed up from its pending push",
+                    proxy_pushed.sequence_number, proxy_pushed.proxy_address
+                );
             }
+            println!(
+                "Event received: {}",
+                display::objective(&redacted, Some(app.as_str()))
+            );
+            let mut solver_params = solver_params.clone();
+            if mirrored {
+                solver_params.submission_policy =
+                    Arc::new(solver_params.submission_policy.simulated());
+            }
+            let tick_duration = solver_params.tick;
+            let app_queue = solver_params.app_queue.clone();
+            let stats_tx = self.stats_tx.clone();
+            let queue = self.queue.clone();
+            #[cfg(feature = "plugins")]
+            let plugin = self.plugins.get(&app_selector).cloned();
+            let dead_letters = self.dead_letters.clone();
+            let dry_run = solver_params.submission_policy.dry_run();
+            let block_ticks = solver_params.block_ticks.clone();
+            let execution_log = solver_params.execution_log.clone();
+            let state_store = match mirrored {
+                true => None,
+                false => self.state_store.clone(),
+            };
+            let switch = self.switch.clone();
+            let circuit_breaker = self.circuit_breaker.clone();
+            if let Some(state_store) = &state_store {
+                state_store.insert(
+                    proxy_pushed.clone(),
+                    created.unwrap_or_else(|| {
+                        SystemTime::now()
+                            .duration_since(SystemTime::UNIX_EPOCH)
+                            .unwrap_or_default()
+                    }),
+                );
+            }
+            let executor_id = Uuid::new_v4();
+            self.executor_index
+                .record(app.as_str(), &proxy_pushed, executor_id);
+            let context = format!(
+                "objective {} of the proxy {:?}",
+                proxy_pushed.sequence_number, proxy_pushed.proxy_address
+            );
+            self.supervisor
+                .spawn("executor", Some(context), async move {
+                    // Objectives received while paused wait, they aren't dropped.
+                    switch.wait_resumed().await;
+                    // The limit of the app is waited for first, without holding a slot
+                    // of the global one.
+                    let _app_permit = match &app_queue {
+                        Some(app_queue) => Some(
+                            app_queue
+                                .acquire(
+                                    priority(&proxy_pushed),
+                                    app.clone(),
+                                    proxy_pushed.sequence_number,
+                                )
+                                .await,
+                        ),
+                        None => None,
+                    };
+                    // Objectives of higher value are executed first.
+                    let _permit = queue
+                        .acquire(
+                            priority(&proxy_pushed),
+                            app.clone(),
+                            proxy_pushed.sequence_number,
+                        )
+                        .await;
+                    // The wallet is held until the executor finishes.
+                    let (solver_params, _wallet) = solver_params.assign_wallet();
+                    let limit_order_selector = selector(limit_order::APP_SELECTOR.to_string());
+                    let event_selector: H256 = proxy_pushed.selector.into();
+                    let res = if event_selector == limit_order_selector {
+                        Some(
+                            match LimitOrderSolver::new(proxy_pushed.clone(), solver_params.clone())
+                            {
+                                Ok(limit_order_solver) => {
+                                    drop(prewarmed);
+                                    let executor =
+                                        TimerRequestExecutor::<LimitOrderSolver<M>>::new(
+                                            limit_order_solver,
+                                            tick_duration,
+                                            stats_tx.clone(),
+                                            dry_run,
+                                            block_ticks.clone(),
+                                        )
+                                        .with_state_store(state_store.clone())
+                                        .with_switch(switch.clone())
+                                        .with_circuit_breaker(circuit_breaker.clone())
+                                        .with_execution_log(execution_log.clone())
+                                        .with_id(executor_id)
+                                        .resumed(created);
+                                    executor.execute(redacted.clone()).await
+                                }
+                                Err(err) => {
+                                    println!("Error creating solver: {}", err);
+                                    (Status::Failed, format!("Error creating solver: {}", err))
+                                }
+                            },
+                        )
+                    } else {
+                        None
+                    };
+                    #[cfg(feature = "plugins")]
+                    let res = match (res, plugin) {
+                        (None, Some(plugin)) => Some(
+                            match PluginSolver::new(plugin, proxy_pushed.clone(), solver_params) {
+                                Ok(plugin_solver) => {
+                                    let executor = TimerRequestExecutor::<PluginSolver<M>>::new(
+                                        plugin_solver,
+                                        tick_duration,
+                                        stats_tx,
+                                        dry_run,
+                                        block_ticks,
+                                    )
+                                    .with_state_store(state_store.clone())
+                                    .with_switch(switch.clone())
+                                    .with_circuit_breaker(circuit_breaker)
+                                    .with_execution_log(execution_log.clone())
+                                    .with_id(executor_id)
+                                    .resumed(created);
+                                    executor.execute(redacted).await
+                                }
+                                Err(err) => {
+                                    println!("Error creating the plugin solver: {}", err);
+                                    (
+                                        Status::Failed,
+                                        format!("Error creating the plugin solver: {}", err),
+                                    )
+                                }
+                            },
+                        ),
+                        (res, _) => res,
+                    };
+                    if let Some(state_store) = &state_store {
+                        state_store.remove(&proxy_pushed);
+                    }
+                    let (status, message) = match res {
+                        Some(res) => res,
+                        None => return,
+                    };
+                    // Failed objectives are kept to be retried, there is nothing left
+                    // to retry of the ones solved by others.
+                    if status != Status::Succeeded
+                        && status != Status::SolvedExternally
+                        && !mirrored
+                    {
+                        dead_letters
+                            .lock()
+                            .await
+                            .add(app, proxy_pushed, status, message);
+                    }
+                })
+                .await;
         }
     }
 }
+
+// The next objective of the channel, never if there is none, e.g. the mempool isn't
+// watched.
+async fn next_objective(rx: &mut Option<Receiver<ProxyPushedFilter>>) -> Option<ProxyPushedFilter> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => pending().await,
+    }
+}
+
+// The tip and the max fee per gas of the objective, zero if not given.
+fn priority(event: &ProxyPushedFilter) -> Priority {
+    let value = |name: &str| {
+        event
+            .data_values
+            .iter()
+            .find(|ad| ad.name == name)
+            .and_then(|ad| U256::from_dec_str(ad.value.as_str()).ok())
+            .unwrap_or_default()
+    };
+    Priority {
+        tip: value("tip"),
+        max_fee_per_gas: value("max_fee_per_gas"),
+    }
+}
@@ -1,36 +1,239 @@
 use axum::{
-    routing::{get, Router},
+    routing::{get, post, Router},
     serve,
 };
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 use ethers::{
-    core::types::Address,
+    core::types::{Address, H256, U256},
     middleware::MiddlewareBuilder,
-    providers::{Provider, Ws},
+    providers::{Middleware, Provider, Ws},
     signers::{LocalWallet, Signer},
 };
 use fatal::fatal;
 use solver::{selector, SolverParams};
-use solvers::limit_order;
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use solvers::{
+    limit_order::{self, PairPool},
+    uniswap_v3,
+};
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc, time::Duration};
 use tokio::{
     net::TcpListener,
-    sync::{
-        mpsc::{self, Receiver, Sender},
-        Mutex,
-    },
-    task::JoinSet,
+    sync::{mpsc, watch, Mutex},
+    time::timeout,
 };
 
-use crate::laminator_listener::LaminatorListener;
-use crate::stats::{get_stats_json, run_stats_receive, TimerExecutorStats};
+use crate::accounting::{get_accounting_json, Accounting};
+use crate::adaptive_tick::AdaptiveTick;
+use crate::admin::{get_status, pause, resume, AdminState, SolvingSwitch};
+#[cfg(feature = "aggregator")]
+use crate::aggregator::{Aggregator, AggregatorKind};
+use crate::app_config::AppConfig;
+#[cfg(feature = "webhooks")]
+use crate::autoscaling::run_autoscaler_push;
+use crate::autoscaling::{get_autoscaling_json, AutoscalingState};
+use crate::block_ticker::run_block_ticker;
+use crate::bundle_composer::BundleComposer;
+use crate::circuit_breaker::{reset_circuit, CircuitBreaker, CircuitBreakerState};
+use crate::config_check::{get_readiness, DeployedConfig};
+use crate::connectivity::{get_healthz, get_metrics, ConnectionState, Connectivity, HealthState};
+use crate::dead_letter::{retry_all, DeadLetterState, DeadLetters};
+use crate::decisions::{get_decisions_json, DecisionHistories};
+#[cfg(feature = "webhooks")]
+use crate::digest::{DigestPeriod, DigestReporter};
+use crate::execution_log::ExecutionLog;
+use crate::executor_index::{get_executors_json, ExecutorIndex, ExecutorIndexState};
+use crate::executor_queue::{get_queue_json, ExecutorQueue};
+use crate::executor_state::ExecutorStateStore;
+use crate::flash_loan::FlashLoanMarket;
+use crate::laminator_listener::{LaminatorListener, MAX_RESUBSCRIBE_ATTEMPTS};
+use crate::mempool::run_mempool_watcher;
+#[cfg(feature = "webhooks")]
+use crate::notifications::{run_notifications, NotificationWebhook};
+#[cfg(feature = "plugins")]
+use crate::plugins::SolverPlugin;
+use crate::price_feed::PriceFeed;
+#[cfg(feature = "audit-store")]
+use crate::redaction::AuditStore;
+use crate::redaction::{RedactionRule, Redactor};
+use crate::replay::ReplayConfig;
+use crate::rpc_limiter::{get_rpc_stats_json, RateLimitedClient, RpcStats};
+use crate::scheduler::{get_tasks_json, Scheduler, SchedulerState, TaskSchedule};
+use crate::sender_filter::{SenderFilter, SenderList};
+#[cfg(feature = "webhooks")]
+use crate::shadow::run_shadow_send;
+use crate::shadow::{receive_shadow_objective, Shadow, ShadowState};
+use crate::sharding::{assign_shard, get_shard_json, ShardRange, Sharding, ShardingState};
+use crate::stats::{get_stats_json, run_stats_receive, stats_router};
+use crate::stats_channel::{stats_channel, OutcomeBus, OverflowPolicy};
+#[cfg(feature = "stats-export")]
+use crate::stats_export::{ExportCredentials, ExportTarget, StatsExporter};
+use crate::stats_retention::{run_stats_gc, StatsRetention};
+use crate::submission::{get_submission_stats_json, Strategy, SubmissionPolicy, SubmissionRule};
+#[cfg(feature = "relay")]
+use crate::submission::{RelayKeys, SubmissionStrategy};
+use crate::supervisor::{get_task_counts_json, Supervisor, TaskCounts};
+use crate::timeline::{get_timeline_json, Timelines};
+use crate::token_metadata::TokenMetadataCache;
+use crate::wallet_monitor::{WalletBalances, WalletMonitor, WatchedToken};
+use crate::wallet_pool::{
+    get_wallet_pool_json, rotate_wallet, WalletAssignment, WalletPool, WalletRotation,
+};
 
+mod accounting;
+mod adaptive_tick;
+mod admin;
+#[cfg(feature = "aggregator")]
+mod aggregator;
+mod app_config;
+mod autoscaling;
+mod block_ticker;
+mod bundle_composer;
+mod call_plan;
+mod chain_client;
+mod circuit_breaker;
+mod config_check;
+mod connectivity;
 mod contracts_abi;
+mod dead_letter;
+mod decisions;
+mod dedup;
+#[cfg(feature = "webhooks")]
+mod digest;
+mod display;
+mod encoded_data;
+mod execution_log;
+mod executor_index;
+mod executor_queue;
+mod executor_state;
+mod flash_loan;
+#[cfg(any(
+    feature = "aggregator",
+    feature = "relay",
+    feature = "stats-export",
+    feature = "top",
+    feature = "webhooks"
+))]
+mod http_client;
+mod init_wizard;
+mod inspect;
 mod laminator_listener;
+mod mempool;
+#[cfg(feature = "webhooks")]
+mod notifications;
+mod param_schema;
+#[cfg(feature = "plugins")]
+mod plugins;
+mod price_feed;
+mod profitability;
+mod redaction;
+mod replay;
+mod rpc_limiter;
+mod scheduler;
+mod sender_filter;
+mod shadow;
+mod sharding;
 mod solver;
 mod solvers;
 mod stats;
+mod stats_channel;
+#[cfg(feature = "stats-export")]
+mod stats_export;
+mod stats_retention;
+#[cfg(feature = "top")]
+mod status_view;
+mod submission;
+mod supervisor;
+mod timeline;
 mod timer_executor;
+mod token_metadata;
+mod wallet_monitor;
+mod wallet_pool;
+
+// How long the chain health check waits for the latest block.
+const CHAIN_HEALTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Prints the help if neither a command nor the args of the solver are given.
+#[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, arg_required_else_help = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub args: Option<Args>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Interactively create the solver config file.
+    Init {
+        #[arg(long, default_value = "solver.env")]
+        output: String,
+    },
+
+    /// Decode and print the objectives pushed in a transaction or an event log.
+    InspectObjective {
+        /// The transaction pushing the objectives, read from the chain at the URL.
+        #[arg(long, required_unless_present = "log", requires = "ws_chain_url")]
+        tx_hash: Option<H256>,
+
+        #[arg(long)]
+        ws_chain_url: Option<String>,
+
+        /// A log or a list of logs as JSON, as returned by eth_getLogs.
+        #[arg(long, conflicts_with = "tx_hash")]
+        log: Option<String>,
+    },
+
+    /// Replay the objectives pushed in past blocks against the archive state, and report
+    /// what the solvers would have come to. Nothing is sent.
+    Replay {
+        #[arg(long)]
+        ws_chain_url: String,
+
+        #[arg(long)]
+        chain_id: u64,
+
+        #[arg(long)]
+        laminator_address: Address,
+
+        #[arg(long)]
+        call_breaker_address: Address,
+
+        #[arg(long)]
+        flash_loan_address: Option<Address>,
+
+        #[arg(long)]
+        swap_pool_address: Option<Address>,
+
+        #[arg(long)]
+        pair_pool: Vec<PairPool>,
+
+        /// The solver the final transactions are simulated from.
+        #[arg(long, default_value_t = Address::zero())]
+        solver_address: Address,
+
+        #[arg(long)]
+        from_block: u64,
+
+        #[arg(long)]
+        to_block: u64,
+
+        /// The objectives are stepped every this many blocks from their push.
+        #[arg(long, default_value_t = 1)]
+        step_blocks: u64,
+    },
+
+    /// Show live executors of a running solver in the terminal.
+    #[cfg(feature = "top")]
+    Top {
+        #[arg(long, default_value = "http://localhost:3030")]
+        url: String,
+
+        #[arg(long, default_value_t = 2)]
+        refresh_secs: u64,
+    },
+}
 
 #[derive(Parser, Debug)]
 pub struct Args {
@@ -52,36 +255,462 @@ pub struct Args {
     #[arg(long)]
     pub flash_loan_address: Address,
 
+    /// The default swap pool, used for pairs without a pool of their own.
+    #[arg(long)]
+    pub swap_pool_address: Option<Address>,
+
+    /// Swap pools for specific pairs, as TOKEN_A:TOKEN_B:POOL, can be repeated.
+    #[arg(long)]
+    pub pair_pool: Vec<PairPool>,
+
+    /// Contracts of the uniswap_v3 strategy of the limit orders, the pools are derived
+    /// from the factory.
+    #[arg(long)]
+    pub uniswap_v3_factory: Option<Address>,
+
+    #[arg(long)]
+    pub uniswap_v3_quoter: Option<Address>,
+
+    #[arg(long)]
+    pub uniswap_v3_router: Option<Address>,
+
+    /// Flash loan providers of specific pairs, as TOKEN_A:TOKEN_B:PROVIDER:ADDRESS with
+    /// the provider mock or aave_v3, can be repeated. The other pairs borrow from the
+    /// mock of --flash-loan-address.
+    #[arg(long)]
+    pub flash_loan_market: Vec<FlashLoanMarket>,
+
+    /// Aggregator API the aggregator strategy of the limit orders routes the swaps
+    /// through, 0x or 1inch.
+    #[cfg(feature = "aggregator")]
+    #[arg(long)]
+    pub aggregator: Option<AggregatorKind>,
+
+    /// Base URL of the aggregator API, its public API if not set.
+    #[cfg(feature = "aggregator")]
+    #[arg(long)]
+    pub aggregator_url: Option<String>,
+
+    #[cfg(feature = "aggregator")]
     #[arg(long)]
-    pub swap_pool_address: Address,
+    pub aggregator_api_key: Option<String>,
+
+    /// The router the quoted swaps must call, the quotes calling another contract are
+    /// rejected.
+    #[cfg(feature = "aggregator")]
+    #[arg(long)]
+    pub aggregator_router: Option<Address>,
 
     #[arg(long)]
     pub limit_order_wallet_private_key: LocalWallet,
 
+    /// More solver wallets the executors are spread over along with the primary one above,
+    /// each with a nonce sequence of its own, can be repeated.
+    #[arg(long)]
+    pub pool_wallet_private_key: Vec<LocalWallet>,
+
+    /// How the executors are given a wallet: round-robin or least-busy.
+    #[arg(long, default_value = "round-robin")]
+    pub wallet_assignment: WalletAssignment,
+
     #[arg(long, default_value_t = 1)]
     pub tick_secs: u64,
 
     #[arg(long, default_value_t = 0)]
     pub tick_nanos: u32,
+
+    /// Apps whose objectives are stepped once per new block instead of once per tick,
+    /// can be repeated.
+    #[arg(long)]
+    pub block_tick_app: Vec<String>,
+
+    /// Requests per second to the chain node, shared by all the executors. Concurrent
+    /// identical reads are sent once either way. Unlimited if not set.
+    #[arg(long)]
+    pub max_rpc_requests_per_sec: Option<u32>,
+
+    /// Price triggered objectives are checked every tick if not set. If set, they are
+    /// checked up to this often when the price is far from the trigger, and down to
+    /// adaptive-tick-min-millis next to it.
+    #[arg(long)]
+    pub adaptive_tick_max_secs: Option<u64>,
+
+    #[arg(long, default_value_t = 250)]
+    pub adaptive_tick_min_millis: u64,
+
+    /// Limit orders ready for their final transaction wait this long for an order of the
+    /// opposite direction on the same pair, to be executed together in one transaction.
+    /// Each order is executed alone if not set.
+    #[arg(long)]
+    pub bundle_window_millis: Option<u64>,
+
+    /// Reads the pool prices once per block for all the executors and wakes them up as
+    /// soon as the price crosses their trigger. Each executor reads the price every tick
+    /// if not set.
+    #[arg(long)]
+    pub price_feed: bool,
+
+    /// Watches the pending pushes to the laminator and warms up the limit order solvers of
+    /// their objectives before the events, with --price-feed the pool prices are read
+    /// ahead. Needs a node sharing its mempool.
+    #[arg(long)]
+    pub mempool_prewarm: bool,
+
+    /// Maximum number of concurrently running executors, the other objectives wait in
+    /// the queue. Unlimited if not set.
+    #[arg(long)]
+    pub max_concurrent_executors: Option<NonZeroUsize>,
+
+    /// Queued objectives waiting longer than this run before the ones of higher value,
+    /// the oldest first. Never if not set.
+    #[arg(long)]
+    pub max_queue_wait_secs: Option<u64>,
+
+    /// Executor settings of an app, overriding the global ones, as
+    /// APP:KEY=VALUE[,KEY=VALUE...] with the keys tick, time_limit,
+    /// max_concurrent_executors and gas_budget, can be repeated. The time limit is used for
+    /// the objectives not giving one.
+    #[arg(long)]
+    pub app_config: Vec<AppConfig>,
+
+    /// Wei the failed final transactions of an objective may spend before it's given up
+    /// on, the objectives may lower it with their gas_budget. Unlimited if not set.
+    #[arg(long)]
+    pub gas_budget_wei: Option<u128>,
+
+    /// Blocks a successful final transaction has to be buried under before the executor
+    /// succeeds. A transaction dropped by a reorg meanwhile is sent again.
+    #[arg(long, default_value_t = 0)]
+    pub confirmations: u64,
+
+    /// Webhook of an external autoscaler, the load signals are pushed to it periodically.
+    #[cfg(feature = "webhooks")]
+    #[arg(long)]
+    pub autoscaler_webhook_url: Option<String>,
+
+    #[cfg(feature = "webhooks")]
+    #[arg(long, default_value_t = 15)]
+    pub autoscaler_push_secs: u64,
+
+    /// Webhook the daily and weekly execution digests are posted to, e.g. of the
+    /// alerting or an email gateway. Scheduled as the daily_digest and weekly_digest
+    /// tasks.
+    #[cfg(feature = "webhooks")]
+    #[arg(long)]
+    pub digest_webhook_url: Option<String>,
+
+    /// How long received objectives are remembered to skip re-delivered events.
+    #[arg(long, default_value_t = 3600)]
+    pub dedup_ttl_secs: u64,
+
+    /// Final transactions of an app failing in a row before the next ones are held for
+    /// the cool-down, never held if not set.
+    #[arg(long)]
+    pub circuit_breaker_failures: Option<u32>,
+
+    /// How long the final transactions are held once the circuit opens.
+    #[arg(long, default_value_t = 300)]
+    pub circuit_breaker_cool_down_secs: u64,
+
+    /// Shards of the objectives this instance executes when several instances share the
+    /// events, as FIRST[-LAST]/COUNT, e.g. 0-1/4. All the objectives if not set, can be
+    /// reassigned at /admin/shard.
+    #[arg(long)]
+    pub shard: Option<ShardRange>,
+
+    /// Capacity of the channel of the executor stats.
+    #[arg(long, default_value_t = 100)]
+    pub stats_channel_capacity: usize,
+
+    /// What is done with the stats sent while the channel is full: block waits for room,
+    /// coalesce keeps the latest stats of each executor aside until the channel is drained.
+    #[arg(long, default_value = "coalesce")]
+    pub stats_overflow_policy: OverflowPolicy,
+
+    /// Failed subscriptions to the laminator events in a row before the solver exits,
+    /// retried with a growing delay.
+    #[arg(long, default_value_t = MAX_RESUBSCRIBE_ATTEMPTS)]
+    pub max_resubscribe_attempts: u32,
+
+    /// Seconds between the checks of the wallet balances.
+    #[arg(long, default_value_t = 60)]
+    pub balance_check_secs: u64,
+
+    /// Native balance in wei below which the wallet is about to run out of gas, the
+    /// balance is only reported if not set.
+    #[arg(long)]
+    pub min_native_balance_wei: Option<u128>,
+
+    /// ERC20 balances of the wallet to watch, as TOKEN or TOKEN:MIN_WEI, can be repeated.
+    #[arg(long)]
+    pub watch_token: Vec<WatchedToken>,
+
+    /// Webhooks notified of the executor outcomes, as [OUTCOME,...=]FORMAT:URL with the
+    /// outcomes success, failure, timeout and revert, and the format json or slack. Can be
+    /// repeated.
+    #[cfg(feature = "webhooks")]
+    #[arg(long)]
+    pub notification_webhook: Vec<NotificationWebhook>,
+
+    /// Webhook the balances dropping below their minimum are posted to.
+    #[cfg(feature = "webhooks")]
+    #[arg(long)]
+    pub balance_alert_webhook_url: Option<String>,
+
+    /// Stats of finished executors are evicted above this number of entries or
+    /// after this age, unlimited if not set.
+    #[arg(long)]
+    pub stats_max_entries: Option<usize>,
+
+    #[arg(long)]
+    pub stats_max_age_secs: Option<u64>,
+
+    /// File the evicted executor stats are appended to, as JSON lines.
+    #[arg(long)]
+    pub stats_archive: Option<String>,
+
+    /// Bucket the stats of the finished executors are exported to, as gzipped JSON lines,
+    /// s3://BUCKET/PREFIX or gs://BUCKET/PREFIX. GCS takes HMAC keys and the auto region.
+    #[cfg(feature = "stats-export")]
+    #[arg(long)]
+    pub stats_export_url: Option<ExportTarget>,
+
+    #[cfg(feature = "stats-export")]
+    #[arg(long, default_value = "")]
+    pub stats_export_access_key_id: String,
+
+    #[cfg(feature = "stats-export")]
+    #[arg(long, default_value = "")]
+    pub stats_export_secret_access_key: String,
+
+    #[cfg(feature = "stats-export")]
+    #[arg(long, default_value = "us-east-1")]
+    pub stats_export_region: String,
+
+    /// A batch is exported once it has this number of stats or after the interval.
+    #[cfg(feature = "stats-export")]
+    #[arg(long, default_value_t = 500)]
+    pub stats_export_batch_size: usize,
+
+    #[cfg(feature = "stats-export")]
+    #[arg(long, default_value_t = 300)]
+    pub stats_export_interval_secs: u64,
+
+    /// Directory the batches are kept in until they are exported, e.g. while the bucket
+    /// can't be reached.
+    #[cfg(feature = "stats-export")]
+    #[arg(long, default_value = "stats_export_spool")]
+    pub stats_export_spool_dir: String,
+
+    /// File the running executors are kept in, they are resumed from it on the next
+    /// start. Holds the raw objective parameters, the redacted ones included.
+    #[arg(long)]
+    pub executor_state: Option<String>,
+
+    /// Bearer token of the /admin/pause, /admin/resume, /admin/status,
+    /// /admin/wallets/rotate, /admin/shard, /admin/tasks, /admin/circuit/reset,
+    /// /deadletter/retry-all and /shadow/objective endpoints, which are not served if not
+    /// set.
+    #[arg(long)]
+    pub admin_token: Option<String>,
+
+    /// URL of a secondary solver to mirror received objectives to, with their params
+    /// redacted.
+    #[cfg(feature = "webhooks")]
+    #[arg(long, requires = "shadow_token")]
+    pub shadow_url: Option<String>,
+
+    /// Admin token of the secondary solver, the mirrored objectives are sent with it.
+    #[cfg(feature = "webhooks")]
+    #[arg(long, requires = "shadow_url")]
+    pub shadow_token: Option<String>,
+
+    /// Accept objectives mirrored by another solver at /shadow/objective, authenticated
+    /// with the admin token. Their final transactions are only simulated.
+    #[arg(long, default_value_t = false, requires = "admin_token")]
+    pub accept_shadow_traffic: bool,
+
+    /// Schedule overrides of maintenance tasks, as NAME=CRON, can be repeated.
+    #[arg(long)]
+    pub task_schedule: Vec<TaskSchedule>,
+
+    /// Maintenance tasks not to run, can be repeated.
+    #[arg(long)]
+    pub disable_task: Vec<String>,
+
+    /// Upper bound of the random delay added to each maintenance task run.
+    #[arg(long, default_value_t = 0)]
+    pub task_max_jitter_secs: u64,
+
+    /// Submission strategies for objectives matching conditions, as CONDITIONS/STRATEGIES,
+    /// can be repeated, the first matching rule is used.
+    #[arg(long)]
+    pub submission_rule: Vec<SubmissionRule>,
+
+    /// Submission strategies for objectives not matching any rule, in fallback order.
+    #[arg(long, value_delimiter = ',', default_value = "public")]
+    pub default_submission_strategies: Vec<Strategy>,
+
+    /// RPC endpoints the redundant strategy broadcasts the transaction to, along with
+    /// the connected node, can be repeated.
+    #[arg(long)]
+    pub broadcast_rpc_url: Vec<String>,
+
+    /// Private relays the redundant strategy sends the transaction to, can be repeated.
+    #[arg(long)]
+    pub private_relay_url: Vec<String>,
+
+    /// Relayer endpoint the relay strategy hands the final transactions to, it signs them
+    /// and pays for the gas. See the Relay strategy for the request it gets.
+    #[cfg(feature = "relay")]
+    #[arg(long)]
+    pub relay_url: Option<String>,
+
+    /// ERC-2771 forwarder the relay sends the final transactions through, the solver
+    /// wallets sign the forward requests for it.
+    #[cfg(feature = "relay")]
+    #[arg(long)]
+    pub relay_forwarder: Option<Address>,
+
+    /// Flashbots style relay the bundle strategy sends the final transactions to with
+    /// eth_sendBundle, keeping them out of the public mempool.
+    #[cfg(feature = "relay")]
+    #[arg(long)]
+    pub bundle_relay_url: Option<String>,
+
+    /// Key the bundle requests are signed with, the relay tells the solver apart by it.
+    /// It holds no funds, a random key is used if not set.
+    #[cfg(feature = "relay")]
+    #[arg(long)]
+    pub bundle_signing_key: Option<LocalWallet>,
+
+    /// Number of the next blocks each bundle is sent for.
+    #[cfg(feature = "relay")]
+    #[arg(long, default_value_t = 3)]
+    pub bundle_target_blocks: u64,
+
+    /// Runs the objectives through the whole pipeline but simulates the final
+    /// transactions instead of sending them.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Shared library implementing a solver app, see plugins.rs for its interface, can
+    /// be repeated.
+    #[cfg(feature = "plugins")]
+    #[arg(long)]
+    pub solver_plugin: Vec<String>,
+
+    /// Objective parameters not to be logged or exposed in the stats, as
+    /// APP=PATTERN[,PATTERN...] with * wildcards, can be repeated.
+    #[arg(long)]
+    pub redact_params: Vec<RedactionRule>,
+
+    /// The only senders whose objectives of the app are solved, as
+    /// APP=ADDRESS[,ADDRESS...], can be repeated. The sender is the owner of the proxy
+    /// the objective was pushed to. All the senders are solved for apps without it.
+    #[arg(long)]
+    pub allow_senders: Vec<SenderList>,
+
+    /// Senders whose objectives of the app are never solved, as APP=ADDRESS[,ADDRESS...],
+    /// can be repeated. Denied senders are skipped even if allowed.
+    #[arg(long)]
+    pub deny_senders: Vec<SenderList>,
+
+    /// File the decisions taken on each objective are appended to as JSON lines, from
+    /// the decoded objective to the receipt of its final transaction. Not kept if not set.
+    #[arg(long)]
+    pub execution_log: Option<String>,
+
+    /// File the raw parameters of the redacted objectives are appended to, encrypted
+    /// with the audit store key. Not kept if not set.
+    #[cfg(feature = "audit-store")]
+    #[arg(long)]
+    pub audit_store: Option<String>,
+
+    /// AES-256 key of the audit store, 32 bytes in hex.
+    #[cfg(feature = "audit-store")]
+    #[arg(long)]
+    pub audit_store_key: Option<H256>,
 }
 
 #[tokio::main]
 async fn main() {
     // Get args
-    let args = Args::parse();
+    let cli = Cli::parse();
+    let args = match cli.command {
+        Some(Command::Init { output }) => {
+            init_wizard::run(output).await;
+            return;
+        }
+        Some(Command::InspectObjective {
+            tx_hash,
+            ws_chain_url,
+            log,
+        }) => {
+            if let Err(err) = inspect::run(ws_chain_url, tx_hash, log).await {
+                fatal!("{}", err);
+            }
+            return;
+        }
+        Some(Command::Replay {
+            ws_chain_url,
+            chain_id,
+            laminator_address,
+            call_breaker_address,
+            flash_loan_address,
+            swap_pool_address,
+            pair_pool,
+            solver_address,
+            from_block,
+            to_block,
+            step_blocks,
+        }) => {
+            let config = ReplayConfig {
+                ws_chain_url,
+                chain_id,
+                laminator_address,
+                call_breaker_address,
+                flash_loan_address,
+                swap_pool_address,
+                pair_pools: pair_pool,
+                solver_address,
+            };
+            if let Err(err) = replay::run(config, from_block, to_block, step_blocks).await {
+                fatal!("{}", err);
+            }
+            return;
+        }
+        #[cfg(feature = "top")]
+        Some(Command::Top { url, refresh_secs }) => {
+            status_view::run(url, Duration::from_secs(refresh_secs)).await;
+            return;
+        }
+        None => cli.args.unwrap_or_else(|| {
+            Cli::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "the solver args are required without a command",
+                )
+                .exit()
+        }),
+    };
     let limit_order_wallet = args
         .limit_order_wallet_private_key
         .with_chain_id(args.chain_id);
     let stats_map = Arc::new(Mutex::new(HashMap::new()));
-    let (stats_tx, mut stats_rx): (Sender<TimerExecutorStats>, Receiver<TimerExecutorStats>) =
-        mpsc::channel(100);
-    let exec_set = Arc::new(Mutex::new(JoinSet::new()));
+    let timelines: Timelines = Arc::new(Mutex::new(HashMap::new()));
+    let decision_histories: DecisionHistories = Arc::new(Mutex::new(HashMap::new()));
+    let accounting = Arc::new(Mutex::new(Accounting::default()));
+    let (stats_tx, stats_rx) =
+        stats_channel(args.stats_channel_capacity, args.stats_overflow_policy);
+    let task_counts = Arc::new(Mutex::new(TaskCounts::default()));
+    let supervisor = Supervisor::new(task_counts.clone());
 
     println!(
         "Connecting to the chain with URL {} ...",
         args.ws_chain_url.as_str()
     );
-    let limit_order_provider = Provider::<Ws>::connect(args.ws_chain_url.as_str()).await;
+    let limit_order_provider = Ws::connect(args.ws_chain_url.as_str()).await;
     if limit_order_provider.is_err() {
         fatal!(
             "Failed connection to the chain: {}",
@@ -91,45 +720,533 @@ async fn main() {
     println!("Connected successfully!");
 
     let limit_order_wallet_address = limit_order_wallet.address();
-    let limit_order_provider = Arc::new(
-        limit_order_provider
-            .ok()
+    // All the RPC calls of the solver go through the shared rate limit.
+    let rpc_stats = Arc::new(Mutex::new(RpcStats::default()));
+    let provider = Provider::new(RateLimitedClient::new(
+        limit_order_provider.ok().unwrap(),
+        args.max_rpc_requests_per_sec,
+        rpc_stats.clone(),
+    ));
+    if let Err(err) = config_check::verify_chain_id(&provider, args.chain_id).await {
+        fatal!("{}", err);
+    }
+    // The relay strategy signs with the keys of the wallets.
+    #[cfg(feature = "relay")]
+    let relay_keys = RelayKeys::default();
+    #[cfg(feature = "relay")]
+    relay_keys
+        .write()
+        .unwrap()
+        .insert(limit_order_wallet_address, limit_order_wallet.clone());
+    let limit_order_provider = Arc::new(provider.clone().with_signer(limit_order_wallet));
+    // The wallets of the pool share the connection and the rate limit.
+    let mut pool_wallets = vec![(limit_order_wallet_address, limit_order_provider.clone())];
+    for key in &args.pool_wallet_private_key {
+        let wallet = key.clone().with_chain_id(args.chain_id);
+        if pool_wallets
+            .iter()
+            .any(|(address, _)| *address == wallet.address())
+        {
+            fatal!("The wallet {:?} is given more than once", wallet.address());
+        }
+        #[cfg(feature = "relay")]
+        relay_keys
+            .write()
             .unwrap()
-            .with_signer(limit_order_wallet),
-    );
+            .insert(wallet.address(), wallet.clone());
+        pool_wallets.push((
+            wallet.address(),
+            Arc::new(provider.clone().with_signer(wallet)),
+        ));
+    }
+    let wallet_pool = Arc::new(WalletPool::new(pool_wallets, args.wallet_assignment));
+    // The keys rotated in sign on the same connection.
+    let pool_signer = {
+        let provider = provider.clone();
+        let chain_id = args.chain_id;
+        #[cfg(feature = "relay")]
+        let relay_keys = relay_keys.clone();
+        Arc::new(move |wallet: LocalWallet| {
+            let wallet = wallet.with_chain_id(chain_id);
+            #[cfg(feature = "relay")]
+            relay_keys
+                .write()
+                .unwrap()
+                .insert(wallet.address(), wallet.clone());
+            provider.clone().with_signer(wallet)
+        })
+    };
+
+    // Readiness fails while the configuration doesn't match the deployed contracts.
+    let config_mismatches = config_check::validate(
+        limit_order_provider.clone(),
+        &DeployedConfig {
+            laminator_address: args.laminator_address,
+            call_breaker_address: args.call_breaker_address,
+            flash_loan_address: args.flash_loan_address,
+            swap_pool_address: args.swap_pool_address,
+            pair_pools: args.pair_pool.clone(),
+        },
+    )
+    .await;
+    for mismatch in &config_mismatches {
+        println!(
+            "Config mismatch in {}: configured {}, deployed {}",
+            mismatch.field, mismatch.configured, mismatch.deployed
+        );
+    }
 
     // Addresses of specific solvers contracts.
     let mut custom_contracts_addresses: HashMap<String, Address> = HashMap::new();
     custom_contracts_addresses.insert("FLASH_LOAN".to_string(), args.flash_loan_address);
-    custom_contracts_addresses.insert("SWAP_POOL".to_string(), args.swap_pool_address);
+    if let Some(swap_pool_address) = args.swap_pool_address {
+        custom_contracts_addresses.insert("SWAP_POOL".to_string(), swap_pool_address);
+    }
+    for pair_pool in &args.pair_pool {
+        custom_contracts_addresses.insert(
+            limit_order::pair_pool_name(pair_pool.token_a, pair_pool.token_b),
+            pair_pool.pool,
+        );
+    }
+    for (name, address) in [
+        (uniswap_v3::FACTORY_NAME, args.uniswap_v3_factory),
+        (uniswap_v3::QUOTER_NAME, args.uniswap_v3_quoter),
+        (uniswap_v3::ROUTER_NAME, args.uniswap_v3_router),
+    ] {
+        if let Some(address) = address {
+            custom_contracts_addresses.insert(name.to_string(), address);
+        }
+    }
 
+    #[cfg(feature = "relay")]
+    {
+        let uses_strategy = |name: &str| {
+            args.submission_rule
+                .iter()
+                .flat_map(|rule| rule.strategies.iter())
+                .chain(args.default_submission_strategies.iter())
+                .any(|strategy| strategy.name() == name)
+        };
+        if uses_strategy("relay") {
+            if args.relay_url.is_none() {
+                fatal!("Missing the parameter relay-url for the relay submission strategy");
+            }
+            if args.relay_forwarder.is_none() {
+                fatal!("Missing the parameter relay-forwarder for the relay submission strategy");
+            }
+        }
+        if args.bundle_relay_url.is_none() && uses_strategy("bundle") {
+            fatal!("Missing the parameter bundle-relay-url for the bundle submission strategy");
+        }
+    }
+    let submission_policy = SubmissionPolicy::new(
+        args.chain_id,
+        args.submission_rule,
+        args.default_submission_strategies,
+        [args.broadcast_rpc_url, args.private_relay_url].concat(),
+        args.dry_run,
+    );
+    #[cfg(feature = "relay")]
+    let submission_policy = submission_policy
+        .with_relay(args.relay_url.clone(), args.relay_forwarder, relay_keys)
+        .with_bundle_relay(
+            args.bundle_relay_url.clone(),
+            args.bundle_signing_key
+                .clone()
+                .unwrap_or_else(|| LocalWallet::new(&mut rand::thread_rng())),
+            args.bundle_target_blocks,
+        );
+    let submission_policy = Arc::new(submission_policy);
+    if args.dry_run {
+        println!("Dry run, the final transactions are simulated and never sent");
+    }
+
+    let price_feed = args
+        .price_feed
+        .then(|| Arc::new(PriceFeed::new(limit_order_provider.clone())));
+
+    // The block ticker runs if any app ticks once per block or the price feed is on.
+    let (block_tick_tx, block_tick_rx) = watch::channel(0);
+    let block_ticks = |app: &str| {
+        args.block_tick_app
+            .iter()
+            .any(|block_tick_app| block_tick_app == app)
+            .then(|| block_tick_rx.clone())
+    };
+
+    let apps = vec![limit_order::APP_SELECTOR.to_string()];
     let mut solver_params = HashMap::new();
+    let params = SolverParams {
+        call_breaker_address: args.call_breaker_address,
+        solver_address: limit_order_wallet_address,
+        middleware: limit_order_provider.clone(),
+        extra_contract_addresses: custom_contracts_addresses.clone(),
+        guard: wallet_pool.primary().guard.clone(),
+        submission_policy: submission_policy.clone(),
+        adaptive_tick: args.adaptive_tick_max_secs.map(|max_secs| AdaptiveTick {
+            min: Duration::from_millis(args.adaptive_tick_min_millis),
+            max: Duration::from_secs(max_secs),
+        }),
+        price_feed: price_feed.clone(),
+        token_metadata: Arc::new(TokenMetadataCache::default()),
+        block_ticks: block_ticks(limit_order::APP_SELECTOR),
+        execution_log: args
+            .execution_log
+            .clone()
+            .map(|path| Arc::new(ExecutionLog::new(path))),
+        wallet_pool: Some(wallet_pool.clone()),
+        tick: Duration::new(args.tick_secs, args.tick_nanos),
+        default_time_limit: None,
+        app_queue: None,
+        gas_budget: args.gas_budget_wei.map(U256::from),
+        confirmations: args.confirmations,
+        #[cfg(feature = "aggregator")]
+        aggregator: args.aggregator.map(|kind| match args.aggregator_router {
+            Some(router) => match Aggregator::new(
+                kind,
+                args.aggregator_url.clone(),
+                args.aggregator_api_key.clone(),
+                args.chain_id,
+                router,
+            ) {
+                Ok(aggregator) => Arc::new(aggregator),
+                Err(err) => fatal!("{}", err),
+            },
+            None => fatal!("Missing the parameter aggregator-router for the aggregator"),
+        }),
+        flash_loan_markets: args.flash_loan_market.clone(),
+        bundle_composer: args
+            .bundle_window_millis
+            .map(|millis| Arc::new(BundleComposer::new(Duration::from_millis(millis)))),
+    };
+    // Apps without a config of their own use the global settings.
+    let max_queue_wait = args.max_queue_wait_secs.map(Duration::from_secs);
+    let app_params = |app: &str, params: SolverParams<_>| match args
+        .app_config
+        .iter()
+        .find(|config| config.app == app)
+    {
+        Some(config) => config.apply(params, max_queue_wait),
+        None => params,
+    };
     solver_params.insert(
         selector(limit_order::APP_SELECTOR.to_string()),
-        SolverParams {
-            call_breaker_address: args.call_breaker_address,
-            solver_address: limit_order_wallet_address,
-            middleware: limit_order_provider.clone(),
-            extra_contract_addresses: custom_contracts_addresses.clone(),
-            guard: Arc::new(Mutex::new(true)),
+        app_params(limit_order::APP_SELECTOR, params.clone()),
+    );
+
+    // Apps of the solver plugins are dispatched along with the built-in ones.
+    #[cfg(feature = "plugins")]
+    let mut apps = apps;
+    #[cfg(feature = "plugins")]
+    let mut plugins = HashMap::new();
+    #[cfg(feature = "plugins")]
+    for path in &args.solver_plugin {
+        let plugin = match SolverPlugin::load(path.as_str()) {
+            Ok(plugin) => plugin,
+            Err(err) => fatal!("Cannot load the solver plugin {}: {}", path, err),
+        };
+        let app_selector = selector(plugin.app.clone());
+        if solver_params.contains_key(&app_selector) {
+            fatal!(
+                "The solver plugin {} declares the app {} which is already served",
+                path,
+                plugin.app
+            );
+        }
+        println!(
+            "Loaded the solver plugin {} for the app {}",
+            path, plugin.app
+        );
+        apps.push(plugin.app.clone());
+        solver_params.insert(
+            app_selector,
+            app_params(
+                plugin.app.as_str(),
+                SolverParams {
+                    block_ticks: block_ticks(plugin.app.as_str()),
+                    ..params.clone()
+                },
+            ),
+        );
+        plugins.insert(app_selector, Arc::new(plugin));
+    }
+
+    for app in &args.block_tick_app {
+        if !apps.contains(app) {
+            fatal!("The app {} ticking once per block is not served", app);
+        }
+    }
+    for config in &args.app_config {
+        if !apps.contains(&config.app) {
+            fatal!("The app {} configured is not served", config.app);
+        }
+    }
+
+    // Traffic shadowing
+    #[cfg(feature = "webhooks")]
+    let (shadow_mirror_tx, shadow_mirror_rx) = match args.shadow_url {
+        Some(_) => {
+            let (tx, rx) = mpsc::channel(100);
+            (Some(tx), Some(rx))
+        }
+        None => (None, None),
+    };
+    #[cfg(not(feature = "webhooks"))]
+    let shadow_mirror_tx = None;
+    let (shadow_incoming_tx, shadow_incoming_rx) = mpsc::channel(100);
+    let shadow = Shadow::new(
+        shadow_mirror_tx,
+        if args.accept_shadow_traffic {
+            Some(shadow_incoming_rx)
+        } else {
+            None
         },
     );
 
+    let sender_filter = Arc::new(SenderFilter::new(
+        args.allow_senders.clone(),
+        args.deny_senders.clone(),
+    ));
+    let redactor = Redactor::new(args.redact_params.clone());
+    #[cfg(feature = "audit-store")]
+    let redactor = match (args.audit_store.clone(), args.audit_store_key) {
+        (Some(path), Some(key)) => redactor.with_audit_store(AuditStore::new(path, key)),
+        (Some(_), None) => fatal!("Missing the parameter audit-store-key"),
+        (None, _) => redactor,
+    };
+    let redactor = Arc::new(redactor);
+
+    let executor_queue = Arc::new(ExecutorQueue::new(
+        args.max_concurrent_executors.map(NonZeroUsize::get),
+        max_queue_wait,
+    ));
+    let autoscaling = AutoscalingState {
+        queue: executor_queue.clone(),
+        stats_map: stats_map.clone(),
+    };
+
+    let connectivity = Arc::new(Mutex::new(Connectivity::new()));
+    let dead_letters = Arc::new(Mutex::new(DeadLetters::default()));
+    let (retry_tx, retry_rx) = mpsc::channel(100);
+    let switch = Arc::new(SolvingSwitch::new());
+    // Each wallet of the pool is checked on its own, with the same minimums.
+    let wallet_balances = wallet_pool
+        .addresses()
+        .into_iter()
+        .map(|_| Arc::new(Mutex::new(WalletBalances::default())))
+        .collect::<Vec<_>>();
+    let wallet_monitors = wallet_pool
+        .addresses()
+        .into_iter()
+        .zip(&wallet_balances)
+        .map(|(wallet, balances)| WalletMonitor {
+            middleware: limit_order_provider.clone(),
+            wallet,
+            min_native_balance: args.min_native_balance_wei.map(U256::from),
+            tokens: args.watch_token.clone(),
+            interval: Duration::from_secs(args.balance_check_secs),
+            balances: balances.clone(),
+            #[cfg(feature = "webhooks")]
+            alert_url: args.balance_alert_webhook_url.clone(),
+        })
+        .collect::<Vec<_>>();
+    let state_store =
+        args.executor_state
+            .clone()
+            .map(|path| match ExecutorStateStore::load(path.clone()) {
+                Ok(state_store) => Arc::new(state_store),
+                Err(err) => fatal!("Cannot load the executor state from {}: {}", path, err),
+            });
+
+    // Objectives of the pending pushes, if the mempool is watched.
+    let (pending_tx, pending_rx) = mpsc::channel(100);
     let mut listener = LaminatorListener::new(
         args.laminator_address,
         limit_order_provider.clone(),
         solver_params,
-        exec_set.clone(),
-        Duration::new(args.tick_secs, args.tick_nanos),
+        supervisor.clone(),
         stats_tx.clone(),
-    );
+        executor_queue.clone(),
+        Duration::from_secs(args.dedup_ttl_secs),
+    )
+    .with_shadow(shadow)
+    .with_redactor(redactor)
+    .with_connectivity(connectivity.clone(), args.max_resubscribe_attempts)
+    .with_dead_letters(dead_letters.clone(), retry_rx)
+    .with_state_store(state_store)
+    .with_switch(switch.clone())
+    .with_sender_filter(sender_filter.clone());
+    #[cfg(feature = "plugins")]
+    {
+        listener = listener.with_plugins(plugins);
+    }
+    if args.mempool_prewarm {
+        listener = listener.with_pending(pending_rx);
+    }
+    let executor_index = Arc::new(ExecutorIndex::default());
+    let circuit_breaker = args.circuit_breaker_failures.map(|max_failures| {
+        Arc::new(CircuitBreaker::new(
+            max_failures,
+            Duration::from_secs(args.circuit_breaker_cool_down_secs),
+        ))
+    });
+    let sharding = Arc::new(Sharding::new(args.shard));
+    listener = listener
+        .with_executor_index(executor_index.clone())
+        .with_circuit_breaker(circuit_breaker.clone())
+        .with_sharding(sharding.clone());
     let stats_map_copy = Arc::clone(&stats_map);
 
+    // Periodic maintenance tasks.
+    let mut scheduler = Scheduler::new(
+        args.task_schedule,
+        args.disable_task,
+        Duration::from_secs(args.task_max_jitter_secs),
+    );
+    {
+        // Degrades the chain connection while the node doesn't respond.
+        let middleware = limit_order_provider.clone();
+        let connectivity = connectivity.clone();
+        scheduler.add("chain_health", "0 * * * * *", move || {
+            let middleware = middleware.clone();
+            let connectivity = connectivity.clone();
+            async move {
+                let res = match timeout(CHAIN_HEALTH_TIMEOUT, middleware.get_block_number()).await {
+                    Ok(Ok(block_number)) => Ok(format!("Latest block {}", block_number)),
+                    Ok(Err(err)) => Err(err.to_string()),
+                    Err(_) => Err(format!("No block number within {:?}", CHAIN_HEALTH_TIMEOUT)),
+                };
+                let mut connectivity = connectivity.lock().await;
+                match &res {
+                    Ok(_) => connectivity.recover(ConnectionState::Degraded),
+                    Err(err) => connectivity.degrade(err.clone()),
+                }
+                res
+            }
+        });
+    }
+
+    #[cfg(feature = "webhooks")]
+    if let Some(url) = args.digest_webhook_url.clone() {
+        let reporter = Arc::new(DigestReporter {
+            url,
+            stats_map: stats_map.clone(),
+            archive_path: args.stats_archive.clone(),
+            submission_stats: submission_policy.stats(),
+            gas_spent_at: Mutex::new(HashMap::new()),
+        });
+        for (name, schedule, period) in [
+            ("daily_digest", "0 0 8 * * *", DigestPeriod::Daily),
+            ("weekly_digest", "0 0 8 * * Mon", DigestPeriod::Weekly),
+        ] {
+            let reporter = reporter.clone();
+            scheduler.add(name, schedule, move || {
+                let reporter = reporter.clone();
+                async move { reporter.send(period).await }
+            });
+        }
+    }
+
     // Axum setup
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/", get(|| async { "Smart Transactions Solver" }))
+        .route("/ready", get(get_readiness))
+        .with_state(Arc::new(config_mismatches))
+        .route("/healthz", get(get_healthz))
+        .route("/metrics", get(get_metrics))
+        .with_state(HealthState {
+            connectivity: connectivity.clone(),
+            wallets: wallet_balances.clone(),
+            sender_filter,
+            stats: stats_tx.clone(),
+            tasks: task_counts.clone(),
+            circuit_breaker: circuit_breaker.clone(),
+            sharding: sharding.clone(),
+        })
+        .route("/stats/wallets", get(get_wallet_pool_json))
+        .with_state(wallet_pool.clone())
+        // Kept for the existing dashboards, the same as /stats/flashliquidity_limitorder.
         .route("/stats/limit_order", get(get_stats_json))
-        .with_state(stats_map);
+        .with_state(stats_map.clone())
+        .route("/executors", get(get_executors_json))
+        .with_state(ExecutorIndexState {
+            index: executor_index.clone(),
+            stats: stats_map.clone(),
+        })
+        .route("/executors/:id/timeline", get(get_timeline_json))
+        .with_state(timelines.clone())
+        .route("/executors/:id/decisions", get(get_decisions_json))
+        .with_state(decision_histories.clone())
+        .route("/accounting", get(get_accounting_json))
+        .with_state(accounting.clone())
+        .route("/stats/submission", get(get_submission_stats_json))
+        .with_state(submission_policy.stats())
+        .route("/stats/rpc", get(get_rpc_stats_json))
+        .with_state(rpc_stats)
+        .route("/stats/autoscaling", get(get_autoscaling_json))
+        .with_state(autoscaling.clone())
+        .route("/stats/queue", get(get_queue_json))
+        .with_state(executor_queue.clone())
+        .route("/stats/shard", get(get_shard_json))
+        .with_state(sharding.clone())
+        .route("/stats/tasks", get(get_task_counts_json))
+        .with_state(task_counts.clone())
+        .merge(stats_router(apps, stats_map.clone()));
+    if let Some(token) = args.admin_token.clone() {
+        let admin = AdminState {
+            switch: switch.clone(),
+            token,
+        };
+        app = app.merge(
+            Router::new()
+                .route("/admin/pause", post(pause))
+                .route("/admin/resume", post(resume))
+                .route("/admin/status", get(get_status))
+                .with_state(admin.clone())
+                .route("/admin/wallets/rotate", post(rotate_wallet))
+                .with_state(WalletRotation {
+                    admin: admin.clone(),
+                    wallet_pool: wallet_pool.clone(),
+                    signer: pool_signer,
+                })
+                .route("/admin/shard", post(assign_shard))
+                .with_state(ShardingState {
+                    admin: admin.clone(),
+                    sharding,
+                })
+                .route("/admin/tasks", get(get_tasks_json))
+                .with_state(SchedulerState {
+                    admin: admin.clone(),
+                    statuses: scheduler.statuses(),
+                })
+                .route("/deadletter/retry-all", post(retry_all))
+                .with_state(DeadLetterState {
+                    admin: admin.clone(),
+                    dead_letters,
+                    retry_tx,
+                }),
+        );
+        if args.accept_shadow_traffic {
+            app = app.merge(
+                Router::new()
+                    .route("/shadow/objective", post(receive_shadow_objective))
+                    .with_state(ShadowState {
+                        admin: admin.clone(),
+                        incoming_tx: shadow_incoming_tx,
+                    }),
+            );
+        }
+        if let Some(circuit_breaker) = &circuit_breaker {
+            app = app.merge(
+                Router::new()
+                    .route("/admin/circuit/reset", post(reset_circuit))
+                    .with_state(CircuitBreakerState {
+                        admin,
+                        circuit_breaker: circuit_breaker.clone(),
+                    }),
+            );
+        }
+    }
 
     let tcp_listener = TcpListener::bind(format!("0.0.0.0:{}", args.port))
         .await
@@ -137,14 +1254,156 @@ async fn main() {
     // Start all services
     println!("Starting server at port {}", args.port);
 
-    {
-        let mut exec_set = exec_set.lock().await;
-        exec_set.spawn(async move {
-            listener.listen().await;
-        });
-        exec_set.spawn(async move {
-            run_stats_receive(&mut stats_rx, stats_map_copy).await;
-        });
+    // The listener and the stats receiver are restarted if they panic, the state they
+    // work on is kept.
+    let listener = Arc::new(Mutex::new(listener));
+    supervisor
+        .spawn_critical("listener", None, move || {
+            let listener = listener.clone();
+            async move {
+                listener.lock().await.listen().await;
+            }
+        })
+        .await;
+    // The outcomes go to the notifications if there are webhooks, and to the stats
+    // export.
+    let outcomes = OutcomeBus::default();
+    #[cfg(feature = "webhooks")]
+    if !args.notification_webhook.is_empty() {
+        let outcome_rx = outcomes.subscribe();
+        let webhooks = args.notification_webhook.clone();
+        supervisor
+            .spawn("notifications", None, async move {
+                run_notifications(outcome_rx, webhooks).await;
+            })
+            .await;
+    }
+    #[cfg(feature = "stats-export")]
+    if let Some(target) = args.stats_export_url.clone() {
+        let export_rx = outcomes.subscribe();
+        {
+            let exporter = StatsExporter {
+                target,
+                credentials: ExportCredentials {
+                    access_key_id: args.stats_export_access_key_id.clone(),
+                    secret_access_key: args.stats_export_secret_access_key.clone(),
+                    region: args.stats_export_region.clone(),
+                },
+                spool_dir: args.stats_export_spool_dir.clone().into(),
+                batch_size: args.stats_export_batch_size.max(1),
+                interval: Duration::from_secs(args.stats_export_interval_secs),
+            };
+            supervisor
+                .spawn("stats_export", None, async move {
+                    exporter.run(export_rx).await;
+                })
+                .await;
+        }
+    }
+    let stats_rx = Arc::new(Mutex::new(stats_rx));
+    let timelines_copy = timelines.clone();
+    let decision_histories_copy = decision_histories.clone();
+    supervisor
+        .spawn_critical("stats_receiver", None, move || {
+            let stats_rx = stats_rx.clone();
+            let stats_map = stats_map_copy.clone();
+            let timelines = timelines_copy.clone();
+            let decision_histories = decision_histories_copy.clone();
+            let accounting = accounting.clone();
+            let outcomes = outcomes.clone();
+            async move {
+                let mut stats_rx = stats_rx.lock().await;
+                run_stats_receive(
+                    &mut stats_rx,
+                    stats_map,
+                    timelines,
+                    decision_histories,
+                    accounting,
+                    outcomes,
+                )
+                .await;
+            }
+        })
+        .await;
+    for wallet_monitor in wallet_monitors {
+        supervisor
+            .spawn("wallet_monitor", None, async move {
+                wallet_monitor.run().await;
+            })
+            .await;
+    }
+    let stats_retention = StatsRetention {
+        max_entries: args.stats_max_entries,
+        max_age: args.stats_max_age_secs.map(Duration::from_secs),
+        archive_path: args.stats_archive.clone(),
     };
+    if stats_retention.is_enabled() {
+        let stats_map = Arc::clone(&stats_map);
+        let timelines = timelines.clone();
+        let decision_histories = decision_histories.clone();
+        let executor_index = executor_index.clone();
+        supervisor
+            .spawn("stats_gc", None, async move {
+                run_stats_gc(
+                    stats_map,
+                    timelines,
+                    decision_histories,
+                    executor_index,
+                    stats_retention,
+                )
+                .await;
+            })
+            .await;
+    }
+    supervisor
+        .spawn("scheduler", None, async move {
+            scheduler.run().await;
+        })
+        .await;
+    if !args.block_tick_app.is_empty() || price_feed.is_some() {
+        let middleware = limit_order_provider.clone();
+        supervisor
+            .spawn("block_ticker", None, async move {
+                run_block_ticker(middleware, block_tick_tx).await;
+            })
+            .await;
+    }
+    if args.mempool_prewarm {
+        let middleware = limit_order_provider.clone();
+        let laminator_address = args.laminator_address;
+        supervisor
+            .spawn("mempool_watcher", None, async move {
+                run_mempool_watcher(middleware, laminator_address, pending_tx).await;
+            })
+            .await;
+    }
+    if let Some(price_feed) = price_feed {
+        let blocks = block_tick_rx.clone();
+        supervisor
+            .spawn("price_feed", None, async move {
+                price_feed.run(blocks).await;
+            })
+            .await;
+    }
+    #[cfg(feature = "webhooks")]
+    if let Some(url) = args.autoscaler_webhook_url {
+        let interval = Duration::from_secs(args.autoscaler_push_secs);
+        supervisor
+            .spawn("autoscaler_push", None, async move {
+                run_autoscaler_push(autoscaling, url, interval).await;
+            })
+            .await;
+    }
+    #[cfg(feature = "webhooks")]
+    if let (Some(url), Some(mut shadow_mirror_rx)) = (args.shadow_url, shadow_mirror_rx) {
+        let token = args.shadow_token.unwrap_or_default();
+        supervisor
+            .spawn("shadow_send", None, async move {
+                run_shadow_send(&mut shadow_mirror_rx, url, token).await;
+            })
+            .await;
+    }
+    // Finished tasks are collected outside of the set.
+    tokio::spawn(supervisor.run());
     serve(tcp_listener, app).await.unwrap();
 }
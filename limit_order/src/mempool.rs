@@ -0,0 +1,107 @@
+use ethers::{
+    abi::AbiDecode,
+    providers::{Middleware, PubsubClient, StreamExt},
+    types::{Address, Transaction},
+};
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::mpsc::Sender, time::sleep};
+
+use crate::contracts_abi::{
+    laminated_proxy::LaminatedProxy,
+    laminator::{CallObject, Laminator, LaminatorCalls, ProxyPushedFilter},
+};
+
+// Delay before subscribing to the pending transactions again after the subscription
+// failed.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+// Pending transactions fetched at once, so that a busy mempool doesn't back the
+// subscription up behind one request at a time.
+const MAX_PENDING_FETCHES: usize = 16;
+
+// The objective a pending push to the laminator would emit once included. The sequence
+// number is the next one of the proxy at the latest block, pushes of the same proxy
+// pending together get the same one and only the first may match its event.
+async fn pending_objective<M: Middleware>(
+    laminator: &Laminator<M>,
+    middleware: Arc<M>,
+    tx: &Transaction,
+) -> Result<Option<ProxyPushedFilter>, String> {
+    let push = match (tx.to, LaminatorCalls::decode(&tx.input)) {
+        (Some(to), Ok(LaminatorCalls::PushToProxy(push))) if to == laminator.address() => push,
+        _ => return Ok(None),
+    };
+    let call_objs = Vec::<CallObject>::decode(&push.c_data)
+        .map_err(|err| format!("invalid calls of the push: {}", err))?;
+    let proxy_address = laminator
+        .compute_proxy_address(tx.from)
+        .call()
+        .await
+        .map_err(|err| format!("error reading the proxy of {:?}: {}", tx.from, err))?;
+    let sequence_number = LaminatedProxy::new(proxy_address, middleware)
+        .next_sequence_number()
+        .call()
+        .await
+        .map_err(|err| {
+            format!(
+                "error reading the sequence number of {:?}: {}",
+                proxy_address, err
+            )
+        })?;
+    Ok(Some(ProxyPushedFilter {
+        proxy_address,
+        call_objs,
+        sequence_number,
+        selector: push.selector,
+        data_values: push.data_values,
+    }))
+}
+
+// Forwards the objectives of the pending pushes to the laminator, for the solvers to be
+// warmed up before their events are emitted. Pending transactions are only seen if the
+// node shares its mempool.
+pub async fn run_mempool_watcher<M>(
+    middleware: Arc<M>,
+    laminator_address: Address,
+    tx: Sender<ProxyPushedFilter>,
+) where
+    M: Middleware,
+    M::Provider: PubsubClient,
+{
+    let laminator = Laminator::new(laminator_address, middleware.clone());
+    loop {
+        match middleware.subscribe_pending_txs().await {
+            Ok(pending) => {
+                let mut objectives = pending
+                    .map(|tx_hash| {
+                        let (laminator, middleware) = (&laminator, middleware.clone());
+                        async move {
+                            // Most pending transactions don't push anything, or are gone
+                            // already.
+                            let pending_tx = match middleware.get_transaction(tx_hash).await {
+                                Ok(Some(pending_tx)) => pending_tx,
+                                _ => return None,
+                            };
+                            pending_objective(laminator, middleware, &pending_tx)
+                                .await
+                                .unwrap_or_else(|err| {
+                                    println!("Pending push {:?} skipped: {}", tx_hash, err);
+                                    None
+                                })
+                        }
+                    })
+                    .buffer_unordered(MAX_PENDING_FETCHES);
+                while let Some(objective) = objectives.next().await {
+                    if let Some(objective) = objective {
+                        if tx.send(objective).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                println!("The subscription of the pending transactions ended");
+            }
+            Err(err) => println!("Error subscribing to the pending transactions: {}", err),
+        }
+        sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
@@ -0,0 +1,194 @@
+use ethers::types::H256;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use tokio::sync::mpsc::UnboundedReceiver;
+use uuid::Uuid;
+
+use crate::{
+    http_client::http_client,
+    solver::selector,
+    stats::{Status, TimerExecutorStats, TransactionStatus},
+};
+
+// Outcomes of the executors the webhooks are notified of.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    Failure,
+    Timeout,
+    // The final transaction reverted, the executor goes on.
+    Revert,
+}
+
+impl Outcome {
+    const ALL: [Outcome; 4] = [
+        Outcome::Success,
+        Outcome::Failure,
+        Outcome::Timeout,
+        Outcome::Revert,
+    ];
+
+    fn of(stats: &TimerExecutorStats) -> Option<Outcome> {
+        match stats.status {
+            Status::Succeeded => Some(Outcome::Success),
+            Status::Failed | Status::BudgetExhausted => Some(Outcome::Failure),
+            Status::Timeout => Some(Outcome::Timeout),
+            Status::Running if stats.transaction_status == TransactionStatus::Reverted => {
+                Some(Outcome::Revert)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "success" => Ok(Outcome::Success),
+            "failure" => Ok(Outcome::Failure),
+            "timeout" => Ok(Outcome::Timeout),
+            "revert" => Ok(Outcome::Revert),
+            _ => Err(format!("unknown outcome \"{}\"", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NotificationFormat {
+    // The notification as a JSON object.
+    Json,
+    // A Slack incoming webhook message.
+    Slack,
+}
+
+// A webhook as [OUTCOME,...=]FORMAT:URL with the format json or slack, notified of all
+// the outcomes if none are given.
+#[derive(Clone, Debug)]
+pub struct NotificationWebhook {
+    pub outcomes: Vec<Outcome>,
+    pub format: NotificationFormat,
+    pub url: String,
+}
+
+impl FromStr for NotificationWebhook {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (outcomes, webhook) = match s.split_once('=') {
+            Some((outcomes, webhook)) => (
+                outcomes
+                    .split(',')
+                    .map(Outcome::from_str)
+                    .collect::<Result<Vec<Outcome>, String>>()?,
+                webhook,
+            ),
+            None => (Outcome::ALL.to_vec(), s),
+        };
+        let (format, url) = match webhook.split_once(':') {
+            Some(("json", url)) if !url.is_empty() => (NotificationFormat::Json, url),
+            Some(("slack", url)) if !url.is_empty() => (NotificationFormat::Slack, url),
+            _ => {
+                return Err(format!(
+                    "expected json:URL or slack:URL, got \"{}\"",
+                    webhook
+                ))
+            }
+        };
+        Ok(NotificationWebhook {
+            outcomes,
+            format,
+            url: url.to_string(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Notification {
+    pub outcome: Outcome,
+    pub executor_id: Uuid,
+    pub app: String,
+    pub app_selector: H256,
+    pub sequence_number: u32,
+    pub transaction_status: TransactionStatus,
+    pub tx_hash: Option<H256>,
+    pub message: String,
+}
+
+impl Notification {
+    fn payload(&self, format: NotificationFormat) -> Value {
+        match format {
+            NotificationFormat::Json => json!(self),
+            NotificationFormat::Slack => json!({
+                "text": format!(
+                    "*{:?}* of objective {} of {}{}\n{}",
+                    self.outcome,
+                    self.sequence_number,
+                    self.app,
+                    match self.tx_hash {
+                        Some(tx_hash) => format!(", transaction {:?}", tx_hash),
+                        None => String::new(),
+                    },
+                    self.message
+                )
+            }),
+        }
+    }
+}
+
+// Notifies the webhooks of the executor outcomes, failures to deliver are only logged.
+pub async fn run_notifications(
+    mut rx: UnboundedReceiver<TimerExecutorStats>,
+    webhooks: Vec<NotificationWebhook>,
+) {
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(err) => {
+            println!("{}, the notifications aren't sent", err);
+            return;
+        }
+    };
+    while let Some(stats) = rx.recv().await {
+        let outcome = match Outcome::of(&stats) {
+            Some(outcome) => outcome,
+            None => continue,
+        };
+        let notification = Notification {
+            outcome,
+            executor_id: stats.id,
+            app_selector: selector(stats.app.clone()),
+            app: stats.app,
+            sequence_number: stats.sequence_number,
+            transaction_status: stats.transaction_status,
+            tx_hash: stats.tx_hash,
+            message: stats.message,
+        };
+        for webhook in webhooks
+            .iter()
+            .filter(|webhook| webhook.outcomes.contains(&outcome))
+        {
+            let res = client
+                .post(webhook.url.as_str())
+                .json(&notification.payload(webhook.format))
+                .send()
+                .await;
+            match res {
+                // The URLs aren't logged, they may hold a secret as the Slack ones.
+                Ok(response) if !response.status().is_success() => println!(
+                    "Notification of {:?} rejected by a {:?} webhook: {}",
+                    outcome,
+                    webhook.format,
+                    response.status()
+                ),
+                Ok(_) => {}
+                Err(err) => println!(
+                    "Error notifying a {:?} webhook of {:?}: {}",
+                    webhook.format, outcome, err
+                ),
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use ethers::types::{Address, U256};
+use std::{collections::HashMap, fmt::Display, str::FromStr, time::Duration};
+
+use crate::{contracts_abi::laminator::AdditionalData, solver::SolverError};
+
+// Longest duration parsed, parse_duration takes very long on huge numbers.
+const MAX_DURATION_LEN: usize = 32;
+
+// Whether the duration has a number in the exponent notation, e.g. 1e9, which
+// parse_duration may take very long to expand.
+fn has_exponent(value: &str) -> bool {
+    value.as_bytes().windows(2).any(|pair| {
+        (pair[0].is_ascii_digit() || pair[0] == b'.') && pair[1].eq_ignore_ascii_case(&b'e')
+    })
+}
+
+#[derive(Clone, Debug)]
+pub enum ParamType {
+    Address,
+    // A decimal unsigned integer within the inclusive bounds.
+    Uint { min: U256, max: U256 },
+    // A positive duration, e.g. 90s or 5 min.
+    Duration,
+    // One of the values, case insensitive.
+    Enum(&'static [&'static str]),
+}
+
+impl ParamType {
+    // Any unsigned integer.
+    pub fn uint() -> ParamType {
+        ParamType::Uint {
+            min: U256::zero(),
+            max: U256::MAX,
+        }
+    }
+
+    pub fn uint_within(min: u64, max: u64) -> ParamType {
+        ParamType::Uint {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+
+    fn parse(&self, value: &str) -> Result<ParamValue, String> {
+        match self {
+            ParamType::Address => Address::from_str(value)
+                .map(ParamValue::Address)
+                .map_err(|err| format!("\"{}\" isn't an address: {}", value, err)),
+            ParamType::Uint { min, max } => {
+                let uint = U256::from_dec_str(value)
+                    .map_err(|err| format!("\"{}\" isn't an unsigned integer: {:?}", value, err))?;
+                if uint < *min || uint > *max {
+                    return Err(format!("{} is out of range {}..{}", uint, min, max));
+                }
+                Ok(ParamValue::Uint(uint))
+            }
+            ParamType::Duration => {
+                if value.len() > MAX_DURATION_LEN {
+                    return Err(format!(
+                        "durations are up to {} characters long",
+                        MAX_DURATION_LEN
+                    ));
+                }
+                if has_exponent(value) {
+                    return Err("durations can't use the exponent notation".to_string());
+                }
+                match parse_duration::parse(value) {
+                    Ok(duration) if duration.is_zero() => Err("the duration is zero".to_string()),
+                    Ok(duration) => Ok(ParamValue::Duration(duration)),
+                    Err(err) => Err(format!("\"{}\" isn't a duration: {}", value, err)),
+                }
+            }
+            ParamType::Enum(values) => values
+                .iter()
+                .find(|allowed| allowed.eq_ignore_ascii_case(value))
+                .map(|allowed| ParamValue::Enum(allowed))
+                .ok_or(format!("\"{}\" isn't one of {}", value, values.join(", "))),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ParamValue {
+    Address(Address),
+    Uint(U256),
+    Duration(Duration),
+    Enum(&'static str),
+}
+
+struct ParamSpec {
+    name: &'static str,
+    param_type: ParamType,
+    required: bool,
+}
+
+// The mev-time data an app expects, the keys not declared are ignored.
+pub struct ParamSchema {
+    params: Vec<ParamSpec>,
+}
+
+impl ParamSchema {
+    pub fn new() -> ParamSchema {
+        ParamSchema { params: Vec::new() }
+    }
+
+    pub fn required(mut self, name: &'static str, param_type: ParamType) -> ParamSchema {
+        self.params.push(ParamSpec {
+            name,
+            param_type,
+            required: true,
+        });
+        self
+    }
+
+    pub fn optional(mut self, name: &'static str, param_type: ParamType) -> ParamSchema {
+        self.params.push(ParamSpec {
+            name,
+            param_type,
+            required: false,
+        });
+        self
+    }
+
+    // Parses the declared parameters, the problems with all of them are reported at
+    // once. A key given more than once takes the last value.
+    pub fn validate(&self, data_values: &[AdditionalData]) -> Result<Params, SolverError> {
+        let mut values = HashMap::new();
+        let mut problems = Vec::new();
+        for spec in &self.params {
+            match data_values.iter().rev().find(|ad| ad.name == spec.name) {
+                Some(ad) => match spec.param_type.parse(ad.value.as_str()) {
+                    Ok(value) => {
+                        values.insert(spec.name, value);
+                    }
+                    Err(err) => problems.push(format!("{}: {}", spec.name, err)),
+                },
+                None if spec.required => problems.push(format!("{} is missing", spec.name)),
+                None => {}
+            }
+        }
+        check(problems)?;
+        Ok(Params { values })
+    }
+}
+
+// The parameters that passed the schema.
+pub struct Params {
+    values: HashMap<&'static str, ParamValue>,
+}
+
+impl Params {
+    pub fn address(&self, name: &str) -> Option<Address> {
+        match self.values.get(name) {
+            Some(ParamValue::Address(address)) => Some(*address),
+            _ => None,
+        }
+    }
+
+    pub fn uint(&self, name: &str) -> Option<U256> {
+        match self.values.get(name) {
+            Some(ParamValue::Uint(uint)) => Some(*uint),
+            _ => None,
+        }
+    }
+
+    pub fn duration(&self, name: &str) -> Option<Duration> {
+        match self.values.get(name) {
+            Some(ParamValue::Duration(duration)) => Some(*duration),
+            _ => None,
+        }
+    }
+
+    // The enum value as the type of the solver.
+    pub fn enum_as<T>(&self, name: &str) -> Result<Option<T>, SolverError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.values.get(name) {
+            Some(ParamValue::Enum(value)) => T::from_str(value)
+                .map(Some)
+                .map_err(|err| SolverError::ParamError(format!("{}: {}", name, err))),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+}
+
+// Fails with all the problems found, e.g. by the checks across the parameters the schema
+// can't express.
+pub fn check(problems: Vec<String>) -> Result<(), SolverError> {
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(SolverError::ParamError(format!(
+            "Invalid parameters: {}",
+            problems.join("; ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(values: &[(&str, &str)]) -> Vec<AdditionalData> {
+        values
+            .iter()
+            .map(|(name, value)| AdditionalData {
+                name: name.to_string(),
+                datatype: 0,
+                value: value.to_string(),
+            })
+            .collect()
+    }
+
+    fn schema() -> ParamSchema {
+        ParamSchema::new()
+            .required("token", ParamType::Address)
+            .required("percent", ParamType::uint_within(1, 99))
+            .optional("time_limit", ParamType::Duration)
+            .optional("side", ParamType::Enum(&["buy", "sell"]))
+    }
+
+    #[test]
+    fn problems_are_reported_together() {
+        let err = schema()
+            .validate(&data(&[
+                ("percent", "100"),
+                ("time_limit", "99999999999999999999999999999999999 s"),
+                ("side", "hold"),
+            ]))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Parameter error, \"Invalid parameters: token is missing; percent: 100 is out of \
+             range 1..99; time_limit: durations are up to 32 characters long; side: \
+             \"hold\" isn't one of buy, sell\""
+        );
+    }
+
+    #[test]
+    fn valid_values_are_parsed() {
+        let params = schema()
+            .validate(&data(&[
+                ("token", "0x0000000000000000000000000000000000000001"),
+                ("percent", "5"),
+                ("side", "SELL"),
+                ("percent", "7"),
+            ]))
+            .ok()
+            .unwrap();
+        assert_eq!(params.address("token"), Some(Address::from_low_u64_be(1)));
+        assert_eq!(params.uint("percent"), Some(7.into()));
+        assert_eq!(params.duration("time_limit"), None);
+        assert_eq!(
+            params.enum_as::<String>("side").ok().unwrap(),
+            Some("sell".to_string())
+        );
+    }
+}
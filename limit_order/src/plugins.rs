@@ -0,0 +1,248 @@
+use ethers::{
+    providers::Middleware,
+    types::{Address, Bytes, TransactionRequest},
+};
+use libloading::Library;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+    ffi::{c_char, c_void, CStr, CString},
+    ptr,
+    sync::{Arc, Mutex},
+};
+use tokio::runtime::Handle;
+
+// Solver plugins are shared libraries implementing an app, loaded at startup. All the
+// strings crossing the boundary are NUL terminated JSON, the strings returned by the
+// plugin are released with its stxn_string_free.
+//
+// Exported by the plugin:
+//   uint32_t stxn_solver_abi_version(void)
+//   const char* stxn_solver_app(void)
+//   void* stxn_solver_new(const char* objective, const StxnHost* host, char** error)
+//   char* stxn_solver_call(void* solver, const char* request)
+//   void stxn_solver_free(void* solver)
+//   void stxn_string_free(char* s)
+//
+// stxn_solver_new returns NULL and sets the error if the objective is rejected. The
+// requests of stxn_solver_call are {"method": "step" | "preconditions" | "profitability"
+// | "final_calls"}, a failed request replies {"error": "..."}. The calls of one solver are
+// never concurrent, but may come from different threads.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+// Chain reads offered to the plugin, valid until the solver is freed. Requests are
+// {"method": "call", "to": "0x..", "data": "0x.."} and {"method": "block_number"}, the
+// reply is {"result": ...} or {"error": "..."} and is released with free_string.
+#[repr(C)]
+pub struct Host {
+    ctx: *const c_void,
+    read: unsafe extern "C" fn(*const c_void, *const c_char) -> *mut c_char,
+    free_string: unsafe extern "C" fn(*mut c_char),
+}
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type AppFn = unsafe extern "C" fn() -> *const c_char;
+type NewFn = unsafe extern "C" fn(*const c_char, *const Host, *mut *mut c_char) -> *mut c_void;
+type CallFn = unsafe extern "C" fn(*mut c_void, *const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_void);
+type StringFreeFn = unsafe extern "C" fn(*mut c_char);
+
+pub struct SolverPlugin {
+    pub app: String,
+    new: NewFn,
+    call: CallFn,
+    free: FreeFn,
+    string_free: StringFreeFn,
+    // The functions above point into the library, it is unloaded last.
+    _library: Library,
+}
+
+impl SolverPlugin {
+    pub fn load(path: &str) -> Result<SolverPlugin, String> {
+        let library = unsafe { Library::new(path) }.map_err(|err| err.to_string())?;
+        unsafe {
+            let abi_version = symbol::<AbiVersionFn>(&library, "stxn_solver_abi_version")?;
+            if abi_version() != PLUGIN_ABI_VERSION {
+                return Err(format!(
+                    "Unsupported plugin ABI version {}, expected {}",
+                    abi_version(),
+                    PLUGIN_ABI_VERSION
+                ));
+            }
+            let app = symbol::<AppFn>(&library, "stxn_solver_app")?();
+            if app.is_null() {
+                return Err("The plugin declares no app".to_string());
+            }
+            Ok(SolverPlugin {
+                app: CStr::from_ptr(app).to_string_lossy().into_owned(),
+                new: symbol(&library, "stxn_solver_new")?,
+                call: symbol(&library, "stxn_solver_call")?,
+                free: symbol(&library, "stxn_solver_free")?,
+                string_free: symbol(&library, "stxn_string_free")?,
+                _library: library,
+            })
+        }
+    }
+
+    // Creates a plugin solver for an objective, chain reads go through the middleware.
+    pub fn instantiate<M: Middleware + 'static>(
+        self: &Arc<Self>,
+        objective: &Value,
+        middleware: Arc<M>,
+    ) -> Result<PluginInstance<M>, String> {
+        let objective = to_c_string(objective.to_string())?;
+        let ctx = Box::new(HostContext { middleware });
+        let host = Box::new(Host {
+            ctx: ctx.as_ref() as *const HostContext<M> as *const c_void,
+            read: host_read::<M>,
+            free_string: host_free_string,
+        });
+        let mut error: *mut c_char = ptr::null_mut();
+        let solver = tokio::task::block_in_place(|| unsafe {
+            (self.new)(objective.as_ptr(), host.as_ref(), &mut error)
+        });
+        if solver.is_null() {
+            return Err(match unsafe { self.take_string(error) } {
+                Some(error) => error,
+                None => "The plugin rejected the objective".to_string(),
+            });
+        }
+        Ok(PluginInstance {
+            solver: Mutex::new(Instance {
+                plugin: self.clone(),
+                solver,
+                _host: host,
+                _ctx: ctx,
+            }),
+        })
+    }
+
+    unsafe fn take_string(&self, s: *mut c_char) -> Option<String> {
+        if s.is_null() {
+            return None;
+        }
+        let ret = CStr::from_ptr(s).to_string_lossy().into_owned();
+        (self.string_free)(s);
+        Some(ret)
+    }
+}
+
+unsafe fn symbol<T: Copy>(library: &Library, name: &str) -> Result<T, String> {
+    library
+        .get::<T>(name.as_bytes())
+        .map(|symbol| *symbol)
+        .map_err(|err| format!("Missing symbol {}: {}", name, err))
+}
+
+fn to_c_string(s: String) -> Result<CString, String> {
+    CString::new(s).map_err(|err| err.to_string())
+}
+
+// A solver created by a plugin for one objective.
+pub struct PluginInstance<M> {
+    solver: Mutex<Instance<M>>,
+}
+
+struct Instance<M> {
+    plugin: Arc<SolverPlugin>,
+    solver: *mut c_void,
+    // Referenced by the plugin solver, freed after it.
+    _host: Box<Host>,
+    _ctx: Box<HostContext<M>>,
+}
+
+// The plugin solver may be called from any thread, one call at a time.
+unsafe impl<M: Send> Send for Instance<M> {}
+
+impl<M> Drop for Instance<M> {
+    fn drop(&mut self) {
+        unsafe { (self.plugin.free)(self.solver) };
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Reply<T> {
+    Error { error: String },
+    Ok(T),
+}
+
+impl<M> PluginInstance<M> {
+    // Runs a request of the plugin solver. The plugin blocks the thread, also while it
+    // waits for its chain reads.
+    pub fn call<T: DeserializeOwned>(&self, method: &str) -> Result<T, String> {
+        let request = to_c_string(json!({ "method": method }).to_string())?;
+        let instance = self.solver.lock().map_err(|err| err.to_string())?;
+        let reply = tokio::task::block_in_place(|| unsafe {
+            let reply = (instance.plugin.call)(instance.solver, request.as_ptr());
+            instance.plugin.take_string(reply)
+        });
+        match reply {
+            Some(reply) => match serde_json::from_str::<Reply<T>>(reply.as_str()) {
+                Ok(Reply::Ok(ret)) => Ok(ret),
+                Ok(Reply::Error { error }) => Err(error),
+                Err(err) => Err(format!("Invalid reply to {}: {}", method, err)),
+            },
+            None => Err(format!("No reply to {}", method)),
+        }
+    }
+}
+
+struct HostContext<M> {
+    middleware: Arc<M>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+enum HostRequest {
+    Call { to: Address, data: Bytes },
+    BlockNumber,
+}
+
+impl<M: Middleware> HostContext<M> {
+    fn read(&self, request: HostRequest) -> Result<Value, String> {
+        // Called by the plugin from within block_in_place on a runtime thread.
+        let handle = Handle::try_current().map_err(|err| err.to_string())?;
+        handle.block_on(async {
+            match request {
+                HostRequest::Call { to, data } => self
+                    .middleware
+                    .call(&TransactionRequest::new().to(to).data(data).into(), None)
+                    .await
+                    .map(|ret| json!(ret))
+                    .map_err(|err| err.to_string()),
+                HostRequest::BlockNumber => self
+                    .middleware
+                    .get_block_number()
+                    .await
+                    .map(|block_number| json!(block_number.as_u64()))
+                    .map_err(|err| err.to_string()),
+            }
+        })
+    }
+}
+
+unsafe extern "C" fn host_read<M: Middleware>(
+    ctx: *const c_void,
+    request: *const c_char,
+) -> *mut c_char {
+    let ctx = &*(ctx as *const HostContext<M>);
+    let request = CStr::from_ptr(request).to_string_lossy();
+    let reply = match serde_json::from_str::<HostRequest>(request.as_ref()) {
+        Ok(request) => match ctx.read(request) {
+            Ok(result) => json!({ "result": result }),
+            Err(err) => json!({ "error": err }),
+        },
+        Err(err) => json!({ "error": format!("Invalid request: {}", err) }),
+    };
+    match to_c_string(reply.to_string()) {
+        Ok(reply) => reply.into_raw(),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn host_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
@@ -0,0 +1,119 @@
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
+use futures::future::join_all;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+use tokio::{sync::watch, time::Instant};
+
+use crate::{chain_client::contract_error, solver::SolverError, solvers::limit_order::SwapPool};
+
+// A cached price older than this is read from the chain again, e.g. when the block
+// subscription is down.
+const MAX_PRICE_AGE: Duration = Duration::from_secs(30);
+
+struct PoolPrice {
+    tx: watch::Sender<Option<U256>>,
+    updated: Option<Instant>,
+}
+
+impl PoolPrice {
+    fn fresh(&self) -> Option<U256> {
+        match self.updated {
+            Some(updated) if updated.elapsed() < MAX_PRICE_AGE => *self.tx.borrow(),
+            _ => None,
+        }
+    }
+}
+
+// Prices of the pools the executors wait on, read once per block and shared by all of
+// them, so that the reads grow with the pools rather than with the executors.
+pub struct PriceFeed<M> {
+    middleware: Arc<M>,
+    pools: StdMutex<HashMap<Address, PoolPrice>>,
+}
+
+impl<M: Middleware> PriceFeed<M> {
+    pub fn new(middleware: Arc<M>) -> PriceFeed<M> {
+        PriceFeed {
+            middleware,
+            pools: StdMutex::new(HashMap::new()),
+        }
+    }
+
+    // Tracks the pool while the receiver is alive, it gets every change of the price.
+    pub fn subscribe(&self, pool: Address) -> watch::Receiver<Option<U256>> {
+        self.pools
+            .lock()
+            .unwrap()
+            .entry(pool)
+            .or_insert_with(|| PoolPrice {
+                tx: watch::channel(None).0,
+                updated: None,
+            })
+            .tx
+            .subscribe()
+    }
+
+    // The cached price of a tracked pool, read from the chain if it isn't fresh.
+    pub async fn price(&self, pool: Address) -> Result<U256, SolverError> {
+        let cached = self
+            .pools
+            .lock()
+            .unwrap()
+            .get(&pool)
+            .and_then(PoolPrice::fresh);
+        if let Some(price) = cached {
+            return Ok(price);
+        }
+        let price = self.read_price(pool).await?;
+        self.update(pool, price);
+        Ok(price)
+    }
+
+    async fn read_price(&self, pool: Address) -> Result<U256, SolverError> {
+        SwapPool::new(pool, self.middleware.clone())
+            .get_price_of_weth()
+            .call()
+            .await
+            .map_err(contract_error)
+    }
+
+    fn update(&self, pool: Address, price: U256) {
+        if let Some(pool_price) = self.pools.lock().unwrap().get_mut(&pool) {
+            pool_price.updated = Some(Instant::now());
+            pool_price.tx.send_if_modified(|current| {
+                let modified = *current != Some(price);
+                *current = Some(price);
+                modified
+            });
+        }
+    }
+
+    // Reads the prices of the tracked pools, the pools nobody waits on are dropped.
+    async fn refresh(&self) {
+        let pools = {
+            let mut pools = self.pools.lock().unwrap();
+            pools.retain(|_, pool_price| pool_price.tx.receiver_count() > 0);
+            pools.keys().copied().collect::<Vec<Address>>()
+        };
+        let prices = join_all(pools.iter().map(|pool| self.read_price(*pool))).await;
+        for (pool, price) in pools.into_iter().zip(prices) {
+            match price {
+                Ok(price) => self.update(pool, price),
+                Err(err) => println!("Error reading the price of the pool {:?}: {}", pool, err),
+            }
+        }
+    }
+
+    // Refreshes the prices on every new block of the block ticker.
+    pub async fn run(&self, mut blocks: watch::Receiver<u64>) {
+        while blocks.changed().await.is_ok() {
+            self.refresh().await;
+        }
+    }
+}
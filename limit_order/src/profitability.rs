@@ -0,0 +1,73 @@
+use ethers::{
+    providers::Middleware,
+    types::{transaction::eip2718::TypedTransaction, U256},
+};
+
+// Gas cost of a transaction against what the solver earns by executing it.
+#[derive(Clone, Debug)]
+pub struct ProfitabilityEstimate {
+    pub gas: U256,
+    pub gas_price: U256,
+    // The tip paid by the user for the objective, in wei.
+    pub tip: U256,
+    // What the solver keeps from the execution itself, in wei.
+    pub surplus: U256,
+}
+
+impl ProfitabilityEstimate {
+    pub fn gas_cost(&self) -> U256 {
+        self.gas.saturating_mul(self.gas_price)
+    }
+
+    pub fn earnings(&self) -> U256 {
+        self.tip.saturating_add(self.surplus)
+    }
+
+    pub fn is_profitable(&self) -> bool {
+        self.earnings() >= self.gas_cost()
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "gas cost {} ({} gas at {} wei), tip {}, surplus {}",
+            self.gas_cost(),
+            self.gas,
+            self.gas_price,
+            self.tip,
+            self.surplus
+        )
+    }
+}
+
+// Estimates the gas cost of the transaction at the current gas price. If the estimation
+// fails, the gas limit of the transaction is taken as the worst case.
+pub async fn estimate<M: Middleware>(
+    middleware: &M,
+    tx: &TypedTransaction,
+    tip: U256,
+    surplus: U256,
+) -> Result<ProfitabilityEstimate, String> {
+    let gas_price = middleware
+        .get_gas_price()
+        .await
+        .map_err(|err| err.to_string())?;
+    let gas = match middleware.estimate_gas(tx, None).await {
+        Ok(gas) => gas,
+        Err(err) => match tx.gas() {
+            Some(gas_limit) => {
+                println!(
+                    "Gas estimation failed, using the gas limit {}: {}",
+                    gas_limit, err
+                );
+                *gas_limit
+            }
+            None => return Err(err.to_string()),
+        },
+    };
+    Ok(ProfitabilityEstimate {
+        gas,
+        gas_price,
+        tip,
+        surplus,
+    })
+}
@@ -0,0 +1,198 @@
+#[cfg(feature = "audit-store")]
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key,
+};
+#[cfg(feature = "audit-store")]
+use ethers::types::{Address, Bytes, H256, U256};
+#[cfg(feature = "audit-store")]
+use serde::Serialize;
+use std::{collections::HashMap, str::FromStr};
+#[cfg(feature = "audit-store")]
+use std::{fs::OpenOptions, io::Write, time::SystemTime};
+
+use crate::contracts_abi::laminator::{AdditionalData, ProxyPushedFilter};
+
+// Value shown instead of a redacted parameter.
+pub const REDACTED: &str = "[redacted]";
+
+// Parameters of an app not to be logged or exposed, as APP=PATTERN[,PATTERN...]. The
+// patterns match parameter names with * wildcards, the app * applies to all apps.
+#[derive(Clone, Debug)]
+pub struct RedactionRule {
+    pub app: String,
+    pub patterns: Vec<String>,
+}
+
+impl FromStr for RedactionRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((app, patterns)) if !app.is_empty() && !patterns.is_empty() => Ok(RedactionRule {
+                app: app.to_string(),
+                patterns: patterns.split(',').map(|p| p.to_string()).collect(),
+            }),
+            _ => Err(format!("expected APP=PATTERN[,PATTERN...], got \"{}\"", s)),
+        }
+    }
+}
+
+// Redacts the objective parameters before they are logged or stored in the stats. The
+// raw values of redacted objectives are kept only in the audit store, if configured.
+pub struct Redactor {
+    rules: HashMap<String, Vec<String>>,
+    #[cfg(feature = "audit-store")]
+    audit_store: Option<AuditStore>,
+}
+
+impl Redactor {
+    pub fn new(rules: Vec<RedactionRule>) -> Redactor {
+        let mut ret = Redactor {
+            rules: HashMap::new(),
+            #[cfg(feature = "audit-store")]
+            audit_store: None,
+        };
+        for rule in rules {
+            ret.rules.entry(rule.app).or_default().extend(rule.patterns);
+        }
+        ret
+    }
+
+    #[cfg(feature = "audit-store")]
+    pub fn with_audit_store(mut self, audit_store: AuditStore) -> Redactor {
+        self.audit_store = Some(audit_store);
+        self
+    }
+
+    fn is_redacted(&self, app: &str, name: &str) -> bool {
+        [app, "*"].iter().any(|app| {
+            self.rules
+                .get(*app)
+                .is_some_and(|patterns| patterns.iter().any(|p| matches(p, name)))
+        })
+    }
+
+    // A copy of the objective with the values of the redacted parameters replaced.
+    pub fn redact(&self, app: &str, event: &ProxyPushedFilter) -> ProxyPushedFilter {
+        let mut redacted = event.clone();
+        redacted.data_values = self.redact_params(app, &event.data_values);
+        redacted
+    }
+
+    pub fn redact_params(&self, app: &str, params: &[AdditionalData]) -> Vec<AdditionalData> {
+        params
+            .iter()
+            .map(|ad| {
+                let mut ad = ad.clone();
+                if self.is_redacted(app, ad.name.as_str()) {
+                    ad.value = REDACTED.to_string();
+                }
+                ad
+            })
+            .collect()
+    }
+
+    // Records the raw parameters of the objective if any of them are redacted.
+    #[cfg(feature = "audit-store")]
+    pub fn audit(&self, app: &str, event: &ProxyPushedFilter) {
+        let audit_store = match &self.audit_store {
+            Some(audit_store) => audit_store,
+            None => return,
+        };
+        if !event
+            .data_values
+            .iter()
+            .any(|ad| self.is_redacted(app, ad.name.as_str()))
+        {
+            return;
+        }
+        if let Err(err) = audit_store.record(app, event) {
+            println!(
+                "Error recording objective {} in the audit store: {}",
+                event.sequence_number, err
+            );
+        }
+    }
+}
+
+// Matches a parameter name against a pattern, * matches any sequence of characters.
+fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts = parts.collect::<Vec<&str>>();
+    match parts.split_last() {
+        None => rest.is_empty(),
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(pos) => rest = &rest[pos + part.len()..],
+                    None => return false,
+                }
+            }
+            rest.ends_with(last)
+        }
+    }
+}
+
+#[cfg(feature = "audit-store")]
+#[derive(Serialize)]
+struct AuditEntry {
+    time: u64,
+    app: String,
+    proxy_address: Address,
+    sequence_number: U256,
+    nonce: Bytes,
+    // AES-256-GCM encrypted JSON of the parameters.
+    params: Bytes,
+}
+
+// Append-only file of the encrypted raw parameters of the redacted objectives, as JSON
+// lines.
+#[cfg(feature = "audit-store")]
+pub struct AuditStore {
+    path: String,
+    cipher: Aes256Gcm,
+}
+
+#[cfg(feature = "audit-store")]
+impl AuditStore {
+    pub fn new(path: String, key: H256) -> AuditStore {
+        AuditStore {
+            path,
+            cipher: Aes256Gcm::new(&Key::<Aes256Gcm>::from(key.to_fixed_bytes())),
+        }
+    }
+
+    fn record(&self, app: &str, event: &ProxyPushedFilter) -> Result<(), String> {
+        let params = serde_json::to_vec(&event.data_values).map_err(|err| err.to_string())?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let params = self
+            .cipher
+            .encrypt(&nonce, params.as_slice())
+            .map_err(|err| err.to_string())?;
+        let entry = AuditEntry {
+            time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            app: app.to_string(),
+            proxy_address: event.proxy_address,
+            sequence_number: event.sequence_number,
+            nonce: nonce.to_vec().into(),
+            params: params.into(),
+        };
+        let line = serde_json::to_string(&entry).map_err(|err| err.to_string())?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|err| err.to_string())?;
+        file.write_all(format!("{}\n", line).as_bytes())
+            .map_err(|err| err.to_string())
+    }
+}
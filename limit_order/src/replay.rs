@@ -0,0 +1,380 @@
+use async_trait::async_trait;
+use ethers::{
+    providers::{JsonRpcClient, Middleware, Provider, Ws},
+    types::{Address, H256, U64},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    contracts_abi::laminator::{Laminator, ProxyPushedFilter},
+    solver::{selector, Solver, SolverParams},
+    solvers::limit_order::{self, LimitOrderSolver, PairPool},
+    submission::SubmissionPolicy,
+    token_metadata::TokenMetadataCache,
+};
+
+// Reads of the chain state, their block is the last param.
+const PINNED_METHODS: &[&str] = &[
+    "eth_call",
+    "eth_estimateGas",
+    "eth_getBalance",
+    "eth_getCode",
+    "eth_getStorageAt",
+];
+
+// Blocks of each eth_getLogs request scanning the range.
+const LOG_CHUNK_BLOCKS: u64 = 2000;
+
+// The params of the state read at the block, rather than at the latest one.
+fn pin_block(method: &str, params: Value, block: u64) -> Value {
+    let mut params = match params {
+        Value::Array(params) if PINNED_METHODS.contains(&method) => params,
+        params => return params,
+    };
+    let tag = Value::String(format!("{:#x}", block));
+    match params.last() {
+        Some(Value::String(last)) if last == "latest" || last == "pending" => {
+            params.pop();
+            params.push(tag);
+        }
+        // The estimate takes the latest block if it isn't given one.
+        _ if method == "eth_estimateGas" && params.len() == 1 => params.push(tag),
+        _ => {}
+    }
+    Value::Array(params)
+}
+
+// JSON-RPC transport reading the chain state at the set block, so that the solvers see
+// the archive state an objective was pushed in. The node must serve archive state.
+#[derive(Clone, Debug)]
+pub struct ArchiveClient<C> {
+    inner: C,
+    block: Arc<AtomicU64>,
+}
+
+impl<C: JsonRpcClient> ArchiveClient<C> {
+    pub fn new(inner: C) -> Self {
+        ArchiveClient {
+            inner,
+            block: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn set_block(&self, block: u64) {
+        self.block.store(block, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl<C> JsonRpcClient for ArchiveClient<C>
+where
+    C: JsonRpcClient,
+    C::Error: From<serde_json::Error>,
+{
+    type Error = C::Error;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = pin_block(
+            method,
+            serde_json::to_value(params)?,
+            self.block.load(Ordering::SeqCst),
+        );
+        self.inner.request(method, params).await
+    }
+}
+
+// The contracts the replayed solvers are configured with, as for the solver.
+pub struct ReplayConfig {
+    pub ws_chain_url: String,
+    pub chain_id: u64,
+    pub laminator_address: Address,
+    pub call_breaker_address: Address,
+    pub flash_loan_address: Option<Address>,
+    pub swap_pool_address: Option<Address>,
+    pub pair_pools: Vec<PairPool>,
+    // The solver the final transactions are simulated from.
+    pub solver_address: Address,
+}
+
+// What replaying an objective came to.
+enum Outcome {
+    // The final transaction was simulated in the block.
+    Solved { block: u64, message: String },
+    // The objective wasn't executable until its time limit or the end of the range, with
+    // the message of the last step.
+    Unsolved(String),
+    // A step failed for good.
+    Failed { block: u64, error: String },
+    // The solver rejected the objective.
+    Rejected(String),
+    // No built-in solver serves the app.
+    UnknownApp(H256),
+}
+
+// Scans the blocks for the pushed objectives and runs the solvers of each in the dry run
+// mode against the archive state, stepping every step_blocks blocks from the push until
+// solved, the time limit or the end of the range. Prints what each would have come to.
+pub async fn run(
+    config: ReplayConfig,
+    from_block: u64,
+    to_block: u64,
+    step_blocks: u64,
+) -> Result<(), String> {
+    if from_block > to_block {
+        return Err(format!(
+            "The range ends at the block {} before its start {}",
+            to_block, from_block
+        ));
+    }
+    let ws = Ws::connect(config.ws_chain_url.as_str())
+        .await
+        .map_err(|err| format!("Failed connection to the chain: {}", err))?;
+    let client = ArchiveClient::new(ws);
+    let provider = Arc::new(Provider::new(client.clone()));
+
+    let laminator = Laminator::new(config.laminator_address, provider.clone());
+    let mut objectives = Vec::new();
+    let mut chunk_start = from_block;
+    while chunk_start <= to_block {
+        let chunk_end = (chunk_start + LOG_CHUNK_BLOCKS - 1).min(to_block);
+        let logs = laminator
+            .event::<ProxyPushedFilter>()
+            .from_block(chunk_start)
+            .to_block(chunk_end)
+            .query_with_meta()
+            .await
+            .map_err(|err| {
+                format!(
+                    "Error reading the objectives of the blocks {}-{}: {}",
+                    chunk_start, chunk_end, err
+                )
+            })?;
+        objectives.extend(logs);
+        chunk_start = chunk_end + 1;
+    }
+    println!(
+        "Found {} objectives in the blocks {}-{}",
+        objectives.len(),
+        from_block,
+        to_block
+    );
+
+    let mut extra_contract_addresses = HashMap::new();
+    if let Some(flash_loan_address) = config.flash_loan_address {
+        extra_contract_addresses
+            .insert(limit_order::FLASH_LOAN_NAME.to_string(), flash_loan_address);
+    }
+    if let Some(swap_pool_address) = config.swap_pool_address {
+        extra_contract_addresses.insert(limit_order::SWAP_POOL_NAME.to_string(), swap_pool_address);
+    }
+    for pair_pool in &config.pair_pools {
+        extra_contract_addresses.insert(
+            limit_order::pair_pool_name(pair_pool.token_a, pair_pool.token_b),
+            pair_pool.pool,
+        );
+    }
+    let params = SolverParams {
+        call_breaker_address: config.call_breaker_address,
+        solver_address: config.solver_address,
+        extra_contract_addresses,
+        middleware: provider.clone(),
+        guard: Arc::new(Mutex::new(true)),
+        submission_policy: Arc::new(SubmissionPolicy::new(
+            config.chain_id,
+            vec![],
+            vec![],
+            vec![],
+            true,
+        )),
+        adaptive_tick: None,
+        price_feed: None,
+        block_ticks: None,
+        execution_log: None,
+        wallet_pool: None,
+        tick: Duration::from_secs(1),
+        default_time_limit: None,
+        app_queue: None,
+        gas_budget: None,
+        confirmations: 0,
+        #[cfg(feature = "aggregator")]
+        aggregator: None,
+        flash_loan_markets: Vec::new(),
+        bundle_composer: None,
+        token_metadata: Arc::new(TokenMetadataCache::default()),
+    };
+
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+    for (objective, meta) in objectives {
+        let outcome = replay_objective(
+            &client,
+            provider.as_ref(),
+            objective.clone(),
+            params.clone(),
+            meta.block_number.as_u64(),
+            to_block,
+            step_blocks.max(1),
+        )
+        .await;
+        let (kind, description) = match outcome {
+            Outcome::Solved { block, message } => (
+                "solved",
+                format!("solved in the block {}: {}", block, message),
+            ),
+            Outcome::Unsolved(message) => ("unsolved", format!("unsolved: {}", message)),
+            Outcome::Failed { block, error } => (
+                "failed",
+                format!("failed in the block {}: {}", block, error),
+            ),
+            Outcome::Rejected(error) => ("rejected", format!("rejected: {}", error)),
+            Outcome::UnknownApp(app_selector) => (
+                "unknown app",
+                format!("no solver for the app {:?}", app_selector),
+            ),
+        };
+        *counts.entry(kind).or_default() += 1;
+        println!(
+            "Objective {} of the proxy {:?}, pushed in {:?} (block {}), {}",
+            objective.sequence_number,
+            objective.proxy_address,
+            meta.transaction_hash,
+            meta.block_number,
+            description
+        );
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort();
+    println!(
+        "Replayed: {}",
+        counts
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    Ok(())
+}
+
+async fn replay_objective<M: Middleware + Clone, C: JsonRpcClient>(
+    client: &ArchiveClient<C>,
+    middleware: &M,
+    objective: ProxyPushedFilter,
+    params: SolverParams<M>,
+    pushed_block: u64,
+    to_block: u64,
+    step_blocks: u64,
+) -> Outcome {
+    let app_selector: H256 = objective.selector.into();
+    if app_selector != selector(limit_order::APP_SELECTOR.to_string()) {
+        return Outcome::UnknownApp(app_selector);
+    }
+    let solver = match LimitOrderSolver::new(objective, params) {
+        Ok(solver) => solver,
+        Err(err) => return Outcome::Rejected(err.to_string()),
+    };
+    let deadline = match (
+        solver.time_limit(),
+        block_timestamp(middleware, pushed_block).await,
+    ) {
+        (Ok(time_limit), Some(pushed_at)) => pushed_at + time_limit.as_secs(),
+        (_, _) => u64::MAX,
+    };
+    let mut block = pushed_block;
+    let mut last_message = String::new();
+    while block <= to_block {
+        if block_timestamp(middleware, block)
+            .await
+            .is_some_and(|timestamp| timestamp > deadline)
+        {
+            return Outcome::Unsolved(format!("time limit reached, {}", last_message));
+        }
+        client.set_block(block);
+        let step = match solver.exec_solver_step().await {
+            Ok(step) if step.succeeded => Ok(()),
+            Ok(step) => Err(step.message),
+            Err(err) if err.is_retryable() => Err(err.to_string()),
+            Err(err) => {
+                return Outcome::Failed {
+                    block,
+                    error: err.to_string(),
+                }
+            }
+        };
+        let checked = match step {
+            Ok(()) => check_and_simulate(&solver).await,
+            Err(message) => Err(Some(message)),
+        };
+        match checked {
+            Ok(message) => return Outcome::Solved { block, message },
+            Err(Some(message)) => last_message = message,
+            Err(None) => {}
+        }
+        block += step_blocks;
+    }
+    Outcome::Unsolved(last_message)
+}
+
+// The preconditions, profitability and simulated final transaction of the triggered
+// objective, with the message of the check that didn't pass otherwise.
+async fn check_and_simulate<S: Solver>(solver: &S) -> Result<String, Option<String>> {
+    for check in [
+        solver.check_preconditions().await,
+        solver.check_profitability().await,
+    ] {
+        match check {
+            Ok(response) if response.succeeded => {}
+            Ok(response) => return Err(Some(response.message)),
+            Err(err) => return Err(Some(err.to_string())),
+        }
+    }
+    match solver.final_exec().await {
+        Ok(response) if response.succeeded => Ok(response.message),
+        Ok(response) => Err(Some(response.message)),
+        Err(err) => Err(Some(err.to_string())),
+    }
+}
+
+async fn block_timestamp<M: Middleware>(middleware: &M, block: u64) -> Option<u64> {
+    match middleware.get_block(U64::from(block)).await {
+        Ok(Some(block)) => Some(block.timestamp.as_u64()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn state_reads_are_pinned_to_the_block() {
+        assert_eq!(
+            pin_block("eth_call", json!([{"to": "0x01"}, "latest"]), 255),
+            json!([{"to": "0x01"}, "0xff"])
+        );
+        assert_eq!(
+            pin_block("eth_estimateGas", json!([{"to": "0x01"}]), 255),
+            json!([{"to": "0x01"}, "0xff"])
+        );
+        // Reads of a given block and other methods are left alone.
+        assert_eq!(
+            pin_block("eth_getBalance", json!(["0x01", "0x10"]), 255),
+            json!(["0x01", "0x10"])
+        );
+        assert_eq!(pin_block("eth_blockNumber", json!([]), 255), json!([]));
+    }
+}
@@ -0,0 +1,319 @@
+use async_trait::async_trait;
+use axum::{extract::State, response::Json};
+use ethers::{
+    providers::{JsonRpcClient, PubsubClient},
+    types::U256,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::{oneshot, Mutex},
+    time::{sleep, Instant},
+};
+
+// Reads whose concurrent identical requests are sent to the node once, e.g. the pool
+// price polled by the executors in the same tick.
+const COALESCED_METHODS: &[&str] = &[
+    "eth_blockNumber",
+    "eth_call",
+    "eth_chainId",
+    "eth_estimateGas",
+    "eth_feeHistory",
+    "eth_gasPrice",
+    "eth_getBalance",
+    "eth_getBlockByNumber",
+    "eth_getCode",
+    "eth_maxPriorityFeePerGas",
+];
+
+pub type RpcStatsMap = Arc<Mutex<RpcStats>>;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RpcStats {
+    // Requests sent to the node.
+    pub sent: u64,
+    // Requests answered with the response of an identical request in flight.
+    pub coalesced: u64,
+    // Requests delayed by the rate limit.
+    pub throttled: u64,
+}
+
+// Refilled at the rate, holds up to a second of requests.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_sec: u32) -> TokenBucket {
+        TokenBucket {
+            rate: requests_per_sec as f64,
+            tokens: requests_per_sec as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    // Takes a token, or returns how long until there is one.
+    fn take(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        let refill = now.duration_since(self.updated).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refill).min(self.rate.max(1.0));
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
+
+type Waiters = Vec<oneshot::Sender<Arc<Value>>>;
+
+// Requests in flight by their method and params, with the id of the leading request and
+// the identical requests waiting for its response.
+type InFlightMap = StdMutex<HashMap<String, (u64, Waiters)>>;
+
+// JSON-RPC transport limiting the requests to the node, shared by everything using the
+// provider and its clones. Subscriptions are passed through.
+#[derive(Clone, Debug)]
+pub struct RateLimitedClient<C> {
+    inner: C,
+    // Not limited if not set.
+    bucket: Option<Arc<Mutex<TokenBucket>>>,
+    in_flight: Arc<InFlightMap>,
+    next_id: Arc<AtomicU64>,
+    stats: RpcStatsMap,
+}
+
+impl<C: JsonRpcClient> RateLimitedClient<C> {
+    pub fn new(inner: C, requests_per_sec: Option<u32>, stats: RpcStatsMap) -> Self {
+        RateLimitedClient {
+            inner,
+            bucket: requests_per_sec
+                .filter(|rate| *rate > 0)
+                .map(|rate| Arc::new(Mutex::new(TokenBucket::new(rate)))),
+            in_flight: Arc::new(StdMutex::new(HashMap::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+            stats,
+        }
+    }
+
+    async fn send<T, R>(&self, method: &str, params: T) -> Result<R, C::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        if let Some(bucket) = &self.bucket {
+            let mut throttled = false;
+            loop {
+                let wait = bucket.lock().await.take();
+                match wait {
+                    Some(wait) => {
+                        throttled = true;
+                        sleep(wait).await;
+                    }
+                    None => break,
+                }
+            }
+            if throttled {
+                self.stats.lock().await.throttled += 1;
+            }
+        }
+        self.stats.lock().await.sent += 1;
+        self.inner.request(method, params).await
+    }
+}
+
+// Entry of a request in flight, removed when the request finishes or is dropped so
+// that the identical requests never wait for nothing.
+struct InFlight<'a> {
+    in_flight: &'a InFlightMap,
+    key: String,
+    id: u64,
+}
+
+impl InFlight<'_> {
+    fn finish(self) -> Waiters {
+        self.remove().unwrap_or_default()
+    }
+
+    // Removes the entry unless it is already the one of a newer identical request.
+    fn remove(&self) -> Option<Waiters> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        match in_flight.get(&self.key) {
+            Some((id, _)) if *id == self.id => {
+                in_flight.remove(&self.key).map(|(_, waiters)| waiters)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+#[async_trait]
+impl<C> JsonRpcClient for RateLimitedClient<C>
+where
+    C: JsonRpcClient,
+    C::Error: From<serde_json::Error>,
+{
+    type Error = C::Error;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        if !COALESCED_METHODS.contains(&method) {
+            return self.send(method, params).await;
+        }
+        let key = format!("{}{}", method, serde_json::to_string(&params)?);
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let waiter = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get_mut(&key) {
+                Some((_, waiters)) => {
+                    let (tx, rx) = oneshot::channel();
+                    waiters.push(tx);
+                    Some(rx)
+                }
+                None => {
+                    in_flight.insert(key.clone(), (id, Vec::new()));
+                    None
+                }
+            }
+        };
+        if let Some(rx) = waiter {
+            // Sent on its own if the identical request failed.
+            return match rx.await {
+                Ok(value) => {
+                    self.stats.lock().await.coalesced += 1;
+                    Ok(serde_json::from_value(value.as_ref().clone())?)
+                }
+                Err(_) => self.send(method, params).await,
+            };
+        }
+        let entry = InFlight {
+            in_flight: &self.in_flight,
+            key,
+            id,
+        };
+        let value = Arc::new(self.send::<T, Value>(method, params).await?);
+        for waiter in entry.finish() {
+            let _ = waiter.send(value.clone());
+        }
+        Ok(serde_json::from_value(Arc::unwrap_or_clone(value))?)
+    }
+}
+
+impl<C> PubsubClient for RateLimitedClient<C>
+where
+    C: PubsubClient,
+    C::Error: From<serde_json::Error>,
+{
+    type NotificationStream = C::NotificationStream;
+
+    fn subscribe<T: Into<U256>>(&self, id: T) -> Result<Self::NotificationStream, Self::Error> {
+        self.inner.subscribe(id)
+    }
+
+    fn unsubscribe<T: Into<U256>>(&self, id: T) -> Result<(), Self::Error> {
+        self.inner.unsubscribe(id)
+    }
+}
+
+pub async fn get_rpc_stats_json(stats: State<RpcStatsMap>) -> Json<RpcStats> {
+    Json(stats.lock().await.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::MockError;
+    use std::sync::atomic::AtomicUsize;
+
+    // Node answering every request after a delay, counting them.
+    #[derive(Debug, Default)]
+    struct SlowNode {
+        requests: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl JsonRpcClient for SlowNode {
+        type Error = MockError;
+
+        async fn request<T, R>(&self, _method: &str, _params: T) -> Result<R, Self::Error>
+        where
+            T: Debug + Serialize + Send + Sync,
+            R: DeserializeOwned + Send,
+        {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            sleep(Duration::from_millis(50)).await;
+            Ok(serde_json::from_value(Value::from("0x1"))?)
+        }
+    }
+
+    #[test]
+    fn bucket_holds_a_second_of_requests() {
+        let mut bucket = TokenBucket::new(2);
+        assert_eq!(bucket.take(), None);
+        assert_eq!(bucket.take(), None);
+        let wait = bucket.take().ok_or("not limited").ok().unwrap();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn identical_requests_are_sent_once() {
+        let stats = RpcStatsMap::default();
+        let client = RateLimitedClient::new(SlowNode::default(), None, stats.clone());
+        let (first, second, other) = tokio::join!(
+            client.request::<_, U256>("eth_blockNumber", ()),
+            client.request::<_, U256>("eth_blockNumber", ()),
+            client.request::<_, U256>("eth_getBalance", ()),
+        );
+        assert_eq!(first.ok(), Some(U256::one()));
+        assert_eq!(second.ok(), Some(U256::one()));
+        assert_eq!(other.ok(), Some(U256::one()));
+        assert_eq!(client.inner.requests.load(Ordering::SeqCst), 2);
+        let stats = stats.lock().await;
+        assert_eq!((stats.sent, stats.coalesced), (2, 1));
+        assert!(client.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn finished_request_keeps_the_entry_of_a_newer_one() {
+        let in_flight = InFlightMap::default();
+        in_flight
+            .lock()
+            .unwrap()
+            .insert("key".to_string(), (0, Vec::new()));
+        let entry = InFlight {
+            in_flight: &in_flight,
+            key: "key".to_string(),
+            id: 0,
+        };
+        assert!(entry.remove().is_some());
+        in_flight
+            .lock()
+            .unwrap()
+            .insert("key".to_string(), (1, Vec::new()));
+        drop(entry);
+        assert!(in_flight.lock().unwrap().contains_key("key"));
+    }
+}
@@ -0,0 +1,202 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{TimeDelta, Utc};
+use cron::Schedule;
+use futures::future::BoxFuture;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    future::Future,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::{sync::Mutex, task::JoinSet, time::sleep};
+
+use crate::admin::AdminState;
+
+type TaskFn = Box<dyn Fn() -> BoxFuture<'static, Result<String, String>> + Send + Sync>;
+
+// Schedule override of a task, passed as NAME=CRON, e.g. "balance_check=0 */5 * * * *".
+#[derive(Clone, Debug)]
+pub struct TaskSchedule {
+    pub name: String,
+    pub schedule: Schedule,
+}
+
+impl FromStr for TaskSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, schedule) = s
+            .split_once('=')
+            .ok_or(format!("expected NAME=CRON, got \"{}\"", s))?;
+        Ok(TaskSchedule {
+            name: name.to_string(),
+            schedule: Schedule::from_str(schedule).map_err(|err| err.to_string())?,
+        })
+    }
+}
+
+// The last run of a task, exposed via the admin API.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub schedule: String,
+    pub enabled: bool,
+    pub runs: u64,
+    pub failures: u64,
+    // Times since Unix epoch.
+    pub last_run: Option<Duration>,
+    pub next_run: Option<Duration>,
+    pub last_duration: Option<Duration>,
+    pub last_succeeded: Option<bool>,
+    pub last_message: String,
+}
+
+pub type TaskStatusMap = Arc<Mutex<HashMap<String, TaskStatus>>>;
+
+struct Task {
+    name: String,
+    schedule: Schedule,
+    job: TaskFn,
+}
+
+// Runs periodic maintenance tasks on cron schedules inside the solver process.
+pub struct Scheduler {
+    tasks: Vec<Task>,
+    schedules: Vec<TaskSchedule>,
+    disabled: Vec<String>,
+    max_jitter: Duration,
+    statuses: TaskStatusMap,
+}
+
+impl Scheduler {
+    pub fn new(
+        schedules: Vec<TaskSchedule>,
+        disabled: Vec<String>,
+        max_jitter: Duration,
+    ) -> Scheduler {
+        Scheduler {
+            tasks: Vec::new(),
+            schedules,
+            disabled,
+            max_jitter,
+            statuses: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn statuses(&self) -> TaskStatusMap {
+        self.statuses.clone()
+    }
+
+    // Registers a task with its default schedule, which can be overridden by name.
+    // The job returns a message describing the outcome of the run.
+    pub fn add<F, Fut>(&mut self, name: &str, default_schedule: &str, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, String>> + Send + 'static,
+    {
+        let schedule = match self.schedules.iter().find(|s| s.name == name) {
+            Some(task_schedule) => task_schedule.schedule.clone(),
+            None => Schedule::from_str(default_schedule).unwrap(),
+        };
+        self.tasks.push(Task {
+            name: name.to_string(),
+            schedule,
+            job: Box::new(move || Box::pin(job())),
+        });
+    }
+
+    pub async fn run(self) {
+        for task_schedule in &self.schedules {
+            if !self.tasks.iter().any(|t| t.name == task_schedule.name) {
+                println!("Unknown task {} in the schedules", task_schedule.name);
+            }
+        }
+        let mut task_set = JoinSet::new();
+        {
+            let mut statuses = self.statuses.lock().await;
+            for task in self.tasks {
+                let enabled = !self.disabled.contains(&task.name);
+                statuses.insert(
+                    task.name.clone(),
+                    TaskStatus {
+                        name: task.name.clone(),
+                        schedule: task.schedule.to_string(),
+                        enabled,
+                        ..Default::default()
+                    },
+                );
+                if !enabled {
+                    println!("Task {} is disabled", task.name);
+                    continue;
+                }
+                let statuses = self.statuses.clone();
+                let max_jitter = self.max_jitter;
+                task_set.spawn(async move {
+                    run_task(task, max_jitter, statuses).await;
+                });
+            }
+        }
+        while task_set.join_next().await.is_some() {}
+    }
+}
+
+async fn run_task(task: Task, max_jitter: Duration, statuses: TaskStatusMap) {
+    let jitter_ms = u64::try_from(max_jitter.as_millis()).unwrap_or(u64::MAX);
+    loop {
+        let next = match task.schedule.upcoming(Utc).next() {
+            Some(next) => {
+                next + TimeDelta::milliseconds(rand::thread_rng().gen_range(0..=jitter_ms) as i64)
+            }
+            None => {
+                println!("Task {} has no upcoming runs", task.name);
+                return;
+            }
+        };
+        if let Some(status) = statuses.lock().await.get_mut(&task.name) {
+            status.next_run = Some(Duration::from_millis(next.timestamp_millis() as u64));
+        }
+        sleep((next - Utc::now()).to_std().unwrap_or_default()).await;
+
+        let started = SystemTime::now();
+        let res = (task.job)().await;
+        let mut statuses = statuses.lock().await;
+        if let Some(status) = statuses.get_mut(&task.name) {
+            status.runs += 1;
+            status.last_run = started.duration_since(SystemTime::UNIX_EPOCH).ok();
+            status.last_duration = started.elapsed().ok();
+            status.last_succeeded = Some(res.is_ok());
+            match res {
+                Ok(message) => status.last_message = message,
+                Err(err) => {
+                    println!("Task {} failed: {}", task.name, err);
+                    status.failures += 1;
+                    status.last_message = err;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SchedulerState {
+    pub admin: AdminState,
+    pub statuses: TaskStatusMap,
+}
+
+pub async fn get_tasks_json(
+    State(state): State<SchedulerState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<TaskStatus>>, (StatusCode, String)> {
+    state.admin.authorize(&headers)?;
+    let statuses = state.statuses.lock().await;
+    let mut tasks = statuses.values().cloned().collect::<Vec<TaskStatus>>();
+    tasks.sort_by(|t1, t2| t1.name.cmp(&t2.name));
+    Ok(Json(tasks))
+}
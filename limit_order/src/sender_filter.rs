@@ -0,0 +1,200 @@
+use ethers::{providers::Middleware, types::Address};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use crate::contracts_abi::{laminated_proxy::LaminatedProxy, laminator::ProxyPushedFilter};
+
+// Senders of an app, as APP=ADDRESS[,ADDRESS...].
+#[derive(Clone, Debug)]
+pub struct SenderList {
+    pub app: String,
+    pub senders: Vec<Address>,
+}
+
+impl FromStr for SenderList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((app, senders)) if !app.is_empty() && !senders.is_empty() => Ok(SenderList {
+                app: app.to_string(),
+                senders: senders
+                    .split(',')
+                    .map(|sender| {
+                        Address::from_str(sender)
+                            .map_err(|err| format!("invalid sender {}: {}", sender, err))
+                    })
+                    .collect::<Result<_, _>>()?,
+            }),
+            _ => Err(format!("expected APP=ADDRESS[,ADDRESS...], got \"{}\"", s)),
+        }
+    }
+}
+
+// Why an objective wasn't solved.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Rejection {
+    NotAllowed,
+    Denied,
+    // The owner of the proxy couldn't be read.
+    UnknownSender,
+}
+
+impl Rejection {
+    fn label(&self) -> &'static str {
+        match self {
+            Rejection::NotAllowed => "not_allowed",
+            Rejection::Denied => "denied",
+            Rejection::UnknownSender => "unknown_sender",
+        }
+    }
+}
+
+// Solves only the objectives of the allowed senders of an app, if the app has an
+// allowlist, and never the ones of its denied senders. The sender of an objective is the
+// owner of the proxy it was pushed to.
+#[derive(Default)]
+pub struct SenderFilter {
+    allowed: HashMap<String, HashSet<Address>>,
+    denied: HashMap<String, HashSet<Address>>,
+    // The owners of the proxies don't change, they are read once.
+    owners: Mutex<HashMap<Address, Address>>,
+    // Rejected objectives by app and reason.
+    rejected: Mutex<BTreeMap<(String, Rejection), u64>>,
+}
+
+impl SenderFilter {
+    pub fn new(allowed: Vec<SenderList>, denied: Vec<SenderList>) -> SenderFilter {
+        let by_app = |lists: Vec<SenderList>| {
+            let mut by_app: HashMap<String, HashSet<Address>> = HashMap::new();
+            for list in lists {
+                by_app.entry(list.app).or_default().extend(list.senders);
+            }
+            by_app
+        };
+        SenderFilter {
+            allowed: by_app(allowed),
+            denied: by_app(denied),
+            ..Default::default()
+        }
+    }
+
+    fn rejection(&self, app: &str, sender: Address) -> Option<Rejection> {
+        if self
+            .denied
+            .get(app)
+            .is_some_and(|denied| denied.contains(&sender))
+        {
+            return Some(Rejection::Denied);
+        }
+        match self.allowed.get(app) {
+            Some(allowed) if !allowed.contains(&sender) => Some(Rejection::NotAllowed),
+            _ => None,
+        }
+    }
+
+    // Checks the sender of the objective, counting the rejected ones.
+    pub async fn check<M: Middleware>(
+        &self,
+        middleware: Arc<M>,
+        app: &str,
+        event: &ProxyPushedFilter,
+    ) -> Result<(), (Rejection, String)> {
+        if !self.allowed.contains_key(app) && !self.denied.contains_key(app) {
+            return Ok(());
+        }
+        let res = match self.owner(middleware, event.proxy_address).await {
+            Ok(sender) => match self.rejection(app, sender) {
+                Some(Rejection::Denied) => Err((
+                    Rejection::Denied,
+                    format!("The sender {:?} is denied", sender),
+                )),
+                Some(rejection) => {
+                    Err((rejection, format!("The sender {:?} is not allowed", sender)))
+                }
+                None => Ok(()),
+            },
+            Err(err) => Err((
+                Rejection::UnknownSender,
+                format!("Error reading the owner of the proxy: {}", err),
+            )),
+        };
+        if let Err((rejection, _)) = &res {
+            *self
+                .rejected
+                .lock()
+                .unwrap()
+                .entry((app.to_string(), *rejection))
+                .or_default() += 1;
+        }
+        res
+    }
+
+    async fn owner<M: Middleware>(
+        &self,
+        middleware: Arc<M>,
+        proxy: Address,
+    ) -> Result<Address, String> {
+        if let Some(owner) = self.owners.lock().unwrap().get(&proxy) {
+            return Ok(*owner);
+        }
+        let owner = LaminatedProxy::new(proxy, middleware)
+            .owner()
+            .call()
+            .await
+            .map_err(|err| err.to_string())?;
+        self.owners.lock().unwrap().insert(proxy, owner);
+        Ok(owner)
+    }
+
+    // The rejected objectives in the Prometheus text format.
+    pub fn write_metrics(&self, body: &mut String) {
+        let _ = writeln!(
+            body,
+            "# HELP solver_rejected_senders_total Objectives not solved because of their sender."
+        );
+        let _ = writeln!(body, "# TYPE solver_rejected_senders_total counter");
+        for ((app, rejection), count) in self.rejected.lock().unwrap().iter() {
+            let _ = writeln!(
+                body,
+                "solver_rejected_senders_total{{app=\"{}\",reason=\"{}\"}} {}",
+                app,
+                rejection.label(),
+                count
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn denied_senders_win_over_allowed_ones() {
+        let alice = Address::repeat_byte(0xa1);
+        let bob = Address::repeat_byte(0xb0);
+        let filter = SenderFilter::new(
+            vec![SenderList::from_str(&format!("APP={:?},{:?}", alice, bob))
+                .ok()
+                .unwrap()],
+            vec![SenderList::from_str(&format!("APP={:?}", bob))
+                .ok()
+                .unwrap()],
+        );
+        assert_eq!(filter.rejection("APP", alice), None);
+        assert_eq!(filter.rejection("APP", bob), Some(Rejection::Denied));
+        assert_eq!(
+            filter.rejection("APP", Address::zero()),
+            Some(Rejection::NotAllowed)
+        );
+        // Apps without an allowlist take all the senders.
+        assert_eq!(filter.rejection("OTHER", Address::zero()), None);
+        assert!(SenderList::from_str("APP=").is_err());
+        assert!(SenderList::from_str("APP=0x12").is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use std::future::pending;
+use tokio::sync::mpsc::{error::TrySendError, Receiver, Sender};
+
+#[cfg(feature = "webhooks")]
+use crate::http_client::http_client;
+use crate::{admin::AdminState, contracts_abi::laminator::ProxyPushedFilter};
+
+// Mirrors objectives received from the chain to a secondary (e.g. staging) solver, and
+// feeds objectives mirrored by another solver into the listener.
+pub struct Shadow {
+    // Objectives to mirror, sent out by run_shadow_send.
+    mirror_tx: Option<Sender<ProxyPushedFilter>>,
+
+    // Objectives mirrored to this solver.
+    incoming_rx: Option<Receiver<ProxyPushedFilter>>,
+}
+
+impl Shadow {
+    pub fn new(
+        mirror_tx: Option<Sender<ProxyPushedFilter>>,
+        incoming_rx: Option<Receiver<ProxyPushedFilter>>,
+    ) -> Shadow {
+        Shadow {
+            mirror_tx,
+            incoming_rx,
+        }
+    }
+
+    // Queues the objective for mirroring, never blocks the listener. The listener gives
+    // it with the params redacted, the proxy and the calls are kept for the secondary
+    // solver to simulate the pull.
+    pub fn mirror(&self, event: &ProxyPushedFilter) {
+        if let Some(mirror_tx) = &self.mirror_tx {
+            match mirror_tx.try_send(event.clone()) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    println!(
+                        "Shadow queue is full, objective {} isn't mirrored",
+                        event.sequence_number
+                    );
+                }
+                Err(TrySendError::Closed(_)) => {
+                    println!("Shadow sender is stopped");
+                }
+            }
+        }
+    }
+
+    // Waits for the next mirrored objective, forever if mirrored traffic isn't accepted.
+    pub async fn next_incoming(&mut self) -> Option<ProxyPushedFilter> {
+        match &mut self.incoming_rx {
+            Some(incoming_rx) => incoming_rx.recv().await,
+            None => pending().await,
+        }
+    }
+}
+
+// The objectives are sent with the admin token of the secondary solver.
+#[cfg(feature = "webhooks")]
+pub async fn run_shadow_send(rx: &mut Receiver<ProxyPushedFilter>, url: String, token: String) {
+    let shadow_url = format!("{}/shadow/objective", url.trim_end_matches('/'));
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(err) => {
+            println!("{}, the objectives aren't mirrored", err);
+            return;
+        }
+    };
+    while let Some(event) = rx.recv().await {
+        match client
+            .post(shadow_url.as_str())
+            .bearer_auth(&token)
+            .json(&event)
+            .send()
+            .await
+        {
+            Ok(response) => {
+                if !response.status().is_success() {
+                    println!(
+                        "Shadow objective {} rejected: {}",
+                        event.sequence_number,
+                        response.status()
+                    );
+                }
+            }
+            Err(err) => {
+                println!(
+                    "Error mirroring objective {}: {}",
+                    event.sequence_number, err
+                );
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ShadowState {
+    pub admin: AdminState,
+    pub incoming_tx: Sender<ProxyPushedFilter>,
+}
+
+pub async fn receive_shadow_objective(
+    State(state): State<ShadowState>,
+    headers: HeaderMap,
+    Json(event): Json<ProxyPushedFilter>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state.admin.authorize(&headers)?;
+    match state.incoming_tx.try_send(event) {
+        Ok(()) => Ok(StatusCode::ACCEPTED),
+        Err(_) => Ok(StatusCode::SERVICE_UNAVAILABLE),
+    }
+}
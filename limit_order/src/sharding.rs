@@ -0,0 +1,242 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use ethers::{
+    abi::{encode, Token},
+    types::U256,
+};
+use keccak_hash::keccak;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use crate::{admin::AdminState, contracts_abi::laminator::ProxyPushedFilter};
+
+// Shards of the objectives an instance executes, as FIRST[-LAST]/COUNT, e.g. 2/4 or
+// 0-1/4 for the first half.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ShardRange {
+    pub first: u32,
+    pub last: u32,
+    pub count: u32,
+}
+
+impl FromStr for ShardRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (shards, count) = match s.split_once('/') {
+            Some(parts) => parts,
+            None => return Err(format!("expected FIRST[-LAST]/COUNT, got \"{}\"", s)),
+        };
+        let number = |value: &str| {
+            value
+                .parse::<u32>()
+                .map_err(|err| format!("invalid shard {}: {}", value, err))
+        };
+        let (first, last) = match shards.split_once('-') {
+            Some((first, last)) => (number(first)?, number(last)?),
+            None => (number(shards)?, number(shards)?),
+        };
+        let count = number(count)?;
+        if first > last || last >= count {
+            return Err(format!("the shards {} aren't within 0-{}", shards, count));
+        }
+        Ok(ShardRange { first, last, count })
+    }
+}
+
+impl fmt::Display for ShardRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}/{}", self.first, self.count)
+        } else {
+            write!(f, "{}-{}/{}", self.first, self.last, self.count)
+        }
+    }
+}
+
+// The shard of an objective, from the hash of its app, proxy and sequence number so that
+// all the instances agree on it.
+pub fn shard(objective: &ProxyPushedFilter, count: u32) -> u32 {
+    let hash = keccak(encode(&[
+        Token::FixedBytes(objective.selector.to_vec()),
+        Token::Address(objective.proxy_address),
+        Token::Uint(objective.sequence_number),
+    ]));
+    (U256::from_big_endian(hash.as_bytes()) % count).as_u32()
+}
+
+// Executes only the objectives of the assigned shards, the instances sharing the events
+// are given disjoint ranges. All the objectives are executed if no range is assigned.
+#[derive(Default)]
+pub struct Sharding {
+    range: Mutex<Option<ShardRange>>,
+    // Objectives of the other shards by app.
+    skipped: Mutex<BTreeMap<String, u64>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ShardStatus {
+    pub range: Option<String>,
+    pub skipped: BTreeMap<String, u64>,
+}
+
+impl Sharding {
+    pub fn new(range: Option<ShardRange>) -> Sharding {
+        Sharding {
+            range: Mutex::new(range),
+            ..Default::default()
+        }
+    }
+
+    pub fn contains(&self, objective: &ProxyPushedFilter) -> bool {
+        match *self.range.lock().unwrap() {
+            Some(range) => (range.first..=range.last).contains(&shard(objective, range.count)),
+            None => true,
+        }
+    }
+
+    // Whether the objective is executed here, counting the ones of the other shards.
+    pub fn check(&self, app: &str, objective: &ProxyPushedFilter) -> bool {
+        let contained = self.contains(objective);
+        if !contained {
+            *self
+                .skipped
+                .lock()
+                .unwrap()
+                .entry(app.to_string())
+                .or_default() += 1;
+        }
+        contained
+    }
+
+    // The running executors are kept, whatever their shard.
+    pub fn assign(&self, range: Option<ShardRange>) {
+        *self.range.lock().unwrap() = range;
+    }
+
+    pub fn status(&self) -> ShardStatus {
+        ShardStatus {
+            range: self.range.lock().unwrap().map(|range| range.to_string()),
+            skipped: self.skipped.lock().unwrap().clone(),
+        }
+    }
+
+    // The assigned shards and the skipped objectives in the Prometheus text format.
+    pub fn write_metrics(&self, body: &mut String) {
+        let _ = writeln!(
+            body,
+            "# HELP solver_shard_range Shards of the objectives executed by the instance."
+        );
+        let _ = writeln!(body, "# TYPE solver_shard_range gauge");
+        if let Some(range) = *self.range.lock().unwrap() {
+            let _ = writeln!(
+                body,
+                "solver_shard_range{{first=\"{}\",last=\"{}\",count=\"{}\"}} 1",
+                range.first, range.last, range.count
+            );
+        }
+        let _ = writeln!(
+            body,
+            "# HELP solver_shard_skipped_total Objectives left to the instances of their shard."
+        );
+        let _ = writeln!(body, "# TYPE solver_shard_skipped_total counter");
+        for (app, count) in self.skipped.lock().unwrap().iter() {
+            let _ = writeln!(
+                body,
+                "solver_shard_skipped_total{{app=\"{}\"}} {}",
+                app, count
+            );
+        }
+    }
+}
+
+pub async fn get_shard_json(sharding: State<Arc<Sharding>>) -> Json<ShardStatus> {
+    Json(sharding.status())
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AssignRequest {
+    // All the shards if not given.
+    pub range: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct ShardingState {
+    pub admin: AdminState,
+    pub sharding: Arc<Sharding>,
+}
+
+// Assigns the shards of the instance, e.g. by a coordination service rebalancing the
+// instances. The body is optional.
+pub async fn assign_shard(
+    State(state): State<ShardingState>,
+    headers: HeaderMap,
+    request: Option<Json<AssignRequest>>,
+) -> Result<Json<ShardStatus>, (StatusCode, String)> {
+    state.admin.authorize(&headers)?;
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let range = request
+        .range
+        .as_deref()
+        .map(ShardRange::from_str)
+        .transpose()
+        .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    state.sharding.assign(range);
+    println!(
+        "Shards assigned: {}",
+        range.map_or("all".to_string(), |range| range.to_string())
+    );
+    Ok(Json(state.sharding.status()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Address;
+
+    #[test]
+    fn objectives_are_split_between_the_shards() {
+        assert_eq!(
+            ShardRange::from_str("0-1/4").ok().unwrap(),
+            ShardRange {
+                first: 0,
+                last: 1,
+                count: 4
+            }
+        );
+        assert_eq!(ShardRange::from_str("2/4").ok().unwrap().to_string(), "2/4");
+        assert!(ShardRange::from_str("4/4").is_err());
+        assert!(ShardRange::from_str("2-1/4").is_err());
+        assert!(ShardRange::from_str("1").is_err());
+        let halves = [
+            Sharding::new(Some(ShardRange::from_str("0-1/4").ok().unwrap())),
+            Sharding::new(Some(ShardRange::from_str("2-3/4").ok().unwrap())),
+        ];
+        for sequence_number in 0..20u64 {
+            let objective = ProxyPushedFilter {
+                proxy_address: Address::repeat_byte(0x11),
+                sequence_number: sequence_number.into(),
+                ..Default::default()
+            };
+            let owners = halves
+                .iter()
+                .filter(|sharding| sharding.check("APP", &objective))
+                .count();
+            assert_eq!(owners, 1);
+        }
+        let skipped = halves
+            .iter()
+            .map(|sharding| sharding.status().skipped.get("APP").copied().unwrap_or(0))
+            .sum::<u64>();
+        assert_eq!(skipped, 20);
+        assert!(Sharding::default().contains(&ProxyPushedFilter::default()));
+    }
+}
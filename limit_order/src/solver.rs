@@ -1,15 +1,32 @@
 use ethers::{
     abi::AbiEncode,
-    types::{Address, H256},
+    types::{Address, H256, U256},
 };
+#[cfg(feature = "plugins")]
+use ethers::{providers::Middleware, types::transaction::eip2718::TypedTransaction};
 use keccak_hash::keccak;
-use std::{
-    collections::HashMap,
-    fmt::{self, Display},
-    sync::Arc,
-    time::Duration,
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
+use thiserror::Error;
+use tokio::{
+    sync::{watch, Mutex},
+    time::sleep,
+};
+
+#[cfg(feature = "aggregator")]
+use crate::aggregator::Aggregator;
+use crate::{
+    accounting::ObjectiveAccounting,
+    adaptive_tick::AdaptiveTick,
+    bundle_composer::BundleComposer,
+    execution_log::ExecutionLog,
+    executor_queue::ExecutorQueue,
+    flash_loan::FlashLoanMarket,
+    price_feed::PriceFeed,
+    submission::{BundleStatus, SubmissionPolicy},
+    token_metadata::TokenMetadataCache,
+    wallet_pool::{WalletLease, WalletPool},
 };
-use tokio::sync::Mutex;
 
 #[derive(Clone)]
 pub struct SolverParams<M>
@@ -21,31 +38,144 @@ where
     pub extra_contract_addresses: HashMap<String, Address>,
     pub middleware: Arc<M>,
     pub guard: Arc<Mutex<bool>>,
+    pub submission_policy: Arc<SubmissionPolicy>,
+    // Tick bounds of the price triggered objectives, the executor tick if not set.
+    pub adaptive_tick: Option<AdaptiveTick>,
+    // Shared pool prices, each solver reads them on its own if not set.
+    pub price_feed: Option<Arc<PriceFeed<M>>>,
+    // Decimals and symbols of the tokens, read once for all the solvers.
+    pub token_metadata: Arc<TokenMetadataCache>,
+    // New block numbers, the solver steps once per block if set and once per tick
+    // otherwise.
+    pub block_ticks: Option<watch::Receiver<u64>>,
+    // The solvers record what they observe and build for each objective if set.
+    pub execution_log: Option<Arc<ExecutionLog>>,
+    // Solver wallets the executors are given one of, the solver address, middleware
+    // and guard above are used if not set.
+    pub wallet_pool: Option<Arc<WalletPool<M>>>,
+    // The executor tick of the app.
+    pub tick: Duration,
+    // Time limit of the objectives not giving one, they have to give it if not set.
+    pub default_time_limit: Option<Duration>,
+    // Limits the running executors of the app, within the global limit.
+    pub app_queue: Option<Arc<ExecutorQueue>>,
+    // Wei the failed final transactions of an objective may spend, the objectives may
+    // lower it. Unlimited if not set.
+    pub gas_budget: Option<U256>,
+    // Blocks a successful final transaction has to be buried under before it's final, it's
+    // checked again after them in case a reorg dropped it. Final at the receipt if 0.
+    pub confirmations: u64,
+    // Quotes the swaps of the aggregator strategy, it isn't available if not set.
+    #[cfg(feature = "aggregator")]
+    pub aggregator: Option<Arc<Aggregator>>,
+    // Flash loan providers of specific pairs, the others borrow from FLASH_LOAN.
+    pub flash_loan_markets: Vec<FlashLoanMarket>,
+    // Executes the complementary limit orders together, each order is executed alone if
+    // not set.
+    pub bundle_composer: Option<Arc<BundleComposer>>,
+}
+
+impl<M: Clone> SolverParams<M> {
+    // The params of an executor, with a wallet of the pool if there is one. The wallet
+    // is held until the lease is dropped.
+    pub fn assign_wallet(self) -> (SolverParams<M>, Option<WalletLease<M>>) {
+        let lease = match &self.wallet_pool {
+            Some(wallet_pool) => wallet_pool.assign(),
+            None => return (self, None),
+        };
+        let wallet = lease.wallet();
+        let params = SolverParams {
+            solver_address: wallet.address,
+            middleware: wallet.middleware.clone(),
+            guard: wallet.guard.clone(),
+            ..self
+        };
+        (params, Some(lease))
+    }
 }
 
 pub struct SolverResponse {
     pub succeeded: bool,
     pub message: String,
+    // The values the step was decided on, e.g. the current and the desired price.
+    pub decision: Vec<DecisionInput>,
+}
+
+// A value a decision was taken on, typed for the users auditing the decisions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum DecisionValue {
+    // A price in the decimal format of the orders.
+    Price(String),
+    Uint(U256),
+    Bool(bool),
+    Address(Address),
+    Text(String),
+}
+
+impl fmt::Display for DecisionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecisionValue::Price(price) => write!(f, "{}", price),
+            DecisionValue::Uint(value) => write!(f, "{}", value),
+            DecisionValue::Bool(value) => write!(f, "{}", value),
+            DecisionValue::Address(address) => write!(f, "{:?}", address),
+            DecisionValue::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DecisionInput {
+    pub name: String,
+    #[serde(flatten)]
+    pub value: DecisionValue,
+}
+
+impl DecisionInput {
+    pub fn new(name: &str, value: DecisionValue) -> DecisionInput {
+        DecisionInput {
+            name: name.to_string(),
+            value,
+        }
+    }
 }
 
+#[derive(Debug, Error)]
 pub enum SolverError {
+    #[error("UnknownSelector: {0}")]
     MisleadingSelector(H256),
+    #[error("Parameter error, \"{0}\"")]
     ParamError(String),
+    #[error("Execution error, {0}")]
     ExecError(String),
+    // The chain node couldn't be reached or didn't answer, e.g. timed out.
+    #[error("RPC error, {0}")]
+    RpcError(String),
+    // The final transaction didn't land, e.g. reverted after the price moved.
+    #[error("Transaction error, {0}")]
+    TxError(String),
 }
 
-impl Display for SolverError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
+impl SolverError {
+    // Whether the call may succeed if repeated later. Bad parameters and failed
+    // executions don't change by retrying, the objective is given up on them.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            SolverError::RpcError(_) | SolverError::TxError(_) => true,
+            SolverError::MisleadingSelector(_)
+            | SolverError::ParamError(_)
+            | SolverError::ExecError(_) => false,
+        }
+    }
+
+    // Prefixes the message with the context of the error, keeping its kind.
+    pub fn context(self, context: &str) -> SolverError {
         match self {
-            SolverError::MisleadingSelector(s) => {
-                write!(f, "UnknownSelector: {}", s)
-            }
-            SolverError::ParamError(s) => {
-                write!(f, "Parameter error, \"{}\"", s)
-            }
-            SolverError::ExecError(s) => {
-                write!(f, "Execution error, {}", s)
-            }
+            SolverError::ExecError(s) => SolverError::ExecError(format!("{}: {}", context, s)),
+            SolverError::RpcError(s) => SolverError::RpcError(format!("{}: {}", context, s)),
+            SolverError::TxError(s) => SolverError::TxError(format!("{}: {}", context, s)),
+            err => err,
         }
     }
 }
@@ -54,9 +184,68 @@ pub trait Solver {
     fn app(&self) -> String;
     fn time_limit(&self) -> Result<Duration, parse_duration::parse::Error>;
     async fn exec_solver_step(&self) -> Result<SolverResponse, SolverError>;
+    // Checks balances and allowances the final transaction depends on, so that a
+    // transaction that would revert isn't sent.
+    async fn check_preconditions(&self) -> Result<SolverResponse, SolverError>;
+    // Checks that the gas cost of the final transaction is covered by the tip and
+    // the surplus of the objective.
+    async fn check_profitability(&self) -> Result<SolverResponse, SolverError>;
     async fn final_exec(&self) -> Result<SolverResponse, SolverError>;
+    // The reason the objective needs no execution anymore, if another solver executed
+    // it first.
+    async fn solved_externally(&self) -> Result<Option<String>, SolverError> {
+        Ok(None)
+    }
+    // Hash of the last final transaction with a receipt, if any.
+    fn tx_hash(&self) -> Option<H256> {
+        None
+    }
+    // What the final transactions sent so far cost and earned, None if none was sent.
+    fn accounting(&self) -> Option<ObjectiveAccounting> {
+        None
+    }
+    // Wei the failed final transactions of the objective may spend, unlimited if None.
+    fn gas_budget(&self) -> Option<U256> {
+        None
+    }
+    // Whether the bundle of the last final transaction landed, None if it wasn't sent
+    // in a bundle.
+    fn bundle_status(&self) -> Option<BundleStatus> {
+        None
+    }
+    // The amount of the objective in whole tokens, e.g. 1.5 USDC, if the solver knows it.
+    fn order_amount(&self) -> Option<String> {
+        None
+    }
+    // The interval before the next step, given the executor tick.
+    async fn next_tick(&self, tick: Duration) -> Duration {
+        tick
+    }
+    // Waits for the next step, which may come before the next tick if the solver is
+    // notified of a change it waits for.
+    async fn wait_next_step(&self, tick: Duration) {
+        sleep(self.next_tick(tick).await).await
+    }
 }
 
 pub fn selector(app: String) -> H256 {
     keccak(app.as_str().encode()).as_fixed_bytes().into()
 }
+
+// The final execution in the dry run mode, the transaction is simulated instead of sent.
+#[cfg(feature = "plugins")]
+pub async fn dry_run<M: Middleware>(
+    submission_policy: &SubmissionPolicy,
+    middleware: &M,
+    tx: TypedTransaction,
+) -> Result<SolverResponse, SolverError> {
+    submission_policy
+        .simulate(middleware, tx)
+        .await
+        .map(|message| SolverResponse {
+            succeeded: true,
+            message,
+            decision: Vec::new(),
+        })
+        .map_err(|err| SolverError::TxError(format!("Final execution error: {}", err)))
+}
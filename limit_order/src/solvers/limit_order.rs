@@ -1,23 +1,42 @@
+#[cfg(feature = "aggregator")]
+use crate::aggregator::{Aggregator, AggregatorQuote};
 use crate::{
+    accounting::ObjectiveAccounting,
+    adaptive_tick::AdaptiveTick,
+    bundle_composer::{self, BundleComposer, Composition},
+    call_plan::CallPlan,
+    chain_client::{ChainClient, EthersClient, Execution},
     contracts_abi::{
         call_breaker::{CallBreaker, CallObject, ReturnObject},
         ierc20::{ApproveCall, IERC20Calls},
         laminated_proxy::{LaminatedProxyCalls, PullCall},
         ProxyPushedFilter,
     },
-    solver::{self, Solver, SolverError, SolverParams, SolverResponse},
+    encoded_data::{hint_indices, AssociatedData},
+    execution_log::{ExecutionRecord, ExecutionTrace, Stage},
+    flash_loan::{FlashLoanProvider, MockProvider, Provider},
+    param_schema::{self, ParamSchema, ParamType},
+    solver::{
+        self, DecisionInput, DecisionValue, Solver, SolverError, SolverParams, SolverResponse,
+    },
+    solvers::{
+        order_price,
+        uniswap_v3::{self, UniswapV3},
+    },
+    submission::BundleStatus,
 };
 use ethers::{
-    abi::{self, AbiEncode, Token},
-    core::abi::ethabi::ethereum_types::FromDecStrErr,
+    abi::{AbiDecode, AbiEncode},
     prelude::abigen,
     providers::Middleware,
-    types::{Address, Bytes, H160, U256}, utils::parse_units,
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, H256, U256},
 };
-use fixed_hash::rustc_hex::FromHexError;
 use parse_duration;
-use std::{str::FromStr, sync::Arc, time::Duration};
-use tokio::sync::Mutex;
+use std::{future::pending, str::FromStr, sync::Arc, time::Duration};
+use tokio::{
+    sync::{watch, Mutex},
+    time::{sleep, timeout},
+};
 
 abigen!(
     FlashLoan,
@@ -31,51 +50,259 @@ pub const APP_SELECTOR: &str = "FLASHLIQUIDITY.LIMITORDER";
 pub const FLASH_LOAN_NAME: &str = "FLASH_LOAN";
 pub const SWAP_POOL_NAME: &str = "SWAP_POOL";
 
-pub struct LimitOrderSolver<M> {
+// Liquidity provided to the pool from the flash loan, in whole tokens.
+const HARDCODED_TOKEN_0_LIQUIDITY: u64 = 1000;
+const HARDCODED_TOKEN_1_LIQUIDITY: u64 = 100;
+
+// Gas limit of the final transaction.
+const FINAL_EXEC_GAS: u64 = 10000000;
+
+// Direction of the order relative to the pool quote (the price of token 1 in token 0).
+// Buy orders take token 1 and trigger when the price drops to the threshold, sell orders
+// give token 1 and trigger when the price rises to the threshold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrderDirection {
+    Buy,
+    Sell,
+}
+
+impl FromStr for OrderDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "buy" => Ok(OrderDirection::Buy),
+            "sell" => Ok(OrderDirection::Sell),
+            _ => Err(format!("unknown direction \"{}\", expected buy or sell", s)),
+        }
+    }
+}
+
+// Kind of the order. Stop orders are sell orders that trigger when the price drops,
+// either to a fixed stop price or by a percentage from the peak seen while ticking.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrderType {
+    Limit,
+    StopLoss,
+    TrailingStop,
+}
+
+impl FromStr for OrderType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "limit" => Ok(OrderType::Limit),
+            "stop_loss" => Ok(OrderType::StopLoss),
+            "trailing_stop" => Ok(OrderType::TrailingStop),
+            _ => Err(format!(
+                "unknown order type \"{}\", expected limit, stop_loss or trailing_stop",
+                s
+            )),
+        }
+    }
+}
+
+// How the order is filled, given as the strategy parameter. The flash loan strategy
+// lends the pool the liquidity for the swap of the user, the direct strategy swaps on
+// the pool with the inventory the solver keeps at the call breaker. Both trade on the
+// mock DAI/WETH pool. The Uniswap V3 strategy swaps the inventory on the V3 pool of the
+// pair instead, and the aggregator strategy on the route quoted by the aggregator API.
+// Defaults to the flash loan if its contract is configured.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExecutionStrategy {
+    FlashLoan,
+    Direct,
+    UniswapV3,
+    #[cfg(feature = "aggregator")]
+    Aggregator,
+}
+
+// The strategies of the build, as listed in the parse errors.
+#[cfg(not(feature = "aggregator"))]
+const STRATEGIES: &str = "flash_loan, direct or uniswap_v3";
+#[cfg(feature = "aggregator")]
+const STRATEGIES: &str = "flash_loan, direct, uniswap_v3 or aggregator";
+
+impl FromStr for ExecutionStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "flash_loan" => Ok(ExecutionStrategy::FlashLoan),
+            "direct" => Ok(ExecutionStrategy::Direct),
+            "uniswap_v3" => Ok(ExecutionStrategy::UniswapV3),
+            #[cfg(feature = "aggregator")]
+            "aggregator" => Ok(ExecutionStrategy::Aggregator),
+            _ => Err(format!(
+                "unknown strategy \"{}\", expected {}",
+                s, STRATEGIES
+            )),
+        }
+    }
+}
+
+// A swap pool registered for a specific token pair, passed as TOKEN_A:TOKEN_B:POOL.
+#[derive(Clone, Debug)]
+pub struct PairPool {
+    pub token_a: Address,
+    pub token_b: Address,
+    pub pool: Address,
+}
+
+impl FromStr for PairPool {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        if parts.len() != 3 {
+            return Err(format!("expected TOKEN_A:TOKEN_B:POOL, got \"{}\"", s));
+        }
+        let parse = |name: &str, value: &str| {
+            Address::from_str(value).map_err(|err| format!("invalid {} address: {}", name, err))
+        };
+        Ok(PairPool {
+            token_a: parse("token", parts[0])?,
+            token_b: parse("token", parts[1])?,
+            pool: parse("pool", parts[2])?,
+        })
+    }
+}
+
+// The name of the extra contract address under which the pool for the given pair is
+// registered. The name doesn't depend on the order of the tokens.
+pub fn pair_pool_name(token_a: Address, token_b: Address) -> String {
+    let (first, second) = if token_a < token_b {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    };
+    format!("{}:{:?}:{:?}", SWAP_POOL_NAME, first, second)
+}
+
+pub struct LimitOrderSolver<M, C = EthersClient<M>> {
     // Solver address
-    _solver_address: Address, // To be used after fixing associated data
+    solver_address: Address,
 
     // Contract addresses to be called.
     proxy_address: Address,
     call_breaker_address: Address,
-    flash_loan_address: Address,
+    // Lends the liquidity of the flash loan strategy, not needed by the others.
+    flash_loan: Option<Provider>,
     swap_pool_address: Address,
+    // The quoter, router and pool of the Uniswap V3 strategy.
+    uniswap_v3: Option<UniswapV3>,
+    // Quotes the routes of the aggregator strategy.
+    #[cfg(feature = "aggregator")]
+    aggregator: Option<Arc<Aggregator>>,
 
     // Sequence number for laminator proxy call
     sequence_number: U256,
+    // The calls pulled from the proxy.
+    pushed_calls: Vec<CallObject>,
 
     // Contracts that are to be called.
     call_breaker_contract: CallBreaker<M>,
-    swap_pool_contract: SwapPool<M>,
+
+    // Chain reads and the final transaction submission.
+    chain: C,
 
     // Limit order params
-    pub give_token: Result<Address, FromHexError>,
-    pub take_token: Result<Address, FromHexError>,
-    amount: Result<U256, FromDecStrErr>,
-    buy_price: Result<U256, FromDecStrErr>,
-    slippage: Result<U256, FromDecStrErr>,
-    time_limit: Result<Duration, parse_duration::parse::Error>,
+    pub give_token: Address,
+    pub take_token: Address,
+    amount: U256,
+    order_type: OrderType,
+    direction: OrderDirection,
+    // Only the trigger of the order type is set.
+    buy_price: Option<U256>,
+    sell_price: Option<U256>,
+    stop_price: Option<U256>,
+    trailing_percent: Option<U256>,
+    slippage: U256,
+    time_limit: Duration,
+    strategy: ExecutionStrategy,
+    // Tip for the solver in wei, optional.
+    tip: U256,
+    // The lower of the gas budgets of the order and the app.
+    gas_budget: Option<U256>,
+    // Blocks the final transaction has to be buried under to succeed.
+    confirmations: u64,
+
+    // The highest price seen across ticks, for trailing stop orders.
+    peak_price: Mutex<Option<U256>>,
+
+    // The tick follows the distance of the price to the trigger if set.
+    adaptive_tick: Option<AdaptiveTick>,
+    next_tick: Mutex<Option<Duration>>,
+
+    // Changes of the pool price, the step is taken as soon as the price crosses the
+    // trigger of the last step if set.
+    price_updates: Option<watch::Receiver<Option<U256>>>,
+    last_trigger: Mutex<Option<(U256, bool)>>,
+    // The tokens of the pool once read, they don't change.
+    pool_pair: std::sync::Mutex<Option<(Address, Address)>>,
+
+    // Hash of the last final transaction with a receipt.
+    tx_hash: std::sync::Mutex<Option<H256>>,
+    accounting: std::sync::Mutex<Option<ObjectiveAccounting>>,
+    // Whether the bundle of the last final transaction landed, if it was sent in one.
+    bundle_status: std::sync::Mutex<Option<BundleStatus>>,
+    // The order amount in whole give tokens, once the token is read.
+    order_amount: std::sync::Mutex<Option<String>>,
+
+    // Records of the decisions on the objective.
+    trace: ExecutionTrace,
+    // The values observed in the current step.
+    step_inputs: std::sync::Mutex<Vec<DecisionInput>>,
 
     // Transaction guard
     guard: Arc<Mutex<bool>>,
+    // Executes the order together with a complementary one, if set.
+    bundle_composer: Option<Arc<BundleComposer>>,
 }
 
-// A clone of the FlashLoanData onchain structure.
-// Cannot be imported by abigen due to visibility restriction.
-// Should be synchronized with the definition in https://github.com/smart-transaction/stxn-contracts-core/blob/6dc025f53af60a0026aa6a4bb0f1d98a881d978a/src/CallBreakerTypes.sol
-struct FlashLoanData {
-    provider: Address,
-    amount_a: U256,
-    amount_b: U256,
+// Balances of the order tokens in the solver wallet before and after a transaction.
+type BalanceChanges = (Vec<(Address, U256)>, Vec<(Address, U256)>);
+
+// The liquidity amounts of token 0 and token 1 in base units, given a whole token of
+// each.
+fn liquidity_wei(token_0_unit: U256, token_1_unit: U256) -> (U256, U256) {
+    (
+        U256::from(HARDCODED_TOKEN_0_LIQUIDITY) * token_0_unit,
+        U256::from(HARDCODED_TOKEN_1_LIQUIDITY) * token_1_unit,
+    )
 }
 
-impl AbiEncode for FlashLoanData {
-    fn encode(self) -> Vec<u8> {
-        let mut res = self.provider.encode();
-        res.extend(self.amount_a.encode());
-        res.extend(self.amount_b.encode());
-        res
+// Estimates by how many basis points swapping amount_in on a constant product pool moves
+// its price of token 1 in token 0. None if the pool has no liquidity on either side, or
+// if the reserves are too large to be computed with.
+fn price_impact_bps(
+    reserve_0: U256,
+    reserve_1: U256,
+    amount_in: U256,
+    token_0_in: bool,
+) -> Option<U256> {
+    if reserve_0.is_zero() || reserve_1.is_zero() {
+        return None;
     }
+    let (new_reserve_0, new_reserve_1) = if token_0_in {
+        let new_reserve_0 = reserve_0.checked_add(amount_in)?;
+        let amount_out = order_price::checked_mul_div(reserve_1, amount_in, new_reserve_0)?;
+        (new_reserve_0, reserve_1.checked_sub(amount_out)?)
+    } else {
+        let new_reserve_1 = reserve_1.checked_add(amount_in)?;
+        let amount_out = order_price::checked_mul_div(reserve_0, amount_in, new_reserve_1)?;
+        (reserve_0.checked_sub(amount_out)?, new_reserve_1)
+    };
+    let scale = U256::exp10(18);
+    let price = order_price::checked_mul_div(reserve_0, scale, reserve_1)?;
+    let new_price = order_price::checked_mul_div(new_reserve_0, scale, new_reserve_1)?;
+    let deviation = if new_price > price {
+        new_price - price
+    } else {
+        price - new_price
+    };
+    order_price::checked_mul_div(deviation, 10000.into(), price)
 }
 
 impl<M: Middleware + Clone> LimitOrderSolver<M> {
@@ -83,308 +310,2148 @@ impl<M: Middleware + Clone> LimitOrderSolver<M> {
         event: ProxyPushedFilter,
         params: SolverParams<M>,
     ) -> Result<LimitOrderSolver<M>, SolverError> {
-        println!("Event received: {}", event);
+        let chain = EthersClient::new(
+            params.middleware.clone(),
+            params.submission_policy.clone(),
+            params.price_feed.clone(),
+            params.token_metadata.clone(),
+        );
+        LimitOrderSolver::with_chain_client(event, params, chain)
+    }
+}
+
+// The mev-time data of the limit orders. Which trigger is needed depends on the order
+// type, the solver checks it.
+fn param_schema() -> ParamSchema {
+    ParamSchema::new()
+        .required("give_token", ParamType::Address)
+        .required("take_token", ParamType::Address)
+        .required("amount", ParamType::uint())
+        .optional(
+            "order_type",
+            ParamType::Enum(&["limit", "stop_loss", "trailing_stop"]),
+        )
+        .optional("direction", ParamType::Enum(&["buy", "sell"]))
+        .optional("buy_price", ParamType::uint())
+        .optional("sell_price", ParamType::uint())
+        .optional("stop_price", ParamType::uint())
+        .optional("trailing_percent", ParamType::uint_within(1, 99))
+        .required("slippage", ParamType::uint_within(0, 100))
+        .optional("time_limit", ParamType::Duration)
+        .optional(
+            "strategy",
+            ParamType::Enum(&["flash_loan", "direct", "uniswap_v3", "aggregator"]),
+        )
+        // Fee tier of the Uniswap V3 pool.
+        .optional("fee", ParamType::Enum(&["100", "500", "3000", "10000"]))
+        .optional("tip", ParamType::uint())
+        // Wei the failed final transactions may spend, within the budget of the app.
+        .optional("gas_budget", ParamType::uint())
+}
+
+impl<M: Middleware + Clone, C: ChainClient> LimitOrderSolver<M, C> {
+    pub fn with_chain_client(
+        event: ProxyPushedFilter,
+        params: SolverParams<M>,
+        chain: C,
+    ) -> Result<LimitOrderSolver<M, C>, SolverError> {
         let flash_liquidity_selector = solver::selector(APP_SELECTOR.to_string());
         if flash_liquidity_selector != event.selector.into() {
             return Err(SolverError::MisleadingSelector(event.selector.into()));
         }
 
-        let flash_loan_address = params.extra_contract_addresses.get(FLASH_LOAN_NAME);
-        if let None = flash_loan_address {
-            return Err(SolverError::ParamError(
-                "missing address for contract FLASH_LOAN".to_string(),
-            ));
+        // Extract parameters, then check what the schema can't.
+        let order_params = param_schema().validate(&event.data_values)?;
+        let mut problems = Vec::new();
+        let order_type = order_params
+            .enum_as("order_type")?
+            .unwrap_or(OrderType::Limit);
+        let direction = match (order_type, order_params.enum_as("direction")?) {
+            (OrderType::Limit, direction) => direction.unwrap_or(OrderDirection::Buy),
+            // Stop orders always sell token 1.
+            (_, Some(OrderDirection::Buy)) => {
+                problems.push(format!(
+                    "direction: {:?} orders are sell orders",
+                    order_type
+                ));
+                OrderDirection::Sell
+            }
+            (_, _) => OrderDirection::Sell,
+        };
+        let trigger = match (order_type, direction) {
+            (OrderType::Limit, OrderDirection::Buy) => "buy_price",
+            (OrderType::Limit, OrderDirection::Sell) => "sell_price",
+            (OrderType::StopLoss, _) => "stop_price",
+            (OrderType::TrailingStop, _) => "trailing_percent",
+        };
+        if !order_params.contains(trigger) {
+            problems.push(format!("{} is missing", trigger));
         }
-        let swap_pool_address = params.extra_contract_addresses.get(SWAP_POOL_NAME);
-        if let None = swap_pool_address {
-            return Err(SolverError::ParamError(
-                "missing adsdress for contract SWAP_POOL".to_string(),
-            ));
+        // Orders without a time limit take the one of the app, if configured.
+        let time_limit = order_params
+            .duration("time_limit")
+            .or(params.default_time_limit);
+        if time_limit.is_none() {
+            problems.push("time_limit is missing".to_string());
+        }
+        // The pair borrows from its market if it has one, from the default mock otherwise.
+        let give_token = order_params.address("give_token").unwrap_or_default();
+        let take_token = order_params.address("take_token").unwrap_or_default();
+        let flash_loan = match params
+            .flash_loan_markets
+            .iter()
+            .find(|market| market.trades(give_token, take_token))
+        {
+            Some(market) => Some(market.provider.clone()),
+            None => params
+                .extra_contract_addresses
+                .get(FLASH_LOAN_NAME)
+                .map(|address| Provider::Mock(MockProvider { address: *address })),
+        };
+        // The flash loan is the default if its contract is configured.
+        let strategy = order_params
+            .enum_as("strategy")?
+            .unwrap_or(match flash_loan {
+                Some(_) => ExecutionStrategy::FlashLoan,
+                None => ExecutionStrategy::Direct,
+            });
+        match strategy {
+            ExecutionStrategy::FlashLoan if flash_loan.is_none() => {
+                problems.push("strategy: missing address for contract FLASH_LOAN".to_string())
+            }
+            // The pool only swaps token 0 for token 1.
+            ExecutionStrategy::Direct if direction != OrderDirection::Buy => {
+                problems.push("strategy: direct swaps only fill buy orders".to_string())
+            }
+            ExecutionStrategy::UniswapV3 => {
+                for name in [
+                    uniswap_v3::FACTORY_NAME,
+                    uniswap_v3::QUOTER_NAME,
+                    uniswap_v3::ROUTER_NAME,
+                ] {
+                    if !params.extra_contract_addresses.contains_key(name) {
+                        problems.push(format!("strategy: missing address for contract {}", name))
+                    }
+                }
+            }
+            #[cfg(feature = "aggregator")]
+            ExecutionStrategy::Aggregator if params.aggregator.is_none() => {
+                problems.push("strategy: no aggregator is configured".to_string())
+            }
+            _ => {}
         }
-        let mut ret = LimitOrderSolver {
+        param_schema::check(problems)?;
+
+        let contract = |name| {
+            params
+                .extra_contract_addresses
+                .get(name)
+                .copied()
+                .unwrap_or_default()
+        };
+        let uniswap_v3 = match strategy {
+            // The V3 pool of the pair and fee tier is derived from the factory.
+            ExecutionStrategy::UniswapV3 => {
+                let fee = order_params
+                    .enum_as("fee")?
+                    .unwrap_or(uniswap_v3::DEFAULT_FEE);
+                Some(UniswapV3 {
+                    quoter: contract(uniswap_v3::QUOTER_NAME),
+                    router: contract(uniswap_v3::ROUTER_NAME),
+                    pool: uniswap_v3::pool_address(
+                        contract(uniswap_v3::FACTORY_NAME),
+                        give_token,
+                        take_token,
+                        fee,
+                    ),
+                    fee,
+                })
+            }
+            _ => None,
+        };
+        // Resolve the pool trading the pair, the default pool is used for pairs that have
+        // no pool of their own.
+        let swap_pool_address = match (
+            uniswap_v3,
+            params
+                .extra_contract_addresses
+                .get(&pair_pool_name(give_token, take_token))
+                .or(params.extra_contract_addresses.get(SWAP_POOL_NAME)),
+        ) {
+            (Some(uniswap_v3), _) => uniswap_v3.pool,
+            (None, Some(swap_pool_address)) => *swap_pool_address,
+            (None, None) => {
+                return Err(SolverError::ParamError(format!(
+                    "missing swap pool for the pair {:?}/{:?}",
+                    give_token, take_token
+                )));
+            }
+        };
+        Ok(LimitOrderSolver {
             proxy_address: event.proxy_address,
             call_breaker_address: params.call_breaker_address,
-            _solver_address: params.solver_address,
-            flash_loan_address: *flash_loan_address.unwrap(),
-            swap_pool_address: *swap_pool_address.unwrap(),
+            solver_address: params.solver_address,
+            flash_loan,
+            swap_pool_address,
+            uniswap_v3,
+            #[cfg(feature = "aggregator")]
+            aggregator: params.aggregator.clone(),
             call_breaker_contract: CallBreaker::new(
                 params.call_breaker_address,
                 params.middleware.clone(),
             ),
-            swap_pool_contract: SwapPool::new(
-                *swap_pool_address.unwrap(),
-                params.middleware.clone(),
-            ),
+            // The price feed only follows the mock pools.
+            price_updates: match uniswap_v3 {
+                Some(_) => None,
+                None => chain.price_updates(swap_pool_address),
+            },
+            chain,
             sequence_number: event.sequence_number,
-            give_token: Result::Err(FromHexError::InvalidHexLength),
-            take_token: Result::Err(FromHexError::InvalidHexLength),
-            amount: Result::Err(FromDecStrErr::InvalidLength),
-            buy_price: Result::Err(FromDecStrErr::InvalidLength),
-            slippage: Result::Err(FromDecStrErr::InvalidLength),
-            time_limit: Result::Err(parse_duration::parse::Error::NoValueFound(
-                "Uninitialized value".to_string(),
-            )),
+            pushed_calls: event
+                .call_objs
+                .iter()
+                .map(|call| CallObject {
+                    amount: call.amount,
+                    addr: call.addr,
+                    gas: call.gas,
+                    callvalue: call.callvalue.clone(),
+                })
+                .collect(),
+            give_token,
+            take_token,
+            amount: order_params.uint("amount").unwrap_or_default(),
+            order_type,
+            direction,
+            buy_price: order_params.uint("buy_price"),
+            sell_price: order_params.uint("sell_price"),
+            stop_price: order_params.uint("stop_price"),
+            trailing_percent: order_params.uint("trailing_percent"),
+            slippage: order_params.uint("slippage").unwrap_or_default(),
+            time_limit: time_limit.unwrap_or_default(),
+            strategy,
+            tip: order_params.uint("tip").unwrap_or_default(),
+            gas_budget: match (order_params.uint("gas_budget"), params.gas_budget) {
+                (Some(order_budget), Some(app_budget)) => Some(order_budget.min(app_budget)),
+                (order_budget, app_budget) => order_budget.or(app_budget),
+            },
+            confirmations: params.confirmations,
+            peak_price: Mutex::new(None),
+            adaptive_tick: params.adaptive_tick,
+            next_tick: Mutex::new(None),
+            last_trigger: Mutex::new(None),
+            pool_pair: std::sync::Mutex::new(None),
+            tx_hash: std::sync::Mutex::new(None),
+            accounting: std::sync::Mutex::new(None),
+            bundle_status: std::sync::Mutex::new(None),
+            order_amount: std::sync::Mutex::new(None),
+            trace: ExecutionTrace::new(params.execution_log.clone(), APP_SELECTOR, &event),
+            step_inputs: std::sync::Mutex::new(Vec::new()),
             guard: params.guard.clone(),
+            bundle_composer: params.bundle_composer.clone(),
+        })
+    }
+}
+
+impl<M: Middleware, C: ChainClient> LimitOrderSolver<M, C> {
+    // Returns the price at which the order triggers and whether it triggers when the
+    // current price drops to it (otherwise when the price rises to it).
+    async fn trigger_price(&self, current_price: U256) -> Result<(U256, bool), SolverError> {
+        let unwrap_price = |price: Option<U256>, name: &str| {
+            price.ok_or(SolverError::ExecError(format!("missing {}", name)))
         };
-        // Extract parameters.
-        for ad in &event.data_values {
-            match ad.name.as_str() {
-                "give_token" => ret.give_token = H160::from_str(ad.value.as_str()),
-                "take_token" => ret.take_token = H160::from_str(ad.value.as_str()),
-                "amount" => ret.amount = U256::from_dec_str(ad.value.as_str()),
-                "buy_price" => ret.buy_price = U256::from_dec_str(ad.value.as_str()),
-                "slippage" => ret.slippage = U256::from_dec_str(ad.value.as_str()),
-                "time_limit" => ret.time_limit = parse_duration::parse(ad.value.as_str()),
-                &_ => {}
+        // The order prices are in whole quote tokens.
+        let order_price = |price: Option<U256>, name: &str| {
+            unwrap_price(price, name).map(order_price::from_whole)
+        };
+        match self.order_type {
+            OrderType::Limit => match self.direction {
+                OrderDirection::Buy => Ok((order_price(self.buy_price, "buy_price")?, true)),
+                OrderDirection::Sell => Ok((order_price(self.sell_price, "sell_price")?, false)),
+            },
+            OrderType::StopLoss => Ok((order_price(self.stop_price, "stop_price")?, true)),
+            OrderType::TrailingStop => {
+                let trailing_percent = unwrap_price(self.trailing_percent, "trailing_percent")?;
+                let mut peak_price = self.peak_price.lock().await;
+                let peak = match *peak_price {
+                    Some(peak) if peak >= current_price => peak,
+                    _ => current_price,
+                };
+                *peak_price = Some(peak);
+                Ok((peak * (U256::from(100) - trailing_percent) / 100, true))
             }
         }
-        // Check that all parameters are successfully extracted.
-        if let Err(err) = ret.give_token {
-            return Err(SolverError::ParamError(format!(
-                "Error in the parameter give_token: {}",
-                err
-            )));
-        }
-        if let Err(err) = ret.take_token {
-            return Err(SolverError::ParamError(format!(
-                "Error in the parameter take_token: {}",
-                err
-            )));
-        }
-        if let Err(err) = ret.amount {
-            return Err(SolverError::ParamError(format!(
-                "Error in the parameter amount: {}",
-                err
-            )));
-        }
-        if let Err(err) = ret.buy_price {
-            return Err(SolverError::ParamError(format!(
-                "Error in the parameter buy_price: {}",
-                err
-            )));
-        }
-        if let Err(err) = ret.slippage {
-            return Err(SolverError::ParamError(format!(
-                "Error in the parameter slippage: {}",
-                err
-            )));
-        }
-        if let Err(err) = ret.time_limit {
-            return Err(SolverError::ParamError(format!(
-                "Error in the parameter time_limit: {}",
-                err
-            )));
-        }
-        Ok(ret)
     }
-}
 
-impl<M: Middleware> Solver for LimitOrderSolver<M> {
-    fn app(&self) -> String {
-        return APP_SELECTOR.to_string();
+    // Returns the order tokens as (token 0, token 1) of the pool, failing if the pool
+    // doesn't trade the order pair.
+    async fn pool_tokens(&self) -> Result<(Address, Address), SolverError> {
+        let give_token = self.give_token;
+        let take_token = self.take_token;
+        // The V3 pool is derived from the order pair, the price is quoted in the order
+        // direction whichever token the pool sorts first.
+        if self.uniswap_v3.is_some() {
+            return Ok(match self.direction {
+                OrderDirection::Buy => (give_token, take_token),
+                OrderDirection::Sell => (take_token, give_token),
+            });
+        }
+        let pool_pair = *self.pool_pair.lock().unwrap();
+        let (token_0, token_1) = match pool_pair {
+            Some(pool_pair) => pool_pair,
+            None => self.chain.pool_tokens(self.swap_pool_address).await?,
+        };
+        if (token_0, token_1) == (give_token, take_token)
+            || (token_0, token_1) == (take_token, give_token)
+        {
+            *self.pool_pair.lock().unwrap() = Some((token_0, token_1));
+            Ok((token_0, token_1))
+        } else {
+            Err(SolverError::ParamError(format!(
+                "the pool {:?} trades {:?}/{:?}, not {:?}/{:?}",
+                self.swap_pool_address, token_0, token_1, give_token, take_token
+            )))
+        }
     }
 
-    fn time_limit(&self) -> Result<Duration, parse_duration::parse::Error> {
-        self.time_limit.clone()
+    // The flash loaned liquidity of the pool tokens in base units.
+    async fn pool_liquidity(
+        &self,
+        token_0: Address,
+        token_1: Address,
+    ) -> Result<(U256, U256), SolverError> {
+        let token_0_unit = self.chain.token_metadata(token_0).await?.unit();
+        let token_1_unit = self.chain.token_metadata(token_1).await?.unit();
+        Ok(liquidity_wei(token_0_unit, token_1_unit))
     }
 
-    async fn exec_solver_step(&self) -> Result<SolverResponse, SolverError> {
-        if let Err(err) = &self.amount {
-            return Err(SolverError::ExecError(err.to_string()));
-        }
-        if let Err(err) = &self.buy_price {
-            return Err(SolverError::ExecError(err.to_string()));
+    // The token the order prices: buy orders take it, sell orders give it.
+    fn asset(&self) -> Address {
+        match self.direction {
+            OrderDirection::Buy => self.take_token,
+            OrderDirection::Sell => self.give_token,
         }
-        // Check the price
-        match self.swap_pool_contract.get_price_of_weth().call().await {
-            Ok(current_price) => {
-                let desired_price = *self.buy_price.as_ref().ok().unwrap();
-                if current_price > desired_price {
-                    return Ok(SolverResponse {
-                        succeeded: false,
-                        message: format!(
-                            "The current price {} is higher than the desired {}",
-                            current_price, desired_price
-                        ),
-                    });
-                }
+    }
+
+    // Reads ahead what the first step needs, for the objective of a pending push. With
+    // the price feed, the pool price is cached and kept fresh while the solver lives.
+    pub async fn prewarm(&self) -> Result<U256, SolverError> {
+        self.current_price().await
+    }
+
+    // The price of the order asset in the quote token, see order_price.
+    async fn current_price(&self) -> Result<U256, SolverError> {
+        let uniswap_v3 = match self.uniswap_v3 {
+            Some(uniswap_v3) => uniswap_v3,
+            None => {
+                let (_, token_1) = self.pool_tokens().await?;
+                let pool_price = self.chain.price_of_weth(self.swap_pool_address).await?;
+                return Ok(order_price::from_pool(pool_price, token_1 == self.asset()));
             }
-            Err(err) => {
-                return Err(SolverError::ExecError(err.to_string()));
+        };
+        // The V3 tokens are (quote, asset), the quote of a whole asset token.
+        let (quote_token, asset) = self.pool_tokens().await?;
+        let quote_unit = self.chain.token_metadata(quote_token).await?.unit();
+        let asset_unit = self.chain.token_metadata(asset).await?.unit();
+        let (amount_out, _) = self
+            .quote(uniswap_v3, asset, quote_token, asset_unit)
+            .await?;
+        Ok(order_price::from_quote(amount_out, quote_unit))
+    }
+
+    // The amount out of the swap on the V3 pool and the sqrt price after it.
+    async fn quote(
+        &self,
+        uniswap_v3: UniswapV3,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+    ) -> Result<(U256, U256), SolverError> {
+        let output = self
+            .chain
+            .simulate_call(
+                self.call_breaker_address,
+                &uniswap_v3.quote_call(token_in, token_out, amount_in),
+            )
+            .await
+            .map_err(|err| err.context("Error quoting the swap"))?;
+        match (
+            uniswap_v3::output_uint(&output, 0),
+            uniswap_v3::output_uint(&output, 1),
+        ) {
+            (Some(amount_out), Some(sqrt_price_after)) => Ok((amount_out, sqrt_price_after)),
+            _ => Err(SolverError::ExecError(format!(
+                "the quoter {:?} returned no quote for the pool {:?}",
+                uniswap_v3.quoter, uniswap_v3.pool
+            ))),
+        }
+    }
+
+    // The current sqrt price of the V3 pool.
+    async fn sqrt_price(&self, uniswap_v3: UniswapV3) -> Result<U256, SolverError> {
+        let output = self
+            .chain
+            .simulate_call(self.call_breaker_address, &uniswap_v3.slot0_call())
+            .await
+            .map_err(|err| err.context("Error reading the pool price"))?;
+        uniswap_v3::output_uint(&output, 0).ok_or(SolverError::ExecError(format!(
+            "the pool {:?} returned no price",
+            uniswap_v3.pool
+        )))
+    }
+
+    // Simulates the swap of the order against the pool reserves, returns why the on-chain
+    // slippage check would fail if it would.
+    async fn slippage_violation(&self) -> Result<Option<String>, SolverError> {
+        if let Some(uniswap_v3) = self.uniswap_v3 {
+            return self.quoted_slippage_violation(uniswap_v3).await;
+        }
+        #[cfg(feature = "aggregator")]
+        if let Some(aggregator) = self.aggregator_strategy() {
+            let quote = self.aggregator_quote(aggregator).await?;
+            return self.min_out_violation(&quote).await;
+        }
+        let (token_0, token_1) = self.pool_tokens().await?;
+        let mut reserve_0 = self
+            .chain
+            .token_balance(token_0, self.swap_pool_address)
+            .await?;
+        let mut reserve_1 = self
+            .chain
+            .token_balance(token_1, self.swap_pool_address)
+            .await?;
+        // The flash loaned liquidity is in the pool during the swap.
+        if self.strategy == ExecutionStrategy::FlashLoan {
+            let (token_0_liquidity_wei, token_1_liquidity_wei) =
+                self.pool_liquidity(token_0, token_1).await?;
+            reserve_0 += token_0_liquidity_wei;
+            reserve_1 += token_1_liquidity_wei;
+        }
+        let slippage = self.slippage;
+        let token_0_in = self.give_token == token_0;
+        let price_impact = price_impact_bps(reserve_0, reserve_1, self.amount, token_0_in);
+        let uint = |name, value| DecisionInput::new(name, DecisionValue::Uint(value));
+        self.observed(
+            [
+                uint("reserve_0", reserve_0),
+                uint("reserve_1", reserve_1),
+                uint("amount", self.amount),
+                uint("slippage_percent", slippage),
+            ]
+            .into_iter()
+            .chain(price_impact.map(|impact| uint("price_impact_bps", impact)))
+            .collect(),
+        );
+        match price_impact {
+            Some(impact) if impact <= slippage * 100 => Ok(None),
+            Some(impact) => Ok(Some(format!(
+                "The swap would move the price by {}.{:02}%, more than the slippage of {}%",
+                impact / 100,
+                (impact % 100).as_u32(),
+                slippage
+            ))),
+            None => Ok(Some(format!(
+                "The pool {:?} lacks the liquidity for the swap",
+                self.swap_pool_address
+            ))),
+        }
+    }
+
+    // The slippage check of the V3 pool, on the quote of the swap of the order.
+    async fn quoted_slippage_violation(
+        &self,
+        uniswap_v3: UniswapV3,
+    ) -> Result<Option<String>, SolverError> {
+        let sqrt_price = self.sqrt_price(uniswap_v3).await?;
+        let (amount_out, sqrt_price_after) = self
+            .quote(uniswap_v3, self.give_token, self.take_token, self.amount)
+            .await?;
+        let slippage = self.slippage;
+        let price_impact = uniswap_v3::price_impact_bps(sqrt_price, sqrt_price_after);
+        let uint = |name, value| DecisionInput::new(name, DecisionValue::Uint(value));
+        self.observed(
+            [
+                uint("sqrt_price_x96", sqrt_price),
+                uint("sqrt_price_x96_after", sqrt_price_after),
+                uint("amount", self.amount),
+                uint("amount_out", amount_out),
+                uint("slippage_percent", slippage),
+            ]
+            .into_iter()
+            .chain(price_impact.map(|impact| uint("price_impact_bps", impact)))
+            .collect(),
+        );
+        match price_impact {
+            Some(impact) if impact <= slippage * 100 && !amount_out.is_zero() => Ok(None),
+            Some(impact) if !amount_out.is_zero() => Ok(Some(format!(
+                "The swap would move the price by {}.{:02}%, more than the slippage of {}%",
+                impact / 100,
+                (impact % 100).as_u32(),
+                slippage
+            ))),
+            _ => Ok(Some(format!(
+                "The pool {:?} lacks the liquidity for the swap",
+                uniswap_v3.pool
+            ))),
+        }
+    }
+
+    // Records the values the step is decided on, in the execution log and in the
+    // response of the step.
+    fn observed(&self, inputs: Vec<DecisionInput>) {
+        self.trace.observed(
+            Stage::Step,
+            &inputs
+                .iter()
+                .map(|input| (input.name.as_str(), input.value.to_string()))
+                .collect::<Vec<_>>(),
+        );
+        self.step_inputs.lock().unwrap().extend(inputs);
+    }
+
+    fn take_step_inputs(&self) -> Vec<DecisionInput> {
+        std::mem::take(&mut *self.step_inputs.lock().unwrap())
+    }
+
+    // What the swap from the inventory gets above the order at its desired price, in wei.
+    // The flash loan liquidity is returned in full, the solver earns only the tip. The
+    // surplus is valued through the WETH of the pool, it isn't counted on V3 pairs whose
+    // WETH side is unknown.
+    async fn surplus_wei(&self) -> Result<U256, SolverError> {
+        let desired_price = match *self.last_trigger.lock().await {
+            Some((desired_price, _)) if self.strategy != ExecutionStrategy::FlashLoan => {
+                desired_price
             }
+            _ => return Ok(U256::zero()),
+        };
+        if self.uniswap_v3.is_some() {
+            return Ok(U256::zero());
         }
-        Ok(SolverResponse {
-            succeeded: true,
-            message: "Price conditions are met".to_string(),
+        let (_, weth) = self.pool_tokens().await?;
+        let current_price = self.current_price().await?;
+        let give_unit = self.chain.token_metadata(self.give_token).await?.unit();
+        let take_unit = self.chain.token_metadata(self.take_token).await?.unit();
+        // The take token surplus, and its value in the give token at the pool price.
+        let (surplus, surplus_given) = match self.direction {
+            OrderDirection::Buy => {
+                let surplus =
+                    order_price::asset_for(self.amount, current_price, take_unit, give_unit)
+                        .saturating_sub(order_price::asset_for(
+                            self.amount,
+                            desired_price,
+                            take_unit,
+                            give_unit,
+                        ));
+                let surplus_given =
+                    order_price::quote_for(surplus, current_price, take_unit, give_unit);
+                (surplus, surplus_given)
+            }
+            OrderDirection::Sell => {
+                let surplus =
+                    order_price::quote_for(self.amount, current_price, give_unit, take_unit)
+                        .saturating_sub(order_price::quote_for(
+                            self.amount,
+                            desired_price,
+                            give_unit,
+                            take_unit,
+                        ));
+                let surplus_given =
+                    order_price::asset_for(surplus, current_price, give_unit, take_unit);
+                (surplus, surplus_given)
+            }
+        };
+        Ok(if self.take_token == weth {
+            surplus
+        } else {
+            surplus_given
         })
     }
 
-    async fn final_exec(&self) -> Result<SolverResponse, SolverError> {
-        let hardcoded_weth_liquidity = 100;
-        let hardcoded_dai_liquidity = 1000;
-        let dai_liquidity_wei = parse_units(hardcoded_dai_liquidity, "ether").ok().unwrap();
-        let weth_liquidity_wei = parse_units(hardcoded_weth_liquidity, "ether").ok().unwrap();
-        let call_objects = vec![
-            CallObject {
+    // Builds the final transaction.
+    async fn final_tx(&self) -> Result<TypedTransaction, SolverError> {
+        // The pool quotes token 1 in token 0, find out which side of the order is which.
+        let (token_0, token_1) = self.pool_tokens().await?;
+        match self.strategy {
+            ExecutionStrategy::FlashLoan => self.flash_loan_tx(token_0, token_1).await,
+            _ => {
+                let plan = self.swap_plan(token_0).await?;
+                self.execute_and_verify_tx(&plan, &[]).await
+            }
+        }
+    }
+
+    // The calls of the strategies swapping from the solver inventory.
+    async fn swap_plan(&self, token_0: Address) -> Result<CallPlan, SolverError> {
+        match self.strategy {
+            ExecutionStrategy::FlashLoan => Err(SolverError::ExecError(
+                "the flash loan strategy doesn't swap from the inventory".to_string(),
+            )),
+            ExecutionStrategy::Direct if self.give_token != token_0 => {
+                Err(SolverError::ParamError(format!(
+                    "direct swaps on the pool {:?} give its token {:?}",
+                    self.swap_pool_address, token_0
+                )))
+            }
+            ExecutionStrategy::Direct => Ok(self.direct_plan(token_0)),
+            ExecutionStrategy::UniswapV3 => match self.uniswap_v3 {
+                Some(uniswap_v3) => self.uniswap_v3_plan(uniswap_v3).await,
+                None => Err(SolverError::ExecError(
+                    "missing the Uniswap V3 contracts".to_string(),
+                )),
+            },
+            #[cfg(feature = "aggregator")]
+            ExecutionStrategy::Aggregator => match self.aggregator_strategy() {
+                Some(aggregator) => self.aggregator_plan(aggregator).await,
+                None => Err(SolverError::ExecError("missing the aggregator".to_string())),
+            },
+        }
+    }
+
+    // The execute_and_verify transaction of the calls, with the sequence numbers of the
+    // orders bundled with this one.
+    async fn execute_and_verify_tx(
+        &self,
+        plan: &CallPlan,
+        bundled: &[U256],
+    ) -> Result<TypedTransaction, SolverError> {
+        let (call_objects, return_objects) = self.call_bundle(plan).await?;
+
+        let hintdices = hint_indices(&call_objects);
+        let call_bytes: Bytes = call_objects.encode().into();
+        let return_bytes: Bytes = return_objects.encode().into();
+        Ok(self
+            .call_breaker_contract
+            .execute_and_verify(
+                call_bytes,
+                return_bytes,
+                self.associated_data(bundled),
+                hintdices,
+            )
+            .gas(FINAL_EXEC_GAS)
+            .tx)
+    }
+
+    fn flash_loan_provider(&self) -> Result<&Provider, SolverError> {
+        self.flash_loan.as_ref().ok_or(SolverError::ExecError(
+            "missing the flash loan provider".to_string(),
+        ))
+    }
+
+    // Provides the pool with flash loaned liquidity around the pull of the user's call.
+    async fn flash_loan_tx(
+        &self,
+        token_0: Address,
+        token_1: Address,
+    ) -> Result<TypedTransaction, SolverError> {
+        let flash_loan = self.flash_loan_provider()?;
+        let (token_0_liquidity_wei, token_1_liquidity_wei) =
+            self.pool_liquidity(token_0, token_1).await?;
+        // The pool calls only pass on the flash loaned liquidity, they return nothing.
+        let plan = CallPlan::new(self.call_breaker_address)
+            .call(CallObject {
                 amount: 0.into(),
-                addr: self.give_token.ok().unwrap(),
+                addr: token_0,
                 gas: 10000000.into(),
                 callvalue: IERC20Calls::Approve(ApproveCall {
                     spender: self.swap_pool_address,
-                    amount: dai_liquidity_wei.into(),
+                    amount: token_0_liquidity_wei,
                 })
                 .encode()
                 .into(),
-            },
-            CallObject {
+            })
+            .named("approve_0")
+            .call(CallObject {
                 amount: 0.into(),
-                addr: self.take_token.ok().unwrap(),
+                addr: token_1,
                 gas: 10000000.into(),
                 callvalue: IERC20Calls::Approve(ApproveCall {
                     spender: self.swap_pool_address,
-                    amount: weth_liquidity_wei.into(),
+                    amount: token_1_liquidity_wei,
                 })
                 .encode()
                 .into(),
-            },
-            CallObject {
-                amount: 0.into(),
-                addr: self.swap_pool_address,
-                gas: 10000000.into(),
-                callvalue: SwapPoolCalls::ProvideLiquidityToDAIETHPool(
-                    ProvideLiquidityToDAIETHPoolCall {
-                        provider: self.call_breaker_address,
-                        amount_0_in: hardcoded_dai_liquidity.into(),
-                        amount_1_in: hardcoded_weth_liquidity.into(),
-                    },
-                )
-                .encode()
-                .into(),
-            },
-            CallObject {
+            })
+            .named("approve_1")
+            .call_returning(
+                CallObject {
+                    amount: 0.into(),
+                    addr: self.swap_pool_address,
+                    gas: 10000000.into(),
+                    callvalue: self
+                        .provide_liquidity_call(
+                            HARDCODED_TOKEN_0_LIQUIDITY.into(),
+                            HARDCODED_TOKEN_1_LIQUIDITY.into(),
+                        )
+                        .encode()
+                        .into(),
+                },
+                Bytes::new(),
+            )
+            .named("provide_liquidity")
+            .after(&["approve_0", "approve_1"])
+            .pull(self.pull_call(), self.pushed_calls.clone())
+            .named("pull")
+            .after(&["provide_liquidity"])
+            .call_returning(
+                CallObject {
+                    amount: 0.into(),
+                    addr: self.swap_pool_address,
+                    gas: 10000000.into(),
+                    callvalue: SwapPoolCalls::CheckSlippage(CheckSlippageCall {
+                        max_deviation_percentage: self.slippage,
+                    })
+                    .encode()
+                    .into(),
+                },
+                Bytes::new(),
+            )
+            .named("check_slippage")
+            .after(&["pull"])
+            .call_returning(
+                CallObject {
+                    amount: 0.into(),
+                    addr: self.swap_pool_address,
+                    gas: 10000000.into(),
+                    callvalue: self
+                        .withdraw_liquidity_call(
+                            HARDCODED_TOKEN_0_LIQUIDITY.into(),
+                            HARDCODED_TOKEN_1_LIQUIDITY.into(),
+                        )
+                        .encode()
+                        .into(),
+                },
+                Bytes::new(),
+            )
+            .named("withdraw_liquidity")
+            .after(&["check_slippage"]);
+        // The loans are repaid once the liquidity is back.
+        let loans = [
+            (token_0, token_0_liquidity_wei),
+            (token_1, token_1_liquidity_wei),
+        ];
+        let plan = flash_loan
+            .repay_calls(&loans)
+            .into_iter()
+            .fold(plan, |plan, call| {
+                plan.call(call).after(&["withdraw_liquidity"])
+            });
+        let (call_objects, return_objects) = self.call_bundle(&plan).await?;
+
+        let hintdices = hint_indices(&call_objects);
+        let flash_loan_data = flash_loan.loan_data(&loans);
+
+        let call_bytes: Bytes = call_objects.encode().into();
+        let return_bytes: Bytes = return_objects.encode().into();
+        Ok(self
+            .call_breaker_contract
+            .execute_and_verify_with_flashloan(
+                call_bytes,
+                return_bytes,
+                self.associated_data(&[]),
+                hintdices,
+                flash_loan_data,
+            )
+            .gas(FINAL_EXEC_GAS)
+            .tx)
+    }
+
+    // Swaps the order amount of token 0 from the solver inventory for token 1 on the
+    // pool after the pull of the user's call.
+    fn direct_plan(&self, token_0: Address) -> CallPlan {
+        let amount = self.amount;
+        CallPlan::new(self.call_breaker_address)
+            .call(CallObject {
                 amount: 0.into(),
-                addr: self.proxy_address,
+                addr: token_0,
                 gas: 10000000.into(),
-                callvalue: LaminatedProxyCalls::Pull(PullCall {
-                    seq_number: self.sequence_number,
+                callvalue: IERC20Calls::Approve(ApproveCall {
+                    spender: self.swap_pool_address,
+                    amount,
                 })
                 .encode()
                 .into(),
-            },
-            CallObject {
+            })
+            .named("approve")
+            .pull(self.pull_call(), self.pushed_calls.clone())
+            .named("pull")
+            // The swap returns nothing.
+            .call_returning(
+                CallObject {
+                    amount: 0.into(),
+                    addr: self.swap_pool_address,
+                    gas: 10000000.into(),
+                    callvalue: SwapPoolCalls::SwapDAIForWETH(SwapDAIForWETHCall {
+                        amount_in: amount,
+                        slippage_percent: self.slippage,
+                    })
+                    .encode()
+                    .into(),
+                },
+                Bytes::new(),
+            )
+            .after(&["approve", "pull"])
+    }
+
+    // Swaps the order amount of the give token from the solver inventory on the V3 pool
+    // after the pull of the user's call. The swap stops at the slippage away from the
+    // current price and reverts below the slippage away from the quote.
+    async fn uniswap_v3_plan(&self, uniswap_v3: UniswapV3) -> Result<CallPlan, SolverError> {
+        let amount = self.amount;
+        let (give_token, take_token) = (self.give_token, self.take_token);
+        let sqrt_price = self.sqrt_price(uniswap_v3).await?;
+        let (amount_out, _) = self
+            .quote(uniswap_v3, give_token, take_token, amount)
+            .await?;
+        let slippage = self.slippage.min(100.into());
+        // The pool sorts its tokens by address, selling token 0 lowers the price.
+        let zero_for_one = give_token < take_token;
+        let plan = CallPlan::new(self.call_breaker_address)
+            .call(CallObject {
                 amount: 0.into(),
-                addr: self.swap_pool_address,
+                addr: give_token,
                 gas: 10000000.into(),
-                callvalue: SwapPoolCalls::CheckSlippage(CheckSlippageCall {
-                    max_deviation_percentage: *self.slippage.as_ref().ok().unwrap(),
+                callvalue: IERC20Calls::Approve(ApproveCall {
+                    spender: uniswap_v3.router,
+                    amount,
                 })
                 .encode()
                 .into(),
-            },
-            CallObject {
+            })
+            .named("approve")
+            .pull(self.pull_call(), self.pushed_calls.clone())
+            .named("pull")
+            // The swap is expected to return the quoted amount.
+            .call_returning(
+                uniswap_v3.exact_input_single_call(
+                    give_token,
+                    take_token,
+                    self.call_breaker_address,
+                    amount,
+                    amount_out * (U256::from(100) - slippage) / 100,
+                    uniswap_v3::sqrt_price_limit(sqrt_price, zero_for_one, slippage),
+                ),
+                amount_out.encode().into(),
+            )
+            .after(&["approve", "pull"]);
+        Ok(plan)
+    }
+
+    // The aggregator if the order is routed through it.
+    #[cfg(feature = "aggregator")]
+    fn aggregator_strategy(&self) -> Option<&Aggregator> {
+        match self.strategy {
+            ExecutionStrategy::Aggregator => self.aggregator.as_deref(),
+            _ => None,
+        }
+    }
+
+    // The route of the order amount quoted by the aggregator, made by the call breaker.
+    #[cfg(feature = "aggregator")]
+    async fn aggregator_quote(
+        &self,
+        aggregator: &Aggregator,
+    ) -> Result<AggregatorQuote, SolverError> {
+        let quote = aggregator
+            .quote(
+                self.give_token,
+                self.take_token,
+                self.amount,
+                self.call_breaker_address,
+                self.slippage,
+            )
+            .await
+            .map_err(|err| err.context("Error quoting the route"))?;
+        self.observed(vec![
+            DecisionInput::new("router", DecisionValue::Address(quote.router)),
+            DecisionInput::new("buy_amount", DecisionValue::Uint(quote.buy_amount)),
+            DecisionInput::new("min_buy_amount", DecisionValue::Uint(quote.min_buy_amount)),
+        ]);
+        Ok(quote)
+    }
+
+    // Checks the minimum out of the quote against the order slippage, from the amount the
+    // order would get at the pool price.
+    #[cfg(feature = "aggregator")]
+    async fn min_out_violation(
+        &self,
+        quote: &AggregatorQuote,
+    ) -> Result<Option<String>, SolverError> {
+        let price = self.current_price().await?;
+        let give_unit = self.chain.token_metadata(self.give_token).await?.unit();
+        let take_unit = self.chain.token_metadata(self.take_token).await?.unit();
+        // Buy orders give the quote token, sell orders give the asset.
+        let expected_out = match self.direction {
+            OrderDirection::Buy => order_price::asset_for(self.amount, price, take_unit, give_unit),
+            OrderDirection::Sell => {
+                order_price::quote_for(self.amount, price, give_unit, take_unit)
+            }
+        };
+        Ok(quote.min_out_violation(expected_out, self.slippage))
+    }
+
+    // Swaps the order amount of the give token from the solver inventory on the route
+    // quoted by the aggregator, after the pull of the user's call. The quote is only
+    // used if its minimum out holds the order slippage.
+    #[cfg(feature = "aggregator")]
+    async fn aggregator_plan(&self, aggregator: &Aggregator) -> Result<CallPlan, SolverError> {
+        let quote = self.aggregator_quote(aggregator).await?;
+        if let Some(violation) = self.min_out_violation(&quote).await? {
+            return Err(SolverError::TxError(violation));
+        }
+        Ok(self.aggregator_swap_plan(&quote))
+    }
+
+    // The swap returns what the route delivers, which moves with the prices since the
+    // quote. It's simulated after the approval and the pull.
+    #[cfg(feature = "aggregator")]
+    fn aggregator_swap_plan(&self, quote: &AggregatorQuote) -> CallPlan {
+        CallPlan::new(self.call_breaker_address)
+            .call(CallObject {
                 amount: 0.into(),
-                addr: self.swap_pool_address,
+                addr: self.give_token,
                 gas: 10000000.into(),
-                callvalue: SwapPoolCalls::WithdrawLiquidityFromDAIETHPool(
-                    WithdrawLiquidityFromDAIETHPoolCall {
-                        provider: self.call_breaker_address,
-                        amount_0_out: hardcoded_dai_liquidity.into(),
-                        amount_1_out: hardcoded_weth_liquidity.into(),
-                    },
-                )
+                callvalue: IERC20Calls::Approve(ApproveCall {
+                    spender: quote.router,
+                    amount: self.amount,
+                })
                 .encode()
                 .into(),
-            },
-        ];
-        let return_objects_from_pull = vec![
-            ReturnObject {
-                returnvalue: true.encode().into(),
-            },
-            ReturnObject {
-                returnvalue: Bytes::new(),
-            },
-        ];
-        let return_objects = vec![
-            ReturnObject {
-                returnvalue: true.encode().into(),
-            },
-            ReturnObject {
-                returnvalue: true.encode().into(),
-            },
-            ReturnObject {
-                returnvalue: Bytes::new(),
-            },
-            ReturnObject {
-                returnvalue: abi::encode(&[Token::Bytes(return_objects_from_pull.encode())]).into(),
-            },
-            ReturnObject {
-                returnvalue: Bytes::new(),
-            },
-            ReturnObject {
-                returnvalue: Bytes::new(),
-            },
-        ];
+            })
+            .named("approve")
+            .pull(self.pull_call(), self.pushed_calls.clone())
+            .named("pull")
+            .call(quote.call())
+            .after(&["approve", "pull"])
+    }
 
-        let associated_data: Bytes = Bytes::from_str("0x00000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000c040364975c732e2b61ede80abbc6666bc882f0e45406caaa44bed3e13479c186300000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000014335858f4c351de51acd8bede5c8889d2390083f7000000000000000000000000632ec94a0831e53d3569cd147364f65fbf6465a359bba763dcbf3dbb7d995bcc000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000002").unwrap();
-        let hintdices: Bytes = Bytes::from_str("0x0000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000c0000000000000000000000000000000000000000000000000000000000000014000000000000000000000000000000000000000000000000000000000000001c0000000000000000000000000000000000000000000000000000000000000024000000000000000000000000000000000000000000000000000000000000002c00000000000000000000000000000000000000000000000000000000000000340a7adac1f40528e8a4a9140ffc6a418676837d76a18d2f529d9972b6493d6bd7f000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000000eba5b91a9bb3539022c141ea5627bf486661098830e60bf44a47a78828a150040000000000000000000000000000000000000000000000000000000000000040000000000000000000000000000000000000000000000000000000000000002000000000000000000000000000000000000000000000000000000000000000016e1b59da3095663ef70ac99cedf4fd4078e8a9db104c61cb5d9ed3c48d37b007000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000002df2e89cf5c2dc7dbfd7662767283cc00e70733c91eb7c22f605deee80e3e07e5000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000003be527a53261197da9c6a489c8d80ac91dc09a26f5def3ba3c6e219da2b1f275d00000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000020000000000000000000000000000000000000000000000000000000000000000412b127dae580c49294132318c253e8415b9292e34d8a03bfb38c06d0ba05c0ef000000000000000000000000000000000000000000000000000000000000004000000000000000000000000000000000000000000000000000000000000000200000000000000000000000000000000000000000000000000000000000000005").unwrap();
+    // Balances of the order tokens in the solver wallet, None if any couldn't be read.
+    async fn wallet_balances(&self) -> Option<Vec<(Address, U256)>> {
+        let mut balances = Vec::new();
+        for token in [self.give_token, self.take_token] {
+            match self.chain.token_balance(token, self.solver_address).await {
+                Ok(balance) => balances.push((token, balance)),
+                Err(err) => {
+                    println!(
+                        "Error reading the solver wallet balance of the token {:?}: {}",
+                        token, err
+                    );
+                    return None;
+                }
+            }
+        }
+        Some(balances)
+    }
+
+    // The calls of the plan with their simulated returns.
+    async fn call_bundle(
+        &self,
+        plan: &CallPlan,
+    ) -> Result<(Vec<CallObject>, Vec<ReturnObject>), SolverError> {
+        let call_objects = plan.call_objects()?;
+        let return_objects = plan.return_objects(&self.chain).await?;
+        self.trace.record(ExecutionRecord::CallBundle {
+            calls: call_objects.clone(),
+            returns: return_objects.clone(),
+        });
+        Ok((call_objects, return_objects))
+    }
 
-        let flash_loan_data: Bytes = FlashLoanData {
-            provider: self.flash_loan_address,
-            amount_a: dai_liquidity_wei.into(),
-            amount_b: weth_liquidity_wei.into(),
+    // The pulls of the orders bundled with this one are listed after its own.
+    fn associated_data(&self, bundled: &[U256]) -> Bytes {
+        let associated_data = AssociatedData::new()
+            .with(
+                "tipYourBartender",
+                self.solver_address.as_bytes().to_vec().into(),
+            )
+            .with("pullIndex", self.sequence_number.encode().into());
+        if bundled.is_empty() {
+            return associated_data.encode();
         }
-        .encode()
-        .into();
+        let pull_indices: Vec<U256> = [self.sequence_number]
+            .into_iter()
+            .chain(bundled.iter().copied())
+            .collect();
+        associated_data
+            .with("pullIndices", pull_indices.encode().into())
+            .encode()
+    }
 
-        let call_bytes: Bytes = call_objects.encode().into();
-        let return_bytes: Bytes = return_objects.encode().into();
+    // Pulls the call pushed by the user to the proxy.
+    fn pull_call(&self) -> CallObject {
+        CallObject {
+            amount: 0.into(),
+            addr: self.proxy_address,
+            gas: 10000000.into(),
+            callvalue: LaminatedProxyCalls::Pull(PullCall {
+                seq_number: self.sequence_number,
+            })
+            .encode()
+            .into(),
+        }
+    }
+
+    // Pool calls, the amounts are in token 0 / token 1 order of the pool.
+    fn provide_liquidity_call(&self, amount_0: U256, amount_1: U256) -> SwapPoolCalls {
+        SwapPoolCalls::ProvideLiquidityToDAIETHPool(ProvideLiquidityToDAIETHPoolCall {
+            provider: self.call_breaker_address,
+            amount_0_in: amount_0,
+            amount_1_in: amount_1,
+        })
+    }
+
+    fn withdraw_liquidity_call(&self, amount_0: U256, amount_1: U256) -> SwapPoolCalls {
+        SwapPoolCalls::WithdrawLiquidityFromDAIETHPool(WithdrawLiquidityFromDAIETHPoolCall {
+            provider: self.call_breaker_address,
+            amount_0_out: amount_0,
+            amount_1_out: amount_1,
+        })
+    }
+}
+
+impl<M: Middleware, C: ChainClient> LimitOrderSolver<M, C> {
+    // Executes the order, in one transaction with a complementary order if one is ready
+    // within the bundle window. The balance changes of a bundle are the ones of the order
+    // leading it.
+    async fn execute(&self) -> Result<(Execution, Option<BalanceChanges>), SolverError> {
+        let composer = match (&self.bundle_composer, self.strategy) {
+            (Some(_), ExecutionStrategy::FlashLoan) | (None, _) => {
+                return self.send(self.final_tx().await?).await;
+            }
+            (Some(composer), _) => composer,
+        };
+        let (token_0, _) = self.pool_tokens().await?;
+        let plan = self.swap_plan(token_0).await?;
+        match composer
+            .compose(self.give_token, self.take_token, self.sequence_number, plan)
+            .await
         {
-            let _guard = self.guard.lock().await;
-            match self
-                .call_breaker_contract
-                .execute_and_verify_with_flashloan(
-                    call_bytes,
-                    return_bytes,
-                    associated_data,
-                    hintdices,
-                    flash_loan_data,
-                )
-                .gas(10000000)
-                .send()
+            Composition::Alone(plan) => {
+                let tx = self.execute_and_verify_tx(&plan, &[]).await?;
+                self.send(tx).await
+            }
+            Composition::Leading(plan, offer) => {
+                let sequence_number = offer.sequence_number;
+                let sent = async {
+                    let plan = plan.then(offer.plan)?;
+                    let tx = self
+                        .execute_and_verify_tx(&plan, &[sequence_number])
+                        .await?;
+                    self.send(tx).await
+                }
                 .await
-            {
-                Ok(pending) => {
-                    println!("Transaction is sent, txhash: {}", pending.tx_hash());
-                    match pending.await {
-                        Ok(receipt) => {
-                            if let Some(receipt) = receipt {
-                                if let Some(status) = receipt.status {
-                                    return Ok(SolverResponse {
-                                        succeeded: status != 0.into(),
-                                        message: format!("Transaction status: {}", status),
-                                    });
-                                }
-                            }
-                            return Ok(SolverResponse {
-                                succeeded: false,
-                                message: "transaction status wasn't received".to_string(),
-                            });
+                .map(|(execution, balances)| (bundle_composer::gas_share(execution), balances));
+                offer
+                    .outcome
+                    .send(sent.as_ref().map(|(execution, _)| execution));
+                sent
+            }
+            Composition::Joined(outcome) => outcome
+                .map(|execution| (execution, None))
+                .map_err(|err| SolverError::TxError(format!("Bundled execution error: {}", err))),
+        }
+    }
+
+    // Sends the final transaction, the wallet balances are read around it.
+    async fn send(
+        &self,
+        tx: TypedTransaction,
+    ) -> Result<(Execution, Option<BalanceChanges>), SolverError> {
+        let _guard = self.guard.lock().await;
+        // The final transactions are sent one at a time, the changes of the wallet balances
+        // around this one are its own.
+        let balances_before = self.wallet_balances().await;
+        let execution = self
+            .chain
+            .execute_and_verify(APP_SELECTOR, self.amount, tx)
+            .await
+            .map_err(|err| err.context("Final execution error"))?;
+        let balances_after = match execution {
+            Execution::Sent { .. } => self.wallet_balances().await,
+            Execution::Simulated(_) => None,
+        };
+        Ok((execution, balances_before.zip(balances_after)))
+    }
+}
+
+impl<M: Middleware, C: ChainClient> Solver for LimitOrderSolver<M, C> {
+    fn app(&self) -> String {
+        return APP_SELECTOR.to_string();
+    }
+
+    fn time_limit(&self) -> Result<Duration, parse_duration::parse::Error> {
+        Ok(self.time_limit)
+    }
+
+    fn tx_hash(&self) -> Option<H256> {
+        *self.tx_hash.lock().unwrap()
+    }
+
+    fn accounting(&self) -> Option<ObjectiveAccounting> {
+        self.accounting.lock().unwrap().clone()
+    }
+
+    fn bundle_status(&self) -> Option<BundleStatus> {
+        self.bundle_status.lock().unwrap().clone()
+    }
+
+    fn order_amount(&self) -> Option<String> {
+        self.order_amount.lock().unwrap().clone()
+    }
+
+    fn gas_budget(&self) -> Option<U256> {
+        self.gas_budget
+    }
+
+    async fn exec_solver_step(&self) -> Result<SolverResponse, SolverError> {
+        self.step_inputs.lock().unwrap().clear();
+        // The stats show the amount once the token is read, it's read again otherwise.
+        if self.order_amount.lock().unwrap().is_none() {
+            if let Ok(give_token) = self.chain.token_metadata(self.give_token).await {
+                *self.order_amount.lock().unwrap() = Some(give_token.format(self.amount));
+            }
+        }
+        // Check the price
+        match self.current_price().await {
+            Ok(current_price) => {
+                let (desired_price, trigger_below) = self.trigger_price(current_price).await?;
+                let triggered = if trigger_below {
+                    current_price <= desired_price
+                } else {
+                    current_price >= desired_price
+                };
+                self.observed(vec![
+                    DecisionInput::new(
+                        "order_type",
+                        DecisionValue::Text(format!("{:?}", self.order_type)),
+                    ),
+                    DecisionInput::new(
+                        "current_price",
+                        DecisionValue::Price(order_price::format(current_price)),
+                    ),
+                    DecisionInput::new(
+                        "desired_price",
+                        DecisionValue::Price(order_price::format(desired_price)),
+                    ),
+                    DecisionInput::new("trigger_below", DecisionValue::Bool(trigger_below)),
+                ]);
+                *self.last_trigger.lock().await = Some((desired_price, trigger_below));
+                *self.next_tick.lock().await = self.adaptive_tick.map(|adaptive_tick| {
+                    if triggered {
+                        adaptive_tick.min
+                    } else {
+                        adaptive_tick.tick(current_price, desired_price)
+                    }
+                });
+                if !triggered {
+                    return Ok(SolverResponse {
+                        succeeded: false,
+                        message: format!(
+                            "The current price {} is {} than the desired {}",
+                            order_price::format(current_price),
+                            if trigger_below { "higher" } else { "lower" },
+                            order_price::format(desired_price)
+                        ),
+                        decision: self.take_step_inputs(),
+                    });
+                }
+            }
+            Err(err) => {
+                return Err(err);
+            }
+        }
+        // Gas is only spent on the final transaction if it's expected to pass the
+        // on-chain slippage check.
+        if let Some(message) = self.slippage_violation().await? {
+            return Ok(SolverResponse {
+                succeeded: false,
+                message,
+                decision: self.take_step_inputs(),
+            });
+        }
+        Ok(SolverResponse {
+            succeeded: true,
+            message: "Price conditions are met".to_string(),
+            decision: self.take_step_inputs(),
+        })
+    }
+
+    async fn check_preconditions(&self) -> Result<SolverResponse, SolverError> {
+        let (token_0, token_1) = self.pool_tokens().await?;
+        let check_error = |err: SolverError| err.context("Precondition check error");
+        let (token_0_liquidity_wei, token_1_liquidity_wei) = self
+            .pool_liquidity(token_0, token_1)
+            .await
+            .map_err(check_error)?;
+        let mut problems = Vec::new();
+
+        let give_token = self.give_token;
+        let amount = self.amount;
+        let give_metadata = self
+            .chain
+            .token_metadata(give_token)
+            .await
+            .map_err(check_error)?;
+        match self.strategy {
+            // The flash loan provider lends the liquidity for the pool, the call breaker
+            // pays its fees from the solver inventory.
+            ExecutionStrategy::FlashLoan => {
+                let flash_loan = self.flash_loan_provider()?;
+                for (token, needed) in [
+                    (token_0, token_0_liquidity_wei),
+                    (token_1, token_1_liquidity_wei),
+                ] {
+                    let metadata = self
+                        .chain
+                        .token_metadata(token)
+                        .await
+                        .map_err(check_error)?;
+                    if let Some(holder) = flash_loan.liquidity_holder() {
+                        let balance = self
+                            .chain
+                            .token_balance(token, holder)
+                            .await
+                            .map_err(check_error)?;
+                        if balance < needed {
+                            problems.push(format!(
+                                "the flash loan {:?} holds {}, needs {}",
+                                holder,
+                                metadata.format(balance),
+                                metadata.format(needed)
+                            ));
                         }
-                        Err(err) => {
-                            return Err(SolverError::ExecError(format!(
-                                "Final execution error: {}",
-                                err
+                    }
+                    let fee = flash_loan.fee(needed);
+                    if fee.is_zero() {
+                        continue;
+                    }
+                    let balance = self
+                        .chain
+                        .token_balance(token, self.call_breaker_address)
+                        .await
+                        .map_err(check_error)?;
+                    if balance < fee {
+                        problems.push(format!(
+                            "the call breaker {:?} holds {} for the solver, the {} flash loan fee is {}",
+                            self.call_breaker_address,
+                            metadata.format(balance),
+                            flash_loan.name(),
+                            metadata.format(fee)
+                        ));
+                    }
+                }
+            }
+            // The other strategies swap the inventory the solver keeps at the call breaker.
+            _ => {
+                let balance = self
+                    .chain
+                    .token_balance(give_token, self.call_breaker_address)
+                    .await
+                    .map_err(check_error)?;
+                if balance < amount {
+                    problems.push(format!(
+                        "the call breaker {:?} holds {} for the solver, the swap needs {}",
+                        self.call_breaker_address,
+                        give_metadata.format(balance),
+                        give_metadata.format(amount)
+                    ));
+                }
+            }
+        }
+
+        // The user's proxy pays the give token when the pushed call is pulled.
+        let proxy_balance = self
+            .chain
+            .token_balance(give_token, self.proxy_address)
+            .await
+            .map_err(check_error)?;
+        if proxy_balance < amount {
+            problems.push(format!(
+                "the proxy {:?} holds {}, the order gives {}",
+                self.proxy_address,
+                give_metadata.format(proxy_balance),
+                give_metadata.format(amount)
+            ));
+        }
+
+        // The pushed calls transferring tokens from an owner, e.g. the user's wallet,
+        // need the proxy to be allowed to.
+        for call in &self.pushed_calls {
+            let transfer = match IERC20Calls::decode(&call.callvalue) {
+                Ok(IERC20Calls::TransferFrom(transfer)) => transfer,
+                _ => continue,
+            };
+            let allowance = self
+                .chain
+                .allowance(call.addr, transfer.from, self.proxy_address)
+                .await
+                .map_err(check_error)?;
+            if allowance < transfer.amount {
+                let metadata = self
+                    .chain
+                    .token_metadata(call.addr)
+                    .await
+                    .map_err(check_error)?;
+                problems.push(format!(
+                    "{:?} allows the proxy {:?} {}, the pull transfers {}",
+                    transfer.from,
+                    self.proxy_address,
+                    metadata.format(allowance),
+                    metadata.format(transfer.amount)
+                ));
+            }
+        }
+
+        // The solver wallet pays for the gas of the final transaction unless it's relayed.
+        if self.chain.wallet_pays_gas(APP_SELECTOR, amount) {
+            let gas_price = self.chain.gas_price().await.map_err(check_error)?;
+            let wallet_balance = self
+                .chain
+                .balance(self.solver_address)
+                .await
+                .map_err(check_error)?;
+            let gas_cost = gas_price * FINAL_EXEC_GAS;
+            if wallet_balance < gas_cost {
+                problems.push(format!(
+                    "the solver wallet {:?} holds {} wei, the transaction may cost up to {}",
+                    self.solver_address, wallet_balance, gas_cost
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(SolverResponse {
+                succeeded: true,
+                message: "Preconditions are met".to_string(),
+                decision: Vec::new(),
+            })
+        } else {
+            Ok(SolverResponse {
+                succeeded: false,
+                message: format!("Preconditions aren't met: {}", problems.join("; ")),
+                decision: Vec::new(),
+            })
+        }
+    }
+
+    async fn check_profitability(&self) -> Result<SolverResponse, SolverError> {
+        // The gas of orders without a tip is paid by the solver whatever it earns.
+        if self.tip.is_zero() {
+            return Ok(SolverResponse {
+                succeeded: true,
+                message: "The order gives no tip, its profitability isn't checked".to_string(),
+                decision: Vec::new(),
+            });
+        }
+        let tx = self.final_tx().await?;
+        let surplus = self.surplus_wei().await?;
+        let estimate = self
+            .chain
+            .estimate_profitability(&tx, self.tip, surplus)
+            .await
+            .map_err(|err| err.context("Profitability check error"))?;
+        if estimate.is_profitable() {
+            Ok(SolverResponse {
+                succeeded: true,
+                message: format!("Execution is profitable: {}", estimate.describe()),
+                decision: Vec::new(),
+            })
+        } else {
+            Ok(SolverResponse {
+                succeeded: false,
+                message: format!("Execution is at a loss: {}", estimate.describe()),
+                decision: Vec::new(),
+            })
+        }
+    }
+
+    async fn solved_externally(&self) -> Result<Option<String>, SolverError> {
+        let executed = self
+            .chain
+            .call_executed(self.proxy_address, self.sequence_number)
+            .await?;
+        Ok(executed.then(|| {
+            format!(
+                "The call {} of the proxy was executed by another solver",
+                self.sequence_number
+            )
+        }))
+    }
+
+    async fn final_exec(&self) -> Result<SolverResponse, SolverError> {
+        match self.execute().await? {
+            (
+                Execution::Sent {
+                    tx_hash,
+                    status,
+                    block_number,
+                    gas_used,
+                    effective_gas_price,
+                    bundle,
+                },
+                balances,
+            ) => {
+                self.trace.record(ExecutionRecord::Transaction {
+                    tx_hash,
+                    status,
+                    block_number,
+                    gas_used,
+                });
+                // A reorg may drop the transaction until it's buried under the
+                // confirmations, it's sent again then.
+                if self.confirmations > 0 && status.is_some_and(|status| status != 0.into()) {
+                    if let (Some(tx_hash), Some(block_number)) = (tx_hash, block_number) {
+                        if !self
+                            .chain
+                            .confirmed(tx_hash, block_number, self.confirmations)
+                            .await?
+                        {
+                            return Err(SolverError::TxError(format!(
+                                "the transaction {:?} was dropped by a reorg",
+                                tx_hash
                             )));
                         }
                     }
                 }
-                Err(err) => {
-                    return Err(SolverError::ExecError(format!(
-                        "Final execution error: {}",
-                        err
-                    )));
+                *self.tx_hash.lock().unwrap() = tx_hash;
+                *self.bundle_status.lock().unwrap() = bundle.clone();
+                {
+                    let mut accounting = self.accounting.lock().unwrap();
+                    let accounting = accounting.get_or_insert_with(ObjectiveAccounting::default);
+                    // The relayer pays for the gas of the relayed transactions.
+                    if self.chain.wallet_pays_gas(APP_SELECTOR, self.amount) {
+                        accounting.add_gas(
+                            gas_used.unwrap_or_default(),
+                            effective_gas_price.unwrap_or_default(),
+                        );
+                    }
+                    if status.is_some_and(|status| status != 0.into()) {
+                        accounting.add_tip(self.tip);
+                    }
+                    if let Some((before, after)) = balances {
+                        for ((token, before), (_, after)) in before.into_iter().zip(after) {
+                            accounting.add_balance_change(token, before, after);
+                        }
+                    }
+                }
+                match (status, bundle) {
+                    (Some(status), _) => Ok(SolverResponse {
+                        succeeded: status != 0.into(),
+                        message: format!("Transaction status: {}", status),
+                        decision: Vec::new(),
+                    }),
+                    (None, Some(BundleStatus::NotIncluded { last_target_block })) => {
+                        Ok(SolverResponse {
+                            succeeded: false,
+                            message: format!(
+                                "bundle wasn't included up to block {}",
+                                last_target_block
+                            ),
+                            decision: Vec::new(),
+                        })
+                    }
+                    (None, _) => Ok(SolverResponse {
+                        succeeded: false,
+                        message: "transaction status wasn't received".to_string(),
+                        decision: Vec::new(),
+                    }),
                 }
             }
+            (Execution::Simulated(message), _) => {
+                self.trace.record(ExecutionRecord::Simulated {
+                    message: message.clone(),
+                });
+                Ok(SolverResponse {
+                    succeeded: true,
+                    message,
+                    decision: Vec::new(),
+                })
+            }
+        }
+    }
+
+    async fn next_tick(&self, tick: Duration) -> Duration {
+        self.next_tick.lock().await.unwrap_or(tick)
+    }
+
+    // Waits for the price to cross the last trigger, up to the next tick.
+    async fn wait_next_step(&self, tick: Duration) {
+        let next_tick = self.next_tick(tick).await;
+        let last_trigger = *self.last_trigger.lock().await;
+        let pool_pair = *self.pool_pair.lock().unwrap();
+        let (mut price_updates, (desired_price, trigger_below), (_, token_1)) =
+            match (self.price_updates.clone(), last_trigger, pool_pair) {
+                (Some(price_updates), Some(last_trigger), Some(pool_pair)) => {
+                    (price_updates, last_trigger, pool_pair)
+                }
+                _ => return sleep(next_tick).await,
+            };
+        // The feed has the prices of the pool, the trigger is in the order orientation.
+        let asset_is_token_1 = token_1 == self.asset();
+        // Only the prices after this step count.
+        price_updates.borrow_and_update();
+        let crossed = async {
+            loop {
+                if price_updates.changed().await.is_err() {
+                    // The feed is gone, wait for the tick.
+                    pending::<()>().await;
+                }
+                let crossed = price_updates.borrow_and_update().is_some_and(|pool_price| {
+                    let price = order_price::from_pool(pool_price, asset_is_token_1);
+                    if trigger_below {
+                        price <= desired_price
+                    } else {
+                        price >= desired_price
+                    }
+                });
+                if crossed {
+                    return;
+                }
+            }
+        };
+        let _ = timeout(next_tick, crossed).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chain_client::mock::MockChainClient,
+        contracts_abi::{ierc20::TransferFromCall, laminator::AdditionalData},
+        submission::SubmissionPolicy,
+        token_metadata::TokenMetadata,
+    };
+    use ethers::providers::{MockProvider, Provider};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::HashMap;
+
+    fn dai() -> Address {
+        Address::repeat_byte(0xda)
+    }
+
+    fn weth() -> Address {
+        Address::repeat_byte(0xee)
+    }
+
+    fn proxy() -> Address {
+        Address::repeat_byte(0x01)
+    }
+
+    fn flash_loan() -> Address {
+        Address::repeat_byte(0xf1)
+    }
+
+    fn call_breaker() -> Address {
+        Address::repeat_byte(0xcb)
+    }
+
+    // A chain where the buy order below can be executed.
+    fn funded_chain() -> MockChainClient {
+        let ether = U256::exp10(18);
+        let (dai_liquidity, weth_liquidity) = liquidity_wei(ether, ether);
+        MockChainClient {
+            price_of_weth: std::sync::Mutex::new(1500.into()),
+            pool_tokens: (dai(), weth()),
+            token_balances: HashMap::from([
+                ((dai(), flash_loan()), dai_liquidity),
+                ((weth(), flash_loan()), weth_liquidity),
+                ((dai(), proxy()), 10.into()),
+            ]),
+            balance: U256::exp10(18),
+            gas_price: 1.into(),
+            gas: 100000.into(),
+            receipt_status: Some(1),
+            ..Default::default()
+        }
+    }
+
+    // A buy order of WETH for 10 DAI at the price of 1500 or lower.
+    fn buy_order(
+        chain: MockChainClient,
+        adaptive_tick: Option<AdaptiveTick>,
+    ) -> LimitOrderSolver<Provider<MockProvider>, MockChainClient> {
+        buy_order_with(
+            chain,
+            adaptive_tick,
+            HashMap::from([
+                (FLASH_LOAN_NAME.to_string(), flash_loan()),
+                (SWAP_POOL_NAME.to_string(), Address::repeat_byte(0x77)),
+            ]),
+        )
+    }
+
+    fn buy_order_with(
+        chain: MockChainClient,
+        adaptive_tick: Option<AdaptiveTick>,
+        extra_contract_addresses: HashMap<String, Address>,
+    ) -> LimitOrderSolver<Provider<MockProvider>, MockChainClient> {
+        let event = order_event(buy_order_values());
+        let params = solver_params(adaptive_tick, extra_contract_addresses);
+        match LimitOrderSolver::with_chain_client(event, params, chain) {
+            Ok(solver) => solver,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    fn buy_order_values() -> Vec<(&'static str, String)> {
+        vec![
+            ("give_token", format!("{:?}", dai())),
+            ("take_token", format!("{:?}", weth())),
+            ("amount", "10".to_string()),
+            ("buy_price", "1500".to_string()),
+            ("slippage", "5".to_string()),
+            ("time_limit", "60s".to_string()),
+            ("tip", "1000000".to_string()),
+        ]
+    }
+
+    fn solver_params(
+        adaptive_tick: Option<AdaptiveTick>,
+        extra_contract_addresses: HashMap<String, Address>,
+    ) -> SolverParams<Provider<MockProvider>> {
+        let (provider, _) = Provider::mocked();
+        SolverParams {
+            call_breaker_address: call_breaker(),
+            solver_address: Address::repeat_byte(0x50),
+            extra_contract_addresses,
+            middleware: Arc::new(provider),
+            guard: Arc::new(Mutex::new(true)),
+            submission_policy: Arc::new(SubmissionPolicy::new(1, vec![], vec![], vec![], false)),
+            adaptive_tick,
+            price_feed: None,
+            block_ticks: None,
+            execution_log: None,
+            wallet_pool: None,
+            tick: Duration::from_secs(1),
+            default_time_limit: None,
+            app_queue: None,
+            gas_budget: None,
+            confirmations: 0,
+            #[cfg(feature = "aggregator")]
+            aggregator: None,
+            flash_loan_markets: Vec::new(),
+            bundle_composer: None,
+            token_metadata: Default::default(),
+        }
+    }
+
+    fn order_event(data_values: Vec<(&str, String)>) -> ProxyPushedFilter {
+        ProxyPushedFilter {
+            proxy_address: proxy(),
+            call_objs: Vec::new(),
+            sequence_number: 7.into(),
+            selector: solver::selector(APP_SELECTOR.to_string()).into(),
+            data_values: data_values
+                .into_iter()
+                .map(|(name, value)| AdditionalData {
+                    name: name.to_string(),
+                    datatype: 0,
+                    value,
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn step_waits_above_the_buy_price() {
+        let chain = funded_chain();
+        *chain.price_of_weth.lock().unwrap() = 1600.into();
+        let solver = buy_order(chain, None);
+        let response = solver.exec_solver_step().await.ok().unwrap();
+        assert!(!response.succeeded);
+        assert_eq!(
+            response.message,
+            "The current price 1600 is higher than the desired 1500"
+        );
+    }
+
+    #[tokio::test]
+    async fn step_triggers_at_the_buy_price() {
+        let solver = buy_order(funded_chain(), None);
+        assert!(solver.exec_solver_step().await.ok().unwrap().succeeded);
+    }
+
+    #[tokio::test]
+    async fn tick_shortens_near_the_trigger() {
+        let adaptive_tick = AdaptiveTick {
+            min: Duration::from_millis(250),
+            max: Duration::from_secs(30),
+        };
+        let chain = funded_chain();
+        *chain.price_of_weth.lock().unwrap() = 3000.into();
+        let solver = buy_order(chain, Some(adaptive_tick));
+        let tick = Duration::from_secs(1);
+        assert_eq!(solver.next_tick(tick).await, tick);
+
+        solver.exec_solver_step().await.ok().unwrap();
+        assert_eq!(solver.next_tick(tick).await, adaptive_tick.max);
+
+        *solver.chain.price_of_weth.lock().unwrap() = 1500.into();
+        solver.exec_solver_step().await.ok().unwrap();
+        assert_eq!(solver.next_tick(tick).await, adaptive_tick.min);
+    }
+
+    #[tokio::test]
+    async fn price_crossing_the_trigger_ends_the_wait() {
+        let mut chain = funded_chain();
+        *chain.price_of_weth.lock().unwrap() = 1600.into();
+        chain.price_updates = Some(watch::channel(None).0);
+        let solver = buy_order(chain, None);
+        assert!(!solver.exec_solver_step().await.ok().unwrap().succeeded);
+
+        let price_updates = solver.chain.price_updates.as_ref().unwrap();
+        let wait = solver.wait_next_step(Duration::from_secs(600));
+        let crossing = async {
+            price_updates.send_replace(Some(1550.into()));
+            tokio::task::yield_now().await;
+            price_updates.send_replace(Some(1500.into()));
+        };
+        let waited = timeout(Duration::from_secs(5), async {
+            tokio::join!(wait, crossing)
+        });
+        assert!(waited.await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn preconditions_need_the_proxy_balance() {
+        let mut chain = funded_chain();
+        chain.token_balances.remove(&(dai(), proxy()));
+        let solver = buy_order(chain, None);
+        let response = solver.check_preconditions().await.ok().unwrap();
+        assert!(!response.succeeded);
+        assert!(
+            response.message.contains("the proxy"),
+            "{}",
+            response.message
+        );
+
+        let solver = buy_order(funded_chain(), None);
+        assert!(solver.check_preconditions().await.ok().unwrap().succeeded);
+    }
+
+    #[tokio::test]
+    async fn preconditions_need_the_allowance_of_the_pull() {
+        let user = Address::repeat_byte(0x55);
+        let transfer_from = CallObject {
+            amount: 0.into(),
+            addr: dai(),
+            gas: 100000.into(),
+            callvalue: IERC20Calls::TransferFrom(TransferFromCall {
+                from: user,
+                to: call_breaker(),
+                amount: 10.into(),
+            })
+            .encode()
+            .into(),
+        };
+        let mut chain = funded_chain();
+        chain.allowances.insert((dai(), user, proxy()), 5.into());
+        let mut solver = buy_order(chain, None);
+        solver.pushed_calls = vec![transfer_from.clone()];
+        let response = solver.check_preconditions().await.ok().unwrap();
+        assert!(!response.succeeded);
+        assert!(
+            response.message.contains("the pull transfers"),
+            "{}",
+            response.message
+        );
+
+        let mut chain = funded_chain();
+        chain.allowances.insert((dai(), user, proxy()), 10.into());
+        let mut solver = buy_order(chain, None);
+        solver.pushed_calls = vec![transfer_from];
+        assert!(solver.check_preconditions().await.ok().unwrap().succeeded);
+    }
+
+    #[tokio::test]
+    async fn flash_loan_liquidity_follows_the_token_decimals() {
+        // Token 0 has 6 decimals like USDC.
+        let usdc = TokenMetadata {
+            decimals: 6,
+            symbol: "USDC".to_string(),
+        };
+        let usdc_liquidity = U256::from(HARDCODED_TOKEN_0_LIQUIDITY) * usdc.unit();
+        let mut chain = funded_chain();
+        chain.token_metadata.insert(dai(), usdc.clone());
+        chain
+            .token_balances
+            .insert((dai(), flash_loan()), usdc_liquidity);
+        let solver = buy_order(chain, None);
+        assert_eq!(
+            solver.pool_liquidity(dai(), weth()).await.ok().unwrap(),
+            (
+                usdc_liquidity,
+                U256::from(HARDCODED_TOKEN_1_LIQUIDITY) * U256::exp10(18)
+            )
+        );
+        assert!(solver.check_preconditions().await.ok().unwrap().succeeded);
+
+        let mut chain = funded_chain();
+        chain.token_metadata.insert(dai(), usdc);
+        chain
+            .token_balances
+            .insert((dai(), flash_loan()), usdc_liquidity - 1);
+        let solver = buy_order(chain, None);
+        let response = solver.check_preconditions().await.ok().unwrap();
+        assert!(!response.succeeded);
+        assert!(
+            response.message.contains("the flash loan"),
+            "{}",
+            response.message
+        );
+    }
+
+    #[tokio::test]
+    async fn relayed_orders_need_no_wallet_funds() {
+        let mut chain = funded_chain();
+        chain.balance = 0.into();
+        let solver = buy_order(chain, None);
+        assert!(!solver.check_preconditions().await.ok().unwrap().succeeded);
+
+        let mut chain = funded_chain();
+        chain.balance = 0.into();
+        chain.relayed = true;
+        let solver = buy_order(chain, None);
+        assert!(solver.check_preconditions().await.ok().unwrap().succeeded);
+    }
+
+    #[tokio::test]
+    async fn gas_above_the_tip_is_unprofitable() {
+        let mut chain = funded_chain();
+        chain.gas_price = 100.into();
+        let solver = buy_order(chain, None);
+        assert!(!solver.check_profitability().await.ok().unwrap().succeeded);
+
+        let solver = buy_order(funded_chain(), None);
+        assert!(solver.check_profitability().await.ok().unwrap().succeeded);
+
+        // Orders without a tip aren't gated.
+        let mut chain = funded_chain();
+        chain.gas_price = 100.into();
+        let mut values = buy_order_values();
+        values.retain(|(name, _)| *name != "tip");
+        let params = solver_params(
+            None,
+            HashMap::from([
+                (FLASH_LOAN_NAME.to_string(), flash_loan()),
+                (SWAP_POOL_NAME.to_string(), Address::repeat_byte(0x77)),
+            ]),
+        );
+        let solver = LimitOrderSolver::with_chain_client(order_event(values), params, chain)
+            .ok()
+            .unwrap();
+        assert!(solver.check_profitability().await.ok().unwrap().succeeded);
+    }
+
+    #[tokio::test]
+    async fn swap_surplus_pays_for_the_gas() {
+        // A direct buy of WETH for 3000 DAI at 1500, the pool quotes 1000.
+        let mut chain = funded_chain();
+        chain.gas_price = 100.into();
+        *chain.price_of_weth.lock().unwrap() = 1000.into();
+        let mut values = buy_order_values();
+        values.retain(|(name, _)| *name != "amount");
+        values.push(("amount", (U256::exp10(18) * U256::from(3000)).to_string()));
+        values.push(("strategy", "direct".to_string()));
+        let params = solver_params(
+            None,
+            HashMap::from([(SWAP_POOL_NAME.to_string(), Address::repeat_byte(0x77))]),
+        );
+        let solver = LimitOrderSolver::with_chain_client(order_event(values), params, chain)
+            .ok()
+            .unwrap();
+        // Before a step there is no desired price to compare with.
+        assert_eq!(solver.surplus_wei().await.ok().unwrap(), U256::zero());
+        assert!(!solver.check_profitability().await.ok().unwrap().succeeded);
+        // 3 WETH at the pool price instead of 2 at the desired one.
+        *solver.last_trigger.lock().await = Some((order_price::from_whole(1500.into()), true));
+        assert_eq!(solver.surplus_wei().await.ok().unwrap(), U256::exp10(18));
+        assert!(solver.check_profitability().await.ok().unwrap().succeeded);
+    }
+
+    #[tokio::test]
+    async fn final_exec_follows_the_receipt_status() {
+        let solver = buy_order(funded_chain(), None);
+        assert!(solver.final_exec().await.ok().unwrap().succeeded);
+        assert_eq!(solver.chain.sent.lock().unwrap().len(), 1);
+        let accounting = solver.accounting().unwrap();
+        assert_eq!(accounting.gas_spent_wei, 100000.into());
+        assert_eq!(accounting.tips_earned_wei, 1000000.into());
+
+        // A reverted transaction costs the gas and earns no tip.
+        let mut chain = funded_chain();
+        chain.receipt_status = Some(0);
+        let solver = buy_order(chain, None);
+        assert!(!solver.final_exec().await.ok().unwrap().succeeded);
+        let accounting = solver.accounting().unwrap();
+        assert_eq!(accounting.gas_spent_wei, 100000.into());
+        assert_eq!(accounting.tips_earned_wei, 0.into());
+
+        let mut chain = funded_chain();
+        chain.receipt_status = None;
+        let solver = buy_order(chain, None);
+        assert!(!solver.final_exec().await.ok().unwrap().succeeded);
+
+        // A bundle that missed its blocks is reported as such.
+        let mut chain = funded_chain();
+        chain.receipt_status = None;
+        chain.bundle = Some(BundleStatus::NotIncluded {
+            last_target_block: 12.into(),
+        });
+        let solver = buy_order(chain, None);
+        let response = solver.final_exec().await.ok().unwrap();
+        assert!(!response.succeeded);
+        assert_eq!(response.message, "bundle wasn't included up to block 12");
+        assert!(solver.bundle_status().is_some());
+    }
+
+    #[tokio::test]
+    async fn transaction_dropped_by_a_reorg_is_sent_again() {
+        let mut chain = funded_chain();
+        chain.dropped = true;
+        let mut solver = buy_order(chain, None);
+        solver.confirmations = 2;
+        match solver.final_exec().await {
+            Err(err) => assert!(err.is_retryable()),
+            Ok(response) => panic!("dropped transaction succeeded: {}", response.message),
+        }
+        assert!(solver.tx_hash().is_none());
+        assert!(solver.accounting().is_none());
+
+        let mut solver = buy_order(funded_chain(), None);
+        solver.confirmations = 2;
+        assert!(solver.final_exec().await.ok().unwrap().succeeded);
+    }
+
+    #[tokio::test]
+    async fn call_executed_by_another_solver_is_solved_externally() {
+        let solver = buy_order(funded_chain(), None);
+        assert!(solver.solved_externally().await.ok().unwrap().is_none());
+
+        let mut chain = funded_chain();
+        chain.call_executed = true;
+        let solver = buy_order(chain, None);
+        assert!(solver.solved_externally().await.ok().unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn dry_run_sends_nothing() {
+        let mut chain = funded_chain();
+        chain.dry_run = true;
+        let solver = buy_order(chain, None);
+        assert!(solver.final_exec().await.ok().unwrap().succeeded);
+        assert!(solver.chain.sent.lock().unwrap().is_empty());
+        assert!(solver.accounting().is_none());
+    }
+
+    #[tokio::test]
+    async fn direct_swap_without_flash_loan() {
+        let contracts = HashMap::from([(SWAP_POOL_NAME.to_string(), Address::repeat_byte(0x77))]);
+        let solver = buy_order_with(funded_chain(), None, contracts.clone());
+        assert_eq!(solver.strategy, ExecutionStrategy::Direct);
+        let response = solver.check_preconditions().await.ok().unwrap();
+        assert!(
+            response.message.contains("the call breaker"),
+            "{}",
+            response.message
+        );
+
+        let mut chain = funded_chain();
+        chain
+            .token_balances
+            .insert((dai(), call_breaker()), 10.into());
+        let solver = buy_order_with(chain, None, contracts);
+        assert!(solver.check_preconditions().await.ok().unwrap().succeeded);
+        assert!(solver.final_exec().await.ok().unwrap().succeeded);
+        assert_eq!(solver.chain.sent.lock().unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "aggregator")]
+    #[tokio::test]
+    async fn aggregator_swap_returns_the_executed_amount() {
+        let router = Address::repeat_byte(0x1c);
+        let quote = AggregatorQuote {
+            router,
+            calldata: Bytes::from(vec![0x07, 0xed, 0x23, 0x79]),
+            value: 0.into(),
+            gas: 210000.into(),
+            sell_amount: 10.into(),
+            buy_amount: 1000.into(),
+            min_buy_amount: 970.into(),
+        };
+        // The price moved since the quote, the route delivers less than quoted.
+        let executed: Bytes = (U256::from(990), U256::from(10)).encode().into();
+        let mut chain = funded_chain();
+        chain.call_outputs.insert(router, executed.clone());
+        let solver = buy_order(chain, None);
+        let plan = solver.aggregator_swap_plan(&quote);
+        let returns = plan.return_objects(&solver.chain).await.ok().unwrap();
+        assert_eq!(returns.len(), 3);
+        assert_eq!(returns[0].returnvalue, Bytes::from(true.encode()));
+        assert_eq!(returns[2].returnvalue, executed);
+    }
+
+    #[tokio::test]
+    async fn uniswap_v3_swap_follows_the_quote() {
+        let factory = Address::repeat_byte(0xfa);
+        let quoter = Address::repeat_byte(0x9a);
+        let contracts = HashMap::from([
+            (uniswap_v3::FACTORY_NAME.to_string(), factory),
+            (uniswap_v3::QUOTER_NAME.to_string(), quoter),
+        ]);
+        let mut values = buy_order_values();
+        values.push(("strategy", "uniswap_v3".to_string()));
+        values.push(("fee", "500".to_string()));
+        let params = solver_params(None, contracts.clone());
+        assert!(matches!(
+            LimitOrderSolver::with_chain_client(
+                order_event(values.clone()),
+                params,
+                funded_chain()
+            ),
+            Err(SolverError::ParamError(_))
+        ));
+
+        // The pool quotes 1500 DAI per WETH, the swap moves the price by 2%.
+        let mut chain = funded_chain();
+        chain
+            .token_balances
+            .insert((dai(), call_breaker()), 10.into());
+        let sqrt_price = U256::from(2).pow(96.into());
+        chain.call_outputs = HashMap::from([
+            (
+                quoter,
+                (U256::exp10(18) * 1500, sqrt_price * 101 / 100)
+                    .encode()
+                    .into(),
+            ),
+            (
+                uniswap_v3::pool_address(factory, dai(), weth(), 500),
+                sqrt_price.encode().into(),
+            ),
+        ]);
+        let mut contracts = contracts;
+        contracts.insert(
+            uniswap_v3::ROUTER_NAME.to_string(),
+            Address::repeat_byte(0x5e),
+        );
+        let params = solver_params(None, contracts);
+        let solver = match LimitOrderSolver::with_chain_client(order_event(values), params, chain) {
+            Ok(solver) => solver,
+            Err(err) => panic!("{}", err),
         };
+        assert!(solver.exec_solver_step().await.ok().unwrap().succeeded);
+        assert!(solver.check_preconditions().await.ok().unwrap().succeeded);
+        assert!(solver.final_exec().await.ok().unwrap().succeeded);
+        assert_eq!(solver.chain.sent.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn unknown_strategy_lists_the_ones_built() {
+        let err = ExecutionStrategy::from_str("otc").err().unwrap();
+        assert_eq!(
+            err.contains("aggregator"),
+            cfg!(feature = "aggregator"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn price_impact_follows_the_reserves() {
+        let ether = U256::exp10(18);
+        // Swapping 1% of a reserve moves the price by about 2%.
+        assert_eq!(
+            price_impact_bps(ether * 1000, ether * 100, ether, true),
+            Some(20.into())
+        );
+        assert_eq!(
+            price_impact_bps(ether * 1000, ether * 100, ether * 10, true),
+            Some(200.into())
+        );
+        assert_eq!(
+            price_impact_bps(ether * 1000, ether * 100, ether, false),
+            Some(197.into())
+        );
+        assert_eq!(price_impact_bps(0.into(), ether, ether, true), None);
+        // Reserves near the limit of U256 don't overflow.
+        assert_eq!(
+            price_impact_bps(U256::MAX, U256::MAX, U256::MAX, true),
+            None
+        );
+        assert_eq!(
+            price_impact_bps(U256::MAX / 2, U256::MAX / 2, ether, false),
+            Some(0.into())
+        );
+    }
+
+    // A value for a parameter, mostly malformed.
+    fn garbage_value(rng: &mut StdRng) -> String {
+        match rng.gen_range(0..11) {
+            0 => String::from_utf8_lossy(
+                &(0..rng.gen_range(0..40))
+                    .map(|_| rng.gen())
+                    .collect::<Vec<u8>>(),
+            )
+            .to_string(),
+            1 => "9".repeat(rng.gen_range(1..200)),
+            2 => format!("-{}", rng.gen::<u64>()),
+            3 => format!("{} weeks", "9".repeat(rng.gen_range(1..30))),
+            4 => format!("1e{} s", rng.gen::<u64>()),
+            5 => format!("0x{}", "z".repeat(rng.gen_range(0..50))),
+            6 => "ΔΣ€\u{0}\u{202e}🙂".to_string(),
+            7 => String::new(),
+            8 => format!("{:?}", Address::random_using(rng)),
+            9 => [
+                "buy",
+                "sell",
+                "limit",
+                "stop_loss",
+                "trailing_stop",
+                "direct",
+            ][rng.gen_range(0..6)]
+            .to_string(),
+            _ => rng.gen::<u32>().to_string(),
+        }
+    }
+
+    #[test]
+    fn malformed_data_values_are_rejected_without_panics() {
+        let names = [
+            "give_token",
+            "take_token",
+            "amount",
+            "buy_price",
+            "sell_price",
+            "stop_price",
+            "trailing_percent",
+            "slippage",
+            "time_limit",
+            "tip",
+            "strategy",
+            "fee",
+            "order_type",
+            "direction",
+        ];
+        let mut rng = StdRng::seed_from_u64(4069);
+        for _ in 0..500 {
+            let mut data_values = Vec::new();
+            for name in names {
+                if rng.gen_bool(0.8) {
+                    data_values.push((name, garbage_value(&mut rng)));
+                }
+            }
+            let params = solver_params(
+                None,
+                HashMap::from([(SWAP_POOL_NAME.to_string(), Address::repeat_byte(0x77))]),
+            );
+            match LimitOrderSolver::with_chain_client(
+                order_event(data_values),
+                params,
+                funded_chain(),
+            ) {
+                Ok(_) | Err(SolverError::ParamError(_)) => {}
+                Err(err) => panic!("unexpected error {}", err),
+            }
+        }
     }
 }
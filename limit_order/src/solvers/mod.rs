@@ -1 +1,5 @@
 pub(crate) mod limit_order;
+pub(crate) mod order_price;
+#[cfg(feature = "plugins")]
+pub(crate) mod plugin;
+pub(crate) mod uniswap_v3;
@@ -0,0 +1,108 @@
+use ethers::{
+    types::{U256, U512},
+    utils::format_units,
+};
+
+// The prices of the orders are of their asset in their quote token, whichever way the
+// pool quotes the pair: the asset is what buy orders take and sell orders give. They
+// are scaled by 10^18 so that prices below one keep their precision, e.g. on inverse
+// pairs.
+const DECIMALS: usize = 18;
+
+fn scale() -> U256 {
+    U256::exp10(DECIMALS)
+}
+
+// a * b / c, saturating if the result doesn't fit.
+fn mul_div(a: U256, b: U256, c: U256) -> U256 {
+    checked_mul_div(a, b, c).unwrap_or(U256::MAX)
+}
+
+// a * b / c, None if c is zero or the result doesn't fit.
+pub fn checked_mul_div(a: U256, b: U256, c: U256) -> Option<U256> {
+    if c.is_zero() {
+        return None;
+    }
+    U256::try_from(a.full_mul(b) / U512::from(c)).ok()
+}
+
+// A price in whole quote tokens per whole asset token, as the orders set them.
+pub fn from_whole(price: U256) -> U256 {
+    price.saturating_mul(scale())
+}
+
+// The price of a pool quoting its token 1 in whole tokens 0, inverted if the asset of
+// the order is token 0.
+pub fn from_pool(pool_price: U256, asset_is_token_1: bool) -> U256 {
+    let price = from_whole(pool_price);
+    match asset_is_token_1 {
+        true => price,
+        false if price.is_zero() => U256::MAX,
+        false => mul_div(scale(), scale(), price),
+    }
+}
+
+// The price from the quote of a whole asset token, in base units of the quote token.
+pub fn from_quote(amount_out: U256, quote_unit: U256) -> U256 {
+    mul_div(amount_out, scale(), quote_unit)
+}
+
+// The amount of asset for an amount of quote token at the price, both in base units.
+pub fn asset_for(quote_amount: U256, price: U256, asset_unit: U256, quote_unit: U256) -> U256 {
+    if price.is_zero() {
+        return U256::zero();
+    }
+    mul_div(
+        mul_div(quote_amount, scale(), price),
+        asset_unit,
+        quote_unit,
+    )
+}
+
+// The amount of quote token for an amount of asset at the price, both in base units.
+pub fn quote_for(asset_amount: U256, price: U256, asset_unit: U256, quote_unit: U256) -> U256 {
+    mul_div(
+        mul_div(asset_amount, price, scale()),
+        quote_unit,
+        asset_unit,
+    )
+}
+
+// The price in whole quote tokens, e.g. 1500 or 0.0005.
+pub fn format(price: U256) -> String {
+    match format_units(price, DECIMALS as u32) {
+        Ok(whole) => whole
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string(),
+        Err(_) => price.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverse_pools_quote_the_order_asset() {
+        // The pool quotes 2000 of its token 0 per token 1, the asset is token 0.
+        let price = from_pool(2000.into(), false);
+        assert_eq!(format(price), "0.0005");
+        assert_eq!(format(from_pool(1500.into(), true)), "1500");
+    }
+
+    #[test]
+    fn amounts_follow_the_decimals() {
+        let price = from_pool(2000.into(), false);
+        // A whole quote token of 18 decimals buys 2000 of the asset of 6 decimals.
+        let ether = U256::exp10(18);
+        assert_eq!(
+            asset_for(ether, price, 1000000.into(), ether),
+            2000000000.into()
+        );
+        assert_eq!(
+            quote_for(2000000000.into(), price, 1000000.into(), ether),
+            ether
+        );
+    }
+}
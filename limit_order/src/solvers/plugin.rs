@@ -0,0 +1,217 @@
+use crate::{
+    contracts_abi::{
+        call_breaker::{CallBreaker, CallObject, ReturnObject},
+        ProxyPushedFilter,
+    },
+    encoded_data::{hint_indices, AssociatedData},
+    param_schema::{ParamSchema, ParamType},
+    plugins::{PluginInstance, SolverPlugin},
+    solver::{self, DecisionInput, Solver, SolverError, SolverParams, SolverResponse},
+    submission::SubmissionPolicy,
+};
+use ethers::{
+    abi::AbiEncode,
+    providers::Middleware,
+    types::{Address, Bytes, H256, U256},
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+use tokio::sync::Mutex;
+
+// Gas limit of the final transaction.
+const FINAL_EXEC_GAS: u64 = 10000000;
+
+// The calls a plugin solves the objective with, the solver tip and pull index are added
+// to the associated data by the host.
+#[derive(Deserialize)]
+struct FinalCalls {
+    calls: Vec<CallObject>,
+    returns: Vec<ReturnObject>,
+    #[serde(default)]
+    associated_data: Vec<AssociatedEntry>,
+}
+
+#[derive(Deserialize)]
+struct AssociatedEntry {
+    name: String,
+    value: Bytes,
+}
+
+// A solver for an app implemented by a solver plugin.
+pub struct PluginSolver<M> {
+    app: String,
+    instance: PluginInstance<M>,
+    solver_address: Address,
+    sequence_number: U256,
+    // Amount of the objective for the submission rules, zero if not given.
+    amount: U256,
+    time_limit: Duration,
+    middleware: Arc<M>,
+    call_breaker_contract: CallBreaker<M>,
+    guard: Arc<Mutex<bool>>,
+    submission_policy: Arc<SubmissionPolicy>,
+    // Hash of the last final transaction with a receipt.
+    tx_hash: StdMutex<Option<H256>>,
+}
+
+impl<M: Middleware + Clone + 'static> PluginSolver<M> {
+    pub fn new(
+        plugin: Arc<SolverPlugin>,
+        event: ProxyPushedFilter,
+        params: SolverParams<M>,
+    ) -> Result<PluginSolver<M>, SolverError> {
+        // The plugin checks the rest of the parameters itself.
+        let executor_params = ParamSchema::new()
+            .optional("time_limit", ParamType::Duration)
+            .optional("amount", ParamType::uint())
+            .validate(&event.data_values)?;
+        // Objectives without a time limit take the one of the app, if configured.
+        let time_limit = match executor_params
+            .duration("time_limit")
+            .or(params.default_time_limit)
+        {
+            Some(time_limit) => time_limit,
+            None => return Err(SolverError::ParamError("time_limit is missing".to_string())),
+        };
+        let amount = executor_params.uint("amount").unwrap_or_default();
+        let objective = json!({
+            "objective": &event,
+            "call_breaker_address": params.call_breaker_address,
+            "solver_address": params.solver_address,
+            "contracts": &params.extra_contract_addresses,
+        });
+        let instance = plugin
+            .instantiate(&objective, params.middleware.clone())
+            .map_err(SolverError::ParamError)?;
+        Ok(PluginSolver {
+            app: plugin.app.clone(),
+            instance,
+            solver_address: params.solver_address,
+            sequence_number: event.sequence_number,
+            amount,
+            time_limit,
+            middleware: params.middleware.clone(),
+            call_breaker_contract: CallBreaker::new(
+                params.call_breaker_address,
+                params.middleware.clone(),
+            ),
+            guard: params.guard.clone(),
+            submission_policy: params.submission_policy.clone(),
+            tx_hash: StdMutex::new(None),
+        })
+    }
+
+    fn request(&self, method: &str) -> Result<SolverResponse, SolverError> {
+        #[derive(Deserialize)]
+        struct Response {
+            succeeded: bool,
+            message: String,
+            // The plugins may tell what the step was decided on.
+            #[serde(default)]
+            decision: Vec<DecisionInput>,
+        }
+        let response = self
+            .instance
+            .call::<Response>(method)
+            .map_err(|err| SolverError::ExecError(format!("Plugin {} error: {}", method, err)))?;
+        Ok(SolverResponse {
+            succeeded: response.succeeded,
+            message: response.message,
+            decision: response.decision,
+        })
+    }
+}
+
+impl<M: Middleware + Clone + 'static> Solver for PluginSolver<M> {
+    fn app(&self) -> String {
+        self.app.clone()
+    }
+
+    fn time_limit(&self) -> Result<Duration, parse_duration::parse::Error> {
+        Ok(self.time_limit)
+    }
+
+    fn tx_hash(&self) -> Option<H256> {
+        *self.tx_hash.lock().unwrap()
+    }
+
+    async fn exec_solver_step(&self) -> Result<SolverResponse, SolverError> {
+        self.request("step")
+    }
+
+    async fn check_preconditions(&self) -> Result<SolverResponse, SolverError> {
+        self.request("preconditions")
+    }
+
+    async fn check_profitability(&self) -> Result<SolverResponse, SolverError> {
+        self.request("profitability")
+    }
+
+    async fn final_exec(&self) -> Result<SolverResponse, SolverError> {
+        let final_calls = self
+            .instance
+            .call::<FinalCalls>("final_calls")
+            .map_err(|err| SolverError::ExecError(format!("Plugin final_calls error: {}", err)))?;
+        let mut associated_data = AssociatedData::new()
+            .with(
+                "tipYourBartender",
+                self.solver_address.as_bytes().to_vec().into(),
+            )
+            .with("pullIndex", self.sequence_number.encode().into());
+        for entry in final_calls.associated_data {
+            associated_data = associated_data.with(entry.name.as_str(), entry.value);
+        }
+        let hintdices = hint_indices(&final_calls.calls);
+        let tx = self
+            .call_breaker_contract
+            .execute_and_verify(
+                final_calls.calls.encode().into(),
+                final_calls.returns.encode().into(),
+                associated_data.encode(),
+                hintdices,
+            )
+            .gas(FINAL_EXEC_GAS)
+            .tx;
+
+        if self.submission_policy.dry_run() {
+            return solver::dry_run(&self.submission_policy, self.middleware.as_ref(), tx).await;
+        }
+        let _guard = self.guard.lock().await;
+        let res = self
+            .submission_policy
+            .submit(self.app.as_str(), self.amount, self.middleware.as_ref(), tx)
+            .await
+            .map(|submitted| submitted.receipt);
+        if let Ok(receipt) = &res {
+            *self.tx_hash.lock().unwrap() =
+                receipt.as_ref().map(|receipt| receipt.transaction_hash);
+        }
+        match res {
+            Ok(Some(receipt)) => match receipt.status {
+                Some(status) => Ok(SolverResponse {
+                    succeeded: status != 0.into(),
+                    message: format!("Transaction status: {}", status),
+                    decision: Vec::new(),
+                }),
+                None => Ok(SolverResponse {
+                    succeeded: false,
+                    message: "transaction status wasn't received".to_string(),
+                    decision: Vec::new(),
+                }),
+            },
+            Ok(None) => Ok(SolverResponse {
+                succeeded: false,
+                message: "transaction status wasn't received".to_string(),
+                decision: Vec::new(),
+            }),
+            Err(err) => Err(SolverError::TxError(format!(
+                "Final execution error: {}",
+                err
+            ))),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use ethers::{
+    abi::{self, AbiEncode, Token},
+    prelude::abigen,
+    types::{Address, Bytes, H256, U256, U512},
+    utils::{get_create2_address_from_hash, keccak256},
+};
+
+use crate::contracts_abi::call_breaker::CallObject;
+
+abigen!(
+    QuoterV2,
+    "./abi_town/IQuoterV2.sol/IQuoterV2.json";
+
+    SwapRouter,
+    "./abi_town/ISwapRouter.sol/ISwapRouter.json";
+
+    UniswapV3Pool,
+    "./abi_town/IUniswapV3Pool.sol/IUniswapV3Pool.json";
+);
+
+pub const FACTORY_NAME: &str = "UNISWAP_V3_FACTORY";
+pub const QUOTER_NAME: &str = "UNISWAP_V3_QUOTER";
+pub const ROUTER_NAME: &str = "UNISWAP_V3_ROUTER";
+
+// Fee tier of the pools the orders not giving one are swapped on, in hundredths of a bip.
+pub const DEFAULT_FEE: u32 = 3000;
+
+// Hash of the init code of the pools deployed by the canonical factory.
+const POOL_INIT_CODE_HASH: &str =
+    "0xe34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54";
+
+// Bounds of the sqrt price, exclusive, from TickMath.
+const MIN_SQRT_RATIO: u64 = 4295128739;
+const MAX_SQRT_RATIO: &str = "1461446703485210103287273052203988822378723970342";
+
+// Gas limit of the swap call.
+const SWAP_GAS: u64 = 10000000;
+
+// The pool of the pair and fee tier, derived from the factory that deployed it.
+pub fn pool_address(factory: Address, token_a: Address, token_b: Address, fee: u32) -> Address {
+    let (token_0, token_1) = if token_a < token_b {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    };
+    let salt = keccak256(abi::encode(&[
+        Token::Address(token_0),
+        Token::Address(token_1),
+        Token::Uint(fee.into()),
+    ]));
+    let init_code_hash: H256 = POOL_INIT_CODE_HASH.parse().unwrap_or_default();
+    get_create2_address_from_hash(factory, salt, init_code_hash)
+}
+
+// The price the swap may move the pool to, the given percentage of slippage away from
+// the current sqrt price. Selling token 0 lowers the price, selling token 1 raises it.
+pub fn sqrt_price_limit(sqrt_price_x96: U256, zero_for_one: bool, slippage_percent: U256) -> U256 {
+    let scale = U256::exp10(18);
+    let percent = slippage_percent.min(100.into());
+    let ratio = if zero_for_one {
+        U256::from(100) - percent
+    } else {
+        U256::from(100) + percent
+    };
+    // The square root of the ratio of the prices, scaled by 10^18.
+    let factor = (ratio * scale * scale / 100).integer_sqrt();
+    let limit = sqrt_price_x96.full_mul(factor) / U512::from(scale);
+    let min = U256::from(MIN_SQRT_RATIO) + 1;
+    let max = U256::from_dec_str(MAX_SQRT_RATIO).unwrap_or_default() - 1;
+    match U256::try_from(limit) {
+        Ok(limit) => limit.clamp(min, max),
+        Err(_) => max,
+    }
+}
+
+// The swap of an order on a Uniswap V3 pool through the router.
+#[derive(Clone, Copy, Debug)]
+pub struct UniswapV3 {
+    pub quoter: Address,
+    pub router: Address,
+    pub pool: Address,
+    pub fee: u32,
+}
+
+impl UniswapV3 {
+    // The current price of the pool, its sqrt price is the first output.
+    pub fn slot0_call(&self) -> CallObject {
+        CallObject {
+            amount: 0.into(),
+            addr: self.pool,
+            gas: SWAP_GAS.into(),
+            callvalue: UniswapV3PoolCalls::Slot0(Slot0Call).encode().into(),
+        }
+    }
+
+    // The quote of the swap, the amount out is the first output.
+    pub fn quote_call(&self, token_in: Address, token_out: Address, amount_in: U256) -> CallObject {
+        CallObject {
+            amount: 0.into(),
+            addr: self.quoter,
+            gas: SWAP_GAS.into(),
+            callvalue: QuoteExactInputSingleCall {
+                params: QuoteExactInputSingleParams {
+                    token_in,
+                    token_out,
+                    amount_in,
+                    fee: self.fee,
+                    sqrt_price_limit_x96: U256::zero(),
+                },
+            }
+            .encode()
+            .into(),
+        }
+    }
+
+    // Swaps the exact amount in, the received tokens go to the recipient. The swap
+    // reverts below the minimum out and stops at the price limit.
+    pub fn exact_input_single_call(
+        &self,
+        token_in: Address,
+        token_out: Address,
+        recipient: Address,
+        amount_in: U256,
+        amount_out_minimum: U256,
+        sqrt_price_limit_x96: U256,
+    ) -> CallObject {
+        CallObject {
+            amount: 0.into(),
+            addr: self.router,
+            gas: SWAP_GAS.into(),
+            callvalue: ExactInputSingleCall {
+                params: ExactInputSingleParams {
+                    token_in,
+                    token_out,
+                    fee: self.fee,
+                    recipient,
+                    // The final transaction is bound by the time limit of the order.
+                    deadline: U256::MAX,
+                    amount_in,
+                    amount_out_minimum,
+                    sqrt_price_limit_x96,
+                },
+            }
+            .encode()
+            .into(),
+        }
+    }
+}
+
+// The output of a call at the given position as an unsigned integer.
+pub fn output_uint(output: &Bytes, index: usize) -> Option<U256> {
+    output
+        .get(index * 32..(index + 1) * 32)
+        .map(U256::from_big_endian)
+}
+
+// How far the swap moves the price, in basis points, from the sqrt prices before and
+// after it. None if the pool has no price.
+pub fn price_impact_bps(sqrt_price_before: U256, sqrt_price_after: U256) -> Option<U256> {
+    if sqrt_price_before.is_zero() {
+        return None;
+    }
+    let before = sqrt_price_before.full_mul(sqrt_price_before);
+    let after = sqrt_price_after.full_mul(sqrt_price_after);
+    let moved = if after > before {
+        after - before
+    } else {
+        before - after
+    };
+    U256::try_from(moved * U512::from(10000) / before).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pools_are_derived_from_the_factory() {
+        let factory: Address = "0x1F98431c8aD98523631AE4a59f267346ea31F984"
+            .parse()
+            .ok()
+            .unwrap();
+        let usdc: Address = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+            .parse()
+            .ok()
+            .unwrap();
+        let weth: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
+            .parse()
+            .ok()
+            .unwrap();
+        let pool: Address = "0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"
+            .parse()
+            .ok()
+            .unwrap();
+        assert_eq!(pool_address(factory, weth, usdc, 500), pool);
+        assert_eq!(pool_address(factory, usdc, weth, 500), pool);
+        assert_ne!(pool_address(factory, usdc, weth, 3000), pool);
+    }
+
+    #[test]
+    fn price_limit_follows_the_slippage() {
+        let sqrt_price = U256::from(2).pow(96.into());
+        // sqrt(0.96) and sqrt(1.04) of the price.
+        let down = sqrt_price_limit(sqrt_price, true, 4.into());
+        let up = sqrt_price_limit(sqrt_price, false, 4.into());
+        assert_eq!(down * 1000 / sqrt_price, 979.into());
+        assert_eq!(up * 1000 / sqrt_price, 1019.into());
+        // The limit stays within the bounds of the pool.
+        assert_eq!(
+            sqrt_price_limit(sqrt_price, true, 100.into()),
+            U256::from(MIN_SQRT_RATIO) + 1
+        );
+        // Moving the sqrt price by 1% moves the price by about 2%.
+        assert_eq!(
+            price_impact_bps(sqrt_price, sqrt_price * 101 / 100),
+            Some(200.into())
+        );
+        assert_eq!(price_impact_bps(0.into(), sqrt_price), None);
+    }
+}
@@ -1,14 +1,23 @@
-use axum::{extract::State, response::Json};
+use axum::{extract::State, response::Json, routing::get, Router};
+use ethers::types::{H256, U256};
 use serde::{Deserialize, Serialize};
-use tokio::sync::{mpsc::Receiver, Mutex};
 use std::{
     collections::HashMap,
     sync::Arc,
-    time::Duration,
+    time::{Duration, SystemTime},
 };
+use tokio::sync::Mutex;
 use uuid::Uuid;
 
-use crate::contracts_abi::laminator::AdditionalData;
+use crate::{
+    accounting::{Accounting, ObjectiveAccounting},
+    contracts_abi::laminator::AdditionalData,
+    decisions::{self, DecisionHistories},
+    solver::DecisionInput,
+    stats_channel::{OutcomeBus, StatsReceiver},
+    submission::BundleStatus,
+    timeline::{self, Timelines},
+};
 
 // Executor statistics
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
@@ -17,6 +26,11 @@ pub enum Status {
     Succeeded,
     Failed,
     Timeout,
+    Duplicate,
+    // Another solver executed the objective first.
+    SolvedExternally,
+    // The failed final transactions spent the gas budget of the objective.
+    BudgetExhausted,
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
@@ -25,8 +39,16 @@ pub enum TransactionStatus {
     StepFailed,
     TransactionFailed,
     StepPending,
+    PreconditionsFailed,
+    Unprofitable,
     TransactionPending,
     NotExecuted,
+    // The final transaction was simulated in the dry run mode, not sent.
+    Simulated,
+    // The final transaction is held by the operator.
+    Suspended,
+    // The final transaction was mined and reverted, it may be sent again.
+    Reverted,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -41,6 +63,65 @@ pub struct TimerExecutorStats {
     pub params: Vec<AdditionalData>,
     pub elapsed: Duration,
     pub remaining: Duration,
+    // Hash of the last final transaction with a receipt.
+    #[serde(default)]
+    pub tx_hash: Option<H256>,
+    // What the objective cost and earned, in the stats of the finished executors that
+    // sent a final transaction.
+    #[serde(default)]
+    pub accounting: Option<ObjectiveAccounting>,
+    // Whether the bundle of the last final transaction landed, if it was sent in one.
+    #[serde(default)]
+    pub bundle_status: Option<BundleStatus>,
+    // The amount of the objective in whole tokens, once the solver read the token.
+    #[serde(default)]
+    pub amount: Option<String>,
+    // The values the last step was decided on.
+    #[serde(default)]
+    pub decision: Vec<DecisionInput>,
+}
+
+impl TimerExecutorStats {
+    // Stats of an objective that was received again and not executed.
+    pub fn duplicate(
+        sequence_number: U256,
+        app: String,
+        params: Vec<AdditionalData>,
+    ) -> TimerExecutorStats {
+        TimerExecutorStats {
+            id: Uuid::new_v4(),
+            sequence_number: sequence_number.as_u32(),
+            app,
+            creation_time: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default(),
+            status: Status::Duplicate,
+            transaction_status: TransactionStatus::NotExecuted,
+            message: "Duplicate objective, already being executed".to_string(),
+            params,
+            elapsed: Duration::new(0, 0),
+            remaining: Duration::new(0, 0),
+            tx_hash: None,
+            accounting: None,
+            bundle_status: None,
+            amount: None,
+            decision: Vec::new(),
+        }
+    }
+
+    // Whether the stats are of an outcome the operators are notified of: the executor
+    // finished, or its final transaction reverted.
+    pub fn is_outcome(&self) -> bool {
+        match self.status {
+            Status::Succeeded
+            | Status::Failed
+            | Status::Timeout
+            | Status::SolvedExternally
+            | Status::BudgetExhausted => true,
+            Status::Running => self.transaction_status == TransactionStatus::Reverted,
+            Status::Duplicate => false,
+        }
+    }
 }
 
 pub async fn get_stats_json(
@@ -55,11 +136,68 @@ pub async fn get_stats_json(
     Json(filtered)
 }
 
+// Stats of the executors of one app.
+pub async fn get_app_stats_json(
+    stats: State<Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>>,
+    app: String,
+) -> Json<Vec<TimerExecutorStats>> {
+    let Json(stats) = get_stats_json(stats).await;
+    Json(stats.into_iter().filter(|el| el.app == app).collect())
+}
+
+// Route path segment of an app, e.g. "cleanapp_scheduler" for "CLEANAPP.SCHEDULER".
+pub fn app_slug(app: &str) -> String {
+    app.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// Stats routes of the solver apps, /stats/{app slug} per app and /stats for all of them.
+pub fn stats_router(
+    apps: Vec<String>,
+    stats_map: Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>,
+) -> Router {
+    let mut router = Router::new().route("/stats", get(get_stats_json));
+    for app in apps {
+        router = router.route(
+            format!("/stats/{}", app_slug(app.as_str())).as_str(),
+            get(move |stats| get_app_stats_json(stats, app)),
+        );
+    }
+    router.with_state(stats_map)
+}
+
+// The outcomes are forwarded to the notifications and to the export if given. The status
+// transitions are recorded in the timelines, the decisions of the steps in their histories.
 pub async fn run_stats_receive(
-    rx: &mut Receiver<TimerExecutorStats>,
+    rx: &mut StatsReceiver,
     stats_map: Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>,
+    timelines: Timelines,
+    decision_histories: DecisionHistories,
+    accounting: Arc<Mutex<Accounting>>,
+    outcomes: OutcomeBus,
 ) {
     while let Some(stats) = rx.recv().await {
+        if let Some(objective_accounting) = &stats.accounting {
+            accounting.lock().await.add(
+                stats.app.as_str(),
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default(),
+                objective_accounting,
+            );
+        }
+        if stats.is_outcome() {
+            rx.publish(&outcomes, &stats);
+        }
+        timeline::record(&mut *timelines.lock().await, &stats);
+        decisions::record(&mut *decision_histories.lock().await, &stats);
         let mut stats_map = stats_map.lock().await;
         stats_map.insert(stats.id, stats);
     }
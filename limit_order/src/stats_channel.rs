@@ -0,0 +1,287 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{
+    mpsc::{
+        self,
+        error::{TryRecvError, TrySendError},
+        Receiver, Sender, UnboundedSender,
+    },
+    Notify,
+};
+use uuid::Uuid;
+
+use crate::stats::TimerExecutorStats;
+
+// What is done with the stats sent while the stats channel is full.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowPolicy {
+    // The executor waits for room in the channel.
+    Block,
+    // The latest stats of each executor are kept aside until the channel is drained,
+    // the stats they replace are dropped unless they are an outcome.
+    Coalesce,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(OverflowPolicy::Block),
+            "coalesce" => Ok(OverflowPolicy::Coalesce),
+            _ => Err(format!(
+                "unknown overflow policy {}, expected block or coalesce",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Overflow {
+    // The stats kept aside by executor.
+    latest: HashMap<Uuid, TimerExecutorStats>,
+    // Outcomes replaced by later stats of their executor, still delivered.
+    outcomes: Vec<TimerExecutorStats>,
+    overflowed: u64,
+    coalesced: u64,
+    dropped: u64,
+    // Outcomes not delivered to a consumer which is gone.
+    unforwarded: u64,
+}
+
+impl Overflow {
+    fn keep(&mut self, stats: TimerExecutorStats) {
+        if let Some(replaced) = self.latest.insert(stats.id, stats) {
+            if replaced.is_outcome() {
+                self.outcomes.push(replaced);
+            } else {
+                self.coalesced += 1;
+            }
+        }
+    }
+
+    fn take(&mut self) -> Vec<TimerExecutorStats> {
+        let mut taken = std::mem::take(&mut self.outcomes);
+        taken.extend(self.latest.drain().map(|(_, stats)| stats));
+        taken
+    }
+}
+
+#[derive(Clone)]
+pub struct StatsSender {
+    tx: Sender<TimerExecutorStats>,
+    policy: OverflowPolicy,
+    overflow: Arc<Mutex<Overflow>>,
+    notify: Arc<Notify>,
+}
+
+pub struct StatsReceiver {
+    rx: Receiver<TimerExecutorStats>,
+    overflow: Arc<Mutex<Overflow>>,
+    notify: Arc<Notify>,
+    ready: VecDeque<TimerExecutorStats>,
+}
+
+pub fn stats_channel(capacity: usize, policy: OverflowPolicy) -> (StatsSender, StatsReceiver) {
+    let (tx, rx) = mpsc::channel(capacity.max(1));
+    let overflow = Arc::new(Mutex::new(Overflow::default()));
+    let notify = Arc::new(Notify::new());
+    (
+        StatsSender {
+            tx,
+            policy,
+            overflow: overflow.clone(),
+            notify: notify.clone(),
+        },
+        StatsReceiver {
+            rx,
+            overflow,
+            notify,
+            ready: VecDeque::new(),
+        },
+    )
+}
+
+impl StatsSender {
+    pub async fn send(&self, stats: TimerExecutorStats) {
+        if self.policy == OverflowPolicy::Block {
+            if let Err(err) = self.tx.send(stats).await {
+                self.overflow.lock().unwrap().dropped += 1;
+                println!("Error sending stats: {}", err);
+            }
+            return;
+        }
+        {
+            // The later stats of an executor with stats kept aside are kept aside too, so
+            // that they are received in order.
+            let mut overflow = self.overflow.lock().unwrap();
+            if overflow.latest.contains_key(&stats.id) {
+                overflow.keep(stats);
+                return;
+            }
+        }
+        match self.tx.try_send(stats) {
+            Ok(()) => {}
+            Err(TrySendError::Full(stats)) => {
+                let mut overflow = self.overflow.lock().unwrap();
+                overflow.overflowed += 1;
+                overflow.keep(stats);
+                drop(overflow);
+                self.notify.notify_one();
+            }
+            Err(TrySendError::Closed(_)) => {
+                self.overflow.lock().unwrap().dropped += 1;
+                println!("Error sending stats: the channel is closed");
+            }
+        }
+    }
+
+    // The overflow counters in the Prometheus text format.
+    pub fn write_metrics(&self, body: &mut String) {
+        let overflow = self.overflow.lock().unwrap();
+        for (name, help, value) in [
+            (
+                "solver_stats_overflowed_total",
+                "Stats kept aside while the stats channel was full.",
+                overflow.overflowed,
+            ),
+            (
+                "solver_stats_coalesced_total",
+                "Stats replaced by later stats of the same executor.",
+                overflow.coalesced,
+            ),
+            (
+                "solver_stats_dropped_total",
+                "Stats which couldn't be sent.",
+                overflow.dropped,
+            ),
+            (
+                "solver_stats_unforwarded_total",
+                "Outcomes not delivered to a consumer which is gone.",
+                overflow.unforwarded,
+            ),
+        ] {
+            let _ = writeln!(body, "# HELP {} {}", name, help);
+            let _ = writeln!(body, "# TYPE {} counter", name);
+            let _ = writeln!(body, "{} {}", name, value);
+        }
+    }
+}
+
+// Delivers the outcomes of the executors to each of the consumers subscribed, e.g. the
+// notifications and the stats export. Every outcome is kept until received, so that a
+// slow consumer neither holds the receiver nor misses any.
+#[derive(Clone, Default)]
+pub struct OutcomeBus {
+    consumers: Arc<Mutex<Vec<UnboundedSender<TimerExecutorStats>>>>,
+}
+
+impl OutcomeBus {
+    #[cfg(any(feature = "webhooks", feature = "stats-export", test))]
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<TimerExecutorStats> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.consumers.lock().unwrap().push(tx);
+        rx
+    }
+}
+
+impl StatsReceiver {
+    // Publishes the outcome to the consumers, the ones which are gone are dropped and
+    // counted.
+    pub fn publish(&self, outcomes: &OutcomeBus, stats: &TimerExecutorStats) {
+        let mut consumers = outcomes.consumers.lock().unwrap();
+        let subscribed = consumers.len();
+        consumers.retain(|tx| tx.send(stats.clone()).is_ok());
+        let gone = subscribed - consumers.len();
+        if gone > 0 {
+            self.overflow.lock().unwrap().unforwarded += gone as u64;
+            println!("Error publishing the outcome: {} consumers are gone", gone);
+        }
+    }
+
+    // The next stats, the ones kept aside once the channel is drained. None once the
+    // senders are dropped and everything is received.
+    pub async fn recv(&mut self) -> Option<TimerExecutorStats> {
+        loop {
+            if let Some(stats) = self.ready.pop_front() {
+                return Some(stats);
+            }
+            match self.rx.try_recv() {
+                Ok(stats) => return Some(stats),
+                Err(err) => {
+                    self.ready.extend(self.overflow.lock().unwrap().take());
+                    if !self.ready.is_empty() {
+                        continue;
+                    }
+                    if err == TryRecvError::Disconnected {
+                        return None;
+                    }
+                    tokio::select! {
+                        stats = self.rx.recv() => return stats,
+                        _ = self.notify.notified() => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::{Status, TransactionStatus};
+    use ethers::types::U256;
+
+    #[tokio::test]
+    async fn full_channel_keeps_the_latest_stats_and_the_outcomes() {
+        let (tx, mut rx) = stats_channel(1, OverflowPolicy::Coalesce);
+        let stats = || TimerExecutorStats::duplicate(U256::one(), "APP".to_string(), Vec::new());
+        let first = stats();
+        let mut running = stats();
+        running.status = Status::Running;
+        let mut reverted = running.clone();
+        reverted.transaction_status = TransactionStatus::Reverted;
+        let mut succeeded = running.clone();
+        succeeded.status = Status::Succeeded;
+
+        tx.send(first.clone()).await;
+        for stats in [running, reverted, succeeded] {
+            tx.send(stats).await;
+        }
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            received.push(rx.recv().await.ok_or("closed").ok().unwrap());
+        }
+        assert_eq!(received[0].id, first.id);
+        // The running stats are coalesced, the reverted ones are an outcome.
+        assert_eq!(received[1].transaction_status, TransactionStatus::Reverted);
+        assert_eq!(received[2].status, Status::Succeeded);
+        // Each consumer gets every outcome, the ones which are gone are counted.
+        let outcomes = OutcomeBus::default();
+        let mut notifications = outcomes.subscribe();
+        drop(outcomes.subscribe());
+        rx.publish(&outcomes, &received[1]);
+        rx.publish(&outcomes, &received[2]);
+        assert_eq!(
+            notifications
+                .recv()
+                .await
+                .map(|stats| stats.transaction_status),
+            Some(TransactionStatus::Reverted)
+        );
+        assert_eq!(
+            notifications.recv().await.map(|stats| stats.status),
+            Some(Status::Succeeded)
+        );
+        let mut body = String::new();
+        tx.write_metrics(&mut body);
+        assert!(body.contains("solver_stats_overflowed_total 1"));
+        assert!(body.contains("solver_stats_coalesced_total 1"));
+        assert!(body.contains("solver_stats_unforwarded_total 1"));
+    }
+}
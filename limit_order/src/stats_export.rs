@@ -0,0 +1,338 @@
+use chrono::{DateTime, Utc};
+use flate2::{write::GzEncoder, Compression};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc::UnboundedReceiver,
+    time::{sleep, timeout_at, Instant},
+};
+use uuid::Uuid;
+
+use crate::{http_client::http_client, stats::TimerExecutorStats};
+
+// Attempts of an upload before the file is left in the spool until the next flush.
+const UPLOAD_ATTEMPTS: u32 = 3;
+const UPLOAD_BACKOFF: Duration = Duration::from_secs(2);
+
+// The bucket the stats are exported to, as s3://BUCKET/PREFIX or gs://BUCKET/PREFIX. GCS
+// is written through its S3 interoperability API, with HMAC keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportTarget {
+    pub gcs: bool,
+    pub bucket: String,
+    pub prefix: String,
+}
+
+impl FromStr for ExportTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (gcs, path) = match (s.strip_prefix("s3://"), s.strip_prefix("gs://")) {
+            (Some(path), _) => (false, path),
+            (_, Some(path)) => (true, path),
+            _ => {
+                return Err(format!(
+                    "expected s3://BUCKET/PREFIX or gs://BUCKET/PREFIX, got \"{}\"",
+                    s
+                ))
+            }
+        };
+        let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+        if bucket.is_empty() {
+            return Err(format!("missing the bucket in \"{}\"", s));
+        }
+        Ok(ExportTarget {
+            gcs,
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+        })
+    }
+}
+
+impl ExportTarget {
+    // The host and the path of an object.
+    fn location(&self, region: &str, key: &str) -> (String, String) {
+        match self.gcs {
+            true => (
+                "storage.googleapis.com".to_string(),
+                format!("/{}/{}", self.bucket, key),
+            ),
+            false => (
+                format!("{}.s3.{}.amazonaws.com", self.bucket, region),
+                format!("/{}", key),
+            ),
+        }
+    }
+}
+
+pub struct ExportCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    // "auto" for GCS.
+    pub region: String,
+}
+
+// Exports the stats of the finished executors in batches, as gzipped JSON lines. Each
+// batch is written to the spool first and deleted once uploaded, the batches left by a
+// failed upload or a previous run are uploaded with the next one.
+pub struct StatsExporter {
+    pub target: ExportTarget,
+    pub credentials: ExportCredentials,
+    pub spool_dir: PathBuf,
+    pub batch_size: usize,
+    pub interval: Duration,
+}
+
+impl StatsExporter {
+    pub async fn run(self, mut stats_rx: UnboundedReceiver<TimerExecutorStats>) {
+        if let Err(err) = fs::create_dir_all(&self.spool_dir) {
+            println!(
+                "Error creating the stats spool {}: {}",
+                self.spool_dir.display(),
+                err
+            );
+            return;
+        }
+        let client = match http_client() {
+            Ok(client) => client,
+            Err(err) => {
+                println!("{}, the stats aren't exported", err);
+                return;
+            }
+        };
+        let mut batch = Vec::new();
+        let mut deadline = Instant::now() + self.interval;
+        loop {
+            match timeout_at(deadline, stats_rx.recv()).await {
+                Ok(Some(stats)) => {
+                    batch.push(stats);
+                    if batch.len() < self.batch_size {
+                        continue;
+                    }
+                }
+                Ok(None) => {
+                    self.spool(&batch);
+                    return;
+                }
+                Err(_) => {}
+            }
+            self.spool(&batch);
+            batch.clear();
+            self.upload_spooled(&client).await;
+            deadline = Instant::now() + self.interval;
+        }
+    }
+
+    fn spool(&self, batch: &[TimerExecutorStats]) {
+        if batch.is_empty() {
+            return;
+        }
+        let name = format!(
+            "{}-{}.jsonl.gz",
+            Utc::now().format("%Y%m%dT%H%M%SZ"),
+            Uuid::new_v4()
+        );
+        if let Err(err) = compress(batch).and_then(|body| {
+            fs::write(self.spool_dir.join(&name), body).map_err(|err| err.to_string())
+        }) {
+            println!("Error spooling {} executor stats: {}", batch.len(), err);
+        }
+    }
+
+    // Uploads the spooled batches, the oldest first, until one fails.
+    async fn upload_spooled(&self, client: &reqwest::Client) {
+        let mut files = match fs::read_dir(&self.spool_dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|extension| extension == "gz"))
+                .collect::<Vec<PathBuf>>(),
+            Err(err) => {
+                println!("Error reading the stats spool: {}", err);
+                return;
+            }
+        };
+        files.sort();
+        for file in files {
+            if let Err(err) = self.upload_with_retries(client, &file).await {
+                println!(
+                    "Error exporting the executor stats {}, kept in the spool: {}",
+                    file.display(),
+                    err
+                );
+                return;
+            }
+            if let Err(err) = fs::remove_file(&file) {
+                println!(
+                    "Error removing the exported stats {}: {}",
+                    file.display(),
+                    err
+                );
+            }
+        }
+    }
+
+    async fn upload_with_retries(
+        &self,
+        client: &reqwest::Client,
+        file: &Path,
+    ) -> Result<(), String> {
+        let body = fs::read(file).map_err(|err| err.to_string())?;
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // Partitioned by the day of the batch, from its name.
+        let day = match (name.get(0..4), name.get(4..6), name.get(6..8)) {
+            (Some(year), Some(month), Some(day)) => format!("{}/{}/{}", year, month, day),
+            _ => "unknown".to_string(),
+        };
+        let key = match self.target.prefix.is_empty() {
+            true => format!("{}/{}", day, name),
+            false => format!("{}/{}/{}", self.target.prefix, day, name),
+        };
+        let mut attempt = 1;
+        loop {
+            match self.upload(client, &key, body.clone()).await {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt >= UPLOAD_ATTEMPTS => return Err(err),
+                Err(_) => {
+                    sleep(UPLOAD_BACKOFF * attempt).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn upload(
+        &self,
+        client: &reqwest::Client,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<(), String> {
+        let (host, path) = self.target.location(&self.credentials.region, key);
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let now = Utc::now();
+        let response = client
+            .put(format!("https://{}{}", host, path))
+            .header(
+                "authorization",
+                authorization(&self.credentials, &host, &path, &payload_hash, now),
+            )
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string())
+            .header("content-type", "application/gzip")
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        match response.status().is_success() {
+            true => Ok(()),
+            false => Err(format!(
+                "{}: {}",
+                response.status(),
+                response.text().await.unwrap_or_default()
+            )),
+        }
+    }
+}
+
+// The stats as gzipped JSON lines.
+fn compress(batch: &[TimerExecutorStats]) -> Result<Vec<u8>, String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    for stats in batch {
+        let line = serde_json::to_string(stats).map_err(|err| err.to_string())?;
+        writeln!(encoder, "{}", line).map_err(|err| err.to_string())?;
+    }
+    encoder.finish().map_err(|err| err.to_string())
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    // HMAC takes keys of any length.
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// The key signing the requests of the day, AWS Signature Version 4.
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(format!("AWS4{}", secret_access_key).as_bytes(), date);
+    let key = hmac_sha256(&key, region);
+    let key = hmac_sha256(&key, service);
+    hmac_sha256(&key, "aws4_request")
+}
+
+// The Authorization header of an object PUT. The keys are made of URL safe characters,
+// the path needs no encoding.
+fn authorization(
+    credentials: &ExportCredentials,
+    host: &str,
+    path: &str,
+    payload_hash: &str,
+    now: DateTime<Utc>,
+) -> String {
+    let date = now.format("%Y%m%d").to_string();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        path, host, payload_hash, amz_date, signed_headers, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, credentials.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex::encode(hmac_sha256(
+        &signing_key(
+            &credentials.secret_access_key,
+            &date,
+            &credentials.region,
+            "s3",
+        ),
+        &string_to_sign,
+    ));
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_and_signing_keys() {
+        assert_eq!(
+            ExportTarget::from_str("gs://analytics/solver/stats/")
+                .ok()
+                .unwrap(),
+            ExportTarget {
+                gcs: true,
+                bucket: "analytics".to_string(),
+                prefix: "solver/stats".to_string(),
+            }
+        );
+        assert!(ExportTarget::from_str("s3:///stats").is_err());
+        assert!(ExportTarget::from_str("analytics/stats").is_err());
+        // The example of the AWS documentation.
+        assert_eq!(
+            hex::encode(signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20120215",
+                "us-east-1",
+                "iam"
+            )),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::Write,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::{sync::Mutex, time::sleep};
+use uuid::Uuid;
+
+use crate::{
+    decisions::DecisionHistories,
+    executor_index::ExecutorIndex,
+    stats::{Status, TimerExecutorStats},
+    timeline::Timelines,
+};
+
+// How often the stats are collected.
+const GC_INTERVAL: Duration = Duration::from_secs(10);
+
+// Limits of the executor stats kept in memory. Only finished executors are evicted.
+#[derive(Clone, Debug, Default)]
+pub struct StatsRetention {
+    pub max_entries: Option<usize>,
+    pub max_age: Option<Duration>,
+    // File the evicted stats are appended to as JSON lines, dropped if not set.
+    pub archive_path: Option<String>,
+}
+
+impl StatsRetention {
+    pub fn is_enabled(&self) -> bool {
+        self.max_entries.is_some() || self.max_age.is_some()
+    }
+
+    // Removes the finished executors over the limits, the oldest first.
+    fn evict(&self, stats_map: &mut HashMap<Uuid, TimerExecutorStats>) -> Vec<TimerExecutorStats> {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut finished = stats_map
+            .values()
+            .filter(|stats| stats.status != Status::Running)
+            .map(|stats| (stats.creation_time, stats.id))
+            .collect::<Vec<(Duration, Uuid)>>();
+        finished.sort();
+        let over_limit = self
+            .max_entries
+            .map(|max| stats_map.len().saturating_sub(max))
+            .unwrap_or(0);
+        let mut evicted = Vec::new();
+        for (i, (creation_time, id)) in finished.into_iter().enumerate() {
+            let expired = self
+                .max_age
+                .is_some_and(|max_age| now.saturating_sub(creation_time) > max_age);
+            if i >= over_limit && !expired {
+                break;
+            }
+            if let Some(stats) = stats_map.remove(&id) {
+                evicted.push(stats);
+            }
+        }
+        evicted
+    }
+
+    fn archive(&self, evicted: &[TimerExecutorStats]) -> Result<(), String> {
+        let archive_path = match &self.archive_path {
+            Some(archive_path) => archive_path,
+            None => return Ok(()),
+        };
+        let mut archive = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(archive_path)
+            .map_err(|err| err.to_string())?;
+        for stats in evicted {
+            let line = serde_json::to_string(stats).map_err(|err| err.to_string())?;
+            writeln!(archive, "{}", line).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+// Periodically evicts the stats of finished executors over the retention limits, with
+// their timelines and decisions.
+pub async fn run_stats_gc(
+    stats_map: Arc<Mutex<HashMap<Uuid, TimerExecutorStats>>>,
+    timelines: Timelines,
+    decision_histories: DecisionHistories,
+    executor_index: Arc<ExecutorIndex>,
+    retention: StatsRetention,
+) {
+    loop {
+        sleep(GC_INTERVAL).await;
+        let evicted = retention.evict(&mut *stats_map.lock().await);
+        if evicted.is_empty() {
+            continue;
+        }
+        {
+            let mut timelines = timelines.lock().await;
+            for stats in &evicted {
+                timelines.remove(&stats.id);
+            }
+        }
+        {
+            let mut decision_histories = decision_histories.lock().await;
+            for stats in &evicted {
+                decision_histories.remove(&stats.id);
+            }
+        }
+        executor_index.forget(&evicted.iter().map(|stats| stats.id).collect());
+        if let Err(err) = retention.archive(&evicted) {
+            println!("Error archiving executor stats: {}", err);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::{io::Write, time::Duration};
+use tokio::time::sleep;
+
+use crate::{
+    http_client::http_client,
+    stats::{Status, TimerExecutorStats, TransactionStatus},
+};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[34m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+// Number of failures shown in the recent failures section.
+const RECENT_FAILURES: usize = 5;
+
+// Polls the stats endpoint of a running solver and redraws the terminal with the
+// current executors of all the apps, the plugin ones included, like `top`.
+pub async fn run(url: String, refresh: Duration) {
+    let stats_url = format!("{}/stats", url.trim_end_matches('/'));
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+    loop {
+        let screen = match client.get(stats_url.as_str()).send().await {
+            Ok(response) => match response.json::<Vec<TimerExecutorStats>>().await {
+                Ok(stats) => render(&url, stats),
+                Err(err) => format!("{}Error decoding stats: {}{}\n", RED, err, RESET),
+            },
+            Err(err) => format!(
+                "{}Error fetching stats from {}: {}{}\n",
+                RED, stats_url, err, RESET
+            ),
+        };
+        print!("{}{}", CLEAR_SCREEN, screen);
+        let _ = std::io::stdout().flush();
+        sleep(refresh).await;
+    }
+}
+
+fn render(url: &str, mut stats: Vec<TimerExecutorStats>) -> String {
+    let count = |status: Status| stats.iter().filter(|s| s.status == status).count();
+    let mut screen = format!(
+        "{}Smart Transactions Solver{} {}  executors: {}  {}running {}{}  {}succeeded {}{}  {}failed {}{}  {}timeout {}{}\n\n",
+        BOLD,
+        RESET,
+        url,
+        stats.len(),
+        YELLOW,
+        count(Status::Running),
+        RESET,
+        GREEN,
+        count(Status::Succeeded),
+        RESET,
+        RED,
+        count(Status::Failed),
+        RESET,
+        MAGENTA,
+        count(Status::Timeout),
+        RESET,
+    );
+
+    // Running executors first, the closest to the deadline on top.
+    stats.sort_by(|a, b| {
+        (a.status != Status::Running)
+            .cmp(&(b.status != Status::Running))
+            .then(a.remaining.cmp(&b.remaining))
+            .then(b.creation_time.cmp(&a.creation_time))
+    });
+    screen.push_str(
+        format!(
+            "{}{:<10} {:<16} {:>6} {:<10} {:<19} {:>10} {:>10}  {}{}\n",
+            BOLD,
+            "ID",
+            "APP",
+            "SEQ",
+            "STATUS",
+            "TRANSACTION",
+            "ELAPSED",
+            "REMAINING",
+            "MESSAGE",
+            RESET
+        )
+        .as_str(),
+    );
+    for s in &stats {
+        screen.push_str(
+            format!(
+                "{:<10} {:<16} {:>6} {}{:<10}{} {:<19} {:>10} {:>10}  {}\n",
+                &s.id.to_string()[..8],
+                truncate(&s.app, 16),
+                s.sequence_number,
+                status_color(&s.status),
+                format!("{:?}", s.status),
+                RESET,
+                format!("{:?}", s.transaction_status),
+                format_duration(s.elapsed),
+                format_duration(s.remaining),
+                truncate(&s.message, 60),
+            )
+            .as_str(),
+        );
+    }
+
+    let mut failures: Vec<&TimerExecutorStats> = stats
+        .iter()
+        .filter(|s| {
+            s.status == Status::Failed
+                || s.status == Status::Timeout
+                || s.status == Status::BudgetExhausted
+                || s.transaction_status == TransactionStatus::StepFailed
+                || s.transaction_status == TransactionStatus::TransactionFailed
+        })
+        .collect();
+    failures.sort_by_key(|s| std::cmp::Reverse(s.creation_time));
+    screen.push_str(format!("\n{}Recent failures{}\n", BOLD, RESET).as_str());
+    if failures.is_empty() {
+        screen.push_str("none\n");
+    }
+    for s in failures.iter().take(RECENT_FAILURES) {
+        screen.push_str(
+            format!(
+                "{}{:<10}{} {} {}\n",
+                RED,
+                &s.id.to_string()[..8],
+                RESET,
+                s.app,
+                s.message
+            )
+            .as_str(),
+        );
+    }
+    screen
+}
+
+fn status_color(status: &Status) -> &'static str {
+    match status {
+        Status::Running => YELLOW,
+        Status::Succeeded => GREEN,
+        Status::Failed => RED,
+        Status::Timeout => MAGENTA,
+        Status::Duplicate => CYAN,
+        Status::SolvedExternally => BLUE,
+        Status::BudgetExhausted => RED,
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+fn truncate(message: &str, max_chars: usize) -> String {
+    if message.chars().count() <= max_chars {
+        message.to_string()
+    } else {
+        format!(
+            "{}...",
+            message.chars().take(max_chars - 3).collect::<String>()
+        )
+    }
+}
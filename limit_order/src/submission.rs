@@ -0,0 +1,926 @@
+use axum::{extract::State, response::Json};
+#[cfg(feature = "relay")]
+use ethers::{
+    abi::{encode, Token},
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::eip712::{EIP712Domain, Eip712},
+        Address, Bytes, Signature, TransactionRequest,
+    },
+    utils::{id, keccak256},
+};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{transaction::eip2718::TypedTransaction, TransactionReceipt, H256, U256, U64},
+};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+#[cfg(feature = "relay")]
+use std::{convert::Infallible, sync::RwLock};
+use tokio::{sync::Mutex, time::sleep};
+
+#[cfg(feature = "relay")]
+use crate::http_client::http_client;
+
+// How often the endpoints are asked for the receipt of a broadcast transaction, and
+// for how long.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Name the simulated transactions are counted under in the submission stats.
+const DRY_RUN: &str = "dry_run";
+
+// What became of a transaction sent in a bundle.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum BundleStatus {
+    Included { block_number: U64 },
+    // Not included in any of the blocks it targeted, up to the given one.
+    NotIncluded { last_target_block: U64 },
+}
+
+// Outcome of a submission, the receipt is missing if it wasn't received in time.
+#[derive(Clone, Debug, Default)]
+pub struct Submitted {
+    pub tx_hash: Option<H256>,
+    pub receipt: Option<TransactionReceipt>,
+    pub bundle: Option<BundleStatus>,
+}
+
+impl Submitted {
+    // The transaction is out, but whether it landed isn't known.
+    fn unknown(tx_hash: H256) -> Submitted {
+        Submitted {
+            tx_hash: Some(tx_hash),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<TransactionReceipt> for Submitted {
+    fn from(receipt: TransactionReceipt) -> Self {
+        Submitted {
+            tx_hash: Some(receipt.transaction_hash),
+            receipt: Some(receipt),
+            bundle: None,
+        }
+    }
+}
+
+// A way of getting the final transaction on chain.
+pub trait SubmissionStrategy {
+    fn name(&self) -> &'static str;
+    async fn submit<M: Middleware>(
+        &self,
+        middleware: &M,
+        tx: TypedTransaction,
+    ) -> Result<Submitted, String>;
+}
+
+// Sends the transaction through the connected node into the public mempool.
+#[derive(Clone, Debug)]
+pub struct PublicMempool;
+
+impl SubmissionStrategy for PublicMempool {
+    fn name(&self) -> &'static str {
+        "public"
+    }
+
+    async fn submit<M: Middleware>(
+        &self,
+        middleware: &M,
+        tx: TypedTransaction,
+    ) -> Result<Submitted, String> {
+        match middleware.send_transaction(tx, None).await {
+            Ok(pending) => {
+                let tx_hash = pending.tx_hash();
+                println!("Transaction is sent, txhash: {:?}", tx_hash);
+                // The transaction is out whatever the node answers, it mustn't be sent
+                // again by the next strategy.
+                match pending.await {
+                    Ok(Some(receipt)) => Ok(receipt.into()),
+                    Ok(None) => Ok(Submitted::unknown(tx_hash)),
+                    Err(err) => {
+                        println!("Error waiting for the receipt of {:?}: {}", tx_hash, err);
+                        Ok(Submitted::unknown(tx_hash))
+                    }
+                }
+            }
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+// Broadcasts the same signed transaction through the connected node and the extra RPC
+// endpoints and private relays at once, and takes the receipt from whichever endpoint
+// reports it first. Every endpoint gets the same signed bytes, so the transaction can
+// only be included once.
+#[derive(Clone, Debug, Default)]
+pub struct RedundantBroadcast {
+    pub endpoints: Vec<String>,
+}
+
+impl SubmissionStrategy for RedundantBroadcast {
+    fn name(&self) -> &'static str {
+        "redundant"
+    }
+
+    async fn submit<M: Middleware>(
+        &self,
+        middleware: &M,
+        mut tx: TypedTransaction,
+    ) -> Result<Submitted, String> {
+        let from = tx
+            .from()
+            .copied()
+            .or(middleware.default_sender())
+            .ok_or("missing the sender of the transaction".to_string())?;
+        tx.set_from(from);
+        middleware
+            .fill_transaction(&mut tx, None)
+            .await
+            .map_err(|err| err.to_string())?;
+        let signature = middleware
+            .sign_transaction(&tx, from)
+            .await
+            .map_err(|err| err.to_string())?;
+        let raw = tx.rlp_signed(&signature);
+        let tx_hash = tx.hash(&signature);
+
+        let mut endpoints = Vec::new();
+        for url in &self.endpoints {
+            match Provider::<Http>::try_from(url.as_str()) {
+                Ok(provider) => endpoints.push((url.as_str(), provider)),
+                Err(err) => println!("Invalid broadcast endpoint {}: {}", url, err),
+            }
+        }
+        let (node_res, endpoint_res) = tokio::join!(
+            async {
+                middleware
+                    .send_raw_transaction(raw.clone())
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| err.to_string())
+            },
+            join_all(endpoints.iter().map(|(_, provider)| async {
+                provider
+                    .send_raw_transaction(raw.clone())
+                    .await
+                    .map(|_| ())
+                    .map_err(|err| err.to_string())
+            }))
+        );
+        let mut errors = Vec::new();
+        let results = std::iter::once(("node", node_res))
+            .chain(endpoints.iter().map(|(url, _)| *url).zip(endpoint_res));
+        for (endpoint, res) in results {
+            if let Err(err) = res {
+                println!("Broadcast via {} failed: {}", endpoint, err);
+                errors.push(format!("{}: {}", endpoint, err));
+            }
+        }
+        if errors.len() == endpoints.len() + 1 {
+            return Err(errors.join("; "));
+        }
+        println!(
+            "Transaction is broadcast to {} endpoints, txhash: {:?}",
+            endpoints.len() + 1 - errors.len(),
+            tx_hash
+        );
+
+        let started = Instant::now();
+        while started.elapsed() < RECEIPT_TIMEOUT {
+            sleep(RECEIPT_POLL_INTERVAL).await;
+            if let Ok(Some(receipt)) = middleware.get_transaction_receipt(tx_hash).await {
+                println!("Transaction {:?} confirmed first via node", tx_hash);
+                return Ok(receipt.into());
+            }
+            for (url, provider) in &endpoints {
+                if let Ok(Some(receipt)) = provider.get_transaction_receipt(tx_hash).await {
+                    println!("Transaction {:?} confirmed first via {}", tx_hash, url);
+                    return Ok(receipt.into());
+                }
+            }
+        }
+        Ok(Submitted::unknown(tx_hash))
+    }
+}
+
+// Domain and type of the requests of an OpenZeppelin MinimalForwarder.
+#[cfg(feature = "relay")]
+const FORWARDER_NAME: &str = "MinimalForwarder";
+#[cfg(feature = "relay")]
+const FORWARDER_VERSION: &str = "0.0.1";
+#[cfg(feature = "relay")]
+const FORWARD_REQUEST_TYPE: &str =
+    "ForwardRequest(address from,address to,uint256 value,uint256 gas,uint256 nonce,bytes data)";
+
+// Keys of the solver wallets by address, the relay requests are signed with the key of
+// the wallet sending the final transaction.
+#[cfg(feature = "relay")]
+pub type RelayKeys = Arc<RwLock<HashMap<Address, LocalWallet>>>;
+
+// The call the forwarder executes for the solver, which appends the solver to the call
+// data so that the called contract sees it as the sender.
+#[cfg(feature = "relay")]
+#[derive(Clone, Debug, Serialize)]
+struct ForwardRequest {
+    #[serde(skip)]
+    domain: EIP712Domain,
+    from: Address,
+    to: Address,
+    value: U256,
+    gas: U256,
+    nonce: U256,
+    data: Bytes,
+}
+
+#[cfg(feature = "relay")]
+impl Eip712 for ForwardRequest {
+    type Error = Infallible;
+
+    fn domain(&self) -> Result<EIP712Domain, Self::Error> {
+        Ok(self.domain.clone())
+    }
+
+    fn type_hash() -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256(FORWARD_REQUEST_TYPE))
+    }
+
+    fn struct_hash(&self) -> Result<[u8; 32], Self::Error> {
+        Ok(keccak256(encode(&[
+            Token::FixedBytes(Self::type_hash()?.to_vec()),
+            Token::Address(self.from),
+            Token::Address(self.to),
+            Token::Uint(self.value),
+            Token::Uint(self.gas),
+            Token::Uint(self.nonce),
+            Token::FixedBytes(keccak256(&self.data).to_vec()),
+        ])))
+    }
+}
+
+// The forward request a relay is asked to send, with the signature of the solver.
+#[cfg(feature = "relay")]
+#[derive(Debug, Serialize)]
+struct RelayRequest {
+    chain_id: u64,
+    forwarder: Address,
+    request: ForwardRequest,
+    signature: Bytes,
+}
+
+#[cfg(feature = "relay")]
+#[derive(Debug, Deserialize)]
+struct RelayResponse {
+    tx_hash: H256,
+}
+
+// Hands the call to a relayer that sends it through an ERC-2771 forwarder and pays for
+// its gas, so the solver wallet doesn't need to be funded. The call is signed by the
+// solver wallet as an EIP-712 ForwardRequest, at the nonce the forwarder holds for it.
+// The relay gets the RelayRequest as a JSON POST and answers with the hash of the
+// transaction it sent, the receipt is then awaited on the connected node.
+#[cfg(feature = "relay")]
+#[derive(Clone, Debug, Default)]
+pub struct Relay {
+    pub url: Option<String>,
+    pub chain_id: u64,
+    pub forwarder: Option<Address>,
+    pub keys: RelayKeys,
+}
+
+#[cfg(feature = "relay")]
+impl Relay {
+    async fn nonce<M: Middleware>(
+        &self,
+        middleware: &M,
+        forwarder: Address,
+        from: Address,
+    ) -> Result<U256, String> {
+        let data = [
+            &id("getNonce(address)")[..],
+            &encode(&[Token::Address(from)])[..],
+        ]
+        .concat();
+        let call = TransactionRequest::new().to(forwarder).data(data).into();
+        let nonce = middleware
+            .call(&call, None)
+            .await
+            .map_err(|err| format!("error reading the forwarder nonce: {}", err))?;
+        if nonce.len() != 32 {
+            return Err("invalid forwarder nonce".to_string());
+        }
+        Ok(U256::from_big_endian(&nonce))
+    }
+
+    // Signs the call with the key of its sender.
+    async fn sign(&self, request: &ForwardRequest) -> Result<Signature, String> {
+        let key = self
+            .keys
+            .read()
+            .unwrap()
+            .get(&request.from)
+            .cloned()
+            .ok_or(format!("missing the key of the wallet {:?}", request.from))?;
+        key.sign_typed_data(request)
+            .await
+            .map_err(|err| format!("error signing the forward request: {}", err))
+    }
+}
+
+#[cfg(feature = "relay")]
+impl SubmissionStrategy for Relay {
+    fn name(&self) -> &'static str {
+        "relay"
+    }
+
+    async fn submit<M: Middleware>(
+        &self,
+        middleware: &M,
+        tx: TypedTransaction,
+    ) -> Result<Submitted, String> {
+        let url = self
+            .url
+            .as_ref()
+            .ok_or("missing the relay URL".to_string())?;
+        let forwarder = self
+            .forwarder
+            .ok_or("missing the relay forwarder".to_string())?;
+        let from = tx
+            .from()
+            .copied()
+            .or(middleware.default_sender())
+            .ok_or("missing the sender of the transaction".to_string())?;
+        let to = tx
+            .to_addr()
+            .copied()
+            .ok_or("missing the recipient of the transaction".to_string())?;
+        let gas = match tx.gas() {
+            Some(gas) => *gas,
+            None => middleware
+                .estimate_gas(&tx, None)
+                .await
+                .map_err(|err| err.to_string())?,
+        };
+        let forward_request = ForwardRequest {
+            domain: EIP712Domain {
+                name: Some(FORWARDER_NAME.to_string()),
+                version: Some(FORWARDER_VERSION.to_string()),
+                chain_id: Some(self.chain_id.into()),
+                verifying_contract: Some(forwarder),
+                salt: None,
+            },
+            from,
+            to,
+            value: tx.value().copied().unwrap_or_default(),
+            gas,
+            nonce: self.nonce(middleware, forwarder, from).await?,
+            data: tx.data().cloned().unwrap_or_default(),
+        };
+        let signature = self.sign(&forward_request).await?;
+        let request = RelayRequest {
+            chain_id: self.chain_id,
+            forwarder,
+            request: forward_request,
+            signature: signature.to_vec().into(),
+        };
+        let response = http_client()?
+            .post(url.as_str())
+            .json(&request)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("the relay answered {}", response.status()));
+        }
+        let tx_hash = response
+            .json::<RelayResponse>()
+            .await
+            .map_err(|err| format!("invalid relay response: {}", err))?
+            .tx_hash;
+        println!("Transaction is relayed, txhash: {:?}", tx_hash);
+
+        let started = Instant::now();
+        while started.elapsed() < RECEIPT_TIMEOUT {
+            sleep(RECEIPT_POLL_INTERVAL).await;
+            if let Ok(Some(receipt)) = middleware.get_transaction_receipt(tx_hash).await {
+                return Ok(receipt.into());
+            }
+        }
+        Ok(Submitted::unknown(tx_hash))
+    }
+}
+
+// Sends the signed transaction alone in a bundle to a Flashbots style relay with
+// eth_sendBundle, for each of the next blocks. The transaction stays out of the public
+// mempool, so other solvers can't frontrun it. The requests are signed with the auth key
+// in the X-Flashbots-Signature header, it identifies the solver to the relay and needs
+// no funds.
+#[cfg(feature = "relay")]
+#[derive(Clone, Debug, Default)]
+pub struct PrivateBundle {
+    pub relay_url: Option<String>,
+    pub auth_key: Option<LocalWallet>,
+    // Number of the next blocks the bundle is sent for.
+    pub target_blocks: u64,
+}
+
+#[cfg(feature = "relay")]
+impl PrivateBundle {
+    async fn send_bundle(
+        &self,
+        client: &reqwest::Client,
+        raw: &Bytes,
+        block_number: U64,
+    ) -> Result<(), String> {
+        let (relay_url, auth_key) = match (&self.relay_url, &self.auth_key) {
+            (Some(relay_url), Some(auth_key)) => (relay_url, auth_key),
+            _ => return Err("missing the bundle relay".to_string()),
+        };
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_sendBundle",
+            "params": [{
+                "txs": [raw],
+                "blockNumber": format!("{:#x}", block_number),
+            }],
+        })
+        .to_string();
+        let signature = auth_key
+            .sign_message(format!("{:?}", H256::from(keccak256(body.as_bytes()))))
+            .await
+            .map_err(|err| err.to_string())?;
+        let response = client
+            .post(relay_url.as_str())
+            .header("Content-Type", "application/json")
+            .header(
+                "X-Flashbots-Signature",
+                format!("{:?}:0x{}", auth_key.address(), signature),
+            )
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("the bundle relay answered {}", response.status()));
+        }
+        let response = response
+            .json::<serde_json::Value>()
+            .await
+            .map_err(|err| format!("invalid bundle relay response: {}", err))?;
+        match response.get("error") {
+            Some(error) => Err(format!("the bundle relay rejected the bundle: {}", error)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "relay")]
+impl SubmissionStrategy for PrivateBundle {
+    fn name(&self) -> &'static str {
+        "bundle"
+    }
+
+    async fn submit<M: Middleware>(
+        &self,
+        middleware: &M,
+        mut tx: TypedTransaction,
+    ) -> Result<Submitted, String> {
+        let from = tx
+            .from()
+            .copied()
+            .or(middleware.default_sender())
+            .ok_or("missing the sender of the transaction".to_string())?;
+        tx.set_from(from);
+        middleware
+            .fill_transaction(&mut tx, None)
+            .await
+            .map_err(|err| err.to_string())?;
+        let signature = middleware
+            .sign_transaction(&tx, from)
+            .await
+            .map_err(|err| err.to_string())?;
+        let raw = tx.rlp_signed(&signature);
+        let tx_hash = tx.hash(&signature);
+
+        let current_block = middleware
+            .get_block_number()
+            .await
+            .map_err(|err| err.to_string())?;
+        let client = http_client()?;
+        let last_target_block = current_block + self.target_blocks.max(1);
+        let mut block_number = current_block + 1;
+        while block_number <= last_target_block {
+            self.send_bundle(&client, &raw, block_number).await?;
+            block_number = block_number + 1;
+        }
+        println!(
+            "Bundle is sent for the blocks {} to {}, txhash: {:?}",
+            current_block + 1,
+            last_target_block,
+            tx_hash
+        );
+
+        let started = Instant::now();
+        while started.elapsed() < RECEIPT_TIMEOUT {
+            sleep(RECEIPT_POLL_INTERVAL).await;
+            if let Ok(Some(receipt)) = middleware.get_transaction_receipt(tx_hash).await {
+                return Ok(Submitted {
+                    tx_hash: Some(tx_hash),
+                    bundle: Some(BundleStatus::Included {
+                        block_number: receipt.block_number.unwrap_or_default(),
+                    }),
+                    receipt: Some(receipt),
+                });
+            }
+            if let Ok(block_number) = middleware.get_block_number().await {
+                if block_number > last_target_block {
+                    break;
+                }
+            }
+        }
+        Ok(Submitted {
+            tx_hash: Some(tx_hash),
+            receipt: None,
+            bundle: Some(BundleStatus::NotIncluded { last_target_block }),
+        })
+    }
+}
+
+// All available strategies, configured by name.
+#[derive(Clone, Debug)]
+pub enum Strategy {
+    Public(PublicMempool),
+    Redundant(RedundantBroadcast),
+    #[cfg(feature = "relay")]
+    Relay(Relay),
+    #[cfg(feature = "relay")]
+    Bundle(PrivateBundle),
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public" => Ok(Strategy::Public(PublicMempool)),
+            // The endpoints are set by the policy.
+            "redundant" => Ok(Strategy::Redundant(RedundantBroadcast::default())),
+            // The relay is set by the policy.
+            #[cfg(feature = "relay")]
+            "relay" => Ok(Strategy::Relay(Relay::default())),
+            // The bundle relay is set by the policy.
+            #[cfg(feature = "relay")]
+            "bundle" => Ok(Strategy::Bundle(PrivateBundle::default())),
+            _ => Err(format!("unknown submission strategy \"{}\"", s)),
+        }
+    }
+}
+
+impl SubmissionStrategy for Strategy {
+    fn name(&self) -> &'static str {
+        match self {
+            Strategy::Public(s) => s.name(),
+            Strategy::Redundant(s) => s.name(),
+            #[cfg(feature = "relay")]
+            Strategy::Relay(s) => s.name(),
+            #[cfg(feature = "relay")]
+            Strategy::Bundle(s) => s.name(),
+        }
+    }
+
+    async fn submit<M: Middleware>(
+        &self,
+        middleware: &M,
+        tx: TypedTransaction,
+    ) -> Result<Submitted, String> {
+        match self {
+            Strategy::Public(s) => s.submit(middleware, tx).await,
+            Strategy::Redundant(s) => s.submit(middleware, tx).await,
+            #[cfg(feature = "relay")]
+            Strategy::Relay(s) => s.submit(middleware, tx).await,
+            #[cfg(feature = "relay")]
+            Strategy::Bundle(s) => s.submit(middleware, tx).await,
+        }
+    }
+}
+
+// Selects strategies for objectives matching all the given conditions, passed as
+// CONDITIONS/STRATEGIES, e.g. "app=FLASHLIQUIDITY.LIMITORDER,min_amount=1000/public".
+// Conditions may be empty, strategies are tried in the given order until one succeeds.
+#[derive(Clone, Debug)]
+pub struct SubmissionRule {
+    pub app: Option<String>,
+    pub min_amount: Option<U256>,
+    pub chain_id: Option<u64>,
+    pub strategies: Vec<Strategy>,
+}
+
+impl SubmissionRule {
+    fn matches(&self, app: &str, amount: U256, chain_id: u64) -> bool {
+        self.app.iter().all(|a| a == app)
+            && self.min_amount.iter().all(|m| amount >= *m)
+            && self.chain_id.iter().all(|c| *c == chain_id)
+    }
+}
+
+impl FromStr for SubmissionRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (conditions, strategies) = s
+            .split_once('/')
+            .ok_or(format!("expected CONDITIONS/STRATEGIES, got \"{}\"", s))?;
+        let mut rule = SubmissionRule {
+            app: None,
+            min_amount: None,
+            chain_id: None,
+            strategies: Vec::new(),
+        };
+        for condition in conditions.split(',').filter(|c| !c.is_empty()) {
+            match condition.split_once('=') {
+                Some(("app", value)) => rule.app = Some(value.to_string()),
+                Some(("min_amount", value)) => {
+                    rule.min_amount =
+                        Some(U256::from_dec_str(value).map_err(|err| err.to_string())?)
+                }
+                Some(("chain_id", value)) => {
+                    rule.chain_id = Some(value.parse().map_err(|err| format!("{}", err))?)
+                }
+                _ => return Err(format!("unknown submission condition \"{}\"", condition)),
+            }
+        }
+        for strategy in strategies.split(',').filter(|s| !s.is_empty()) {
+            rule.strategies.push(Strategy::from_str(strategy)?);
+        }
+        if rule.strategies.is_empty() {
+            return Err(format!("no submission strategies in \"{}\"", s));
+        }
+        Ok(rule)
+    }
+}
+
+// Submission counters of a single strategy.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SubmissionStats {
+    pub attempts: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+    // Gas cost of the mined transactions.
+    pub gas_spent_wei: U256,
+}
+
+pub type SubmissionStatsMap = Arc<Mutex<HashMap<String, SubmissionStats>>>;
+
+// Chooses the strategies for each objective and falls back to the next one on failure.
+pub struct SubmissionPolicy {
+    chain_id: u64,
+    rules: Vec<SubmissionRule>,
+    default_strategies: Vec<Strategy>,
+    stats: SubmissionStatsMap,
+    // Simulate the transactions instead of sending them.
+    dry_run: bool,
+}
+
+impl SubmissionPolicy {
+    pub fn new(
+        chain_id: u64,
+        mut rules: Vec<SubmissionRule>,
+        mut default_strategies: Vec<Strategy>,
+        broadcast_endpoints: Vec<String>,
+        dry_run: bool,
+    ) -> SubmissionPolicy {
+        for strategy in rules
+            .iter_mut()
+            .flat_map(|r| r.strategies.iter_mut())
+            .chain(default_strategies.iter_mut())
+        {
+            if let Strategy::Redundant(s) = strategy {
+                s.endpoints = broadcast_endpoints.clone();
+            }
+        }
+        SubmissionPolicy {
+            chain_id,
+            rules,
+            default_strategies,
+            stats: Arc::new(Mutex::new(HashMap::new())),
+            dry_run,
+        }
+    }
+
+    #[cfg(feature = "relay")]
+    pub fn with_relay(
+        mut self,
+        url: Option<String>,
+        forwarder: Option<Address>,
+        keys: RelayKeys,
+    ) -> SubmissionPolicy {
+        for strategy in self
+            .rules
+            .iter_mut()
+            .flat_map(|r| r.strategies.iter_mut())
+            .chain(self.default_strategies.iter_mut())
+        {
+            if let Strategy::Relay(s) = strategy {
+                s.url = url.clone();
+                s.chain_id = self.chain_id;
+                s.forwarder = forwarder;
+                s.keys = keys.clone();
+            }
+        }
+        self
+    }
+
+    #[cfg(feature = "relay")]
+    pub fn with_bundle_relay(
+        mut self,
+        relay_url: Option<String>,
+        auth_key: LocalWallet,
+        target_blocks: u64,
+    ) -> SubmissionPolicy {
+        for strategy in self
+            .rules
+            .iter_mut()
+            .flat_map(|r| r.strategies.iter_mut())
+            .chain(self.default_strategies.iter_mut())
+        {
+            if let Strategy::Bundle(s) = strategy {
+                s.relay_url = relay_url.clone();
+                s.auth_key = Some(auth_key.clone());
+                s.target_blocks = target_blocks;
+            }
+        }
+        self
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    // The same policy only simulating the transactions.
+    pub fn simulated(&self) -> SubmissionPolicy {
+        SubmissionPolicy {
+            chain_id: self.chain_id,
+            rules: self.rules.clone(),
+            default_strategies: self.default_strategies.clone(),
+            stats: self.stats.clone(),
+            dry_run: true,
+        }
+    }
+
+    // Whether the solver wallet may pay for the gas of the transaction of the objective,
+    // it doesn't if the transaction is only relayed.
+    pub fn wallet_pays_gas(&self, app: &str, amount: U256) -> bool {
+        self.strategies(app, amount)
+            .iter()
+            .any(|strategy| strategy.name() != "relay")
+    }
+
+    pub fn stats(&self) -> SubmissionStatsMap {
+        self.stats.clone()
+    }
+
+    fn strategies(&self, app: &str, amount: U256) -> &Vec<Strategy> {
+        self.rules
+            .iter()
+            .find(|r| r.matches(app, amount, self.chain_id))
+            .map_or(&self.default_strategies, |r| &r.strategies)
+    }
+
+    // Submits the transaction of the objective with the given app and amount, returning
+    // the outcome of the first strategy that succeeds.
+    pub async fn submit<M: Middleware>(
+        &self,
+        app: &str,
+        amount: U256,
+        middleware: &M,
+        tx: TypedTransaction,
+    ) -> Result<Submitted, String> {
+        let mut errors = Vec::new();
+        for strategy in self.strategies(app, amount) {
+            let res = strategy.submit(middleware, tx.clone()).await;
+            {
+                let mut stats = self.stats.lock().await;
+                let stats = stats.entry(strategy.name().to_string()).or_default();
+                stats.attempts += 1;
+                match &res {
+                    Ok(submitted) => {
+                        stats.succeeded += 1;
+                        if let Some(receipt) = &submitted.receipt {
+                            stats.gas_spent_wei += receipt.gas_used.unwrap_or_default()
+                                * receipt.effective_gas_price.unwrap_or_default();
+                        }
+                    }
+                    Err(_) => stats.failed += 1,
+                }
+            }
+            match res {
+                Ok(submitted) => return Ok(submitted),
+                Err(err) => {
+                    println!("Submission via {} failed: {}", strategy.name(), err);
+                    errors.push(format!("{}: {}", strategy.name(), err));
+                }
+            }
+        }
+        Err(errors.join("; "))
+    }
+
+    // Runs the transaction against the latest block without sending it, returning the
+    // would-be transaction and its estimated cost. Fails if the transaction would revert.
+    pub async fn simulate<M: Middleware>(
+        &self,
+        middleware: &M,
+        tx: TypedTransaction,
+    ) -> Result<String, String> {
+        let res = simulate(middleware, &tx).await;
+        {
+            let mut stats = self.stats.lock().await;
+            let stats = stats.entry(DRY_RUN.to_string()).or_default();
+            stats.attempts += 1;
+            match &res {
+                Ok(_) => stats.succeeded += 1,
+                Err(_) => stats.failed += 1,
+            }
+        }
+        let (gas, gas_price) = res?;
+        Ok(format!(
+            "Dry run, transaction not sent: {}, estimated gas {} at {} wei, cost {} wei",
+            serde_json::to_string(&tx).map_err(|err| err.to_string())?,
+            gas,
+            gas_price,
+            gas.saturating_mul(gas_price)
+        ))
+    }
+}
+
+async fn simulate<M: Middleware>(
+    middleware: &M,
+    tx: &TypedTransaction,
+) -> Result<(U256, U256), String> {
+    middleware
+        .call(tx, None)
+        .await
+        .map_err(|err| format!("Simulation reverted: {}", err))?;
+    let gas = middleware
+        .estimate_gas(tx, None)
+        .await
+        .map_err(|err| format!("Gas estimation failed: {}", err))?;
+    let gas_price = middleware
+        .get_gas_price()
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok((gas, gas_price))
+}
+
+pub async fn get_submission_stats_json(
+    stats: State<SubmissionStatsMap>,
+) -> Json<HashMap<String, SubmissionStats>> {
+    let stats = stats.lock().await;
+    Json(stats.clone())
+}
+
+#[cfg(all(test, feature = "relay"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn forward_requests_are_signed_by_their_sender() {
+        let key = LocalWallet::new(&mut rand::thread_rng());
+        let relay = Relay::default();
+        relay
+            .keys
+            .write()
+            .unwrap()
+            .insert(key.address(), key.clone());
+        let request = ForwardRequest {
+            domain: EIP712Domain {
+                name: Some(FORWARDER_NAME.to_string()),
+                version: Some(FORWARDER_VERSION.to_string()),
+                chain_id: Some(1.into()),
+                verifying_contract: Some(Address::repeat_byte(0x22)),
+                salt: None,
+            },
+            from: key.address(),
+            to: Address::repeat_byte(0x33),
+            value: U256::zero(),
+            gas: 100000.into(),
+            nonce: 7.into(),
+            data: vec![1, 2, 3].into(),
+        };
+        let signature = relay.sign(&request).await.ok().unwrap();
+        let digest = H256(request.encode_eip712().ok().unwrap());
+        assert_eq!(signature.recover(digest).ok().unwrap(), key.address());
+        let stranger = ForwardRequest {
+            from: Address::repeat_byte(0x44),
+            ..request
+        };
+        assert!(relay.sign(&stranger).await.is_err());
+    }
+}
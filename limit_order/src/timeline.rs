@@ -0,0 +1,108 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::stats::{Status, TimerExecutorStats, TransactionStatus};
+
+// A state an executor entered, with the time it stayed in it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Transition {
+    // Time the state was entered since Unix epoch.
+    pub time: Duration,
+    pub status: Status,
+    pub transaction_status: TransactionStatus,
+    pub message: String,
+    // Until the next transition, None for the current state.
+    pub duration: Option<Duration>,
+}
+
+// The status transitions of the executors, recorded from their stats.
+pub type Timelines = Arc<Mutex<HashMap<Uuid, Vec<Transition>>>>;
+
+// Appends the state of the stats to the timeline of its executor if it changed. The
+// timeline starts with the creation of the executor.
+pub fn record(timelines: &mut HashMap<Uuid, Vec<Transition>>, stats: &TimerExecutorStats) {
+    let timeline = timelines.entry(stats.id).or_insert_with(|| {
+        vec![Transition {
+            time: stats.creation_time,
+            status: Status::Running,
+            transaction_status: TransactionStatus::NotExecuted,
+            message: "Created".to_string(),
+            duration: None,
+        }]
+    });
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    if let Some(last) = timeline.last_mut() {
+        if last.status == stats.status && last.transaction_status == stats.transaction_status {
+            return;
+        }
+        last.duration = Some(now.saturating_sub(last.time));
+    }
+    timeline.push(Transition {
+        time: now,
+        status: stats.status.clone(),
+        transaction_status: stats.transaction_status.clone(),
+        message: stats.message.clone(),
+        duration: None,
+    });
+}
+
+pub async fn get_timeline_json(
+    State(timelines): State<Timelines>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Transition>>, (StatusCode, String)> {
+    match timelines.lock().await.get(&id) {
+        Some(timeline) => Ok(Json(timeline.clone())),
+        None => Err((StatusCode::NOT_FOUND, format!("Unknown executor {}", id))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_changes_of_the_status_are_recorded() {
+        let mut stats = TimerExecutorStats::duplicate(1.into(), "APP".to_string(), vec![]);
+        let mut timelines = HashMap::new();
+        for (status, transaction_status) in [
+            (Status::Running, TransactionStatus::StepPending),
+            (Status::Running, TransactionStatus::StepPending),
+            (Status::Running, TransactionStatus::TransactionPending),
+            (Status::Succeeded, TransactionStatus::Succeeded),
+        ] {
+            stats.status = status;
+            stats.transaction_status = transaction_status;
+            record(&mut timelines, &stats);
+        }
+        let timeline = &timelines[&stats.id];
+        assert_eq!(
+            timeline
+                .iter()
+                .map(|transition| transition.transaction_status.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                TransactionStatus::NotExecuted,
+                TransactionStatus::StepPending,
+                TransactionStatus::TransactionPending,
+                TransactionStatus::Succeeded,
+            ]
+        );
+        assert!(timeline[..3]
+            .iter()
+            .all(|transition| transition.duration.is_some()));
+        assert_eq!(timeline[3].duration, None);
+    }
+}
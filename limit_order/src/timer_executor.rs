@@ -1,15 +1,30 @@
-use ethers::types::U256;
 use fatal::fatal;
-use std::time::{Duration, SystemTime};
-use tokio::{sync::mpsc::Sender, time::{sleep, Instant}};
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    sync::watch,
+    time::{sleep, timeout, Instant},
+};
 use uuid::Uuid;
 
 use crate::{
-    contracts_abi::laminator::{AdditionalData, ProxyPushedFilter},
-    solver::Solver,
+    admin::SolvingSwitch,
+    circuit_breaker::CircuitBreaker,
+    contracts_abi::laminator::ProxyPushedFilter,
+    execution_log::{ExecutionLog, ExecutionRecord, ExecutionTrace, Stage},
+    executor_state::ExecutorStateStore,
+    solver::{DecisionInput, Solver},
     stats::{Status, TimerExecutorStats, TransactionStatus},
+    stats_channel::StatsSender,
 };
 
+// Upper bound of the delay before retrying after transient errors in a row, the delay
+// starts at the tick and doubles with each one.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
 // The executor combined with a timer, PoC version.
 // For real prod version the timer is to be moved into its own thread to reduce a number of
 // contract read calls.
@@ -27,14 +42,38 @@ pub struct TimerRequestExecutor<S> {
     tick_duration: Duration,
 
     // The channel for sending current stats
-    stats_tx: Sender<TimerExecutorStats>,
+    stats_tx: StatsSender,
+
+    // The final transaction is simulated instead of sent
+    dry_run: bool,
+
+    // New block numbers, the steps run once per block if set and once per tick
+    // otherwise.
+    block_ticks: Option<watch::Receiver<u64>>,
+
+    // Persists the progress to resume the executor after a restart if set.
+    state_store: Option<Arc<ExecutorStateStore>>,
+
+    // Suspends the final transactions if set.
+    switch: Option<Arc<SolvingSwitch>>,
+
+    // Records the decisions on the objective if set.
+    execution_log: Option<Arc<ExecutionLog>>,
+
+    // Holds the final transactions of the app after failures in a row if set.
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+
+    // The values the last step was decided on, sent with the stats.
+    decision: Mutex<Vec<DecisionInput>>,
 }
 
 impl<S: Solver> TimerRequestExecutor<S> {
     pub fn new(
         solver: S,
         tick_duration: Duration,
-        stats_tx: Sender<TimerExecutorStats>,
+        stats_tx: StatsSender,
+        dry_run: bool,
+        block_ticks: Option<watch::Receiver<u64>>,
     ) -> TimerRequestExecutor<S> {
         let creation_time_res = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH);
         if creation_time_res.is_err() {
@@ -49,153 +88,504 @@ impl<S: Solver> TimerRequestExecutor<S> {
             creation_time: creation_time_res.ok().unwrap(),
             tick_duration,
             stats_tx,
+            dry_run,
+            block_ticks,
+            state_store: None,
+            switch: None,
+            execution_log: None,
+            circuit_breaker: None,
+            decision: Mutex::new(Vec::new()),
         };
 
         ret
     }
 
-    // Execute the FlashLiquidity executor with given params.
-    pub async fn execute(&self, event: ProxyPushedFilter) {
+    pub fn with_state_store(mut self, state_store: Option<Arc<ExecutorStateStore>>) -> Self {
+        self.state_store = state_store;
+        self
+    }
+
+    pub fn with_switch(mut self, switch: Arc<SolvingSwitch>) -> Self {
+        self.switch = Some(switch);
+        self
+    }
+
+    pub fn with_execution_log(mut self, execution_log: Option<Arc<ExecutionLog>>) -> Self {
+        self.execution_log = execution_log;
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, circuit_breaker: Option<Arc<CircuitBreaker>>) -> Self {
+        self.circuit_breaker = circuit_breaker;
+        self
+    }
+
+    // The id the executor was recorded with when its objective was received.
+    pub fn with_id(mut self, id: Uuid) -> Self {
+        self.id = id;
+        self
+    }
+
+    // Resumes the executor of an objective received at the given time since Unix epoch,
+    // its time limit counts from it.
+    pub fn resumed(mut self, creation_time: Option<Duration>) -> Self {
+        if let Some(creation_time) = creation_time {
+            self.creation_time = creation_time;
+        }
+        self
+    }
+
+    // Execute the FlashLiquidity executor with given params, returns the final status
+    // and message.
+    pub async fn execute(&self, event: ProxyPushedFilter) -> (Status, String) {
+        let trace = ExecutionTrace::new(
+            self.execution_log.clone(),
+            self.solver.app().as_str(),
+            &event,
+        )
+        .with_executor(self.id);
+        trace.record(ExecutionRecord::Objective {
+            data_values: event.data_values.clone(),
+            call_objs: event.call_objs.clone(),
+        });
+        let (status, message) = self.run(&event, &trace).await;
+        trace.record(ExecutionRecord::Finished {
+            status: status.clone(),
+            message: message.clone(),
+        });
+        (status, message)
+    }
+
+    async fn run(&self, event: &ProxyPushedFilter, trace: &ExecutionTrace) -> (Status, String) {
         println!("Executor {} started", self.id);
-        // Initialize timer
-        let now = Instant::now();
+        // Initialize timer, a resumed executor started before.
+        let started = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .saturating_sub(self.creation_time);
+        let now = Instant::now()
+            .checked_sub(started)
+            .unwrap_or_else(Instant::now);
         // Create a solver of a given type
-        if self.solver.time_limit().is_err() {
-            print!(
-                "Error getting time limit: {}",
-                &self.solver.time_limit().err().unwrap()
-            );
-            return;
-        }
-        // Tokens reading.
-        let time_limit = self.solver.time_limit().ok().unwrap();
+        let time_limit = match self.solver.time_limit() {
+            Ok(time_limit) => time_limit,
+            Err(err) => {
+                let message = format!("Error getting time limit: {}", err);
+                print!("{}", message);
+                return (Status::Failed, message);
+            }
+        };
         let mut last_transaction_status = TransactionStatus::NotExecuted;
         let mut last_message = String::new();
+        // Transient errors in a row.
+        let mut retries = 0;
+        // Only the blocks after the start count.
+        let mut block_ticks = self.block_ticks.clone();
+        if let Some(block_ticks) = &mut block_ticks {
+            block_ticks.borrow_and_update();
+        }
         while now.elapsed() < time_limit {
+            // Stop ticking once another solver executed the objective.
+            match self.solver.solved_externally().await {
+                Ok(Some(message)) => {
+                    println!("Executor {} solved externally: {}", self.id, message);
+                    self.send_stats(
+                        event,
+                        self.solver.app(),
+                        Status::SolvedExternally,
+                        TransactionStatus::NotExecuted,
+                        message.clone(),
+                        &time_limit,
+                        &now,
+                    )
+                    .await;
+                    return (Status::SolvedExternally, message);
+                }
+                Ok(None) => {}
+                // The objective is checked again in the next tick.
+                Err(err) => println!("Error checking the call of the proxy: {}", err),
+            }
             // Actions
-            match self.solver.exec_solver_step().await {
+            let step = self.solver.exec_solver_step().await;
+            trace.decision(Stage::Step, &step);
+            *self.decision.lock().unwrap() = match &step {
+                Ok(response) => response.decision.clone(),
+                Err(_) => Vec::new(),
+            };
+            match step {
                 Ok(response) => {
                     last_message = response.message.clone();
                     if response.succeeded {
+                        // Don't send a transaction that is known to revert.
+                        if let Some(message) = self.unmet_preconditions(trace).await {
+                            self.send_stats(
+                                event,
+                                self.solver.app(),
+                                Status::Running,
+                                TransactionStatus::PreconditionsFailed,
+                                message.clone(),
+                                &time_limit,
+                                &now,
+                            )
+                            .await;
+                            last_message = message;
+                            last_transaction_status = TransactionStatus::PreconditionsFailed;
+                            self.wait_next_tick(
+                                &mut block_ticks,
+                                time_limit.saturating_sub(now.elapsed()),
+                                sleep(self.tick_duration),
+                            )
+                            .await;
+                            continue;
+                        }
+                        // Don't execute at a loss, the gas price may drop in later ticks.
+                        if let Some(message) = self.unprofitable(trace).await {
+                            self.send_stats(
+                                event,
+                                self.solver.app(),
+                                Status::Running,
+                                TransactionStatus::Unprofitable,
+                                message.clone(),
+                                &time_limit,
+                                &now,
+                            )
+                            .await;
+                            last_message = message;
+                            last_transaction_status = TransactionStatus::Unprofitable;
+                            self.wait_next_tick(
+                                &mut block_ticks,
+                                time_limit.saturating_sub(now.elapsed()),
+                                sleep(self.tick_duration),
+                            )
+                            .await;
+                            continue;
+                        }
+                        // The operator may hold the final transactions during an incident,
+                        // the circuit breaker after failures in a row.
+                        let suspension = if self
+                            .switch
+                            .as_ref()
+                            .is_some_and(|switch| switch.broadcast_suspended())
+                        {
+                            Some("The final transaction is suspended".to_string())
+                        } else {
+                            self.circuit_breaker.as_ref().and_then(|circuit_breaker| {
+                                circuit_breaker.open_message(self.solver.app().as_str())
+                            })
+                        };
+                        if let Some(message) = suspension {
+                            self.send_stats(
+                                event,
+                                self.solver.app(),
+                                Status::Running,
+                                TransactionStatus::Suspended,
+                                message.clone(),
+                                &time_limit,
+                                &now,
+                            )
+                            .await;
+                            last_message = message;
+                            last_transaction_status = TransactionStatus::Suspended;
+                            self.wait_next_tick(
+                                &mut block_ticks,
+                                time_limit.saturating_sub(now.elapsed()),
+                                sleep(self.tick_duration),
+                            )
+                            .await;
+                            continue;
+                        }
                         self.send_stats(
-                            event.sequence_number,
+                            event,
                             self.solver.app(),
                             Status::Running,
                             TransactionStatus::TransactionPending,
                             response.message.clone(),
                             &time_limit,
                             &now,
-                            &event.data_values,
                         )
                         .await;
-                        match self.solver.final_exec().await {
+                        let final_exec = self.solver.final_exec().await;
+                        trace.decision(Stage::FinalExec, &final_exec);
+                        match final_exec {
                             Ok(response) => {
                                 last_message = response.message.clone();
                                 if response.succeeded {
+                                    self.record_final_exec(true);
                                     self.send_stats(
-                                        event.sequence_number,
+                                        event,
                                         self.solver.app(),
                                         Status::Succeeded,
-                                        TransactionStatus::Succeeded,
+                                        if self.dry_run {
+                                            TransactionStatus::Simulated
+                                        } else {
+                                            TransactionStatus::Succeeded
+                                        },
                                         response.message.clone(),
                                         &time_limit,
                                         &now,
-                                        &event.data_values,
                                     )
                                     .await;
                                     println!("Executor {} successfully finished", self.id);
-                                    return;
+                                    return (Status::Succeeded, response.message);
                                 } else {
+                                    // A receipt that isn't a success is a revert.
+                                    let transaction_status = match self.solver.tx_hash() {
+                                        Some(_) => {
+                                            self.record_final_exec(false);
+                                            TransactionStatus::Reverted
+                                        }
+                                        None => TransactionStatus::TransactionPending,
+                                    };
+                                    // The reverts spend gas, they stop at the budget.
+                                    if let Some(message) = self.budget_exhausted() {
+                                        println!("Executor {}: {}", self.id, message);
+                                        self.send_stats(
+                                            event,
+                                            self.solver.app(),
+                                            Status::BudgetExhausted,
+                                            transaction_status,
+                                            message.clone(),
+                                            &time_limit,
+                                            &now,
+                                        )
+                                        .await;
+                                        return (Status::BudgetExhausted, message);
+                                    }
                                     self.send_stats(
-                                        event.sequence_number,
+                                        event,
                                         self.solver.app(),
                                         Status::Running,
-                                        TransactionStatus::TransactionPending,
+                                        transaction_status.clone(),
                                         response.message.clone(),
                                         &time_limit,
                                         &now,
-                                        &event.data_values,
                                     )
                                     .await;
-                                    last_transaction_status = TransactionStatus::TransactionPending;
+                                    last_transaction_status = transaction_status;
                                 }
                             }
-                            Err(err) => {
-                                println!("Error in solver final exec: {}", err);
+                            Err(err) if err.is_retryable() => {
+                                retries += 1;
+                                let backoff = self.retry_backoff(retries);
+                                println!(
+                                    "Error in solver final exec, retrying in {:?}: {}",
+                                    backoff, err
+                                );
                                 self.send_stats(
-                                    event.sequence_number,
+                                    event,
                                     self.solver.app(),
                                     Status::Running,
                                     TransactionStatus::TransactionFailed,
                                     err.to_string(),
                                     &time_limit,
                                     &now,
-                                    &event.data_values,
                                 )
                                 .await;
+                                last_message = err.to_string();
                                 last_transaction_status = TransactionStatus::TransactionFailed;
+                                sleep(backoff).await;
+                                continue;
+                            }
+                            Err(err) => {
+                                println!("Error in solver final exec: {}", err);
+                                self.record_final_exec(false);
+                                self.send_stats(
+                                    event,
+                                    self.solver.app(),
+                                    Status::Failed,
+                                    TransactionStatus::TransactionFailed,
+                                    err.to_string(),
+                                    &time_limit,
+                                    &now,
+                                )
+                                .await;
+                                return (Status::Failed, err.to_string());
                             }
                         }
                     } else {
                         self.send_stats(
-                            event.sequence_number,
+                            event,
                             self.solver.app(),
                             Status::Running,
                             TransactionStatus::StepPending,
                             response.message.clone(),
                             &time_limit,
                             &now,
-                            &event.data_values,
                         )
                         .await;
                         last_transaction_status = TransactionStatus::StepPending;
                     }
                 }
+                Err(err) if err.is_retryable() => {
+                    retries += 1;
+                    let backoff = self.retry_backoff(retries);
+                    println!(
+                        "Error in solver step call, retrying in {:?}: {}",
+                        backoff, err
+                    );
+                    self.send_stats(
+                        event,
+                        self.solver.app(),
+                        Status::Running,
+                        TransactionStatus::StepFailed,
+                        err.to_string(),
+                        &time_limit,
+                        &now,
+                    )
+                    .await;
+                    last_message = err.to_string();
+                    last_transaction_status = TransactionStatus::StepFailed;
+                    sleep(backoff).await;
+                    continue;
+                }
+                // Permanent errors, e.g. bad parameters, fail the objective right away.
                 Err(err) => {
                     println!("Error in solver step call: {}", err);
                     self.send_stats(
-                        event.sequence_number,
+                        event,
                         self.solver.app(),
                         Status::Failed,
                         TransactionStatus::StepFailed,
                         err.to_string(),
                         &time_limit,
                         &now,
-                        &event.data_values,
                     )
                     .await;
-                    last_transaction_status = TransactionStatus::StepFailed;
+                    return (Status::Failed, err.to_string());
                 }
             }
+            retries = 0;
             // Wait for the next tick
-            sleep(self.tick_duration).await;
+            self.wait_next_tick(
+                &mut block_ticks,
+                time_limit.saturating_sub(now.elapsed()),
+                self.solver.wait_next_step(self.tick_duration),
+            )
+            .await;
         }
         // Sending post-exec stats
         self.send_stats(
-            event.sequence_number,
+            event,
             self.solver.app(),
             Status::Timeout,
             last_transaction_status,
-            last_message,
+            last_message.clone(),
             &time_limit,
             &now,
-            &event.data_values,
         )
         .await;
         println!("Executor {} finished by timeout", self.id);
+        (Status::Timeout, last_message)
+    }
+
+    // Waits for the next block in the block tick mode, up to the end of the time limit
+    // or the timer if the blocks stall. Waits for the timer otherwise, or if the blocks
+    // are gone.
+    async fn wait_next_tick<F: Future<Output = ()>>(
+        &self,
+        block_ticks: &mut Option<watch::Receiver<u64>>,
+        time_left: Duration,
+        timer: F,
+    ) {
+        match block_ticks {
+            Some(block_ticks) => {
+                tokio::pin!(timer);
+                let blocks_gone = tokio::select! {
+                    changed = timeout(time_left, block_ticks.changed()) => {
+                        matches!(changed, Ok(Err(_)))
+                    }
+                    _ = &mut timer => false,
+                };
+                if blocks_gone {
+                    timer.await
+                }
+            }
+            None => timer.await,
+        }
+    }
+
+    // Why the objective is given up on, once its failed final transactions spent its
+    // gas budget.
+    fn budget_exhausted(&self) -> Option<String> {
+        let budget = self.solver.gas_budget()?;
+        let spent = self.solver.accounting()?.gas_spent_wei;
+        (spent >= budget).then(|| {
+            format!(
+                "The failed final transactions spent {} wei of the gas budget of {} wei",
+                spent, budget
+            )
+        })
+    }
+
+    // Counts the reverted and failed final transactions of the app, the transient errors
+    // don't count.
+    fn record_final_exec(&self, succeeded: bool) {
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            let app = self.solver.app();
+            if succeeded {
+                circuit_breaker.record_success(app.as_str());
+            } else {
+                circuit_breaker.record_failure(app.as_str());
+            }
+        }
+    }
+
+    // Delay before the next attempt after the given number of transient errors in a row.
+    fn retry_backoff(&self, retries: u32) -> Duration {
+        self.tick_duration
+            .saturating_mul(2u32.saturating_pow(retries.saturating_sub(1)))
+            .min(MAX_RETRY_BACKOFF)
+    }
+
+    // Returns the reason if the solver preconditions for the final execution aren't met.
+    async fn unmet_preconditions(&self, trace: &ExecutionTrace) -> Option<String> {
+        let res = self.solver.check_preconditions().await;
+        trace.decision(Stage::Preconditions, &res);
+        match res {
+            Ok(response) => {
+                if response.succeeded {
+                    None
+                } else {
+                    Some(response.message)
+                }
+            }
+            Err(err) => {
+                println!("Error in solver preconditions check: {}", err);
+                Some(err.to_string())
+            }
+        }
+    }
+
+    // Returns the reason if the final execution would cost more than it earns.
+    async fn unprofitable(&self, trace: &ExecutionTrace) -> Option<String> {
+        let res = self.solver.check_profitability().await;
+        trace.decision(Stage::Profitability, &res);
+        match res {
+            Ok(response) => {
+                println!("Executor {}: {}", self.id, response.message);
+                if response.succeeded {
+                    None
+                } else {
+                    Some(response.message)
+                }
+            }
+            Err(err) => {
+                println!("Error in solver profitability check: {}", err);
+                Some(err.to_string())
+            }
+        }
     }
 
     // Send statistics into the stats channel
     async fn send_stats(
         &self,
-        sequence_number: U256,
+        event: &ProxyPushedFilter,
         app: String,
         status: Status,
         transaction_status: TransactionStatus,
         message: String,
         time_limit: &Duration,
         now: &Instant,
-        params: &Vec<AdditionalData>,
     ) {
         let remaining;
         if status == Status::Running {
@@ -203,23 +593,42 @@ impl<S: Solver> TimerRequestExecutor<S> {
         } else {
             remaining = Duration::new(0, 0);
         }
-        let res = self
-            .stats_tx
+        if let Some(state_store) = &self.state_store {
+            state_store.progress(
+                event,
+                self.creation_time + *time_limit,
+                &transaction_status,
+                message.as_str(),
+            );
+        }
+        // Accounted once per objective, when the executor finishes.
+        let accounting = match status {
+            Status::Running | Status::Duplicate => None,
+            Status::Succeeded
+            | Status::Failed
+            | Status::Timeout
+            | Status::SolvedExternally
+            | Status::BudgetExhausted => self.solver.accounting(),
+        };
+        let decision = self.decision.lock().unwrap().clone();
+        self.stats_tx
             .send(TimerExecutorStats {
                 id: self.id,
-                sequence_number: sequence_number.as_u32(),
+                sequence_number: event.sequence_number.as_u32(),
                 app,
                 creation_time: self.creation_time,
                 status,
                 transaction_status,
                 message,
-                params: params.clone(),
+                params: event.data_values.clone(),
                 elapsed: now.elapsed(),
                 remaining,
+                tx_hash: self.solver.tx_hash(),
+                accounting,
+                bundle_status: self.solver.bundle_status(),
+                amount: self.solver.order_amount(),
+                decision,
             })
             .await;
-        if let Some(err) = res.err() {
-            println!("Error sending stats: {}", err);
-        }
     }
 }
@@ -0,0 +1,93 @@
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+    utils::format_units,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{chain_client::contract_error, contracts_abi::ierc20::IERC20, solver::SolverError};
+
+// What the amounts of an ERC-20 token are scaled by.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub decimals: u8,
+    pub symbol: String,
+}
+
+impl TokenMetadata {
+    // One whole token in base units.
+    pub fn unit(&self) -> U256 {
+        U256::exp10(self.decimals as usize)
+    }
+
+    // The amount in whole tokens, e.g. 1.5 USDC for 1500000.
+    pub fn format(&self, amount: U256) -> String {
+        let whole = match format_units(amount, self.decimals as u32) {
+            Ok(whole) if whole.contains('.') => whole
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string(),
+            Ok(whole) => whole,
+            Err(_) => amount.to_string(),
+        };
+        format!("{} {}", whole, self.symbol)
+    }
+}
+
+// Metadata of the tokens read so far, shared by the solvers. It doesn't change once the
+// token is deployed.
+#[derive(Default)]
+pub struct TokenMetadataCache {
+    tokens: Mutex<HashMap<Address, TokenMetadata>>,
+}
+
+impl TokenMetadataCache {
+    pub async fn read<M: Middleware>(
+        &self,
+        middleware: Arc<M>,
+        token: Address,
+    ) -> Result<TokenMetadata, SolverError> {
+        if let Some(metadata) = self.tokens.lock().unwrap().get(&token) {
+            return Ok(metadata.clone());
+        }
+        let erc20 = IERC20::new(token, middleware);
+        let decimals = erc20.decimals().call().await.map_err(|err| {
+            contract_error(err).context(&format!("Error reading the decimals of {:?}", token))
+        })?;
+        // Some tokens predate the symbol of the standard, they are shown by address.
+        let symbol = match erc20.symbol().call().await.map_err(contract_error) {
+            Ok(symbol) => symbol,
+            Err(err) if err.is_retryable() => {
+                return Err(err.context(&format!("Error reading the symbol of {:?}", token)))
+            }
+            Err(_) => format!("{:?}", token),
+        };
+        let metadata = TokenMetadata { decimals, symbol };
+        self.tokens.lock().unwrap().insert(token, metadata.clone());
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_are_formatted_in_whole_tokens() {
+        let usdc = TokenMetadata {
+            decimals: 6,
+            symbol: "USDC".to_string(),
+        };
+        assert_eq!(usdc.format(1500000.into()), "1.5 USDC");
+        assert_eq!(usdc.format(2000000.into()), "2 USDC");
+        let points = TokenMetadata {
+            decimals: 0,
+            symbol: "PTS".to_string(),
+        };
+        assert_eq!(points.format(150.into()), "150 PTS");
+    }
+}
@@ -0,0 +1,248 @@
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, U256},
+};
+use serde::Serialize;
+use std::{fmt::Write, str::FromStr, sync::Arc, time::Duration};
+use tokio::{sync::Mutex, time::sleep};
+
+use crate::contracts_abi::ierc20::IERC20;
+#[cfg(feature = "webhooks")]
+use crate::http_client::http_client;
+
+// An ERC20 balance of the wallet to watch, as TOKEN or TOKEN:MIN_WEI.
+#[derive(Clone, Debug)]
+pub struct WatchedToken {
+    pub token: Address,
+    pub min_balance: Option<U256>,
+}
+
+impl FromStr for WatchedToken {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (token, min_balance) = match s.split_once(':') {
+            Some((token, min_balance)) => (token, Some(min_balance)),
+            None => (s, None),
+        };
+        Ok(WatchedToken {
+            token: Address::from_str(token)
+                .map_err(|err| format!("invalid token address: {}", err))?,
+            min_balance: min_balance
+                .map(|min_balance| {
+                    U256::from_dec_str(min_balance)
+                        .map_err(|err| format!("invalid minimum balance: {}", err))
+                })
+                .transpose()?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Balance {
+    // The native token if not set.
+    pub token: Option<Address>,
+    pub balance: U256,
+    pub min_balance: Option<U256>,
+    pub low: bool,
+}
+
+impl Balance {
+    fn label(&self) -> String {
+        match self.token {
+            Some(token) => format!("{:?}", token),
+            None => "native".to_string(),
+        }
+    }
+}
+
+// The balances of a solver wallet as of the last check.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct WalletBalances {
+    pub wallet: Address,
+    pub balances: Vec<Balance>,
+    // Nonce of the next transaction of the wallet, counting the pending ones.
+    pub nonce: Option<U256>,
+    // Error of the last check, the balances are from the check before.
+    pub last_error: Option<String>,
+}
+
+pub type WalletBalancesMap = Arc<Mutex<WalletBalances>>;
+
+// The balances and the nonces of the wallets in the Prometheus text format.
+pub fn write_metrics(wallets: &[WalletBalances], body: &mut String) {
+    let _ = writeln!(
+        body,
+        "# HELP solver_wallet_balance_wei Balance of the solver wallet."
+    );
+    let _ = writeln!(body, "# TYPE solver_wallet_balance_wei gauge");
+    for wallet in wallets {
+        for balance in &wallet.balances {
+            // Read as a float by Prometheus, exact up to 2^53 wei.
+            let _ = writeln!(
+                body,
+                "solver_wallet_balance_wei{{wallet=\"{:?}\",token=\"{}\"}} {}",
+                wallet.wallet,
+                balance.label(),
+                balance.balance
+            );
+        }
+    }
+    let _ = writeln!(
+        body,
+        "# HELP solver_wallet_balance_low Whether the balance is below its minimum."
+    );
+    let _ = writeln!(body, "# TYPE solver_wallet_balance_low gauge");
+    for wallet in wallets {
+        for balance in &wallet.balances {
+            let _ = writeln!(
+                body,
+                "solver_wallet_balance_low{{wallet=\"{:?}\",token=\"{}\"}} {}",
+                wallet.wallet,
+                balance.label(),
+                balance.low as u8
+            );
+        }
+    }
+    let _ = writeln!(
+        body,
+        "# HELP solver_wallet_nonce Nonce of the next transaction of the solver wallet."
+    );
+    let _ = writeln!(body, "# TYPE solver_wallet_nonce gauge");
+    for wallet in wallets {
+        if let Some(nonce) = wallet.nonce {
+            let _ = writeln!(
+                body,
+                "solver_wallet_nonce{{wallet=\"{:?}\"}} {}",
+                wallet.wallet, nonce
+            );
+        }
+    }
+}
+
+// Checks the balances of the wallet periodically, warns when one drops below its minimum.
+pub struct WalletMonitor<M> {
+    pub middleware: Arc<M>,
+    pub wallet: Address,
+    pub min_native_balance: Option<U256>,
+    pub tokens: Vec<WatchedToken>,
+    pub interval: Duration,
+    pub balances: WalletBalancesMap,
+    // The balances that went low are posted to it.
+    #[cfg(feature = "webhooks")]
+    pub alert_url: Option<String>,
+}
+
+impl<M: Middleware> WalletMonitor<M> {
+    pub async fn run(&self) {
+        #[cfg(feature = "webhooks")]
+        let client = http_client();
+        loop {
+            let went_low = match self.check().await {
+                Ok((balances, nonce)) => {
+                    let mut wallet_balances = self.balances.lock().await;
+                    let mut went_low = Vec::new();
+                    for balance in &balances {
+                        let was_low = wallet_balances
+                            .balances
+                            .iter()
+                            .any(|previous| previous.token == balance.token && previous.low);
+                        if balance.low && !was_low {
+                            went_low.push(balance.clone());
+                        } else if !balance.low && was_low {
+                            println!(
+                                "The {} balance of the wallet {:?} is back above the minimum",
+                                balance.label(),
+                                self.wallet
+                            );
+                        }
+                    }
+                    *wallet_balances = WalletBalances {
+                        wallet: self.wallet,
+                        balances,
+                        nonce: Some(nonce),
+                        last_error: None,
+                    };
+                    went_low
+                }
+                Err(err) => {
+                    println!("Error checking the wallet balances: {}", err);
+                    self.balances.lock().await.last_error = Some(err);
+                    Vec::new()
+                }
+            };
+            // Alerted once per drop, outside of the lock.
+            for balance in &went_low {
+                println!(
+                    "Warning: the {} balance {} of the wallet {:?} is below the minimum {}",
+                    balance.label(),
+                    balance.balance,
+                    self.wallet,
+                    balance.min_balance.unwrap_or_default()
+                );
+                #[cfg(feature = "webhooks")]
+                if let Some(url) = &self.alert_url {
+                    match &client {
+                        Ok(client) => self.alert(client, url, balance).await,
+                        Err(err) => println!("{}, the alert isn't sent", err),
+                    }
+                }
+            }
+            sleep(self.interval).await;
+        }
+    }
+
+    async fn check(&self) -> Result<(Vec<Balance>, U256), String> {
+        let native = self
+            .middleware
+            .get_balance(self.wallet, None)
+            .await
+            .map_err(|err| err.to_string())?;
+        let mut balances = vec![balance(None, native, self.min_native_balance)];
+        for watched in &self.tokens {
+            let token_balance = IERC20::new(watched.token, self.middleware.clone())
+                .balance_of(self.wallet)
+                .call()
+                .await
+                .map_err(|err| format!("token {:?}: {}", watched.token, err))?;
+            balances.push(balance(
+                Some(watched.token),
+                token_balance,
+                watched.min_balance,
+            ));
+        }
+        let nonce = self
+            .middleware
+            .get_transaction_count(self.wallet, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|err| format!("nonce: {}", err))?;
+        Ok((balances, nonce))
+    }
+
+    #[cfg(feature = "webhooks")]
+    async fn alert(&self, client: &reqwest::Client, url: &str, balance: &Balance) {
+        let alert = serde_json::json!({
+            "wallet": self.wallet,
+            "token": balance.label(),
+            "balance": balance.balance.to_string(),
+            "min_balance": balance.min_balance.unwrap_or_default().to_string(),
+        });
+        match client.post(url).json(&alert).send().await {
+            Ok(response) => {
+                if !response.status().is_success() {
+                    println!("Balance alert rejected: {}", response.status());
+                }
+            }
+            Err(err) => println!("Error sending the balance alert: {}", err),
+        }
+    }
+}
+
+fn balance(token: Option<Address>, balance: U256, min_balance: Option<U256>) -> Balance {
+    Balance {
+        token,
+        balance,
+        min_balance,
+        low: min_balance.is_some_and(|min_balance| balance < min_balance),
+    }
+}
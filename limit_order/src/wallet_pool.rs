@@ -0,0 +1,329 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::Address,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+use tokio::sync::Mutex;
+
+use crate::admin::AdminState;
+
+// How the executors are given a wallet of the pool.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WalletAssignment {
+    // Each in turn.
+    RoundRobin,
+    // The wallet with the fewest running executors, the first of them on a tie.
+    LeastBusy,
+}
+
+impl FromStr for WalletAssignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "round-robin" => Ok(WalletAssignment::RoundRobin),
+            "least-busy" => Ok(WalletAssignment::LeastBusy),
+            _ => Err(format!(
+                "unknown wallet assignment {}, expected round-robin or least-busy",
+                s
+            )),
+        }
+    }
+}
+
+// A solver wallet with the middleware signing for it. The final transactions of a wallet
+// are sent one at a time under its guard, so each wallet keeps a nonce sequence of its
+// own and the wallets send in parallel.
+pub struct PoolWallet<M> {
+    pub address: Address,
+    pub middleware: Arc<M>,
+    pub guard: Arc<Mutex<bool>>,
+    busy: AtomicUsize,
+    assigned: AtomicU64,
+    // Set once the wallet is rotated out. It's given to no new executor and leaves the
+    // pool once the ones holding it finished, with their transactions.
+    retiring: AtomicBool,
+}
+
+impl<M> PoolWallet<M> {
+    fn new(address: Address, middleware: Arc<M>) -> PoolWallet<M> {
+        PoolWallet {
+            address,
+            middleware,
+            guard: Arc::new(Mutex::new(true)),
+            busy: AtomicUsize::new(0),
+            assigned: AtomicU64::new(0),
+            retiring: AtomicBool::new(false),
+        }
+    }
+
+    fn is_retiring(&self) -> bool {
+        self.retiring.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PoolWalletStats {
+    pub address: Address,
+    // Executors currently holding the wallet.
+    pub busy: usize,
+    // Executors given the wallet since the start.
+    pub assigned: u64,
+    // The wallet was rotated out and drains its executors.
+    pub retiring: bool,
+}
+
+pub struct WalletPool<M> {
+    // The retiring wallets are kept until they're drained.
+    wallets: RwLock<Vec<Arc<PoolWallet<M>>>>,
+    assignment: WalletAssignment,
+    next: AtomicUsize,
+}
+
+impl<M> WalletPool<M> {
+    // The first wallet is the primary one, it has to be given.
+    pub fn new(wallets: Vec<(Address, Arc<M>)>, assignment: WalletAssignment) -> WalletPool<M> {
+        assert!(!wallets.is_empty(), "the wallet pool has no wallet");
+        WalletPool {
+            wallets: RwLock::new(
+                wallets
+                    .into_iter()
+                    .map(|(address, middleware)| Arc::new(PoolWallet::new(address, middleware)))
+                    .collect(),
+            ),
+            assignment,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    // The wallets given to new executors, the primary one first.
+    fn active(&self) -> Vec<Arc<PoolWallet<M>>> {
+        self.wallets
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|wallet| !wallet.is_retiring())
+            .cloned()
+            .collect()
+    }
+
+    pub fn primary(&self) -> Arc<PoolWallet<M>> {
+        self.active()[0].clone()
+    }
+
+    pub fn addresses(&self) -> Vec<Address> {
+        self.active().iter().map(|wallet| wallet.address).collect()
+    }
+
+    // Gives a wallet to an executor, until the lease is dropped.
+    pub fn assign(&self) -> WalletLease<M> {
+        // The drained wallets leave the pool.
+        self.wallets
+            .write()
+            .unwrap()
+            .retain(|wallet| !wallet.is_retiring() || wallet.busy.load(Ordering::Relaxed) > 0);
+        let wallets = self.active();
+        let index = match self.assignment {
+            WalletAssignment::RoundRobin => {
+                self.next.fetch_add(1, Ordering::Relaxed) % wallets.len()
+            }
+            WalletAssignment::LeastBusy => wallets
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, wallet)| wallet.busy.load(Ordering::Relaxed))
+                .map(|(index, _)| index)
+                .unwrap_or_default(),
+        };
+        let wallet = wallets[index].clone();
+        wallet.busy.fetch_add(1, Ordering::Relaxed);
+        wallet.assigned.fetch_add(1, Ordering::Relaxed);
+        WalletLease { wallet }
+    }
+
+    // Replaces a wallet by a new one, which takes its place in the pool, e.g. as the
+    // primary wallet. The executors holding the replaced wallet finish with it.
+    pub fn rotate(
+        &self,
+        retired: Address,
+        address: Address,
+        middleware: Arc<M>,
+    ) -> Result<(), String> {
+        let mut wallets = self.wallets.write().unwrap();
+        if wallets.iter().any(|wallet| wallet.address == address) {
+            return Err(format!("The wallet {:?} is already in the pool", address));
+        }
+        let position = wallets
+            .iter()
+            .position(|wallet| wallet.address == retired && !wallet.is_retiring())
+            .ok_or(format!("The wallet {:?} isn't in the pool", retired))?;
+        wallets[position].retiring.store(true, Ordering::Relaxed);
+        wallets.insert(position, Arc::new(PoolWallet::new(address, middleware)));
+        Ok(())
+    }
+
+    pub fn stats(&self) -> Vec<PoolWalletStats> {
+        self.wallets
+            .read()
+            .unwrap()
+            .iter()
+            .map(|wallet| PoolWalletStats {
+                address: wallet.address,
+                busy: wallet.busy.load(Ordering::Relaxed),
+                assigned: wallet.assigned.load(Ordering::Relaxed),
+                retiring: wallet.is_retiring(),
+            })
+            .collect()
+    }
+}
+
+pub struct WalletLease<M> {
+    wallet: Arc<PoolWallet<M>>,
+}
+
+impl<M> WalletLease<M> {
+    pub fn wallet(&self) -> &PoolWallet<M> {
+        &self.wallet
+    }
+}
+
+impl<M> Drop for WalletLease<M> {
+    fn drop(&mut self) {
+        self.wallet.busy.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub async fn get_wallet_pool_json<M>(
+    wallet_pool: State<Arc<WalletPool<M>>>,
+) -> Json<Vec<PoolWalletStats>> {
+    Json(wallet_pool.stats())
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RotationRequest {
+    // The wallet rotated out.
+    pub retire: Address,
+    // File with the private key of the new wallet. It's read on the solver host, the
+    // key isn't sent to the API.
+    pub key_file: String,
+}
+
+// Rotates the wallets of the pool, the new keys sign on the connection of the pool.
+pub struct WalletRotation<M> {
+    pub admin: AdminState,
+    pub wallet_pool: Arc<WalletPool<M>>,
+    pub signer: Arc<dyn Fn(LocalWallet) -> M + Send + Sync>,
+}
+
+impl<M> Clone for WalletRotation<M> {
+    fn clone(&self) -> Self {
+        WalletRotation {
+            admin: self.admin.clone(),
+            wallet_pool: self.wallet_pool.clone(),
+            signer: self.signer.clone(),
+        }
+    }
+}
+
+// Switches the new executors to a new key, without a restart.
+pub async fn rotate_wallet<M>(
+    State(rotation): State<WalletRotation<M>>,
+    headers: HeaderMap,
+    Json(request): Json<RotationRequest>,
+) -> Result<Json<Vec<PoolWalletStats>>, (StatusCode, String)> {
+    rotation.admin.authorize(&headers)?;
+    let wallet = fs::read_to_string(&request.key_file)
+        .map_err(|err| err.to_string())
+        .and_then(|key| LocalWallet::from_str(key.trim()).map_err(|err| err.to_string()))
+        .map_err(|err| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Error reading the key in {}: {}", request.key_file, err),
+            )
+        })?;
+    let address = wallet.address();
+    rotation
+        .wallet_pool
+        .rotate(request.retire, address, Arc::new((rotation.signer)(wallet)))
+        .map_err(|err| (StatusCode::CONFLICT, err))?;
+    println!(
+        "Rotated the wallet {:?} out for {:?}",
+        request.retire, address
+    );
+    Ok(Json(rotation.wallet_pool.stats()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(assignment: WalletAssignment) -> WalletPool<()> {
+        WalletPool::new(
+            (1..=3)
+                .map(|i| (Address::repeat_byte(i), Arc::new(())))
+                .collect(),
+            assignment,
+        )
+    }
+
+    #[test]
+    fn wallets_are_assigned_in_turn_or_by_load() {
+        let round_robin = pool(WalletAssignment::RoundRobin);
+        let addresses = (0..4)
+            .map(|_| round_robin.assign().wallet().address)
+            .collect::<Vec<_>>();
+        assert_eq!(addresses, [1, 2, 3, 1].map(Address::repeat_byte).to_vec());
+
+        let least_busy = pool(WalletAssignment::LeastBusy);
+        let first = least_busy.assign();
+        let second = least_busy.assign();
+        assert_eq!(first.wallet().address, Address::repeat_byte(1));
+        assert_eq!(second.wallet().address, Address::repeat_byte(2));
+        drop(first);
+        // The released wallet is the least busy again.
+        assert_eq!(
+            least_busy.assign().wallet().address,
+            Address::repeat_byte(1)
+        );
+        let stats = least_busy.stats();
+        assert_eq!(stats[0].assigned, 2);
+        assert_eq!(stats[1].busy, 1);
+        assert_eq!(stats[2].busy, 0);
+    }
+
+    #[test]
+    fn rotated_wallets_drain_their_executors() {
+        let wallet_pool = pool(WalletAssignment::RoundRobin);
+        let held = wallet_pool.assign();
+        let new = Address::repeat_byte(4);
+        wallet_pool
+            .rotate(Address::repeat_byte(1), new, Arc::new(()))
+            .ok()
+            .unwrap();
+        assert!(wallet_pool
+            .rotate(Address::repeat_byte(2), new, Arc::new(()))
+            .is_err());
+        assert_eq!(wallet_pool.primary().address, new);
+        let addresses = (0..3)
+            .map(|_| wallet_pool.assign().wallet().address)
+            .collect::<Vec<_>>();
+        assert!(!addresses.contains(&Address::repeat_byte(1)));
+        assert_eq!(wallet_pool.stats().len(), 4);
+        // The retired wallet leaves once its executor finished.
+        drop(held);
+        wallet_pool.assign();
+        assert_eq!(wallet_pool.stats().len(), 3);
+    }
+}
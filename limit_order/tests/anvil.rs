@@ -0,0 +1,346 @@
+// End-to-end test of the limit order pipeline on a local Anvil chain: the contracts
+// from abi_town are deployed, an objective is pushed to the laminator and the solver
+// binary is expected to pick it up and land execute_and_verify.
+//
+// Runs with `cargo test --features anvil-tests`, needs anvil (foundry) in PATH. The
+// abi_town has neither a token contract nor the AssociatedDataLib the CallBreaker is
+// linked with, they are taken from the forge output of stxn-contracts-core in
+// STXN_CONTRACTS_OUT. The token is MockERC20 with the constructor (string name,
+// string symbol) and mint(address to, uint256 amount).
+#![cfg(feature = "anvil-tests")]
+
+use ethers::{
+    abi::{Abi, AbiEncode, Tokenize},
+    contract::{abigen, ContractFactory},
+    core::utils::{keccak256, parse_ether, Anvil, AnvilInstance},
+    middleware::SignerMiddleware,
+    providers::{Http, Provider},
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, U256},
+};
+use serde_json::Value;
+use std::{
+    fs,
+    process::Stdio,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::{Child, Command},
+    time::{sleep, timeout},
+};
+
+abigen!(
+    Laminator,
+    "./abi_town/Laminator.sol/Laminator.json";
+
+    MockDaiWethPool,
+    "./abi_town/MockDaiWethPool.sol/MockDaiWethPool.json";
+
+    MockFlashLoan,
+    "./abi_town/MockFlashLoan.sol/MockFlashLoan.json";
+
+    MockErc20,
+    r#"[
+        function mint(address to, uint256 amount)
+        function approve(address spender, uint256 amount) returns (bool)
+        function balanceOf(address account) view returns (uint256)
+    ]"#;
+);
+
+const APP_SELECTOR: &str = "FLASHLIQUIDITY.LIMITORDER";
+const SOLVER_PORT: u16 = 3939;
+// How long the solver gets to start listening and to execute the objective.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+const EXECUTION_TIMEOUT: Duration = Duration::from_secs(120);
+
+type Client = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+struct Deployment {
+    dai: Address,
+    weth: Address,
+    call_breaker: Address,
+    laminator: Address,
+    pool: Address,
+    flash_loan: Address,
+}
+
+fn client(anvil: &AnvilInstance, index: usize) -> Arc<Client> {
+    let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+    let wallet = LocalWallet::from(anvil.keys()[index].clone()).with_chain_id(anvil.chain_id());
+    Arc::new(SignerMiddleware::new(provider, wallet))
+}
+
+// The ABI and the creation bytecode of a forge artifact.
+fn artifact(path: &str) -> (Abi, Value) {
+    let artifact: Value = serde_json::from_str(
+        fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("cannot read {}: {}", path, err))
+            .as_str(),
+    )
+    .unwrap();
+    (
+        serde_json::from_value(artifact["abi"].clone()).unwrap(),
+        artifact["bytecode"].clone(),
+    )
+}
+
+fn contracts_out(artifact: &str) -> String {
+    let out = std::env::var("STXN_CONTRACTS_OUT")
+        .expect("STXN_CONTRACTS_OUT must point at the forge output of stxn-contracts-core");
+    format!("{}/{}", out, artifact)
+}
+
+async fn deploy_artifact<T: Tokenize>(
+    client: Arc<Client>,
+    abi: Abi,
+    bytecode: &str,
+    args: T,
+) -> Address {
+    let bytecode: Bytes = bytecode.parse().unwrap();
+    ContractFactory::new(abi, bytecode, client)
+        .deploy(args)
+        .unwrap()
+        .send()
+        .await
+        .unwrap()
+        .address()
+}
+
+async fn deploy_erc20(client: Arc<Client>, name: &str, symbol: &str) -> Address {
+    let (abi, bytecode) = artifact(contracts_out("MockERC20.sol/MockERC20.json").as_str());
+    deploy_artifact(
+        client,
+        abi,
+        bytecode["object"].as_str().unwrap(),
+        (name.to_string(), symbol.to_string()),
+    )
+    .await
+}
+
+// Deploys the library and the CallBreaker linked with it.
+async fn deploy_call_breaker(client: Arc<Client>) -> Address {
+    let (abi, bytecode) =
+        artifact(contracts_out("CallBreakerTypes.sol/AssociatedDataLib.json").as_str());
+    let library = deploy_artifact(
+        client.clone(),
+        abi,
+        bytecode["object"].as_str().unwrap(),
+        (),
+    )
+    .await;
+    let (abi, bytecode) = artifact("./abi_town/CallBreaker.sol/CallBreaker.json");
+    let mut code = bytecode["object"].as_str().unwrap().to_string();
+    let library_hex = format!("{:x}", library);
+    for references in bytecode["linkReferences"]
+        .as_object()
+        .into_iter()
+        .flat_map(|files| files.values())
+        .filter_map(|libraries| libraries.get("AssociatedDataLib"))
+        .filter_map(|references| references.as_array())
+    {
+        for reference in references {
+            // Offsets are in bytes of the code after the 0x prefix.
+            let start = 2 + 2 * reference["start"].as_u64().unwrap() as usize;
+            code.replace_range(start..start + 40, library_hex.as_str());
+        }
+    }
+    deploy_artifact(client, abi, code.as_str(), ()).await
+}
+
+// Deploys the contracts and funds the pool, the flash loan and the user's proxy.
+async fn deploy(client: Arc<Client>, user: Address, amount: U256) -> Deployment {
+    let dai = deploy_erc20(client.clone(), "Dai Stablecoin", "DAI").await;
+    let weth = deploy_erc20(client.clone(), "Wrapped Ether", "WETH").await;
+    let call_breaker = deploy_call_breaker(client.clone()).await;
+    let laminator = Laminator::deploy(client.clone(), call_breaker)
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    let pool = MockDaiWethPool::deploy(client.clone(), (call_breaker, dai, weth))
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    let flash_loan = MockFlashLoan::deploy(client.clone(), (dai, weth))
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    let proxy = laminator.compute_proxy_address(user).call().await.unwrap();
+
+    for (token, holder, value) in [
+        (dai, pool.address(), parse_ether(10000).unwrap()),
+        (weth, pool.address(), parse_ether(10).unwrap()),
+        (dai, flash_loan.address(), parse_ether(10000).unwrap()),
+        (weth, flash_loan.address(), parse_ether(1000).unwrap()),
+        (dai, proxy, amount),
+    ] {
+        MockErc20::new(token, client.clone())
+            .mint(holder, value)
+            .send()
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+    }
+    pool.mint_initial_liquidity()
+        .send()
+        .await
+        .unwrap()
+        .await
+        .unwrap();
+
+    Deployment {
+        dai,
+        weth,
+        call_breaker,
+        laminator: laminator.address(),
+        pool: pool.address(),
+        flash_loan: flash_loan.address(),
+    }
+}
+
+// Starts the solver binary and waits until it listens to the laminator events.
+async fn start_solver(anvil: &AnvilInstance, deployment: &Deployment) -> Child {
+    let mut solver = Command::new(env!("CARGO_BIN_EXE_solver"))
+        .args([
+            format!("--port={}", SOLVER_PORT),
+            format!("--chain-id={}", anvil.chain_id()),
+            format!("--ws-chain-url={}", anvil.ws_endpoint()),
+            format!("--laminator-address={:?}", deployment.laminator),
+            format!("--call-breaker-address={:?}", deployment.call_breaker),
+            format!("--flash-loan-address={:?}", deployment.flash_loan),
+            format!("--swap-pool-address={:?}", deployment.pool),
+            format!(
+                "--limit-order-wallet-private-key={}",
+                ethers::utils::hex::encode(anvil.keys()[1].to_bytes())
+            ),
+        ])
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .expect("cannot start the solver");
+    let mut lines = BufReader::new(solver.stdout.take().unwrap()).lines();
+    timeout(STARTUP_TIMEOUT, async {
+        while let Some(line) = lines.next_line().await.unwrap() {
+            println!("solver: {}", line);
+            if line.starts_with("Listening the event ProxyPushed") {
+                return;
+            }
+        }
+        panic!("the solver exited before listening");
+    })
+    .await
+    .expect("the solver didn't start listening");
+    // Keep echoing the solver output for the test log.
+    tokio::spawn(async move {
+        while let Ok(Some(line)) = lines.next_line().await {
+            println!("solver: {}", line);
+        }
+    });
+    solver
+}
+
+// Pushes a buy order of WETH for the given DAI amount, which triggers at any price.
+async fn push_objective(user: Arc<Client>, deployment: &Deployment, amount: U256) {
+    let laminator = Laminator::new(deployment.laminator, user);
+    let calls = vec![
+        CallObject {
+            amount: 0.into(),
+            gas: 10000000.into(),
+            addr: deployment.dai,
+            callvalue: MockErc20Calls::Approve(ApproveCall {
+                spender: deployment.pool,
+                amount,
+            })
+            .encode()
+            .into(),
+        },
+        CallObject {
+            amount: 0.into(),
+            gas: 10000000.into(),
+            addr: deployment.pool,
+            callvalue: MockDaiWethPoolCalls::SwapDAIForWETH(SwapDAIForWETHCall {
+                amount_in: amount,
+                slippage_percent: 10.into(),
+            })
+            .encode()
+            .into(),
+        },
+    ];
+    let data_values = [
+        ("give_token", format!("{:?}", deployment.dai)),
+        ("take_token", format!("{:?}", deployment.weth)),
+        ("amount", amount.to_string()),
+        ("direction", "buy".to_string()),
+        ("buy_price", U256::MAX.to_string()),
+        ("slippage", "10".to_string()),
+        ("time_limit", "60s".to_string()),
+    ]
+    .into_iter()
+    .map(|(name, value)| AdditionalData {
+        name: name.to_string(),
+        datatype: 0,
+        value,
+    })
+    .collect::<Vec<AdditionalData>>();
+    laminator
+        .push_to_proxy(
+            calls.encode().into(),
+            0,
+            keccak256(APP_SELECTOR.to_string().encode()),
+            data_values,
+        )
+        .send()
+        .await
+        .unwrap()
+        .await
+        .unwrap();
+}
+
+// Waits for the executor of the objective to finish, returns its stats.
+async fn wait_for_executor() -> Value {
+    let url = format!("http://127.0.0.1:{}/stats/limit_order", SOLVER_PORT);
+    let started = Instant::now();
+    while started.elapsed() < EXECUTION_TIMEOUT {
+        let stats: Vec<Value> = reqwest::get(url.as_str())
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        if let Some(stats) = stats.into_iter().find(|stats| stats["status"] != "Running") {
+            return stats;
+        }
+        sleep(Duration::from_secs(1)).await;
+    }
+    panic!("the executor didn't finish in {:?}", EXECUTION_TIMEOUT);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn limit_order_lands_execute_and_verify() {
+    let anvil = Anvil::new().block_time(1u64).spawn();
+    let deployer = client(&anvil, 0);
+    let user = client(&anvil, 2);
+    let amount = parse_ether(10).unwrap();
+
+    let deployment = deploy(deployer, user.address(), amount).await;
+    let _solver = start_solver(&anvil, &deployment).await;
+    push_objective(user.clone(), &deployment, amount).await;
+
+    let stats = wait_for_executor().await;
+    assert_eq!(stats["status"], "Succeeded", "executor stats: {}", stats);
+    assert_eq!(stats["transaction_status"], "Succeeded");
+
+    // The pushed swap was pulled by the final transaction.
+    let weth = MockErc20::new(deployment.weth, user.clone());
+    let proxy = Laminator::new(deployment.laminator, user.clone())
+        .compute_proxy_address(user.address())
+        .call()
+        .await
+        .unwrap();
+    assert!(weth.balance_of(proxy).call().await.unwrap() > U256::zero());
+}